@@ -1,6 +1,6 @@
 use yinyang::repl::{create_env, repl};
 
 fn main() {
-    let mut env = create_env();
-    repl(&mut env);
+    let env = create_env();
+    repl(&env);
 }
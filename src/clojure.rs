@@ -1,20 +1,147 @@
 use crate::edn::*;
-use crate::immutant::list;
+use crate::immutant::list::List;
+use crate::immutant::map::{PersistentHashMap, TransientHashMap};
+use crate::immutant::set::TransientHashSet;
+use crate::immutant::vector::{PersistentVector, TransientVector};
+use crate::namespace::{self, Namespace};
+use crate::pattern::Pattern;
+use crate::seq::{self, Seq, Seqable};
+use crate::var::{self, Var};
 use bigdecimal::BigDecimal;
-use log::debug;
 use num_bigint::BigInt;
 use regex::Regex;
-use std::backtrace::Backtrace;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
+use std::iter::Peekable;
 use std::str::Chars;
 use std::str::FromStr;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock};
+use std::thread::{self, ThreadId};
+use tracing::{debug, debug_span};
 
+/// The global namespace table that `def` writes to. It is shared, so a
+/// closure sees definitions made after it was created.
 pub type Environment = Arc<RwLock<HashMap<String, Value>>>;
 
-pub struct NativeFn(pub Arc<dyn Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static>);
+/// A lexical scope: a chain of local frames over the namespace the code was
+/// evaluated in. Frames are immutable and shared, so entering a `let` or a
+/// call only allocates the new frame, and a closure captures its scope by
+/// reference.
+#[derive(Clone)]
+pub struct Scope {
+    globals: Environment,
+    // `None` in the implicit `user` namespace, which is just `globals`
+    ns: Option<Namespace>,
+    locals: Option<Arc<Frame>>,
+}
+
+struct Frame {
+    bindings: Vec<(String, Value)>,
+    parent: Option<Arc<Frame>>,
+}
+
+impl Scope {
+    /// A scope with no locals in the current namespace of `globals`.
+    pub fn new(globals: &Environment) -> Self {
+        Scope {
+            globals: globals.clone(),
+            ns: Namespace::current(globals),
+            locals: None,
+        }
+    }
+
+    pub fn globals(&self) -> &Environment {
+        &self.globals
+    }
+
+    /// Returns a child scope with `bindings` in a new innermost frame.
+    pub fn extend(&self, bindings: Vec<(String, Value)>) -> Self {
+        Scope {
+            globals: self.globals.clone(),
+            ns: self.ns.clone(),
+            locals: Some(Arc::new(Frame {
+                bindings,
+                parent: self.locals.clone(),
+            })),
+        }
+    }
+
+    /// Resolves `name` in the innermost frame that binds it, then in the
+    /// scope's namespace.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        let mut frame = self.locals.as_deref();
+        while let Some(f) = frame {
+            // Later bindings in a frame shadow earlier ones.
+            if let Some((_, value)) = f.bindings.iter().rev().find(|(n, _)| n == name) {
+                return Some(value.clone());
+            }
+            frame = f.parent.as_deref();
+        }
+        match &self.ns {
+            Some(ns) => ns.lookup(&self.globals, name),
+            None if name == "*ns*" => Some(Value::Namespace(Namespace::installed(&self.globals))),
+            None if var::has_thread_bindings() => {
+                Namespace::root(&self.globals).lookup(&self.globals, name)
+            }
+            None => self.globals.read().unwrap().get(name).cloned(),
+        }
+    }
+
+    /// The var a global name refers to from the scope's namespace.
+    pub fn resolve_var(&self, name: &str) -> Option<Var> {
+        Var::resolve(&self.globals, &self.namespace(), name)
+    }
+
+    fn namespace(&self) -> Namespace {
+        match &self.ns {
+            Some(ns) => ns.clone(),
+            None => Namespace::root(&self.globals),
+        }
+    }
+
+    /// The name of the namespace the scope resolves globals in.
+    pub fn ns_name(&self) -> &str {
+        self.ns.as_ref().map_or(namespace::USER, |ns| ns.name())
+    }
+
+    fn local_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut frame = self.locals.as_deref();
+        while let Some(f) = frame {
+            names.extend(f.bindings.iter().map(|(n, _)| n.as_str()));
+            frame = f.parent.as_deref();
+        }
+        names
+    }
+}
+
+// Closures stored in the global table capture that same table, so printing
+// it would never end. Only the local names are shown.
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("locals", &self.local_names())
+            .finish_non_exhaustive()
+    }
+}
+
+// Unlinks frames one at a time so a deep chain does not drop recursively.
+impl Drop for Frame {
+    fn drop(&mut self) {
+        let mut next = self.parent.take();
+        while let Some(frame) = next {
+            match Arc::try_unwrap(frame) {
+                Ok(mut frame) => next = frame.parent.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+pub struct NativeFn(
+    pub Arc<dyn Fn(Vec<Value>) -> Result<Value, EvalError> + Send + Sync + 'static>,
+);
 
 impl Clone for NativeFn {
     fn clone(&self) -> Self {
@@ -28,76 +155,423 @@ impl std::fmt::Debug for NativeFn {
     }
 }
 
+/// One arity of a `fn`: its fixed parameters, an optional `& rest`
+/// parameter and the body forms.
+#[derive(Debug, Clone)]
+pub struct Arity {
+    pub params: Vec<Pattern>,
+    pub rest: Option<Pattern>,
+    pub body: Vec<EDN>,
+}
+
+impl Arity {
+    fn accepts(&self, argc: usize) -> bool {
+        match self.rest {
+            Some(_) => argc >= self.params.len(),
+            None => argc == self.params.len(),
+        }
+    }
+
+    // The number of values the arity binds, which a `recur` must supply;
+    // the rest parameter takes a single seq.
+    fn width(&self) -> usize {
+        self.params.len() + usize::from(self.rest.is_some())
+    }
+
+    fn patterns(&self) -> impl Iterator<Item = &Pattern> {
+        self.params.iter().chain(&self.rest)
+    }
+
+    // Gathers the arguments past the fixed parameters into the rest list,
+    // a `Value::list` so fns can be passed through `&`.
+    fn collect_args(&self, mut args: Vec<Value>) -> Result<Vec<Value>, EvalError> {
+        if self.rest.is_some() {
+            let rest = args.split_off(self.params.len());
+            args.push(if rest.is_empty() {
+                Value::EDN(EDN::Nil)
+            } else {
+                Value::list(rest)
+            });
+        }
+        Ok(args)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Callable {
     Lambda {
-        params: Vec<Value>,
-        body: EDN,
-        closure: HashMap<String, Value>,
+        name: Option<String>,
+        arities: Arc<[Arity]>,
+        closure: Scope,
     },
     Native(NativeFn),
 }
 
 impl Callable {
-    fn call(&self, args: Vec<Value>) -> Result<Value, String> {
+    /// Whether both are the same fn, rather than equal ones.
+    pub fn ptr_eq(&self, other: &Callable) -> bool {
+        match (self, other) {
+            (Callable::Lambda { arities: a, .. }, Callable::Lambda { arities: b, .. }) => {
+                Arc::ptr_eq(a, b)
+            }
+            (Callable::Native(a), Callable::Native(b)) => Arc::ptr_eq(&a.0, &b.0),
+            _ => false,
+        }
+    }
+
+    pub fn call(&self, args: Vec<Value>) -> Result<Value, EvalError> {
         match self {
             Callable::Lambda {
-                params,
-                body,
+                name,
+                arities,
                 closure,
             } => {
-                if args.len() != params.len() {
-                    return Err(format!(
-                        "Expected {} args, got {}",
-                        params.len(),
-                        args.len()
-                    ));
-                }
-
-                // Create new environment starting with closure values
-                let mut new_env = closure.clone();
-
-                // Bind parameters to arguments
-                for (param, arg) in params.iter().zip(args.iter()) {
-                    match param {
-                        Value::EDN(EDN::Symbol(name)) => {
-                            new_env.insert(name.clone(), arg.clone());
-                        }
-                        _ => return Err("Parameter must be a symbol".to_string()),
+                let arity = arities
+                    .iter()
+                    .find(|arity| arity.accepts(args.len()))
+                    .ok_or_else(|| EvalError::Arity {
+                        name: name.clone().unwrap_or_else(|| "fn".to_string()),
+                        expected: expected_arities(arities),
+                        got: args.len(),
+                    })?;
+
+                // A named fn can refer to itself
+                let scope = match name {
+                    Some(name) => {
+                        closure.extend(vec![(name.clone(), Value::Function(self.clone()))])
+                    }
+                    None => closure.clone(),
+                };
+
+                // `recur` in the body re-enters it here rather than nesting a call
+                let mut values = arity.collect_args(args)?;
+                loop {
+                    match eval_do(&arity.body, &bind_all(arity.patterns(), values, &scope)?)? {
+                        Tail::Return(value) => return Ok(value),
+                        Tail::Recur(recur) => values = recur_args(recur, arity.width())?,
                     }
                 }
-
-                // Create thread-safe environment and evaluate
-                let env = Arc::new(RwLock::new(new_env));
-                eval(body.clone(), &env)
             }
             Callable::Native(f) => f.0(args),
         }
     }
 }
 
+// Describes the accepted argument counts, e.g. "1 or at least 3".
+fn expected_arities(arities: &[Arity]) -> String {
+    let counts: Vec<String> = arities
+        .iter()
+        .map(|arity| match arity.rest {
+            Some(_) => format!("at least {}", arity.params.len()),
+            None => arity.params.len().to_string(),
+        })
+        .collect();
+    match counts.split_last() {
+        Some((last, init)) if !init.is_empty() => format!("{} or {}", init.join(", "), last),
+        _ => counts.concat(),
+    }
+}
+
+/// A transient vector, map or set as a yinyang value. Like Clojure's, it
+/// may only be edited by the thread that created it and is unusable once
+/// `persistent!` has been called on it.
+#[derive(Clone)]
+pub struct Transient(Arc<Mutex<TransientState>>);
+
+struct TransientState {
+    owner: ThreadId,
+    coll: Option<TransientColl>,
+}
+
+pub enum TransientColl {
+    Vector(TransientVector<EDN>),
+    Map(TransientHashMap<EDN, EDN>),
+    Set(TransientHashSet<EDN>),
+}
+
+impl Transient {
+    pub fn new(coll: &EDN) -> Result<Transient, String> {
+        let coll = match coll {
+            EDN::Vector(v) => TransientColl::Vector(v.transient()),
+            EDN::RrbVector(v) => TransientColl::Vector(v.to_vector().transient()),
+            EDN::Map(m) => TransientColl::Map(m.transient()),
+            EDN::Set(s) => TransientColl::Set(s.transient()),
+            _ => return Err(format!("Cannot make a transient from {}", coll)),
+        };
+        Ok(Transient(Arc::new(Mutex::new(TransientState {
+            owner: thread::current().id(),
+            coll: Some(coll),
+        }))))
+    }
+
+    // Locks the state, checking that this thread owns it.
+    fn lock(&self, name: &str) -> Result<MutexGuard<'_, TransientState>, String> {
+        let state = self
+            .0
+            .lock()
+            .map_err(|_| format!("{}: transient lock poisoned", name))?;
+        if state.owner != thread::current().id() {
+            return Err(format!("{}: transient used by non-owner thread", name));
+        }
+        Ok(state)
+    }
+
+    /// Runs `f` on the collection after checking that this thread owns it
+    /// and that it has not been made persistent.
+    pub fn edit<R, F>(&self, name: &str, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut TransientColl) -> Result<R, String>,
+    {
+        match self.lock(name)?.coll.as_mut() {
+            Some(coll) => f(coll),
+            None => Err(format!("{}: transient used after persistent! call", name)),
+        }
+    }
+
+    /// Freezes the collection. Any later use of this transient is an error.
+    pub fn persistent(&self) -> Result<EDN, String> {
+        match self.lock("persistent!")?.coll.take() {
+            Some(TransientColl::Vector(v)) => Ok(EDN::Vector(v.persistent())),
+            Some(TransientColl::Map(m)) => Ok(EDN::Map(m.persistent())),
+            Some(TransientColl::Set(s)) => Ok(EDN::Set(s.persistent())),
+            None => Err("persistent!: transient used after persistent! call".to_string()),
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Transient) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// An identity for hashing, shared by all clones of this transient.
+    pub fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+}
+
+impl std::fmt::Debug for Transient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<transient>")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Value {
     EDN(EDN),
-    Var {
-        ns: String,
-        name: String,
-        value: Box<Value>,
-    },
+    // A reference to a var, as `#'x` gives it. It holds no value: the var's
+    // current value lives in its namespace, and deref reads it from there.
+    Var { ns: String, name: String },
     Function(Callable),
+    Macro(Callable),
+    Transient(Transient),
+    Namespace(Namespace),
+    // A list holding values that are not EDN, such as fns, as the rest
+    // arguments of a variadic fn can. The seq natives (`first`, `rest`,
+    // `next`, `seq`, `seq?`, `count`, `empty?`, `cons`, `conj`, `list`,
+    // `map`, `filter`, `take`, `drop`) and destructuring accept it as a seq.
+    Args(List<Value>),
     // Future additions:
     // Atom(AtomRef),
     // Class(Class),
     // etc.
 }
 
+/// Why evaluation failed. Errors raised inside a form are wrapped in a
+/// `Trace` for that form and for each enclosing form on the way out, so the
+/// outermost error carries the whole chain, like a Clojure stack trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// A function called with the wrong number of arguments.
+    Arity {
+        name: String,
+        expected: String,
+        got: usize,
+    },
+    /// A symbol with no local or global binding.
+    Unbound(String),
+    /// A value of the wrong kind, such as calling something that is not a
+    /// function.
+    Type(String),
+    /// A special form with the wrong shape.
+    Syntax(String),
+    /// A value raised by `throw`.
+    Thrown(Value),
+    /// An error returned by a native function.
+    Native(String),
+    Trace {
+        frame: Box<TraceFrame>,
+        error: Box<EvalError>,
+    },
+}
+
+/// A form that was being evaluated when an error was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub form: EDN,
+    /// The function or special form the form calls, when it names one.
+    pub name: Option<String>,
+    /// Where the reader found the form, when it recorded that.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl TraceFrame {
+    fn new(form: &List<EDN>) -> Self {
+        let position = |key: &str| {
+            form.meta()
+                .and_then(|meta| meta.get(&EDN::keyword(key)))
+                .and_then(|n| n.as_i64())
+                .and_then(|n| usize::try_from(n).ok())
+        };
+        TraceFrame {
+            form: EDN::List(Box::new(form.clone())),
+            name: form.first().and_then(|f| f.as_symbol()).map(str::to_string),
+            line: position("line"),
+            column: position("column"),
+        }
+    }
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name.as_deref().unwrap_or("<form>"))?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " ({}:{})", line, column)?;
+        }
+        write!(f, " {}", self.form)
+    }
+}
+
+impl EvalError {
+    fn within(self, form: &List<EDN>) -> Self {
+        EvalError::Trace {
+            frame: Box::new(TraceFrame::new(form)),
+            error: Box::new(self),
+        }
+    }
+
+    /// The error that started the failure, without its trace.
+    pub fn cause(&self) -> &EvalError {
+        let mut error = self;
+        while let EvalError::Trace { error: inner, .. } = error {
+            error = inner;
+        }
+        error
+    }
+
+    /// The forms being evaluated, innermost first.
+    pub fn trace(&self) -> Vec<&TraceFrame> {
+        let mut frames = Vec::new();
+        let mut error = self;
+        while let EvalError::Trace {
+            frame,
+            error: inner,
+        } = error
+        {
+            frames.push(frame.as_ref());
+            error = inner;
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// The message followed by one `at` line per traced form.
+    pub fn stack_trace(&self) -> String {
+        let mut out = self.to_string();
+        for frame in self.trace() {
+            out.push_str(&format!("\n  at {}", frame));
+        }
+        out
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.cause() {
+            EvalError::Arity {
+                name,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Wrong number of args ({}) passed to {}, expected {}",
+                got, name, expected
+            ),
+            EvalError::Unbound(name) => write!(f, "Unable to resolve symbol: {}", name),
+            EvalError::Type(msg) | EvalError::Syntax(msg) | EvalError::Native(msg) => {
+                write!(f, "{}", msg)
+            }
+            EvalError::Thrown(value) => write!(f, "Uncaught: {}", value),
+            EvalError::Trace { .. } => unreachable!("cause is never a trace"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+// Natives and the collections report their own errors as strings; errors
+// from the functions they call pass through unchanged.
+impl From<String> for EvalError {
+    fn from(msg: String) -> Self {
+        EvalError::Native(msg)
+    }
+}
+
+impl From<&str> for EvalError {
+    fn from(msg: &str) -> Self {
+        EvalError::Native(msg.to_string())
+    }
+}
+
+// Values are handed to other threads, so every collection shares its
+// structure through `Arc`. This fails to compile if that ever regresses.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<EDN>();
+    assert_send_sync::<Value>();
+    assert_send_sync::<Environment>();
+    assert_send_sync::<Scope>();
+    assert_send_sync::<EvalError>();
+};
+
+impl Value {
+    /// A list of `items`: an EDN list when every item is a value, and
+    /// `Value::Args` when some are not, such as fns.
+    pub fn list(items: Vec<Value>) -> Value {
+        if items.iter().all(|item| matches!(item, Value::EDN(_))) {
+            let items = items.into_iter().filter_map(|item| match item {
+                Value::EDN(edn) => Some(edn),
+                _ => None,
+            });
+            Value::EDN(EDN::List(Box::new(List::from_vec(items.collect()))))
+        } else {
+            Value::Args(List::from_vec(items))
+        }
+    }
+
+    /// Realizes the lazy seqs in the value; see `seq::force`.
+    pub fn force(&self) -> Result<(), EvalError> {
+        match self {
+            Value::EDN(edn) => seq::force(edn),
+            Value::Args(args) => args.iter().try_for_each(Value::force),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl fmt::Display for Value {
     #[allow(unused_variables)]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::EDN(edn) => write!(f, "{}", edn),
-            Value::Var { ns, name, value } => write!(f, "#'{}/{}", ns, name),
+            Value::Var { ns, name } => write!(f, "#'{}/{}", ns, name),
             Value::Function(_) => write!(f, "#<function>"),
+            Value::Macro(_) => write!(f, "#<macro>"),
+            Value::Transient(_) => write!(f, "#<transient>"),
+            Value::Namespace(ns) => write!(f, "{}", ns),
+            Value::Args(args) => {
+                let items: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "({})", items.join(" "))
+            }
         }
     }
 }
@@ -113,14 +587,17 @@ impl PartialEq for Value {
                 Value::Var {
                     ns: ns1,
                     name: name1,
-                    value: value1,
                 },
                 Value::Var {
                     ns: ns2,
                     name: name2,
-                    value: value2,
                 },
-            ) => ns1 == ns2 && name1 == name2 && value1 == value2,
+            ) => ns1 == ns2 && name1 == name2,
+
+            // Transients are mutable, so only identity counts
+            (Value::Transient(t1), Value::Transient(t2)) => t1.ptr_eq(t2),
+            (Value::Namespace(n1), Value::Namespace(n2)) => n1.ptr_eq(n2),
+            (Value::Args(a1), Value::Args(a2)) => a1.iter().eq(a2.iter()),
 
             // Different variants are never equal
             _ => false,
@@ -134,202 +611,260 @@ impl From<EDN> for Value {
     }
 }
 
-fn handle_nested_string(astr_iter: &mut Chars, items: &mut Vec<EDN>, buffer: &mut String) {
-    //let orig_str: String = astr_iter.clone().collect();
+// Reads forms one character at a time. Lists remember where they were
+// read, as {:line l :column c} metadata, so evaluation errors can point at
+// them. Both count from 1.
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
 
-    while let Some(ch) = astr_iter.next() {
-        if ch == '"' {
-            break;
-        } else {
-            buffer.push(ch);
+impl<'a> Reader<'a> {
+    fn new(source: &'a str) -> Self {
+        Reader {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
         }
     }
-    let val = EDN::String(buffer.to_string());
-    items.push(val);
-    buffer.clear();
-}
 
-fn parse_collection_helper(
-    astr_iter: &mut Chars,
-    mut nesting_level: i8,
-    items: &mut Vec<EDN>,
-    collection_type: &EDN,
-) -> Result<EDN, ParseError> {
-    let orig_str: String = astr_iter.clone().collect();
-    let mut buffer = String::new();
-    let config = EDN::collection_config(collection_type);
-    let closing_char = config.closing.chars().next().unwrap();
-    let mut string_start = false;
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
 
-    while let Some(ch) = astr_iter.next() {
-        if ch == '"' && string_start == false {
-            handle_nested_string(astr_iter, items, &mut buffer);
-            string_start = true;
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
+        Some(ch)
+    }
 
-        if !matches!(ch, ' ' | '\n' | ',' | ')' | ']' | '}') {
-            buffer.push(ch);
+    // Commas are whitespace, and `;` comments run to the end of the line.
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch == ';' {
+                while !matches!(self.next_char(), Some('\n') | None) {}
+            } else if ch.is_whitespace() || ch == ',' {
+                self.next_char();
+            } else {
+                break;
+            }
         }
+    }
 
-        match buffer.as_str() {
-            "(" => handle_nested_collection(
-                &EDN::List(Box::new(list::List::new())),
-                astr_iter,
-                &mut nesting_level,
-                items,
-                &mut buffer,
-            ),
-            "[" => handle_nested_collection(
-                &EDN::Vector(Vec::new()),
-                astr_iter,
-                &mut nesting_level,
-                items,
-                &mut buffer,
-            ),
-            "#{" => handle_nested_collection(
-                &EDN::Set(HashSet::new()),
-                astr_iter,
-                &mut nesting_level,
-                items,
-                &mut buffer,
-            ),
-            "{" => handle_nested_collection(
-                &EDN::Map(HashMap::new()),
-                astr_iter,
-                &mut nesting_level,
-                items,
-                &mut buffer,
-            ),
-            _ => {
-                if ch == closing_char {
-                    nesting_level -= 1;
-                    parse_buffer(&mut buffer, items);
-                    if nesting_level == 0 {
-                        break;
+    /// The next form, or `None` once only whitespace and comments are left.
+    fn read(&mut self) -> Result<Option<EDN>, ParseError> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+        let Some(ch) = self.peek() else {
+            return Ok(None);
+        };
+        let form = match ch {
+            '(' => {
+                self.next_char();
+                let items = self.read_until(')')?;
+                let position = position_meta(line, column);
+                EDN::List(Box::new(List::from_vec(items).with_meta(Some(position))))
+            }
+            '[' => {
+                self.next_char();
+                vec_to_vector(self.read_until(']')?)
+            }
+            '{' => {
+                self.next_char();
+                let items = self.read_until('}')?;
+                if items.len() % 2 != 0 {
+                    return Err(ParseError::RegularError(format!(
+                        "Map literal at line {line}, column {column} must contain an even number of forms"
+                    )));
+                }
+                EDN::Map(vec_to_map(items))
+            }
+            ')' | ']' | '}' => {
+                return Err(ParseError::NestingError(format!(
+                    "Unmatched delimiter {ch} at line {line}, column {column}"
+                )))
+            }
+            '"' => {
+                self.next_char();
+                self.read_string_literal()?
+            }
+            '\'' => {
+                self.next_char();
+                self.read_wrapped("quote", line, column)?
+            }
+            '^' => {
+                self.next_char();
+                self.read_meta()?
+            }
+            '#' => {
+                self.next_char();
+                match self.next_char() {
+                    Some('{') => vec_to_set(self.read_until('}')?),
+                    Some('\'') => self.read_wrapped("var", line, column)?,
+                    other => {
+                        return Err(ParseError::RegularError(format!(
+                            "Unsupported reader macro #{} at line {line}, column {column}",
+                            other.map(String::from).unwrap_or_default()
+                        )))
                     }
-                } else if matches!(ch, ' ' | '\n' | ',') {
-                    parse_buffer(&mut buffer, items);
                 }
             }
-        }
+            _ => self.read_token()?,
+        };
+        Ok(Some(form))
     }
 
-    if nesting_level != 0 {
-        return Err(ParseError::NestingError(format!(
-            "Unmatched delimiters {:?} buffer={:?}",
-            orig_str, buffer
-        )));
+    // A form that must follow a prefix such as `'` or `^`.
+    fn read_following(&mut self, prefix: &str) -> Result<EDN, ParseError> {
+        match self.read()? {
+            Some(form) => Ok(form),
+            None => Err(ParseError::NestingError(format!(
+                "EOF while reading the form after {prefix}"
+            ))),
+        }
     }
 
-    Ok((config.constructor)(items.to_vec()))
-}
-
-fn handle_nested_collection(
-    collection_type: &EDN,
-    astr_iter: &mut Chars,
-    nesting_level: &mut i8,
-    items: &mut Vec<EDN>,
-    buffer: &mut String,
-) {
-    if *nesting_level > 0 {
-        let nested =
-            parse_collection_helper(astr_iter, 1, &mut Vec::new(), collection_type).unwrap();
-        items.push(nested);
-    } else {
-        *nesting_level += 1;
+    fn read_until(&mut self, closing: char) -> Result<Vec<EDN>, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => {
+                    return Err(ParseError::NestingError(format!(
+                        "EOF while reading, expected {closing}"
+                    )))
+                }
+                Some(ch) if ch == closing => {
+                    self.next_char();
+                    return Ok(items);
+                }
+                Some(_) => items.extend(self.read()?),
+            }
+        }
     }
-    buffer.clear();
-}
 
-fn parse_buffer(buffer: &mut String, items: &mut Vec<EDN>) {
-    if !buffer.is_empty() {
-        if let Ok(edn_val) = read_string(&buffer.trim()) {
-            items.push(edn_val);
+    fn read_string_literal(&mut self) -> Result<EDN, ParseError> {
+        let mut s = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => return Ok(EDN::String(s)),
+                Some('\\') => match self.next_char() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('0') => s.push('\0'),
+                    Some(ch @ ('"' | '\\')) => s.push(ch),
+                    Some(ch) => {
+                        return Err(ParseError::RegularError(format!(
+                            "Unsupported escape character \\{ch}"
+                        )))
+                    }
+                    None => break,
+                },
+                Some(ch) => s.push(ch),
+                None => break,
+            }
         }
-        buffer.clear();
+        Err(ParseError::NestingError(
+            "EOF while reading string".to_string(),
+        ))
     }
-}
-
-fn parse_collection_with_type(astr: &str, collection_type: &EDN) -> Result<EDN, ParseError> {
-    let astr = astr.trim();
-    let config = EDN::collection_config(collection_type);
 
-    if astr.starts_with(config.opening) {
-        let mut items = Vec::new();
-        let result = parse_collection_helper(&mut astr.chars(), 0, &mut items, collection_type)?;
-        Ok(result)
-    } else {
-        Err(ParseError::RegularError(format!(
-            "Cannot parse {}",
-            config.opening
+    // `'x` reads as `(quote x)` and `#'x` as `(var x)`.
+    fn read_wrapped(&mut self, head: &str, line: usize, column: usize) -> Result<EDN, ParseError> {
+        let form = self.read_following(head)?;
+        let items = vec![EDN::Symbol(head.to_string(), None), form];
+        let position = position_meta(line, column);
+        Ok(EDN::List(Box::new(
+            List::from_vec(items).with_meta(Some(position)),
         )))
     }
-}
 
-fn parse_nil(astr: &str) -> Result<EDN, ParseError> {
-    if astr == "nil" || astr.is_empty() {
-        Ok(EDN::Nil)
-    } else {
-        Err(ParseError::RegularError(format!("No EDN::Nil: {}", astr)))
+    // `^:kw form`, `^Tag form` and `^{..} form` read as `form` with the
+    // metadata merged into what it already carries.
+    fn read_meta(&mut self) -> Result<EDN, ParseError> {
+        let meta = match self.read_following("^")? {
+            EDN::Map(m) => m,
+            EDN::Keyword(k) => PersistentHashMap::new().assoc(EDN::Keyword(k), EDN::Bool(true)),
+            tag @ (EDN::Symbol(..) | EDN::String(_)) => {
+                PersistentHashMap::new().assoc(EDN::keyword("tag"), tag)
+            }
+            other => {
+                return Err(ParseError::RegularError(format!(
+                    "Metadata must be a symbol, keyword, string or map, got {other}"
+                )))
+            }
+        };
+        let form = self.read_following("metadata")?;
+        let merged = match form.meta() {
+            EDN::Map(old) => meta
+                .iter()
+                .fold(old, |m, (k, v)| m.assoc(k.clone(), v.clone())),
+            _ => meta,
+        };
+        form.with_meta(&EDN::Map(merged))
+            .map_err(ParseError::RegularError)
     }
-}
 
-fn parse_bool(astr: &str) -> Result<EDN, ParseError> {
-    astr.parse::<bool>()
-        .map(EDN::Bool)
-        .map_err(|_| ParseError::RegularError(format!("No EDN::Bool: {}", astr)))
-}
-
-fn parse_int(astr: &str) -> Result<EDN, ParseError> {
-    astr.parse::<BigInt>()
-        .map(EDN::Integer)
-        .map_err(|_| ParseError::RegularError(format!("No EDN::Integer: {}", astr)))
-}
-
-fn parse_float(astr: &str) -> Result<EDN, ParseError> {
-    BigDecimal::from_str(astr)
-        .map(EDN::Float)
-        .map_err(|_| ParseError::RegularError(format!("No EDN::Float: {}", astr)))
-}
-
-fn parse_keyword(astr: &str) -> Result<EDN, ParseError> {
-    if astr.starts_with(':') {
-        Ok(EDN::Keyword(astr.to_string()))
-    } else {
-        Err(ParseError::RegularError(format!(
-            "No EDN::Keyword: {}",
-            astr
-        )))
+    fn read_token(&mut self) -> Result<EDN, ParseError> {
+        let mut token = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace()
+                || matches!(ch, ',' | ';' | '"' | '(' | ')' | '[' | ']' | '{' | '}')
+            {
+                break;
+            }
+            token.push(ch);
+            self.next_char();
+        }
+        parse_token(&token)
     }
 }
 
-fn parse_string(astr: &str) -> Result<EDN, ParseError> {
-    if astr.starts_with('"') {
-        let mut buffer = String::new();
-        let mut chars = astr.chars();
-        chars.next(); // Skip opening quote
+fn position_meta(line: usize, column: usize) -> PersistentHashMap<EDN, EDN> {
+    PersistentHashMap::new()
+        .assoc(EDN::keyword("line"), EDN::from(line as i64))
+        .assoc(EDN::keyword("column"), EDN::from(column as i64))
+}
 
-        while let Some(ch) = chars.next() {
-            if ch == '"' {
-                return Ok(EDN::String(buffer));
-            }
-            buffer.push(ch);
-        }
+fn parse_token(token: &str) -> Result<EDN, ParseError> {
+    let numeric = token.starts_with(|c: char| c.is_ascii_digit())
+        || (token.starts_with(['+', '-']) && token[1..].starts_with(|c: char| c.is_ascii_digit()));
+    match token {
+        "nil" => Ok(EDN::Nil),
+        "true" => Ok(EDN::Bool(true)),
+        "false" => Ok(EDN::Bool(false)),
+        _ if numeric => token
+            .parse::<BigInt>()
+            .map(EDN::Integer)
+            .or_else(|_| BigDecimal::from_str(token).map(EDN::Float))
+            .map_err(|_| ParseError::RegularError(format!("Invalid number: {}", token))),
+        _ if token.starts_with(':') && token.len() > 1 => Ok(EDN::Keyword(token.to_string())),
+        _ => parse_symbol(token),
     }
-    Err(ParseError::RegularError("Cannot parse string".to_string()))
 }
 
 pub fn parse_symbol(astr: &str) -> Result<EDN, ParseError> {
+    static SYMBOL: OnceLock<Regex> = OnceLock::new();
     if matches!(astr, "nil" | "true" | "false") {
         return Err(ParseError::RegularError(format!("Reserved name: {}", astr)));
     }
 
-    let symbol_regex = Regex::new(
-        r"^[a-zA-Z*+!_?$%&=<>'#\-][a-zA-Z0-9*+!_?$%&=<>'#\-\.]*(?:/[a-zA-Z0-9*+!_?$%&=<>'#\-\.]+)?$"
-    ).unwrap();
+    let symbol_regex = SYMBOL.get_or_init(|| {
+        Regex::new(
+            r"^[a-zA-Z*+!_?$%&=<>'#\-][a-zA-Z0-9*+!_?$%&=<>'#\-\.]*(?:/[a-zA-Z0-9*+!_?$%&=<>'#\-\.]+)?$",
+        )
+        .unwrap()
+    });
 
     if symbol_regex.is_match(astr) && !astr.ends_with(':') {
-        Ok(EDN::Symbol(astr.to_string()))
+        Ok(EDN::Symbol(astr.to_string(), None))
     } else {
         Err(ParseError::RegularError(format!(
             "Cannot parse symbol {:?}",
@@ -338,298 +873,822 @@ pub fn parse_symbol(astr: &str) -> Result<EDN, ParseError> {
     }
 }
 
-fn parse_list(astr: &str) -> Result<EDN, ParseError> {
-    parse_collection_with_type(astr, &EDN::List(Box::new(list::List::new())))
-}
-
-fn parse_vector(astr: &str) -> Result<EDN, ParseError> {
-    parse_collection_with_type(astr, &EDN::Vector(Vec::new()))
-}
-
-fn parse_set(astr: &str) -> Result<EDN, ParseError> {
-    parse_collection_with_type(astr, &EDN::Set(HashSet::new()))
-}
-
-fn parse_map(astr: &str) -> Result<EDN, ParseError> {
-    parse_collection_with_type(astr, &EDN::Map(HashMap::new()))
+/// Reads the first form in `astr`; an empty string reads as `nil`.
+pub fn read_string(astr: &str) -> Result<EDN, ParseError> {
+    Reader::new(astr)
+        .read()
+        .map(|form| form.unwrap_or(EDN::Nil))
 }
 
-fn parse_all(astr: &str) -> Result<EDN, ParseError> {
-    let parsers: Vec<fn(&str) -> Result<EDN, ParseError>> = vec![
-        parse_nil,
-        parse_bool,
-        parse_int,
-        parse_float,
-        parse_keyword,
-        parse_string,
-        parse_list,
-        parse_map,
-        parse_set,
-        parse_vector,
-    ];
-
-    for parser in parsers {
-        debug!("parser={:?}", parser);
-        let ast = parser(astr);
-        match ast {
-            Ok(result) => return Ok(result),
-            Err(ParseError::NestingError(e)) => {
-                return Err(ParseError::NestingError(e));
-            }
-            Err(ParseError::RegularError(_s)) => continue,
-        }
+/// Reads every form in `astr`, such as the top level of a source file.
+pub fn read_all(astr: &str) -> Result<Vec<EDN>, ParseError> {
+    let mut reader = Reader::new(astr);
+    let mut forms = Vec::new();
+    while let Some(form) = reader.read()? {
+        forms.push(form);
     }
-
-    Err(ParseError::RegularError("No valid EDN found".to_string()))
+    Ok(forms)
 }
 
-fn parse_first_valid_expr(astr: &str) -> Result<EDN, ParseError> {
-    let first = astr.split_whitespace().next().unwrap_or("");
-    parse_all(first)
+pub fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::EDN(EDN::Nil) | Value::EDN(EDN::Bool(false)))
 }
 
-fn replace_quote_syntax(input: &str) -> String {
-    let re = Regex::new(r"'([^\s\(\)\[\]\{\}]+|\(.*?\)|\[.*?\]|\{.*?\})").unwrap();
-
-    let mut output = input.to_string();
-    while re.is_match(&output) {
-        output = re
-            .replace_all(&output, |caps: &regex::Captures| {
-                let quoted = &caps[1];
-                format!("(quote {})", quoted)
-            })
-            .to_string();
-    }
-
-    output
+fn is_special_form(form_name: &str) -> bool {
+    let special_forms = ["quote", "if", "def", "fn"];
+    special_forms.contains(&form_name)
 }
 
-pub fn read_string(astr: &str) -> Result<EDN, ParseError> {
-    let astr = &replace_quote_syntax(astr.trim());
-    parse_all(astr)
-        .or_else(|_| parse_first_valid_expr(astr))
-        .or_else(|e| match e {
-            ParseError::NestingError(_) => return Err(e),
-            ParseError::RegularError(_) => {
-                return parse_symbol(astr);
-            }
-        })
+/// Evaluates `ast` at the top level of `env`.
+pub fn eval(ast: EDN, env: &Environment) -> Result<Value, EvalError> {
+    eval_in(ast, &Scope::new(env))
 }
 
-fn is_truthy(value: &Value) -> bool {
-    match value {
-        Value::EDN(EDN::Nil) => false,
-        Value::EDN(EDN::Bool(false)) => false,
-        _ => true,
+pub fn eval_in(ast: EDN, env: &Scope) -> Result<Value, EvalError> {
+    match eval_tail(ast, env)? {
+        Tail::Return(value) => Ok(value),
+        Tail::Recur(_) => syntax_error("Can only recur from tail position"),
     }
 }
 
-fn is_special_form(form_name: &str) -> bool {
-    let special_forms = ["quote", "if", "def", "fn"];
-    special_forms.contains(&form_name)
+// The outcome of evaluating a form in tail position: a value, or the
+// arguments of a `recur` for the enclosing `loop` or `fn` to rebind.
+enum Tail {
+    Return(Value),
+    Recur(Vec<Value>),
 }
 
-pub fn eval(ast: EDN, env: &Environment) -> Result<Value, String> {
+fn eval_tail(ast: EDN, env: &Scope) -> Result<Tail, EvalError> {
     match ast {
         EDN::List(list) => {
             let l = *list;
 
-            l.first()
-                .ok_or("Empty list".to_string())
-                .and_then(|first| match first {
-                    EDN::Symbol(s) => eval_special_form(&s, &l.rest().to_vec(), env)
-                        .or_else(|_| eval_function_call(&l.to_vec(), env)),
-                    _ => Err("Expected a function symbol".to_string()),
-                })
+            let result = match l.first() {
+                None => Err(EvalError::Syntax("Empty list".to_string())),
+                Some(EDN::Symbol(s, _)) => match eval_special_form(s, &l.rest().to_vec(), env) {
+                    Some(result) => result,
+                    // The expansion takes the call's place, tail position included
+                    None => match env.lookup(s) {
+                        Some(Value::Macro(m)) => {
+                            expand(&m, &l, env).and_then(|form| eval_tail(form, env))
+                        }
+                        Some(func) => eval_function_call(func, &l.to_vec(), env).map(Tail::Return),
+                        None => Err(EvalError::Unbound(s.clone())),
+                    },
+                },
+                Some(_) => Err(EvalError::Type("Expected a function symbol".to_string())),
+            };
+            result.map_err(|e| e.within(&l))
         }
-        EDN::Symbol(ref s) => {
+        EDN::Symbol(ref s, _) => {
             if is_special_form(s) {
-                Ok(Value::EDN(EDN::Symbol(s.clone())))
+                Ok(Tail::Return(Value::EDN(EDN::Symbol(s.clone(), None))))
             } else {
-                let env_read = env.read().unwrap();
-                env_read
-                    .get(s)
-                    .cloned()
-                    .ok_or_else(|| format!("Undefined symbol: {}", s))
+                match env.lookup(s) {
+                    Some(Value::Macro(_)) => Err(EvalError::Type(format!(
+                        "Can't take value of a macro: {}",
+                        s
+                    ))),
+                    Some(value) => Ok(Tail::Return(value)),
+                    None => Err(EvalError::Unbound(s.clone())),
+                }
             }
         }
-        _ => Ok(Value::EDN(ast)),
+        _ => Ok(Tail::Return(Value::EDN(ast))),
     }
 }
 
-fn eval_special_form(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    eval_quote(form, args)
-        .or_else(|_| eval_do(form, args, env))
-        .or_else(|_| eval_if(form, args, env))
-        .or_else(|_| eval_def(form, args, env))
-        .or_else(|_| eval_let(form, args, env))
-        .or_else(|_| eval_fn(form, args, env))
-        .or_else(|e| {
-            println!("boom form={:?} args={:?} ", form, args);
-            Err(format!("Unknown special form: {}", form))
-        })
+const SPECIAL_FORMS: &[&str] = &[
+    "do",
+    "if",
+    "let",
+    "recur",
+    "quote",
+    "def",
+    "defmacro",
+    "ns",
+    "fn",
+    "loop",
+    "doseq",
+    "lazy-seq",
+    "throw",
+    "var",
+    "binding",
+    "with-redefs",
+];
+
+// Returns `None` when `form` is not a special form, so the list is evaluated
+// as a function call. Errors from a special form are returned as they are.
+fn eval_special_form(form: &str, args: &[EDN], env: &Scope) -> Option<Result<Tail, EvalError>> {
+    let value = match form {
+        "do" => return Some(eval_do(args, env)),
+        "if" => return Some(eval_if(args, env)),
+        "let" => return Some(eval_let(args, env)),
+        "recur" => return Some(eval_recur(args, env)),
+        "quote" => eval_quote(args),
+        "def" => eval_def(args, env),
+        "defmacro" => eval_defmacro(args, env),
+        "ns" => namespace::ns_form(env.globals(), args),
+        "fn" => eval_fn(args, env),
+        "loop" => eval_loop(args, env),
+        "doseq" => eval_doseq(args, env),
+        "lazy-seq" => eval_lazy_seq(args, env),
+        "throw" => eval_throw(args, env),
+        "var" => eval_var(args, env),
+        "binding" => eval_binding(args, env),
+        "with-redefs" => eval_with_redefs(args, env),
+        _ => return None,
+    };
+    Some(value.map(Tail::Return))
 }
 
-fn eval_quote(form: &str, args: &[EDN]) -> Result<Value, String> {
-    if form != "quote" {
-        return Err("Not a quote form".to_string());
-    }
+fn syntax_error<T>(msg: &str) -> Result<T, EvalError> {
+    Err(EvalError::Syntax(msg.to_string()))
+}
 
+fn eval_quote(args: &[EDN]) -> Result<Value, EvalError> {
     if args.len() == 1 {
         Ok(Value::EDN(args[0].clone()))
     } else {
-        Err("Incorrect number of arguments for 'quote'".to_string())
+        syntax_error("Incorrect number of arguments for 'quote'")
     }
 }
 
-fn eval_do(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    println!("do-1 form={:?}", form);
-    if form != "do" {
-        println!("do-1.1 form={:?}", form);
-        return Err("Not a do form".to_string());
+fn eval_do(args: &[EDN], env: &Scope) -> Result<Tail, EvalError> {
+    match args.split_last() {
+        None => Ok(Tail::Return(Value::EDN(EDN::Nil))),
+        Some((last, init)) => {
+            for expr in init {
+                eval_in(expr.clone(), env)?;
+            }
+            eval_tail(last.clone(), env)
+        }
     }
-    println!("do-2");
-    args.iter()
-        .try_fold(Value::EDN(EDN::Nil), |_, expr| eval(expr.clone(), env))
 }
 
-fn eval_if(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    if form != "if" {
-        return Err("Not an if form".to_string());
+// Evaluates a body that is not in tail position, like `do` inside `eval_in`.
+fn eval_body(body: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    match eval_do(body, env)? {
+        Tail::Return(value) => Ok(value),
+        Tail::Recur(_) => syntax_error("Can only recur from tail position"),
     }
+}
 
+fn eval_if(args: &[EDN], env: &Scope) -> Result<Tail, EvalError> {
     if args.len() < 2 || args.len() > 3 {
-        return Err("'if' requires 2 or 3 arguments".to_string());
+        return syntax_error("'if' requires 2 or 3 arguments");
     }
 
-    eval(args[0].clone(), env).and_then(|condition| {
+    eval_in(args[0].clone(), env).and_then(|condition| {
         if is_truthy(&condition) {
-            eval(args[1].clone(), env)
+            eval_tail(args[1].clone(), env)
         } else if args.len() == 3 {
-            eval(args[2].clone(), env)
+            eval_tail(args[2].clone(), env)
         } else {
-            Ok(Value::EDN(EDN::Nil))
+            Ok(Tail::Return(Value::EDN(EDN::Nil)))
         }
     })
 }
 
-fn eval_def(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    if form != "def" {
-        return Err("Not a def form".to_string());
+// `(def name value?)`. Without a value the var is left unbound; with
+// `^:dynamic` on the name it can be rebound by `binding`.
+fn eval_def(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let (symbol, meta) = match args {
+        [name] | [name, _] => def_name("def", name)?,
+        _ => return syntax_error("'def' requires a name and at most one value"),
+    };
+    // A qualified name may only name the current namespace
+    let symbol = match symbol.split_once('/') {
+        Some((ns, name)) if !ns.is_empty() && !name.is_empty() => {
+            if ns != env.ns_name() {
+                return syntax_error(&format!(
+                    "Can't create defs outside of current ns: {}",
+                    symbol
+                ));
+            }
+            name.to_string()
+        }
+        _ => symbol,
+    };
+
+    let dynamic = meta.get(&EDN::keyword("dynamic"));
+    let dynamic = !matches!(dynamic, None | Some(EDN::Nil) | Some(EDN::Bool(false)));
+    // Redefining a var without ^:dynamic makes it static again
+    if dynamic || env.ns.is_some() {
+        env.namespace().set_dynamic(&symbol, dynamic);
     }
 
-    if args.len() != 2 {
-        return Err("'def' requires exactly 2 arguments".to_string());
+    match args.get(1) {
+        Some(form) => {
+            let value = eval_in(form.clone(), env)?;
+            Ok(define(env, symbol, value))
+        }
+        None => {
+            env.namespace().declare(env.globals(), &symbol);
+            Ok(Value::Var {
+                ns: env.ns_name().to_string(),
+                name: symbol,
+            })
+        }
     }
+}
 
-    let symbol = match &args[0] {
-        EDN::Symbol(name) => Ok(name.clone()),
-        _ => Err("First argument to 'def' must be a symbol".to_string()),
-    }?;
+// The name a `def`-like form defines, with the metadata the symbol carries,
+// such as `^:dynamic` or `^{:doc ".."}` from the reader.
+fn def_name(
+    form_name: &str,
+    name: &EDN,
+) -> Result<(String, PersistentHashMap<EDN, EDN>), EvalError> {
+    match name {
+        EDN::Symbol(name, meta) => Ok((name.clone(), meta.as_deref().cloned().unwrap_or_default())),
+        _ => syntax_error(&format!(
+            "First argument to '{}' must be a symbol",
+            form_name
+        )),
+    }
+}
 
-    let value = eval(args[1].clone(), env)?;
+fn define(env: &Scope, name: String, value: Value) -> Value {
+    debug!(ns = env.ns_name(), name = %name, value = %Traced(&value), "def");
+    match &env.ns {
+        Some(ns) => ns.intern(env.globals(), name.clone(), value.clone()),
+        None => {
+            let mut env_write = env.globals().write().unwrap();
+            env_write.insert(name.clone(), value.clone());
+        }
+    }
 
-    // Update placeholder with actual value
-    {
-        println!("def value={:?}", value);
-        let mut env_write = env.write().unwrap();
-        env_write.insert(symbol.clone(), value.clone());
+    Value::Var {
+        ns: env.ns_name().to_string(),
+        name,
     }
+}
+
+// `(defmacro name doc? [params] body*)`, or with several arities as for
+// `fn`. The macro fn also receives the call as `&form` and a map of the
+// local names in scope as `&env`.
+fn eval_defmacro(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let (name, rest) = match args.split_first() {
+        Some((name, rest)) => (def_name("defmacro", name)?.0, rest),
+        None => return syntax_error("First argument to 'defmacro' must be a symbol"),
+    };
+    let rest = match rest.first() {
+        Some(EDN::String(_)) => &rest[1..],
+        _ => rest,
+    };
 
-    Ok(Value::Var {
-        ns: "user".to_string(),
-        name: symbol,
-        value: Box::new(value),
+    let implicit = |params: &EDN| match params {
+        EDN::Vector(params) => {
+            let mut with_implicit = PersistentVector::new()
+                .conj(EDN::Symbol("&form".to_string(), None))
+                .conj(EDN::Symbol("&env".to_string(), None));
+            for param in params.iter() {
+                with_implicit = with_implicit.conj(param.clone());
+            }
+            Ok(EDN::Vector(with_implicit))
+        }
+        _ => syntax_error("defmacro parameters must be a vector"),
+    };
+    let mut fn_args = vec![EDN::Symbol(name.clone(), None)];
+    match rest.first() {
+        Some(EDN::Vector(_)) => {
+            fn_args.push(implicit(&rest[0])?);
+            fn_args.extend_from_slice(&rest[1..]);
+        }
+        _ => {
+            for arity in rest {
+                let arity = match arity {
+                    EDN::List(l) => l.to_vec(),
+                    _ => return syntax_error("Each defmacro arity must be a list"),
+                };
+                let (params, body) = match arity.split_first() {
+                    Some(split) => split,
+                    None => return syntax_error("Empty defmacro arity"),
+                };
+                let arity: List<EDN> = std::iter::once(implicit(params)?)
+                    .chain(body.iter().cloned())
+                    .collect();
+                fn_args.push(EDN::List(Box::new(arity)));
+            }
+        }
+    }
+
+    match eval_fn(&fn_args, env)? {
+        Value::Function(f) => Ok(define(env, name, Value::Macro(f))),
+        _ => unreachable!("fn evaluates to a function"),
+    }
+}
+
+// Calls `m` on the unevaluated arguments of `form`. Seqs the macro built
+// with `cons` or `list` become lists, so the expansion evaluates as code.
+fn expand(m: &Callable, form: &List<EDN>, env: &Scope) -> Result<EDN, EvalError> {
+    let locals = env
+        .local_names()
+        .into_iter()
+        .map(|name| (EDN::Symbol(name.to_string(), None), EDN::Nil))
+        .collect();
+    let mut args = vec![
+        Value::EDN(EDN::List(Box::new(form.clone()))),
+        Value::EDN(EDN::Map(locals)),
+    ];
+    args.extend(form.iter().skip(1).cloned().map(Value::EDN));
+    match m.call(args)? {
+        Value::EDN(expansion) => Ok(as_code(expansion)?),
+        other => Err(EvalError::Type(format!(
+            "Macro expanded to a non-EDN value: {}",
+            other
+        ))),
+    }
+}
+
+fn as_code(form: EDN) -> Result<EDN, EvalError> {
+    Ok(match form {
+        EDN::Seq(s) => EDN::List(Box::new(
+            s.iter()
+                .map(|item| as_code(item?))
+                .collect::<Result<List<EDN>, EvalError>>()?,
+        )),
+        EDN::List(l) => {
+            let items = l
+                .iter()
+                .map(|item| as_code(item.clone()))
+                .collect::<Result<List<EDN>, EvalError>>()?;
+            EDN::List(Box::new(items.with_meta(l.meta().cloned())))
+        }
+        EDN::Vector(v) => EDN::Vector(
+            v.iter()
+                .map(|item| as_code(item.clone()))
+                .collect::<Result<PersistentVector<EDN>, EvalError>>()?,
+        ),
+        form => form,
     })
 }
 
-fn eval_let(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    if form != "let" {
-        return Err("Not a let form".to_string());
+// The expansion of `form` if it is a macro call, else `None`.
+fn expand_once(form: &EDN, env: &Scope) -> Result<Option<EDN>, EvalError> {
+    let list = match form {
+        EDN::List(list) => list,
+        _ => return Ok(None),
+    };
+    match list.first() {
+        Some(EDN::Symbol(s, _)) if !SPECIAL_FORMS.contains(&s.as_str()) => match env.lookup(s) {
+            Some(Value::Macro(m)) => expand(&m, list, env).map(Some),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Expands `form` once if it is a macro call; other forms are returned as
+/// they are.
+pub fn macroexpand_1(form: EDN, env: &Scope) -> Result<EDN, EvalError> {
+    Ok(expand_once(&form, env)?.unwrap_or(form))
+}
+
+/// Expands `form` until it is no longer a macro call.
+pub fn macroexpand(mut form: EDN, env: &Scope) -> Result<EDN, EvalError> {
+    while let Some(expansion) = expand_once(&form, env)? {
+        form = expansion;
     }
+    Ok(form)
+}
 
-    if args.len() != 2 {
-        return Err("'let' requires exactly 2 arguments".to_string());
+// The body is an implicit `do`, as for `fn` and `loop`.
+fn eval_let(args: &[EDN], env: &Scope) -> Result<Tail, EvalError> {
+    if args.is_empty() {
+        return syntax_error("'let' requires a binding vector");
     }
 
-    let bindings = match &args[0] {
-        EDN::Vector(bindings) => bindings,
-        _ => return Err("First argument to 'let' must be a vector".to_string()),
+    let bindings = compile_bindings("let", &args[0])?;
+    eval_do(&args[1..], &eval_bindings(&bindings, env)?)
+}
+
+// Compiles a binding vector into patterns and the forms they bind.
+fn compile_bindings(form: &str, bindings: &EDN) -> Result<Vec<(Pattern, EDN)>, EvalError> {
+    let bindings = match bindings {
+        EDN::Vector(bindings) => bindings.to_vec(),
+        _ => return syntax_error(&format!("First argument to '{}' must be a vector", form)),
     };
 
     if bindings.len() % 2 != 0 {
-        return Err("Binding vector requires an even number of forms".to_string());
+        return syntax_error("Binding vector requires an even number of forms");
     }
 
-    let mut new_env = env.read().unwrap().clone();
+    bindings
+        .chunks(2)
+        .map(|chunk| Ok((Pattern::compile(&chunk[0])?, chunk[1].clone())))
+        .collect()
+}
 
-    // Process bindings in pairs
-    for chunk in bindings.chunks(2) {
-        let sym = match &chunk[0] {
-            EDN::Symbol(name) => name.clone(),
-            _ => return Err("Binding target must be a symbol".to_string()),
-        };
+// Each binding gets its own frames, so later values see earlier names.
+fn eval_bindings(bindings: &[(Pattern, EDN)], env: &Scope) -> Result<Scope, EvalError> {
+    bindings
+        .iter()
+        .try_fold(env.clone(), |scope, (pattern, form)| {
+            let value = eval_in(form.clone(), &scope)?;
+            pattern.bind(value, &scope)
+        })
+}
+
+fn bind_all<'a>(
+    patterns: impl IntoIterator<Item = &'a Pattern>,
+    values: Vec<Value>,
+    env: &Scope,
+) -> Result<Scope, EvalError> {
+    patterns
+        .into_iter()
+        .zip(values)
+        .try_fold(env.clone(), |scope, (pattern, value)| {
+            pattern.bind(value, &scope)
+        })
+}
 
-        let val = eval(
-            chunk[1].clone(),
-            &Environment::new(RwLock::new(new_env.clone())),
-        )?;
-        new_env.insert(sym, val);
+// The body is evaluated in a loop: each `recur` rebinds the loop patterns
+// over `env` and runs it again, so iteration takes no stack.
+fn eval_loop(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    if args.is_empty() {
+        return syntax_error("'loop' requires a binding vector");
     }
+    let bindings = compile_bindings("loop", &args[0])?;
+    let body = &args[1..];
+    check_body(body, Some(bindings.len()), env)?;
+
+    let mut scope = eval_bindings(&bindings, env)?;
+    loop {
+        match eval_do(body, &scope)? {
+            Tail::Return(value) => return Ok(value),
+            Tail::Recur(values) => {
+                let values = recur_args(values, bindings.len())?;
+                scope = bind_all(bindings.iter().map(|(pattern, _)| pattern), values, env)?;
+            }
+        }
+    }
+}
+
+// Runs the body for every item, with each later binding nested inside the
+// ones before it.
+fn eval_doseq(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let (bindings, body) = match args.split_first() {
+        Some(split) => split,
+        None => return syntax_error("'doseq' requires a binding vector"),
+    };
+    let bindings = compile_bindings("doseq", bindings)?;
+    check_body(body, None, env)?;
+
+    doseq(&bindings, body, env)?;
+    Ok(Value::EDN(EDN::Nil))
+}
+
+fn doseq(bindings: &[(Pattern, EDN)], body: &[EDN], env: &Scope) -> Result<(), EvalError> {
+    let ((pattern, coll), rest) = match bindings.split_first() {
+        Some(split) => split,
+        None => {
+            return body
+                .iter()
+                .try_for_each(|form| eval_in(form.clone(), env).map(drop))
+        }
+    };
+    let coll = match eval_in(coll.clone(), env)? {
+        Value::EDN(coll) => coll,
+        other => {
+            return Err(EvalError::Type(format!(
+                "Don't know how to create a seq from {}",
+                other
+            )))
+        }
+    };
+    for item in seq::to_seq(&coll)?.iter() {
+        doseq(rest, body, &pattern.bind(Value::EDN(item?), env)?)?;
+    }
+    Ok(())
+}
+
+fn eval_recur(args: &[EDN], env: &Scope) -> Result<Tail, EvalError> {
+    args.iter()
+        .map(|arg| eval_in(arg.clone(), env))
+        .collect::<Result<Vec<Value>, EvalError>>()
+        .map(Tail::Recur)
+}
 
-    // Evaluate body in new environment
-    eval(args[1].clone(), &Environment::new(RwLock::new(new_env)))
+fn recur_args(values: Vec<Value>, expected: usize) -> Result<Vec<Value>, EvalError> {
+    if values.len() == expected {
+        Ok(values)
+    } else {
+        Err(EvalError::Arity {
+            name: "recur".to_string(),
+            expected: expected.to_string(),
+            got: values.len(),
+        })
+    }
 }
 
-fn eval_fn(form: &str, args: &[EDN], env: &Environment) -> Result<Value, String> {
-    if form != "fn" {
-        return Err("Not a fn form".to_string());
+// Checks a body whose last form is in tail position when `tail` is.
+fn check_body(body: &[EDN], tail: Option<usize>, env: &Scope) -> Result<(), EvalError> {
+    match body.split_last() {
+        Some((last, init)) => {
+            for form in init {
+                check_recur(form, None, env)?;
+            }
+            check_recur(last, tail, env)
+        }
+        None => Ok(()),
     }
+}
 
-    if args.len() != 2 {
-        return Err("'fn' requires exactly 2 arguments".to_string());
+// Rejects a `recur` anywhere but in tail position of `form`, or with other
+// than the number of values its target binds. `tail` is that number when
+// `form` itself is in tail position of the enclosing loop or fn, and `None`
+// when it is not. Macro calls are checked by their expansion.
+fn check_recur(form: &EDN, tail: Option<usize>, env: &Scope) -> Result<(), EvalError> {
+    let list = match form {
+        EDN::List(list) => list.to_vec(),
+        _ => return Ok(()),
+    };
+    let head = match list.first() {
+        Some(EDN::Symbol(s, _)) => s.as_str(),
+        _ => "",
+    };
+    let args = list.get(1..).unwrap_or_default();
+    let all = |forms: &[EDN], tail: Option<usize>| {
+        forms.iter().try_for_each(|f| check_recur(f, tail, env))
+    };
+    let binding_values = |bindings: Option<&EDN>| match bindings {
+        Some(EDN::Vector(v)) => v
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .try_for_each(|f| check_recur(f, None, env)),
+        _ => Ok(()),
+    };
+    match head {
+        "quote" => Ok(()),
+        "recur" => match tail {
+            None => syntax_error("Can only recur from tail position"),
+            Some(expected) if args.len() != expected => Err(EvalError::Arity {
+                name: "recur".to_string(),
+                expected: expected.to_string(),
+                got: args.len(),
+            }),
+            Some(_) => all(args, None),
+        },
+        "if" => {
+            all(args.get(..1).unwrap_or_default(), None)?;
+            all(args.get(1..).unwrap_or_default(), tail)
+        }
+        "do" => check_body(args, tail, env),
+        "let" => {
+            binding_values(args.first())?;
+            check_body(args.get(1..).unwrap_or_default(), tail, env)
+        }
+        // These start a new recur target; they are checked when evaluated
+        "loop" => binding_values(args.first()),
+        "fn" | "defmacro" => Ok(()),
+        "doseq" => {
+            binding_values(args.first())?;
+            all(args.get(1..).unwrap_or_default(), None)
+        }
+        _ => match expand_once(form, env)? {
+            Some(expansion) => check_recur(&expansion, tail, env),
+            None => all(args, None),
+        },
     }
+}
+
+// Accepts `(fn name? [params] body*)` and `(fn name? ([params] body*)+)`.
+fn eval_fn(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let (name, args) = match args.split_first() {
+        Some((EDN::Symbol(name, _), rest)) => (Some(name.clone()), rest),
+        _ => (None, args),
+    };
 
-    let params = match &args[0] {
-        EDN::Vector(param_list) => param_list
+    let arities = match args.first() {
+        Some(EDN::Vector(_)) => vec![parse_arity(&args[0], &args[1..])?],
+        Some(EDN::List(_)) => args
             .iter()
-            .map(|param| match param {
-                EDN::Symbol(name) => Ok(Value::EDN(EDN::Symbol(name.clone()))),
-                _ => Err("Parameters must be symbols".to_string()),
+            .map(|arity| match arity {
+                EDN::List(l) => match l.to_vec().split_first() {
+                    Some((params, body)) => parse_arity(params, body),
+                    None => syntax_error("Empty fn arity"),
+                },
+                _ => syntax_error("Each fn arity must be a list"),
             })
-            .collect::<Result<Vec<Value>, String>>()?,
-        _ => return Err("First argument to 'fn' must be a vector".to_string()),
+            .collect::<Result<Vec<Arity>, EvalError>>()?,
+        _ => return syntax_error("'fn' requires a parameter vector"),
     };
 
+    let mut variadic = arities.iter().filter(|arity| arity.rest.is_some());
+    let required = variadic.next().map(|arity| arity.params.len());
+    if variadic.next().is_some() {
+        return syntax_error("Can't have more than 1 variadic overload");
+    }
+    for (i, arity) in arities.iter().enumerate().filter(|(_, a)| a.rest.is_none()) {
+        let argc = arity.params.len();
+        if required.is_some_and(|required| argc > required) {
+            return syntax_error(
+                "Can't have fixed arity function with more params than variadic function",
+            );
+        }
+        if arities[..i]
+            .iter()
+            .any(|a| a.rest.is_none() && a.params.len() == argc)
+        {
+            return syntax_error("Can't have 2 overloads with same arity");
+        }
+    }
+
+    for arity in &arities {
+        check_body(&arity.body, Some(arity.width()), env)?;
+    }
+
     Ok(Value::Function(Callable::Lambda {
-        params,
-        body: args[1].clone(),
-        closure: env.read().unwrap().clone(),
+        name,
+        arities: arities.into(),
+        closure: env.clone(),
     }))
 }
 
-fn eval_function_call(list: &[EDN], env: &Environment) -> Result<Value, String> {
-    // Evaluate first element to get the function
-    let l0 = list[0].clone();
-    let l1 = list[0].clone();
-    let func = eval(l0, env)?;
-    println!("l1={:?} func={:?} list={:?}", l1, func, list);
-    println!("env={:?}", env);
+fn parse_arity(params: &EDN, body: &[EDN]) -> Result<Arity, EvalError> {
+    let params = match params {
+        EDN::Vector(params) => params.to_vec(),
+        _ => return syntax_error("fn parameters must be a vector"),
+    };
+
+    let ampersand = EDN::Symbol("&".to_string(), None);
+    let (params, rest) = match params.iter().position(|param| *param == ampersand) {
+        None => (&params[..], None),
+        Some(i) => match &params[i + 1..] {
+            [rest] => (&params[..i], Some(Pattern::compile(rest)?)),
+            _ => return syntax_error("'&' must be followed by exactly one parameter"),
+        },
+    };
+
+    Ok(Arity {
+        params: params
+            .iter()
+            .map(Pattern::compile)
+            .collect::<Result<Vec<Pattern>, EvalError>>()?,
+        rest,
+        body: body.to_vec(),
+    })
+}
+
+// The body runs the first time the seq is walked, in the environment the
+// form was evaluated in. A seq result is kept as is, so it can stay lazy.
+fn eval_lazy_seq(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let body = args.to_vec();
+    let env = env.clone();
+    Ok(Value::EDN(EDN::Seq(Seq::lazy(move || {
+        let result = body
+            .into_iter()
+            .try_fold(Value::EDN(EDN::Nil), |_, expr| eval_in(expr, &env))?;
+        match result {
+            Value::EDN(EDN::Seq(s)) => Ok(s),
+            Value::EDN(coll) => Ok(coll.seq()?.unwrap_or_else(Seq::empty)),
+            _ => Err(EvalError::Type(
+                "lazy-seq body must return a collection".to_string(),
+            )),
+        }
+    }))))
+}
+
+fn eval_throw(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    match args {
+        [expr] => Err(EvalError::Thrown(eval_in(expr.clone(), env)?)),
+        _ => syntax_error("'throw' requires exactly 1 argument"),
+    }
+}
+
+// `(var name)`, read from `#'name`
+fn eval_var(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    match args {
+        [name] => Ok(var_named(name, env)?.to_value()),
+        _ => syntax_error("'var' requires exactly 1 argument"),
+    }
+}
+
+fn var_named(name: &EDN, env: &Scope) -> Result<Var, EvalError> {
+    match name {
+        EDN::Symbol(s, _) => env.resolve_var(s).ok_or_else(|| {
+            EvalError::Syntax(format!("Unable to resolve var: {} in this context", s))
+        }),
+        _ => syntax_error(&format!("Expected a var name, got {}", name)),
+    }
+}
+
+// Resolves the vars of a `[name value*]` vector and evaluates their values.
+fn var_bindings(
+    form: &str,
+    bindings: Option<&EDN>,
+    env: &Scope,
+) -> Result<Vec<(Var, Value)>, EvalError> {
+    let bindings = match bindings {
+        Some(EDN::Vector(v)) if v.len() % 2 == 0 => v.to_vec(),
+        _ => {
+            return syntax_error(&format!(
+                "'{}' requires a vector with an even number of forms",
+                form
+            ))
+        }
+    };
+    bindings
+        .chunks(2)
+        .map(|pair| Ok((var_named(&pair[0], env)?, eval_in(pair[1].clone(), env)?)))
+        .collect()
+}
+
+// `(binding [name value*] body*)` rebinds dynamic vars on this thread while
+// the body runs.
+fn eval_binding(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let frame = var_bindings("binding", args.first(), env)?;
+    if let Some((var, _)) = frame.iter().find(|(var, _)| !var.is_dynamic()) {
+        return Err(EvalError::Native(format!(
+            "Can't dynamically bind non-dynamic var: {}",
+            var
+        )));
+    }
+    let _bindings = var::push_thread_bindings(frame);
+    eval_body(&args[1..], env)
+}
+
+// `(with-redefs [name value*] body*)` replaces the roots of the vars, for
+// every thread, while the body runs.
+fn eval_with_redefs(args: &[EDN], env: &Scope) -> Result<Value, EvalError> {
+    let redefs = var_bindings("with-redefs", args.first(), env)?;
+    let globals = env.globals();
+    let saved: Vec<(Var, Option<Value>)> = redefs
+        .iter()
+        .map(|(var, _)| (var.clone(), var.root(globals)))
+        .collect();
+    for (var, value) in redefs {
+        var.set_root(globals, value);
+    }
+    let result = eval_body(&args[1..], env);
+    for (var, root) in saved.into_iter().rev() {
+        match root {
+            Some(root) => var.set_root(globals, root),
+            None => var.unbind_root(globals),
+        }
+    }
+    result
+}
+
+fn eval_function_call(func: Value, list: &[EDN], env: &Scope) -> Result<Value, EvalError> {
     match func {
+        // Calling a var calls its current value
+        Value::Var { .. } => {
+            let var = Var::of(env.globals(), &func)?;
+            match var.get(env.globals()) {
+                Some(value) => eval_function_call(value, list, env),
+                None => Err(EvalError::Native(format!(
+                    "Attempting to call unbound fn: #'{}",
+                    var
+                ))),
+            }
+        }
         Value::Function(f) => {
             // Evaluate all arguments
-            let args: Result<Vec<Value>, String> =
-                list[1..].iter().map(|arg| eval(arg.clone(), env)).collect();
-            let args = args?;
+            let args = list[1..]
+                .iter()
+                .map(|arg| eval_in(arg.clone(), env))
+                .collect::<Result<Vec<Value>, EvalError>>()?;
+
+            let span = debug_span!("call", name = %list[0], args = %Args(&args));
+            let _entered = span.enter();
+            let result = f.call(args);
+            match &result {
+                Ok(value) => debug!(result = %Traced(value)),
+                Err(e) => debug!(error = %e),
+            }
+            result
+        }
+        _ => Err(EvalError::Type(format!("{} is not a function", list[0]))),
+    }
+}
+
+// Formats call arguments for a trace span only when the span is recorded.
+struct Args<'a>(&'a [Value]);
 
-            f.call(args)
+impl fmt::Display for Args<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, arg) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", Traced(arg))?;
         }
-        _ => {
-            //println!("call {:?} env={:?}", list, env);
-            // let backtrace = Backtrace::force_capture();
-            // println!("Stack trace:\n{}", backtrace);
+        Ok(())
+    }
+}
+
+// Formats a value for the trace without realizing lazy seqs, which may be
+// infinite or depend on the order of evaluation being traced.
+struct Traced<'a>(&'a Value);
 
-            Err("First element is not a function".to_string())
+impl fmt::Display for Traced<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Value::EDN(edn) => write!(f, "{}", edn.preview()),
+            other => write!(f, "{}", other),
         }
     }
 }
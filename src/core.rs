@@ -1,105 +1,119 @@
 use crate::clojure::*;
 use crate::edn::*;
+use crate::immutant::list::List;
+use crate::immutant::map::PersistentHashMap;
+use crate::immutant::rrb::RrbVector;
+use crate::immutant::set::PersistentHashSet;
+use crate::immutant::sorted::{Comparator, PersistentTreeMap, PersistentTreeSet};
+use crate::immutant::vector::PersistentVector;
+use crate::schema::{Schema, SchemaError};
+use crate::seq::{self, ISeq, Seq, Seqable};
+use crate::zip::{Kind, Loc};
 
 use bigdecimal::BigDecimal;
-use num_bigint::BigInt;
+use num_bigint::{BigInt, Sign};
+use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
 
-pub fn add(args: Vec<Value>) -> Result<Value, String> {
-    let mut sum = BigDecimal::from(0);
+// Arithmetic stays integral while every operand is an integer, and gives
+// a float once any operand is one, so (+ 1 1) is 2 and (+ 1 1.0) is 2.0.
+fn number(arg: Value, op: &str) -> Result<EDN, EvalError> {
+    match arg {
+        Value::EDN(n @ (EDN::Integer(_) | EDN::Float(_))) => Ok(n),
+        _ => Err(format!("Arguments to {} must be numbers", op).into()),
+    }
+}
 
+fn decimal(n: EDN) -> BigDecimal {
+    match n {
+        EDN::Integer(i) => BigDecimal::from(i),
+        EDN::Float(f) => f,
+        _ => unreachable!("not a number: {}", n),
+    }
+}
+
+fn combine(
+    a: EDN,
+    b: EDN,
+    on_integers: fn(BigInt, BigInt) -> BigInt,
+    on_floats: fn(BigDecimal, BigDecimal) -> BigDecimal,
+) -> EDN {
+    match (a, b) {
+        (EDN::Integer(a), EDN::Integer(b)) => EDN::Integer(on_integers(a, b)),
+        (a, b) => EDN::Float(on_floats(decimal(a), decimal(b))),
+    }
+}
+
+pub fn add(args: Vec<Value>) -> Result<Value, EvalError> {
+    let mut sum = EDN::Integer(BigInt::from(0));
     for arg in args {
-        match arg {
-            Value::EDN(EDN::Integer(i)) => {
-                sum += BigDecimal::from(i);
-            }
-            Value::EDN(EDN::Float(f)) => {
-                sum += f;
-            }
-            _ => return Err("Arguments to + must be numbers".to_string()),
-        }
+        sum = combine(sum, number(arg, "+")?, |a, b| a + b, |a, b| a + b);
     }
-    Ok(Value::EDN(EDN::Float(sum)))
+    Ok(Value::EDN(sum))
 }
 
-pub fn subtract(args: Vec<Value>) -> Result<Value, String> {
+pub fn subtract(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.is_empty() {
-        return Err("Subtract requires at least one argument".to_string());
+        return Err("Subtract requires at least one argument".into());
     }
 
+    // (- x) negates x
     let mut iter = args.into_iter();
-    let first = match iter.next().unwrap() {
-        Value::EDN(EDN::Integer(i)) => BigDecimal::from(i),
-        Value::EDN(EDN::Float(f)) => f,
-        _ => return Err("Arguments to - must be numbers".to_string()),
+    let mut result = match iter.len() {
+        1 => EDN::Integer(BigInt::from(0)),
+        _ => number(iter.next().unwrap(), "-")?,
     };
-
-    let result = iter.fold(first, |acc, arg| match arg {
-        Value::EDN(EDN::Integer(i)) => acc - BigDecimal::from(i),
-        Value::EDN(EDN::Float(f)) => acc - f,
-        _ => acc,
-    });
-
-    Ok(Value::EDN(EDN::Float(result)))
+    for arg in iter {
+        result = combine(result, number(arg, "-")?, |a, b| a - b, |a, b| a - b);
+    }
+    Ok(Value::EDN(result))
 }
 
-pub fn multiply(args: Vec<Value>) -> Result<Value, String> {
-    let mut product = BigDecimal::from(1);
-
+pub fn multiply(args: Vec<Value>) -> Result<Value, EvalError> {
+    let mut product = EDN::Integer(BigInt::from(1));
     for arg in args {
-        match arg {
-            Value::EDN(EDN::Integer(i)) => {
-                product *= BigDecimal::from(i);
-            }
-            Value::EDN(EDN::Float(f)) => {
-                product *= f;
-            }
-            _ => return Err("Arguments to * must be numbers".to_string()),
-        }
+        product = combine(product, number(arg, "*")?, |a, b| a * b, |a, b| a * b);
     }
-    Ok(Value::EDN(EDN::Float(product)))
+    Ok(Value::EDN(product))
 }
 
-pub fn divide(args: Vec<Value>) -> Result<Value, String> {
+/// Integers that divide exactly give an integer; otherwise the quotient
+/// is a float.
+pub fn divide(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.is_empty() {
-        return Err("Divide requires at least one argument".to_string());
+        return Err("Divide requires at least one argument".into());
     }
 
+    // (/ x) is the reciprocal of x
     let mut iter = args.into_iter();
-    let first = match iter.next().unwrap() {
-        Value::EDN(EDN::Integer(i)) => BigDecimal::from(BigInt::from(i)), // Convert to BigInt first
-        Value::EDN(EDN::Float(f)) => f,
-        _ => return Err("Arguments to / must be numbers".to_string()),
+    let mut result = match iter.len() {
+        1 => EDN::Integer(BigInt::from(1)),
+        _ => number(iter.next().unwrap(), "/")?,
     };
-
-    let result = iter.try_fold(first, |acc, arg| match arg {
-        Value::EDN(EDN::Integer(i)) => {
-            let divisor = BigDecimal::from(BigInt::from(i)); // Convert to BigInt before division
-            if divisor == BigDecimal::from(0) {
-                Err("Division by zero".to_string())
-            } else {
-                Ok(acc / divisor)
+    for arg in iter {
+        let divisor = number(arg, "/")?;
+        result = match (result, divisor) {
+            (_, EDN::Integer(d)) if d.sign() == Sign::NoSign => {
+                return Err("Division by zero".into())
             }
-        }
-        Value::EDN(EDN::Float(f)) => {
-            if f == BigDecimal::from(0) {
-                Err("Division by zero".to_string())
-            } else {
-                Ok(acc / f)
+            (_, EDN::Float(d)) if d == BigDecimal::from(0) => return Err("Division by zero".into()),
+            (EDN::Integer(a), EDN::Integer(d)) if (&a % &d).sign() == Sign::NoSign => {
+                EDN::Integer(a / d)
             }
-        }
-        _ => Err("Arguments to / must be numbers".to_string()),
-    })?;
-
-    Ok(Value::EDN(EDN::Float(result)))
+            (a, d) => EDN::Float(decimal(a) / decimal(d)),
+        };
+    }
+    Ok(Value::EDN(result))
 }
 
-pub fn println_fn(args: Vec<Value>) -> Result<Value, String> {
+pub fn println_fn(args: Vec<Value>) -> Result<Value, EvalError> {
+    args.iter().try_for_each(Value::force)?;
     let strings: Vec<String> = args.iter().map(|arg| format!("{}", arg)).collect();
     println!("{}", strings.join(" "));
     Ok(Value::EDN(EDN::Nil))
@@ -113,22 +127,20 @@ pub fn slurp(path: &str) -> Result<String, String> {
     Ok(content)
 }
 
-pub fn slurp_wrapper(args: Vec<Value>) -> Result<Value, String> {
+pub fn slurp_wrapper(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() != 1 {
-        return Err("slurp requires exactly one argument".to_string());
+        return Err("slurp requires exactly one argument".into());
     }
 
     match &args[0] {
-        Value::EDN(EDN::String(path)) => {
-            slurp(path).map(|content| Value::EDN(EDN::String(content)))
-        }
-        _ => Err("slurp argument must be a string representing a file path".to_string()),
+        Value::EDN(EDN::String(path)) => Ok(Value::EDN(EDN::String(slurp(path)?))),
+        _ => Err("slurp argument must be a string representing a file path".into()),
     }
 }
 
 pub fn register_native_fn<F>(env: &mut HashMap<String, Value>, name: &str, f: F)
 where
-    F: Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    F: Fn(Vec<Value>) -> Result<Value, EvalError> + Send + Sync + 'static,
 {
     env.insert(
         name.to_string(),
@@ -142,29 +154,49 @@ fn hash_value(value: &Value) -> u64 {
     let mut hasher = DefaultHasher::new();
     match value {
         Value::EDN(edn) => edn.hash(&mut hasher), // Use EDN's Hash implementation
-        Value::Var { ns, name, value } => {
+        Value::Var { ns, name, .. } => {
             ns.hash(&mut hasher);
             name.hash(&mut hasher);
-            hash_value(value).hash(&mut hasher);
         }
         Value::Function(_) => {
             // Functions cannot be compared for equality meaningfully
             hasher.write_u8(255);
         }
+        Value::Macro(_) => hasher.write_u8(253),
+        Value::Namespace(ns) => {
+            hasher.write_u8(252);
+            ns.name().hash(&mut hasher);
+        }
+        Value::Args(args) => {
+            hasher.write_u8(251);
+            for arg in args.iter() {
+                hasher.write_u64(hash_value(arg));
+            }
+        }
+        Value::Transient(t) => {
+            // Transients are equal only to themselves
+            hasher.write_u8(254);
+            hasher.write_usize(t.id());
+        }
     }
     hasher.finish()
 }
 
 /// Generalized equality function using hash comparison
-pub fn equal(args: Vec<Value>) -> Result<Value, String> {
+pub fn equal(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() < 2 {
-        return Err("equal requires at least two arguments".to_string());
+        return Err("equal requires at least two arguments".into());
     }
+    args.iter().try_for_each(Value::force)?;
 
     let first_hash = hash_value(&args[0]);
 
     for arg in &args[1..] {
-        if hash_value(arg) != first_hash {
+        let same = match (&args[0], arg) {
+            (Value::EDN(a), Value::EDN(b)) => a == b,
+            _ => hash_value(arg) == first_hash,
+        };
+        if !same {
             return Ok(Value::EDN(EDN::Bool(false)));
         }
     }
@@ -172,16 +204,16 @@ pub fn equal(args: Vec<Value>) -> Result<Value, String> {
     Ok(Value::EDN(EDN::Bool(true)))
 }
 
-pub fn less_than(args: Vec<Value>) -> Result<Value, String> {
+pub fn less_than(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() < 2 {
-        return Err("< requires at least two arguments".to_string());
+        return Err("< requires at least two arguments".into());
     }
 
     // Convert first argument to BigDecimal
     let first = match &args[0] {
         Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
         Value::EDN(EDN::Float(f)) => f.clone(),
-        _ => return Err("Arguments to < must be numbers".to_string()),
+        _ => return Err("Arguments to < must be numbers".into()),
     };
 
     // Compare each pair of adjacent numbers
@@ -190,7 +222,7 @@ pub fn less_than(args: Vec<Value>) -> Result<Value, String> {
         let curr = match arg {
             Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
             Value::EDN(EDN::Float(f)) => f.clone(),
-            _ => return Err("Arguments to < must be numbers".to_string()),
+            _ => return Err("Arguments to < must be numbers".into()),
         };
 
         if prev >= curr {
@@ -202,16 +234,16 @@ pub fn less_than(args: Vec<Value>) -> Result<Value, String> {
     Ok(Value::EDN(EDN::Bool(true)))
 }
 
-pub fn less_than_equal(args: Vec<Value>) -> Result<Value, String> {
+pub fn less_than_equal(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() < 2 {
-        return Err("<= requires at least two arguments".to_string());
+        return Err("<= requires at least two arguments".into());
     }
 
     // Convert first argument to BigDecimal
     let first = match &args[0] {
         Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
         Value::EDN(EDN::Float(f)) => f.clone(),
-        _ => return Err("Arguments to <= must be numbers".to_string()),
+        _ => return Err("Arguments to <= must be numbers".into()),
     };
 
     // Compare each pair of adjacent numbers
@@ -220,7 +252,7 @@ pub fn less_than_equal(args: Vec<Value>) -> Result<Value, String> {
         let curr = match arg {
             Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
             Value::EDN(EDN::Float(f)) => f.clone(),
-            _ => return Err("Arguments to <= must be numbers".to_string()),
+            _ => return Err("Arguments to <= must be numbers".into()),
         };
 
         if prev > curr {
@@ -232,16 +264,16 @@ pub fn less_than_equal(args: Vec<Value>) -> Result<Value, String> {
     Ok(Value::EDN(EDN::Bool(true)))
 }
 
-pub fn greater_than(args: Vec<Value>) -> Result<Value, String> {
+pub fn greater_than(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() < 2 {
-        return Err("> requires at least two arguments".to_string());
+        return Err("> requires at least two arguments".into());
     }
 
     // Convert first argument to BigDecimal
     let first = match &args[0] {
         Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
         Value::EDN(EDN::Float(f)) => f.clone(),
-        _ => return Err("Arguments to > must be numbers".to_string()),
+        _ => return Err("Arguments to > must be numbers".into()),
     };
 
     // Compare each pair of adjacent numbers
@@ -250,10 +282,10 @@ pub fn greater_than(args: Vec<Value>) -> Result<Value, String> {
         let curr = match arg {
             Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
             Value::EDN(EDN::Float(f)) => f.clone(),
-            _ => return Err("Arguments to > must be numbers".to_string()),
+            _ => return Err("Arguments to > must be numbers".into()),
         };
 
-        if prev < curr {
+        if prev <= curr {
             return Ok(Value::EDN(EDN::Bool(false)));
         }
         prev = curr;
@@ -262,16 +294,16 @@ pub fn greater_than(args: Vec<Value>) -> Result<Value, String> {
     Ok(Value::EDN(EDN::Bool(true)))
 }
 
-pub fn greater_than_equal(args: Vec<Value>) -> Result<Value, String> {
+pub fn greater_than_equal(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() < 2 {
-        return Err(">= requires at least two arguments".to_string());
+        return Err(">= requires at least two arguments".into());
     }
 
     // Convert first argument to BigDecimal
     let first = match &args[0] {
         Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
         Value::EDN(EDN::Float(f)) => f.clone(),
-        _ => return Err("Arguments to >= must be numbers".to_string()),
+        _ => return Err("Arguments to >= must be numbers".into()),
     };
 
     // Compare each pair of adjacent numbers
@@ -280,7 +312,7 @@ pub fn greater_than_equal(args: Vec<Value>) -> Result<Value, String> {
         let curr = match arg {
             Value::EDN(EDN::Integer(i)) => BigDecimal::from(i.clone()),
             Value::EDN(EDN::Float(f)) => f.clone(),
-            _ => return Err("Arguments to >= must be numbers".to_string()),
+            _ => return Err("Arguments to >= must be numbers".into()),
         };
 
         if prev < curr {
@@ -291,3 +323,1159 @@ pub fn greater_than_equal(args: Vec<Value>) -> Result<Value, String> {
 
     Ok(Value::EDN(EDN::Bool(true)))
 }
+
+fn edn_args<'a>(name: &str, args: &'a [Value]) -> Result<Vec<&'a EDN>, EvalError> {
+    args.iter()
+        .map(|arg| match arg {
+            Value::EDN(edn) => Ok(edn),
+            _ => Err(format!("{} does not accept functions as arguments", name).into()),
+        })
+        .collect()
+}
+
+pub fn hash_map(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("hash-map", &args)?;
+    if items.len() % 2 != 0 {
+        return Err("hash-map requires an even number of arguments".into());
+    }
+    items
+        .iter()
+        .step_by(2)
+        .try_for_each(|key| seq::force(key))?;
+    let map: PersistentHashMap<EDN, EDN> = items
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    Ok(Value::EDN(EDN::Map(map)))
+}
+
+pub fn assoc(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("assoc", &args)?;
+    match items.split_first() {
+        Some((coll, pairs)) if !pairs.is_empty() && pairs.len() % 2 == 0 => pairs
+            .chunks(2)
+            .try_fold((*coll).clone(), |acc, pair| {
+                acc.assoc(pair[0].clone(), pair[1].clone())
+            })
+            .map(Value::EDN),
+        _ => Err("assoc requires a collection and key/value pairs".into()),
+    }
+}
+
+pub fn dissoc(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("dissoc", &args)?;
+    match items.split_first() {
+        Some((coll, keys)) => keys
+            .iter()
+            .try_fold((*coll).clone(), |acc, key| acc.dissoc(key))
+            .map(Value::EDN),
+        None => Err("dissoc requires a map".into()),
+    }
+}
+
+/// Merges maps left to right, later keys winning. `nil` arguments are
+/// skipped, and merging nothing but `nil` is `nil`.
+pub fn merge(args: Vec<Value>) -> Result<Value, EvalError> {
+    let mut result: Option<PersistentHashMap<EDN, EDN>> = None;
+    for item in edn_args("merge", &args)? {
+        match (item, &result) {
+            (EDN::Nil, _) => {}
+            (EDN::Map(m), None) => result = Some(m.clone()),
+            (EDN::Map(m), Some(acc)) => result = Some(acc.merge(m)),
+            _ => return Err(format!("merge requires maps, got {}", item).into()),
+        }
+    }
+    Ok(Value::EDN(result.map_or(EDN::Nil, EDN::Map)))
+}
+
+pub fn get(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("get", &args)?[..] {
+        [coll, key] => Ok(Value::EDN(coll.lookup(key)?.cloned().unwrap_or(EDN::Nil))),
+        [coll, key, default] => Ok(Value::EDN(coll.lookup(key)?.unwrap_or(default).clone())),
+        _ => Err("get requires a collection, a key and an optional default".into()),
+    }
+}
+
+pub fn contains(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("contains?", &args)?[..] {
+        [coll, key] => Ok(Value::EDN(EDN::Bool(coll.lookup(key)?.is_some()))),
+        _ => Err("contains? requires a collection and a key".into()),
+    }
+}
+
+// Takes the single collection argument of a seq native as a seq.
+fn seq_arg(name: &str, args: &[Value]) -> Result<Option<Seq>, EvalError> {
+    match edn_args(name, args)?[..] {
+        [coll] => coll.seq(),
+        _ => Err(format!("{} requires exactly 1 argument", name).into()),
+    }
+}
+
+// The rest arguments a variadic fn got as `Value::Args`, which the seq
+// functions below walk directly.
+fn args_arg(args: &[Value]) -> Option<&List<Value>> {
+    match args {
+        [Value::Args(args)] => Some(args),
+        _ => None,
+    }
+}
+
+pub fn seq(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return Ok(match rest.is_empty() {
+            true => Value::EDN(EDN::Nil),
+            false => Value::Args(rest.clone()),
+        });
+    }
+    Ok(Value::EDN(
+        seq_arg("seq", &args)?.map_or(EDN::Nil, EDN::Seq),
+    ))
+}
+
+pub fn first(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return Ok(rest.first().cloned().unwrap_or(Value::EDN(EDN::Nil)));
+    }
+    seq_arg("first", &args)?
+        .map_or(Ok(EDN::Nil), |s| s.first())
+        .map(Value::EDN)
+}
+
+pub fn rest(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return Ok(Value::Args(rest.rest()));
+    }
+    seq_arg("rest", &args)?
+        .map_or(Ok(Seq::empty()), |s| s.rest())
+        .map(|s| Value::EDN(EDN::Seq(s)))
+}
+
+pub fn next(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return seq(vec![Value::Args(rest.rest())]);
+    }
+    let next = match seq_arg("next", &args)? {
+        Some(s) => s.next()?,
+        None => None,
+    };
+    Ok(Value::EDN(next.map_or(EDN::Nil, EDN::Seq)))
+}
+
+pub fn cons(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [item, Value::Args(rest)] => return Ok(Value::Args(rest.cons(item.clone()))),
+        [item, Value::EDN(EDN::Nil)] if !matches!(item, Value::EDN(_)) => {
+            return Ok(Value::list(vec![item.clone()]))
+        }
+        _ => {}
+    }
+    match edn_args("cons", &args)?[..] {
+        [item, coll] => {
+            let tail = seq::to_seq(coll)?;
+            Ok(Value::EDN(EDN::Seq(tail.cons(item.clone()))))
+        }
+        _ => Err("cons requires exactly 2 arguments".into()),
+    }
+}
+
+pub fn list(args: Vec<Value>) -> Result<Value, EvalError> {
+    Ok(Value::list(args))
+}
+
+pub fn concat(args: Vec<Value>) -> Result<Value, EvalError> {
+    let colls = edn_args("concat", &args)?
+        .into_iter()
+        .map(seq::to_seq)
+        .collect::<Result<Vec<Seq>, EvalError>>()?;
+    lazy(
+        colls
+            .into_iter()
+            .rev()
+            .fold(Seq::empty(), |tail, coll| seq::concat(coll, tail)),
+    )
+}
+
+pub fn is_var(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [x] => Ok(Value::EDN(EDN::Bool(matches!(x, Value::Var { .. })))),
+        _ => Err("var? requires exactly 1 argument".into()),
+    }
+}
+
+pub fn is_seq(args: Vec<Value>) -> Result<Value, EvalError> {
+    if args_arg(&args).is_some() {
+        return Ok(Value::EDN(EDN::Bool(true)));
+    }
+    match edn_args("seq?", &args)?[..] {
+        [x] => Ok(Value::EDN(EDN::Bool(matches!(
+            x,
+            EDN::List(_) | EDN::Seq(_)
+        )))),
+        _ => Err("seq? requires exactly 1 argument".into()),
+    }
+}
+
+pub fn is_string(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("string?", &args)?[..] {
+        [x] => Ok(Value::EDN(EDN::Bool(matches!(x, EDN::String(_))))),
+        _ => Err("string? requires exactly 1 argument".into()),
+    }
+}
+
+static GENSYM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// `(gensym)` or `(gensym prefix)`: a symbol with a name no other call
+/// returns, for macros to bind without capturing the caller's names.
+pub fn gensym(args: Vec<Value>) -> Result<Value, EvalError> {
+    let prefix = match edn_args("gensym", &args)?[..] {
+        [] => "G__",
+        [EDN::String(prefix)] => prefix,
+        _ => return Err("gensym takes an optional prefix string".into()),
+    };
+    let n = GENSYM_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+    Ok(Value::EDN(EDN::Symbol(format!("{}{}", prefix, n), None)))
+}
+
+pub fn count(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return Ok(Value::EDN(EDN::from(rest.len())));
+    }
+    match edn_args("count", &args)?[..] {
+        [coll] => Ok(Value::EDN(EDN::from(seq::count(coll)?))),
+        _ => Err("count requires exactly 1 argument".into()),
+    }
+}
+
+pub fn is_empty(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let Some(rest) = args_arg(&args) {
+        return Ok(Value::EDN(EDN::Bool(rest.is_empty())));
+    }
+    Ok(Value::EDN(EDN::Bool(seq_arg("empty?", &args)?.is_none())))
+}
+
+// Calls the function argument of a seq native on values.
+fn call_fn(name: &str, f: &Value, args: Vec<Value>) -> Result<Value, EvalError> {
+    match f {
+        Value::Function(f) => f.call(args),
+        _ => Err(format!("{} requires a function, got {}", name, f).into()),
+    }
+}
+
+// Walks a collection argument item by item, where it may be rest args
+// holding fns.
+enum Items {
+    Args(List<Value>),
+    Seq(Option<Seq>),
+}
+
+impl Items {
+    fn of(coll: &Value) -> Result<Items, EvalError> {
+        match coll {
+            Value::Args(items) => Ok(Items::Args(items.clone())),
+            Value::EDN(coll) => Ok(Items::Seq(coll.seq()?)),
+            other => Err(format!("Don't know how to create a seq from {}", other).into()),
+        }
+    }
+
+    fn next_item(&mut self) -> Result<Option<Value>, EvalError> {
+        match self {
+            Items::Args(items) => {
+                let first = items.first().cloned();
+                *items = items.rest();
+                Ok(first)
+            }
+            Items::Seq(seq) => match seq.take() {
+                Some(s) => {
+                    let first = s.first()?;
+                    *seq = s.next()?;
+                    Ok(Some(Value::EDN(first)))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+// Wraps a function argument so the lazy seq combinators can call it.
+fn seq_fn(name: &str, f: &Value) -> Result<seq::SeqFn, EvalError> {
+    let f = match f {
+        Value::Function(f) => f.clone(),
+        _ => return Err(format!("{} requires a function, got {}", name, f).into()),
+    };
+    let name = name.to_string();
+    Ok(Arc::new(move |args: &[EDN]| {
+        match f.call(args.iter().cloned().map(Value::EDN).collect())? {
+            Value::EDN(edn) => Ok(edn),
+            other => Err(format!("{} got a non-EDN result: {}", name, other).into()),
+        }
+    }))
+}
+
+fn count_arg(name: &str, n: &EDN) -> Result<usize, EvalError> {
+    n.as_i64()
+        .map(|i| usize::try_from(i).unwrap_or(0))
+        .ok_or_else(|| format!("{} requires an integer count, got {}", name, n).into())
+}
+
+fn lazy(seq: Seq) -> Result<Value, EvalError> {
+    Ok(Value::EDN(EDN::Seq(seq)))
+}
+
+pub fn range(args: Vec<Value>) -> Result<Value, EvalError> {
+    let zero = EDN::from(0i64);
+    let one = EDN::from(1i64);
+    match edn_args("range", &args)?[..] {
+        [] => lazy(seq::range(zero, None, one)?),
+        [end] => lazy(seq::range(zero, Some(end.clone()), one)?),
+        [start, end] => lazy(seq::range(start.clone(), Some(end.clone()), one)?),
+        [start, end, step] => lazy(seq::range(start.clone(), Some(end.clone()), step.clone())?),
+        _ => Err("range accepts at most 3 arguments".into()),
+    }
+}
+
+pub fn iterate(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [f, Value::EDN(x)] => lazy(seq::iterate(seq_fn("iterate", f)?, x.clone())),
+        _ => Err("iterate requires a function and a value".into()),
+    }
+}
+
+pub fn repeat(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("repeat", &args)?[..] {
+        [x] => lazy(seq::repeat(x.clone(), None)),
+        [n, x] => lazy(seq::repeat(x.clone(), Some(count_arg("repeat", n)?))),
+        _ => Err("repeat requires an optional count and a value".into()),
+    }
+}
+
+pub fn cycle(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("cycle", &args)?[..] {
+        [coll] => lazy(seq::cycle(seq::to_seq(coll)?)),
+        _ => Err("cycle requires exactly 1 argument".into()),
+    }
+}
+
+pub fn map(args: Vec<Value>) -> Result<Value, EvalError> {
+    match args.split_first() {
+        Some((f, colls)) if colls.iter().any(|c| matches!(c, Value::Args(_))) => {
+            // Rest args are finite, so they are mapped over at once,
+            // alongside the first items of any other collections
+            let mut colls = colls.iter().map(Items::of).collect::<Result<Vec<_>, _>>()?;
+            let mut results = Vec::new();
+            'items: loop {
+                let mut f_args = Vec::with_capacity(colls.len());
+                for coll in &mut colls {
+                    match coll.next_item()? {
+                        Some(item) => f_args.push(item),
+                        None => break 'items,
+                    }
+                }
+                results.push(call_fn("map", f, f_args)?);
+            }
+            Ok(Value::list(results))
+        }
+        Some((f, colls)) if !colls.is_empty() => {
+            let colls = edn_args("map", colls)?
+                .into_iter()
+                .map(seq::to_seq)
+                .collect::<Result<Vec<_>, _>>()?;
+            lazy(seq::map(seq_fn("map", f)?, colls))
+        }
+        _ => Err("map requires a function and at least one collection".into()),
+    }
+}
+
+pub fn filter(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [pred, Value::Args(items)] => {
+            let mut kept = Vec::new();
+            for item in items.iter() {
+                if is_truthy(&call_fn("filter", pred, vec![item.clone()])?) {
+                    kept.push(item.clone());
+                }
+            }
+            Ok(Value::list(kept))
+        }
+        [pred, Value::EDN(coll)] => lazy(seq::filter(seq_fn("filter", pred)?, seq::to_seq(coll)?)),
+        _ => Err("filter requires a predicate and a collection".into()),
+    }
+}
+
+pub fn take_fn(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let [Value::EDN(n), Value::Args(items)] = &args[..] {
+        let n = count_arg("take", n)?;
+        return Ok(Value::list(items.iter().take(n).cloned().collect()));
+    }
+    match edn_args("take", &args)?[..] {
+        [n, coll] => lazy(seq::take(count_arg("take", n)?, seq::to_seq(coll)?)),
+        _ => Err("take requires a count and a collection".into()),
+    }
+}
+
+pub fn drop_fn(args: Vec<Value>) -> Result<Value, EvalError> {
+    if let [Value::EDN(n), Value::Args(items)] = &args[..] {
+        let n = count_arg("drop", n)?;
+        return Ok(Value::list(items.iter().skip(n).cloned().collect()));
+    }
+    match edn_args("drop", &args)?[..] {
+        [n, coll] => lazy(seq::drop(count_arg("drop", n)?, seq::to_seq(coll)?)),
+        _ => Err("drop requires a count and a collection".into()),
+    }
+}
+
+// Shared by doall and dorun: realizes all of `coll`, or its first n items.
+fn realize(name: &str, args: &[Value]) -> Result<EDN, EvalError> {
+    let (limit, coll) = match edn_args(name, args)?[..] {
+        [coll] => (None, coll),
+        [n, coll] => (Some(count_arg(name, n)?), coll),
+        _ => return Err(format!("{} requires an optional count and a collection", name).into()),
+    };
+    if let EDN::Seq(s) = coll {
+        s.realize_all(limit)?;
+    }
+    Ok(coll.clone())
+}
+
+pub fn doall(args: Vec<Value>) -> Result<Value, EvalError> {
+    realize("doall", &args).map(Value::EDN)
+}
+
+pub fn dorun(args: Vec<Value>) -> Result<Value, EvalError> {
+    realize("dorun", &args).map(|_| Value::EDN(EDN::Nil))
+}
+
+pub fn is_realized(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("realized?", &args)?[..] {
+        [EDN::Seq(s)] => Ok(Value::EDN(EDN::Bool(s.is_realized()))),
+        [coll] => Err(format!("realized? requires a lazy seq, got {}", coll).into()),
+        _ => Err("realized? requires exactly 1 argument".into()),
+    }
+}
+
+pub fn meta(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("meta", &args)?[..] {
+        [obj] => Ok(Value::EDN(obj.meta())),
+        _ => Err("meta requires exactly 1 argument".into()),
+    }
+}
+
+pub fn with_meta(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("with-meta", &args)?[..] {
+        [obj, meta] => Ok(Value::EDN(obj.with_meta(meta)?)),
+        _ => Err("with-meta requires an object and a metadata map".into()),
+    }
+}
+
+// (vary-meta obj f & args) sets the metadata to (apply f (meta obj) args).
+pub fn vary_meta(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [Value::EDN(obj), Value::Function(f), rest @ ..] => {
+            let mut f_args = vec![Value::EDN(obj.meta())];
+            f_args.extend(rest.iter().cloned());
+            match f.call(f_args)? {
+                Value::EDN(meta) => Ok(Value::EDN(obj.with_meta(&meta)?)),
+                other => Err(format!("vary-meta function must return a map, got {}", other).into()),
+            }
+        }
+        _ => Err("vary-meta requires an object, a function and optional arguments".into()),
+    }
+}
+
+pub fn hash_set(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("hash-set", &args)?;
+    items.iter().try_for_each(|item| seq::force(item))?;
+    Ok(Value::EDN(EDN::Set(items.into_iter().cloned().collect())))
+}
+
+pub fn conj(args: Vec<Value>) -> Result<Value, EvalError> {
+    // Lists conj onto the front, and can take fns once they are rest args
+    let front = match args.split_first() {
+        Some((Value::Args(rest), items)) => Some((rest.clone(), items)),
+        Some((Value::EDN(EDN::Nil), items))
+            if items.iter().any(|i| !matches!(i, Value::EDN(_))) =>
+        {
+            Some((List::new(), items))
+        }
+        Some((Value::EDN(EDN::List(l)), items))
+            if items.iter().any(|i| !matches!(i, Value::EDN(_))) =>
+        {
+            Some((l.iter().cloned().map(Value::EDN).collect(), items))
+        }
+        _ => None,
+    };
+    if let Some((list, items)) = front {
+        let list = items
+            .iter()
+            .fold(list, |list, item| list.cons(item.clone()));
+        return Ok(Value::Args(list));
+    }
+    let items = edn_args("conj", &args)?;
+    match items.split_first() {
+        Some((coll, items)) => items
+            .iter()
+            .try_fold((*coll).clone(), |acc, item| acc.conj((*item).clone()))
+            .map(Value::EDN),
+        None => Ok(Value::EDN(EDN::Vector(PersistentVector::new()))),
+    }
+}
+
+pub fn peek(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("peek", &args)?[..] {
+        [coll] => Ok(Value::EDN(coll.peek()?)),
+        _ => Err("peek requires exactly 1 argument".into()),
+    }
+}
+
+pub fn pop(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("pop", &args)?[..] {
+        [coll] => Ok(Value::EDN(coll.pop()?)),
+        _ => Err("pop requires exactly 1 argument".into()),
+    }
+}
+
+// Plain vectors are copied into an RRB tree once; RRB vectors are shared.
+fn rrb_vector(name: &str, edn: &EDN) -> Result<RrbVector<EDN>, EvalError> {
+    match edn {
+        EDN::RrbVector(v) => Ok(v.clone()),
+        EDN::Vector(v) => Ok(RrbVector::from(v)),
+        _ => Err(format!("{} requires vectors, got {}", name, edn).into()),
+    }
+}
+
+pub fn catvec(args: Vec<Value>) -> Result<Value, EvalError> {
+    edn_args("catvec", &args)?
+        .into_iter()
+        .try_fold(RrbVector::new(), |acc, v| {
+            Ok(acc.concat(&rrb_vector("catvec", v)?))
+        })
+        .map(|v| Value::EDN(EDN::RrbVector(v)))
+}
+
+pub fn subvec(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("subvec", &args)?;
+    let (v, start, end) = match &items[..] {
+        [v, start] => (rrb_vector("subvec", v)?, *start, None),
+        [v, start, end] => (rrb_vector("subvec", v)?, *start, Some(*end)),
+        _ => return Err("subvec requires a vector, a start and an optional end".into()),
+    };
+    let index = |edn: &EDN| {
+        edn.as_i64()
+            .and_then(|i| usize::try_from(i).ok())
+            .ok_or_else(|| format!("subvec index must be a non-negative integer, got {}", edn))
+    };
+    let start = index(start)?;
+    let end = end.map_or(Ok(v.len()), index)?;
+    v.slice(start, end)
+        .map(|v| Value::EDN(EDN::RrbVector(v)))
+        .ok_or_else(|| {
+            format!(
+                "subvec range {}..{} out of bounds for vector of {}",
+                start,
+                end,
+                v.len()
+            )
+            .into()
+        })
+}
+
+pub fn disj(args: Vec<Value>) -> Result<Value, EvalError> {
+    let items = edn_args("disj", &args)?;
+    match items.split_first() {
+        Some((coll, items)) => items
+            .iter()
+            .try_fold((*coll).clone(), |acc, item| acc.disj(item))
+            .map(Value::EDN),
+        None => Err("disj requires a set".into()),
+    }
+}
+
+fn set_args(name: &str, args: &[Value]) -> Result<Vec<PersistentHashSet<EDN>>, EvalError> {
+    edn_args(name, args)?
+        .into_iter()
+        .map(|item| match item {
+            EDN::Set(s) => Ok(s.clone()),
+            EDN::Nil => Ok(PersistentHashSet::new()),
+            _ => Err(format!("{} requires sets, got {}", name, item).into()),
+        })
+        .collect()
+}
+
+pub fn set_union(args: Vec<Value>) -> Result<Value, EvalError> {
+    let sets = set_args("set/union", &args)?;
+    let union = sets
+        .iter()
+        .fold(PersistentHashSet::new(), |acc, s| acc.union(s));
+    Ok(Value::EDN(EDN::Set(union)))
+}
+
+pub fn set_intersection(args: Vec<Value>) -> Result<Value, EvalError> {
+    let sets = set_args("set/intersection", &args)?;
+    match sets.split_first() {
+        Some((first, rest)) => Ok(Value::EDN(EDN::Set(
+            rest.iter()
+                .fold(first.clone(), |acc, s| acc.intersection(s)),
+        ))),
+        None => Err("set/intersection requires at least one set".into()),
+    }
+}
+
+pub fn set_difference(args: Vec<Value>) -> Result<Value, EvalError> {
+    let sets = set_args("set/difference", &args)?;
+    match sets.split_first() {
+        Some((first, rest)) => Ok(Value::EDN(EDN::Set(
+            rest.iter().fold(first.clone(), |acc, s| acc.difference(s)),
+        ))),
+        None => Err("set/difference requires at least one set".into()),
+    }
+}
+
+pub fn set_is_subset(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &set_args("set/subset?", &args)?[..] {
+        [a, b] => Ok(Value::EDN(EDN::Bool(a.is_subset(b)))),
+        _ => Err("set/subset? requires exactly 2 sets".into()),
+    }
+}
+
+pub fn set_is_superset(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &set_args("set/superset?", &args)?[..] {
+        [a, b] => Ok(Value::EDN(EDN::Bool(a.is_superset(b)))),
+        _ => Err("set/superset? requires exactly 2 sets".into()),
+    }
+}
+
+pub fn compare(args: Vec<Value>) -> Result<Value, EvalError> {
+    match edn_args("compare", &args)?[..] {
+        [a, b] => Ok(Value::EDN(EDN::Integer(BigInt::from(a.compare(b)? as i8)))),
+        _ => Err("compare requires exactly 2 arguments".into()),
+    }
+}
+
+fn natural_comparator() -> Comparator<EDN> {
+    Arc::new(|a: &EDN, b: &EDN| Ok(a.compare(b)?))
+}
+
+/// Wraps a yinyang function as a comparator. Like Clojure, it accepts
+/// functions returning a number (negative, zero or positive) as well as
+/// boolean predicates such as `<`, which are asked both ways round.
+fn fn_comparator(name: &str, f: &Value) -> Result<Comparator<EDN>, EvalError> {
+    let f = match f {
+        Value::Function(f) => f.clone(),
+        _ => return Err(format!("{} requires a comparator function", name).into()),
+    };
+    Ok(Arc::new(move |a: &EDN, b: &EDN| {
+        let call = |x: &EDN, y: &EDN| f.call(vec![Value::EDN(x.clone()), Value::EDN(y.clone())]);
+        match call(a, b)? {
+            Value::EDN(EDN::Integer(i)) => Ok(i.sign().cmp(&Sign::NoSign)),
+            Value::EDN(EDN::Float(d)) => Ok(d.cmp(&BigDecimal::from(0))),
+            Value::EDN(EDN::Bool(true)) => Ok(Ordering::Less),
+            Value::EDN(EDN::Bool(false)) => match call(b, a)? {
+                Value::EDN(EDN::Bool(true)) => Ok(Ordering::Greater),
+                _ => Ok(Ordering::Equal),
+            },
+            other => Err(format!(
+                "Comparator must return a number or a boolean, got {}",
+                other
+            )
+            .into()),
+        }
+    }))
+}
+
+fn build_sorted_map(
+    name: &str,
+    comparator: Comparator<EDN>,
+    items: &[Value],
+) -> Result<Value, EvalError> {
+    let items = edn_args(name, items)?;
+    if items.len() % 2 != 0 {
+        return Err(format!("{} requires an even number of key/value arguments", name).into());
+    }
+    items
+        .chunks(2)
+        .try_fold(PersistentTreeMap::with_comparator(comparator), |m, pair| {
+            m.assoc(pair[0].clone(), pair[1].clone())
+        })
+        .map(|m| Value::EDN(EDN::SortedMap(m)))
+}
+
+fn build_sorted_set(
+    name: &str,
+    comparator: Comparator<EDN>,
+    items: &[Value],
+) -> Result<Value, EvalError> {
+    edn_args(name, items)?
+        .into_iter()
+        .try_fold(PersistentTreeSet::with_comparator(comparator), |s, item| {
+            s.conj(item.clone())
+        })
+        .map(|s| Value::EDN(EDN::SortedSet(s)))
+}
+
+pub fn sorted_map(args: Vec<Value>) -> Result<Value, EvalError> {
+    build_sorted_map("sorted-map", natural_comparator(), &args)
+}
+
+pub fn sorted_map_by(args: Vec<Value>) -> Result<Value, EvalError> {
+    match args.split_first() {
+        Some((f, items)) => {
+            build_sorted_map("sorted-map-by", fn_comparator("sorted-map-by", f)?, items)
+        }
+        None => Err("sorted-map-by requires a comparator function".into()),
+    }
+}
+
+pub fn sorted_set(args: Vec<Value>) -> Result<Value, EvalError> {
+    build_sorted_set("sorted-set", natural_comparator(), &args)
+}
+
+pub fn sorted_set_by(args: Vec<Value>) -> Result<Value, EvalError> {
+    match args.split_first() {
+        Some((f, items)) => {
+            build_sorted_set("sorted-set-by", fn_comparator("sorted-set-by", f)?, items)
+        }
+        None => Err("sorted-set-by requires a comparator function".into()),
+    }
+}
+
+// One bound of a subseq range: a test such as `>` or `<=` applied to the
+// result of comparing an element's key with `key`.
+struct SeqBound<'a> {
+    test: &'a Value,
+    key: &'a EDN,
+}
+
+impl SeqBound<'_> {
+    // Applies the test to `ord` and zero, as in `(> ord 0)`.
+    fn test(&self, ord: i8) -> Result<bool, EvalError> {
+        match self.test {
+            Value::Function(f) => Ok(is_truthy(&f.call(vec![
+                Value::EDN(EDN::Integer(BigInt::from(ord))),
+                Value::EDN(EDN::Integer(BigInt::from(0))),
+            ])?)),
+            _ => Err("subseq tests must be functions such as < or >=".into()),
+        }
+    }
+
+    fn includes(&self, comparator: &Comparator<EDN>, k: &EDN) -> Result<bool, EvalError> {
+        self.test(comparator(k, self.key)? as i8)
+    }
+
+    // True for `>` and `>=`, the tests that bound a range from below.
+    fn is_lower(&self) -> Result<bool, EvalError> {
+        self.test(1)
+    }
+}
+
+type SortedEntries<'a> = (Comparator<EDN>, Vec<(&'a EDN, EDN)>);
+
+// Walks a sorted collection as (key, element) pairs, where map elements are
+// [key value] entries, starting from `from` when given.
+fn sorted_entries<'a>(
+    coll: &'a EDN,
+    from: Option<&EDN>,
+    ascending: bool,
+) -> Result<SortedEntries<'a>, EvalError> {
+    let entry = |(k, v): (&'a EDN, &'a EDN)| (k, EDN::Vector(vec![k.clone(), v.clone()].into()));
+    match coll {
+        EDN::SortedMap(m) => {
+            let entries = match (from, ascending) {
+                (Some(key), _) => m.seq_from(key, true, ascending)?.map(entry).collect(),
+                (None, true) => m.iter().map(entry).collect(),
+                (None, false) => m.rseq().map(entry).collect(),
+            };
+            Ok((m.comparator().clone(), entries))
+        }
+        EDN::SortedSet(s) => {
+            let items: Vec<&EDN> = match (from, ascending) {
+                (Some(key), _) => s.seq_from(key, true, ascending)?.collect(),
+                (None, true) => s.iter().collect(),
+                (None, false) => s.rseq().collect(),
+            };
+            let entries = items.into_iter().map(|k| (k, k.clone())).collect();
+            Ok((s.comparator().clone(), entries))
+        }
+        _ => Err(format!("subseq requires a sorted collection, got {}", coll).into()),
+    }
+}
+
+// Shared by subseq and rsubseq. `start` is the bound met first in the
+// direction of travel; when it is on the far side of the collection's order
+// the walk starts at the edge and stops as soon as `start` fails.
+fn sorted_range(name: &str, args: &[Value], ascending: bool) -> Result<Value, EvalError> {
+    let (coll, start, end) = match args {
+        [Value::EDN(coll), test, Value::EDN(key)] => (coll, SeqBound { test, key }, None),
+        [Value::EDN(coll), start_test, Value::EDN(start_key), end_test, Value::EDN(end_key)] => {
+            let (near, far) = (
+                SeqBound {
+                    test: start_test,
+                    key: start_key,
+                },
+                SeqBound {
+                    test: end_test,
+                    key: end_key,
+                },
+            );
+            if ascending {
+                (coll, near, Some(far))
+            } else {
+                (coll, far, Some(near))
+            }
+        }
+        _ => {
+            return Err(format!(
+                "{} requires a sorted collection and one or two test/key bounds",
+                name
+            )
+            .into())
+        }
+    };
+
+    let seeks = start.is_lower()? == ascending;
+    let (comparator, entries) = sorted_entries(coll, seeks.then_some(start.key), ascending)?;
+
+    let mut items = Vec::new();
+    for (i, (k, item)) in entries.into_iter().enumerate() {
+        if !start.includes(&comparator, k)? {
+            // Seeking is inclusive, so an exclusive test may drop the first
+            // element; otherwise the start bound has run out.
+            if seeks && i == 0 {
+                continue;
+            }
+            break;
+        }
+        if let Some(end) = &end {
+            if !end.includes(&comparator, k)? {
+                break;
+            }
+        }
+        items.push(item);
+    }
+    Ok(Value::EDN(EDN::List(Box::new(List::from_vec(items)))))
+}
+
+/// `(subseq sc test key)` or `(subseq sc start-test start-key end-test
+/// end-key)`: the ascending elements of a sorted map or set within the bounds.
+pub fn subseq(args: Vec<Value>) -> Result<Value, EvalError> {
+    sorted_range("subseq", &args, true)
+}
+
+/// As `subseq`, in descending order.
+pub fn rsubseq(args: Vec<Value>) -> Result<Value, EvalError> {
+    sorted_range("rsubseq", &args, false)
+}
+
+pub fn transient(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [Value::EDN(coll)] => Ok(Value::Transient(Transient::new(coll)?)),
+        _ => Err("transient requires a vector, map or set".into()),
+    }
+}
+
+pub fn persistent(args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [Value::Transient(t)] => Ok(Value::EDN(t.persistent()?)),
+        _ => Err("persistent! requires a transient".into()),
+    }
+}
+
+// Splits the arguments of a bang native into the transient and the rest,
+// all of which must be EDN.
+fn transient_args<'a>(
+    name: &str,
+    args: &'a [Value],
+) -> Result<(&'a Transient, Vec<&'a EDN>), EvalError> {
+    match args.split_first() {
+        Some((Value::Transient(t), rest)) => Ok((t, edn_args(name, rest)?)),
+        _ => Err(format!("{} requires a transient", name).into()),
+    }
+}
+
+pub fn conj_bang(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (t, items) = transient_args("conj!", &args)?;
+    t.edit("conj!", |coll| {
+        for item in items {
+            match coll {
+                TransientColl::Vector(v) => {
+                    v.conj(item.clone());
+                }
+                TransientColl::Set(s) => {
+                    s.conj(item.clone());
+                }
+                TransientColl::Map(m) => match item.as_vector() {
+                    Some(pair) if pair.len() == 2 => {
+                        m.assoc(pair[0].clone(), pair[1].clone());
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Map entries must be [key value] vectors, got {}",
+                            item
+                        ))
+                    }
+                },
+            }
+        }
+        Ok(())
+    })?;
+    Ok(args[0].clone())
+}
+
+pub fn assoc_bang(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (t, items) = transient_args("assoc!", &args)?;
+    if items.is_empty() || items.len() % 2 != 0 {
+        return Err("assoc! requires a transient and key/value pairs".into());
+    }
+    t.edit("assoc!", |coll| {
+        for pair in items.chunks(2) {
+            let (key, value) = (pair[0], pair[1]);
+            match coll {
+                TransientColl::Map(m) => {
+                    m.assoc(key.clone(), value.clone());
+                }
+                TransientColl::Vector(v) => {
+                    let len = v.len();
+                    key.as_i64()
+                        .and_then(|i| usize::try_from(i).ok())
+                        .and_then(|i| v.assoc(i, value.clone()))
+                        .ok_or_else(|| {
+                            format!("Index {} out of bounds for vector of {}", key, len)
+                        })?;
+                }
+                TransientColl::Set(_) => return Err("Cannot assoc! on a set".to_string()),
+            }
+        }
+        Ok(())
+    })?;
+    Ok(args[0].clone())
+}
+
+pub fn dissoc_bang(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (t, keys) = transient_args("dissoc!", &args)?;
+    t.edit("dissoc!", |coll| match coll {
+        TransientColl::Map(m) => {
+            for key in keys {
+                m.dissoc(key);
+            }
+            Ok(())
+        }
+        _ => Err("dissoc! requires a transient map".to_string()),
+    })?;
+    Ok(args[0].clone())
+}
+
+pub fn disj_bang(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (t, items) = transient_args("disj!", &args)?;
+    t.edit("disj!", |coll| match coll {
+        TransientColl::Set(s) => {
+            for item in items {
+                s.disj(item);
+            }
+            Ok(())
+        }
+        _ => Err("disj! requires a transient set".to_string()),
+    })?;
+    Ok(args[0].clone())
+}
+
+pub fn pop_bang(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (t, rest) = transient_args("pop!", &args)?;
+    if !rest.is_empty() {
+        return Err("pop! requires exactly 1 argument".into());
+    }
+    t.edit("pop!", |coll| match coll {
+        TransientColl::Vector(v) => v
+            .pop()
+            .map(|_| ())
+            .ok_or_else(|| "Can't pop empty vector".to_string()),
+        _ => Err("pop! requires a transient vector".to_string()),
+    })?;
+    Ok(args[0].clone())
+}
+
+fn loc_arg(name: &str, args: &[Value]) -> Result<Loc, EvalError> {
+    match args.first() {
+        Some(Value::EDN(edn)) => Ok(Loc::from_edn(edn)?),
+        _ => Err(format!("{} requires a zipper location", name).into()),
+    }
+}
+
+fn loc_value(loc: Option<Loc>) -> Value {
+    Value::EDN(loc.map_or(EDN::Nil, |l| l.to_edn()))
+}
+
+fn zip_move(name: &str, args: Vec<Value>, f: fn(&Loc) -> Option<Loc>) -> Result<Value, EvalError> {
+    if args.len() != 1 {
+        return Err(format!("{} requires exactly 1 argument", name).into());
+    }
+    Ok(loc_value(f(&loc_arg(name, &args)?)))
+}
+
+fn zip_insert(
+    name: &str,
+    args: Vec<Value>,
+    f: fn(&Loc, EDN) -> Result<Loc, String>,
+) -> Result<Value, EvalError> {
+    match &args[..] {
+        [_, Value::EDN(item)] => Ok(loc_value(Some(f(&loc_arg(name, &args)?, item.clone())?))),
+        _ => Err(format!("{} requires a location and an EDN value", name).into()),
+    }
+}
+
+fn zipper(name: &str, args: Vec<Value>, kind: Kind) -> Result<Value, EvalError> {
+    match &args[..] {
+        [Value::EDN(root)] => Ok(loc_value(Some(Loc::with_kind(root.clone(), kind)))),
+        _ => Err(format!("{} requires exactly 1 EDN argument", name).into()),
+    }
+}
+
+pub fn zip_edn_zip(args: Vec<Value>) -> Result<Value, EvalError> {
+    zipper("edn-zip", args, Kind::Edn)
+}
+
+pub fn zip_vector_zip(args: Vec<Value>) -> Result<Value, EvalError> {
+    zipper("vector-zip", args, Kind::Vector)
+}
+
+pub fn zip_seq_zip(args: Vec<Value>) -> Result<Value, EvalError> {
+    zipper("seq-zip", args, Kind::Seq)
+}
+
+pub fn zip_node(args: Vec<Value>) -> Result<Value, EvalError> {
+    loc_arg("zip/node", &args).map(|loc| Value::EDN(loc.node().clone()))
+}
+
+pub fn zip_is_branch(args: Vec<Value>) -> Result<Value, EvalError> {
+    loc_arg("zip/branch?", &args).map(|loc| Value::EDN(EDN::Bool(loc.is_branch())))
+}
+
+pub fn zip_children(args: Vec<Value>) -> Result<Value, EvalError> {
+    let loc = loc_arg("zip/children", &args)?;
+    Ok(Value::EDN(
+        loc.children().map_or(EDN::Nil, |c| EDN::Vector(c.into())),
+    ))
+}
+
+pub fn zip_is_end(args: Vec<Value>) -> Result<Value, EvalError> {
+    loc_arg("zip/end?", &args).map(|loc| Value::EDN(EDN::Bool(loc.is_end())))
+}
+
+pub fn zip_root(args: Vec<Value>) -> Result<Value, EvalError> {
+    loc_arg("zip/root", &args).map(|loc| Value::EDN(loc.root()))
+}
+
+pub fn zip_up(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/up", args, Loc::up)
+}
+
+pub fn zip_down(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/down", args, Loc::down)
+}
+
+pub fn zip_left(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/left", args, Loc::left)
+}
+
+pub fn zip_right(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/right", args, Loc::right)
+}
+
+pub fn zip_leftmost(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/leftmost", args, |loc| Some(loc.leftmost()))
+}
+
+pub fn zip_rightmost(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/rightmost", args, |loc| Some(loc.rightmost()))
+}
+
+pub fn zip_next(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/next", args, |loc| Some(loc.next()))
+}
+
+pub fn zip_prev(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_move("zip/prev", args, Loc::prev)
+}
+
+pub fn zip_replace(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_insert("zip/replace", args, Loc::replace)
+}
+
+pub fn zip_insert_left(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_insert("zip/insert-left", args, Loc::insert_left)
+}
+
+pub fn zip_insert_right(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_insert("zip/insert-right", args, Loc::insert_right)
+}
+
+pub fn zip_insert_child(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_insert("zip/insert-child", args, Loc::insert_child)
+}
+
+pub fn zip_append_child(args: Vec<Value>) -> Result<Value, EvalError> {
+    zip_insert("zip/append-child", args, Loc::append_child)
+}
+
+pub fn zip_remove(args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.len() != 1 {
+        return Err("zip/remove requires exactly 1 argument".into());
+    }
+    let loc = loc_arg("zip/remove", &args)?.remove()?;
+    Ok(loc_value(Some(loc)))
+}
+
+/// `(zip/edit loc f & args)` replaces the node with `(apply f node args)`.
+pub fn zip_edit(args: Vec<Value>) -> Result<Value, EvalError> {
+    let loc = loc_arg("zip/edit", &args)?;
+    let f = match args.get(1) {
+        Some(Value::Function(f)) => f,
+        _ => return Err("zip/edit requires a function as its second argument".into()),
+    };
+    // Calls `f` itself rather than through `Loc::edit`, so an error from
+    // `f` keeps its trace.
+    let mut call_args = vec![Value::EDN(loc.node().clone())];
+    call_args.extend(args[2..].iter().cloned());
+    let node = match f.call(call_args)? {
+        Value::EDN(edn) => edn,
+        other => {
+            return Err(EvalError::Native(format!(
+                "zip/edit function returned a non-EDN value: {}",
+                other
+            )))
+        }
+    };
+    let edited = loc.replace(node)?;
+    Ok(loc_value(Some(edited)))
+}
+
+fn schema_args(name: &str, args: &[Value]) -> Result<(Schema, EDN), EvalError> {
+    match args {
+        [Value::EDN(schema), Value::EDN(value)] => Ok((Schema::parse(schema)?, value.clone())),
+        _ => Err(format!("{} requires a schema and an EDN value", name).into()),
+    }
+}
+
+pub fn schema_validate(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (schema, value) = schema_args("schema/validate", &args)?;
+    Ok(Value::EDN(EDN::Bool(schema.validate(&value))))
+}
+
+/// Returns `nil` for a valid value, otherwise a vector of error maps with
+/// `:path`, `:value`, `:schema` and `:message`.
+pub fn schema_explain(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (schema, value) = schema_args("schema/explain", &args)?;
+    let errors = schema.explain(&value);
+    if errors.is_empty() {
+        Ok(Value::EDN(EDN::Nil))
+    } else {
+        Ok(Value::EDN(EDN::Vector(
+            errors.iter().map(SchemaError::to_edn).collect(),
+        )))
+    }
+}
+
+pub fn schema_coerce(args: Vec<Value>) -> Result<Value, EvalError> {
+    let (schema, value) = schema_args("schema/coerce", &args)?;
+    schema.coerce(&value).map(Value::EDN).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(SchemaError::to_string).collect();
+        format!("Coercion failed: {}", errors.join("; ")).into()
+    })
+}
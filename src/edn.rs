@@ -1,10 +1,20 @@
+use crate::clojure::EvalError;
 use crate::immutant::list;
-use bigdecimal::BigDecimal;
+use crate::immutant::map::{Meta, PersistentHashMap, TransientHashMap};
+use crate::immutant::queue::PersistentQueue;
+use crate::immutant::rrb::RrbVector;
+use crate::immutant::set::{PersistentHashSet, TransientHashSet};
+use crate::immutant::sorted::{PersistentTreeMap, PersistentTreeSet};
+use crate::immutant::vector::{PersistentVector, TransientVector};
+use crate::seq::{self, ISeq, Seq};
+use bigdecimal::{BigDecimal, ToPrimitive};
 use num_bigint::BigInt;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
+use std::ops::Index;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum EDN {
@@ -13,12 +23,20 @@ pub enum EDN {
     Integer(BigInt),
     Float(BigDecimal),
     String(String),
-    Symbol(String),
+    // A symbol may carry metadata, which takes no part in equality.
+    Symbol(String, Meta),
     Keyword(String),
     List(Box<list::List<EDN>>),
-    Vector(Vec<EDN>),
-    Map(HashMap<EDN, EDN>),
-    Set(HashSet<EDN>),
+    Vector(PersistentVector<EDN>),
+    Map(PersistentHashMap<EDN, EDN>),
+    Set(PersistentHashSet<EDN>),
+    SortedMap(PersistentTreeMap<EDN, EDN>),
+    SortedSet(PersistentTreeSet<EDN>),
+    Queue(PersistentQueue<EDN>),
+    // The result of `catvec` and `subvec`; prints and compares as a vector.
+    RrbVector(RrbVector<EDN>),
+    // A seq over some collection; prints and compares as a list.
+    Seq(Seq),
 }
 
 #[derive(Debug)]
@@ -45,7 +63,7 @@ impl EDN {
             EDN::Vector(_) => CollectionConfig {
                 opening: "[",
                 closing: "]",
-                constructor: |items| EDN::Vector(items),
+                constructor: vec_to_vector,
             },
             EDN::Set(_) => CollectionConfig {
                 opening: "#{",
@@ -62,6 +80,466 @@ impl EDN {
     }
 }
 
+static NIL: EDN = EDN::Nil;
+
+impl EDN {
+    /// Builds a keyword, accepting the name with or without its leading colon.
+    pub fn keyword(name: &str) -> EDN {
+        if name.starts_with(':') {
+            EDN::Keyword(name.to_string())
+        } else {
+            EDN::Keyword(format!(":{}", name))
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(self, EDN::Nil)
+    }
+
+    /// Orders two values the way Clojure's `compare` does: `nil` before
+    /// everything, numbers numerically, strings, symbols and keywords
+    /// lexically, `false` before `true`, and vectors by length and then
+    /// element by element. Other pairs cannot be compared.
+    pub fn compare(&self, other: &EDN) -> Result<Ordering, String> {
+        match (self, other) {
+            (EDN::Nil, EDN::Nil) => Ok(Ordering::Equal),
+            (EDN::Nil, _) => Ok(Ordering::Less),
+            (_, EDN::Nil) => Ok(Ordering::Greater),
+            (EDN::Bool(a), EDN::Bool(b)) => Ok(a.cmp(b)),
+            (EDN::Integer(a), EDN::Integer(b)) => Ok(a.cmp(b)),
+            (EDN::Float(a), EDN::Float(b)) => Ok(a.cmp(b)),
+            (EDN::Integer(a), EDN::Float(b)) => Ok(BigDecimal::from(a.clone()).cmp(b)),
+            (EDN::Float(a), EDN::Integer(b)) => Ok(a.cmp(&BigDecimal::from(b.clone()))),
+            (EDN::String(a), EDN::String(b))
+            | (EDN::Symbol(a, _), EDN::Symbol(b, _))
+            | (EDN::Keyword(a), EDN::Keyword(b)) => Ok(a.cmp(b)),
+            (a, b) if a.is_vector() && b.is_vector() => {
+                let (a, b) = (a.vector_items().unwrap(), b.vector_items().unwrap());
+                if a.len() != b.len() {
+                    return Ok(a.len().cmp(&b.len()));
+                }
+                for (x, y) in a.zip(b) {
+                    match x.compare(y)? {
+                        Ordering::Equal => {}
+                        ord => return Ok(ord),
+                    }
+                }
+                Ok(Ordering::Equal)
+            }
+            _ => Err(format!("Cannot compare {} with {}", self, other)),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            EDN::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_bigint(&self) -> Option<&BigInt> {
+        match self {
+            EDN::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_bigint().and_then(|i| i.to_i64())
+    }
+
+    pub fn as_bigdecimal(&self) -> Option<&BigDecimal> {
+        match self {
+            EDN::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            EDN::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_keyword(&self) -> Option<&str> {
+        match self {
+            EDN::Keyword(k) => Some(k),
+            _ => None,
+        }
+    }
+
+    pub fn as_symbol(&self) -> Option<&str> {
+        match self {
+            EDN::Symbol(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&list::List<EDN>> {
+        match self {
+            EDN::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<&PersistentVector<EDN>> {
+        match self {
+            EDN::Vector(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&PersistentHashMap<EDN, EDN>> {
+        match self {
+            EDN::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn as_set(&self) -> Option<&PersistentHashSet<EDN>> {
+        match self {
+            EDN::Set(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_rrb_vector(&self) -> Option<&RrbVector<EDN>> {
+        match self {
+            EDN::RrbVector(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// True for both plain and RRB-backed vectors.
+    pub fn is_vector(&self) -> bool {
+        matches!(self, EDN::Vector(_) | EDN::RrbVector(_))
+    }
+
+    /// Iterates the elements of either kind of vector.
+    pub fn vector_items(&self) -> Option<Box<dyn ExactSizeIterator<Item = &EDN> + '_>> {
+        match self {
+            EDN::Vector(v) => Some(Box::new(v.iter())),
+            EDN::RrbVector(v) => Some(Box::new(v.iter())),
+            _ => None,
+        }
+    }
+
+    /// True for both hash and sorted maps.
+    pub fn is_map(&self) -> bool {
+        matches!(self, EDN::Map(_) | EDN::SortedMap(_))
+    }
+
+    /// Iterates the entries of either kind of map.
+    pub fn map_entries(&self) -> Option<Box<dyn Iterator<Item = (&EDN, &EDN)> + '_>> {
+        match self {
+            EDN::Map(m) => Some(Box::new(m.iter())),
+            EDN::SortedMap(m) => Some(Box::new(m.iter())),
+            _ => None,
+        }
+    }
+
+    /// True for both hash and sorted sets.
+    pub fn is_set(&self) -> bool {
+        matches!(self, EDN::Set(_) | EDN::SortedSet(_))
+    }
+
+    /// Iterates the elements of either kind of set.
+    pub fn set_items(&self) -> Option<Box<dyn Iterator<Item = &EDN> + '_>> {
+        match self {
+            EDN::Set(s) => Some(Box::new(s.iter())),
+            EDN::SortedSet(s) => Some(Box::new(s.iter())),
+            _ => None,
+        }
+    }
+
+    /// True for every collection, lazy seqs included.
+    pub fn is_coll(&self) -> bool {
+        matches!(
+            self,
+            EDN::List(_)
+                | EDN::Vector(_)
+                | EDN::RrbVector(_)
+                | EDN::Map(_)
+                | EDN::SortedMap(_)
+                | EDN::Set(_)
+                | EDN::SortedSet(_)
+                | EDN::Queue(_)
+                | EDN::Seq(_)
+        )
+    }
+
+    pub fn as_queue(&self) -> Option<&PersistentQueue<EDN>> {
+        match self {
+            EDN::Queue(q) => Some(q),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` the way Clojure's `get` does: maps by key, vectors by
+    /// integer index, sets by membership. Anything else, lists included, is
+    /// `None`, and so is a key a sorted collection's comparator rejects; use
+    /// `lookup` to see that error.
+    pub fn get(&self, key: &EDN) -> Option<&EDN> {
+        self.lookup(key).ok().flatten()
+    }
+
+    /// Like `get`, but fails when a sorted collection cannot compare `key`
+    /// with its keys, as Clojure's `get` does.
+    pub fn lookup(&self, key: &EDN) -> Result<Option<&EDN>, EvalError> {
+        match self {
+            EDN::SortedMap(m) => m.get(key),
+            EDN::SortedSet(s) => s.get(key),
+            EDN::Map(m) => seq::force(key).map(|_| m.get(key)),
+            EDN::Vector(v) => Ok(index_of(key).and_then(|i| v.get(i))),
+            EDN::RrbVector(v) => Ok(index_of(key).and_then(|i| v.get(i))),
+            EDN::Set(s) => seq::force(key).map(|_| s.get(key)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn get_in(&self, path: &[EDN]) -> Option<&EDN> {
+        path.iter().try_fold(self, |current, key| current.get(key))
+    }
+
+    /// Returns a copy with `key` bound to `value`. Vectors accept any index up
+    /// to and including their length, so assoc at the end appends; `nil`
+    /// becomes a single-entry map. A key to be hashed is `force`d first.
+    pub fn assoc(&self, key: EDN, value: EDN) -> Result<EDN, EvalError> {
+        if matches!(self, EDN::Nil | EDN::Map(_)) {
+            seq::force(&key)?;
+        }
+        match self {
+            EDN::Nil => Ok(EDN::Map(PersistentHashMap::new().assoc(key, value))),
+            EDN::Map(m) => Ok(EDN::Map(m.assoc(key, value))),
+            EDN::SortedMap(m) => m.assoc(key, value).map(EDN::SortedMap),
+            EDN::Vector(v) => index_of(&key)
+                .and_then(|i| v.assoc(i, value))
+                .map(EDN::Vector)
+                .ok_or_else(|| {
+                    format!("Index {} out of bounds for vector of {}", key, v.len()).into()
+                }),
+            EDN::RrbVector(v) => index_of(&key)
+                .and_then(|i| v.assoc(i, value))
+                .map(EDN::RrbVector)
+                .ok_or_else(|| {
+                    format!("Index {} out of bounds for vector of {}", key, v.len()).into()
+                }),
+            _ => Err(format!("Cannot assoc on {}", self).into()),
+        }
+    }
+
+    pub fn assoc_in(&self, path: &[EDN], value: EDN) -> Result<EDN, EvalError> {
+        self.update_in(path, |_| value)
+    }
+
+    /// Replaces the value at `path` with `f` applied to it, creating maps for
+    /// missing intermediate keys. `f` sees `nil` when nothing is there yet.
+    pub fn update_in<F>(&self, path: &[EDN], f: F) -> Result<EDN, EvalError>
+    where
+        F: FnOnce(&EDN) -> EDN,
+    {
+        match path.split_first() {
+            None => Ok(f(self)),
+            Some((key, rest)) => {
+                let child = self.get(key).unwrap_or(&NIL);
+                let new_child = child.update_in(rest, f)?;
+                self.assoc(key.clone(), new_child)
+            }
+        }
+    }
+
+    /// Returns a copy of a map without `key`. Dissociating from `nil` is `nil`.
+    pub fn dissoc(&self, key: &EDN) -> Result<EDN, EvalError> {
+        match self {
+            EDN::Nil => Ok(EDN::Nil),
+            EDN::Map(m) => seq::force(key).map(|_| EDN::Map(m.dissoc(key))),
+            EDN::SortedMap(m) => m.dissoc(key).map(EDN::SortedMap),
+            _ => Err(format!("Cannot dissoc on {}", self).into()),
+        }
+    }
+
+    /// Adds `item` the way Clojure's `conj` does: at the front of lists, the
+    /// end of vectors, into sets, and `[key value]` entries into maps. `nil`
+    /// becomes a single-element list.
+    pub fn conj(&self, item: EDN) -> Result<EDN, EvalError> {
+        match self {
+            EDN::Nil => Ok(EDN::List(Box::new(list::List::singleton(item)))),
+            EDN::List(l) => Ok(EDN::List(Box::new(l.cons(item)))),
+            EDN::Seq(s) => Ok(EDN::Seq(s.cons(item))),
+            EDN::Vector(v) => Ok(EDN::Vector(v.conj(item))),
+            EDN::RrbVector(v) => Ok(EDN::RrbVector(v.conj(item))),
+            EDN::Set(s) => seq::force(&item).map(|_| EDN::Set(s.conj(item))),
+            EDN::Queue(q) => Ok(EDN::Queue(q.conj(item))),
+            EDN::SortedSet(s) => s.conj(item).map(EDN::SortedSet),
+            EDN::Map(_) | EDN::SortedMap(_) => match item.as_vector() {
+                Some(pair) if pair.len() == 2 => self.assoc(pair[0].clone(), pair[1].clone()),
+                _ => Err(format!("Map entries must be [key value] vectors, got {}", item).into()),
+            },
+            _ => Err(format!("Cannot conj on {}", self).into()),
+        }
+    }
+
+    /// Returns the item `pop` would remove: the head of a list or queue, the
+    /// last element of a vector. Empty collections and `nil` give `nil`.
+    pub fn peek(&self) -> Result<EDN, String> {
+        let item = match self {
+            EDN::Nil => None,
+            EDN::List(l) => l.head(),
+            EDN::Vector(v) => v.peek(),
+            EDN::RrbVector(v) => v.peek(),
+            EDN::Queue(q) => q.peek(),
+            _ => return Err(format!("Cannot peek on {}", self)),
+        };
+        Ok(item.cloned().unwrap_or(EDN::Nil))
+    }
+
+    /// Removes the item `peek` returns. Popping an empty list or vector is an
+    /// error; an empty queue pops to itself.
+    pub fn pop(&self) -> Result<EDN, String> {
+        match self {
+            EDN::Nil => Ok(EDN::Nil),
+            EDN::List(l) if l.is_empty() => Err("Can't pop empty list".to_string()),
+            EDN::List(l) => Ok(EDN::List(Box::new(l.rest()))),
+            EDN::Vector(v) => v
+                .pop()
+                .map(EDN::Vector)
+                .ok_or_else(|| "Can't pop empty vector".to_string()),
+            EDN::RrbVector(v) => v
+                .pop()
+                .map(EDN::RrbVector)
+                .ok_or_else(|| "Can't pop empty vector".to_string()),
+            EDN::Queue(q) => Ok(EDN::Queue(q.pop())),
+            _ => Err(format!("Cannot pop on {}", self)),
+        }
+    }
+
+    /// Returns a copy of a set without `item`. Disjoining from `nil` is `nil`.
+    pub fn disj(&self, item: &EDN) -> Result<EDN, EvalError> {
+        match self {
+            EDN::Nil => Ok(EDN::Nil),
+            EDN::Set(s) => seq::force(item).map(|_| EDN::Set(s.disj(item))),
+            EDN::SortedSet(s) => s.disj(item).map(EDN::SortedSet),
+            _ => Err(format!("Cannot disj on {}", self).into()),
+        }
+    }
+
+    /// Removes the last key of `path` from the map that holds it. Paths that
+    /// do not exist leave the value unchanged.
+    pub fn dissoc_in(&self, path: &[EDN]) -> Result<EDN, EvalError> {
+        match path.split_first() {
+            None => Ok(self.clone()),
+            Some((key, [])) => self.dissoc(key),
+            Some((key, rest)) => match self.get(key) {
+                Some(child) => {
+                    let new_child = child.dissoc_in(rest)?;
+                    self.assoc(key.clone(), new_child)
+                }
+                None => Ok(self.clone()),
+            },
+        }
+    }
+
+    /// The metadata map of a collection or symbol, or `nil` when it has none
+    /// or cannot carry any.
+    pub fn meta(&self) -> EDN {
+        let meta = match self {
+            EDN::Symbol(_, meta) => meta.as_deref(),
+            EDN::List(l) => l.meta(),
+            EDN::Vector(v) => v.meta(),
+            EDN::Map(m) => m.meta(),
+            EDN::Set(s) => s.meta(),
+            EDN::SortedMap(m) => m.meta(),
+            EDN::SortedSet(s) => s.meta(),
+            EDN::RrbVector(v) => v.meta(),
+            EDN::Queue(q) => q.meta(),
+            _ => None,
+        };
+        meta.map_or(EDN::Nil, |m| EDN::Map(m.clone()))
+    }
+
+    /// Returns a copy of a collection or symbol carrying `meta`, which must
+    /// be a map or `nil`. Metadata does not affect equality or hashing.
+    pub fn with_meta(&self, meta: &EDN) -> Result<EDN, String> {
+        let meta = match meta {
+            EDN::Nil => None,
+            EDN::Map(m) => Some(m.clone()),
+            _ => return Err(format!("Metadata must be a map, got {}", meta)),
+        };
+        match self {
+            EDN::Symbol(name, _) => Ok(EDN::Symbol(name.clone(), meta.map(Arc::new))),
+            EDN::List(l) => Ok(EDN::List(Box::new(l.with_meta(meta)))),
+            EDN::Vector(v) => Ok(EDN::Vector(v.with_meta(meta))),
+            EDN::Map(m) => Ok(EDN::Map(m.with_meta(meta))),
+            EDN::Set(s) => Ok(EDN::Set(s.with_meta(meta))),
+            EDN::SortedMap(m) => Ok(EDN::SortedMap(m.with_meta(meta))),
+            EDN::SortedSet(s) => Ok(EDN::SortedSet(s.with_meta(meta))),
+            EDN::RrbVector(v) => Ok(EDN::RrbVector(v.with_meta(meta))),
+            EDN::Queue(q) => Ok(EDN::Queue(q.with_meta(meta))),
+            _ => Err(format!("Cannot attach metadata to {}", self)),
+        }
+    }
+}
+
+fn index_of(key: &EDN) -> Option<usize> {
+    key.as_bigint().and_then(|i| i.to_usize())
+}
+
+impl From<bool> for EDN {
+    fn from(b: bool) -> Self {
+        EDN::Bool(b)
+    }
+}
+
+impl From<i64> for EDN {
+    fn from(i: i64) -> Self {
+        EDN::Integer(BigInt::from(i))
+    }
+}
+
+impl From<usize> for EDN {
+    fn from(i: usize) -> Self {
+        EDN::Integer(BigInt::from(i))
+    }
+}
+
+impl From<&str> for EDN {
+    fn from(s: &str) -> Self {
+        EDN::String(s.to_string())
+    }
+}
+
+impl From<String> for EDN {
+    fn from(s: String) -> Self {
+        EDN::String(s)
+    }
+}
+
+/// `edn[i]` is the item at position `i` of a vector or list, as with
+/// Clojure's `nth`, or `nil` past the end or for anything else. Unlike
+/// `get`, it indexes lists.
+impl Index<usize> for EDN {
+    type Output = EDN;
+
+    fn index(&self, i: usize) -> &EDN {
+        match self {
+            EDN::List(l) => l.iter().nth(i).unwrap_or(&NIL),
+            _ => self.get(&EDN::from(i)).unwrap_or(&NIL),
+        }
+    }
+}
+
+/// `edn[&key]` looks up `key` as `get` does, e.g. `edn[&EDN::keyword("port")]`
+/// or `edn[&EDN::from("port")]` for a string key; missing keys index to
+/// `nil`.
+impl Index<&EDN> for EDN {
+    type Output = EDN;
+
+    fn index(&self, key: &EDN) -> &EDN {
+        self.get(key).unwrap_or(&NIL)
+    }
+}
+
 impl Eq for EDN {}
 
 impl PartialEq for EDN {
@@ -72,12 +550,36 @@ impl PartialEq for EDN {
             (EDN::Integer(i1), EDN::Integer(i2)) => i1 == i2,
             (EDN::Float(f1), EDN::Float(f2)) => f1 == f2,
             (EDN::String(s1), EDN::String(s2)) => s1 == s2,
-            (EDN::Symbol(sym1), EDN::Symbol(sym2)) => sym1 == sym2,
+            (EDN::Symbol(sym1, _), EDN::Symbol(sym2, _)) => sym1 == sym2,
             (EDN::Keyword(k1), EDN::Keyword(k2)) => k1 == k2,
             (EDN::List(l1), EDN::List(l2)) => l1 == l2,
+            (EDN::Seq(s1), EDN::Seq(s2)) => s1 == s2,
+            (EDN::Seq(s), EDN::List(l)) | (EDN::List(l), EDN::Seq(s)) => s == &**l,
             (EDN::Vector(v1), EDN::Vector(v2)) => v1 == v2,
             (EDN::Map(m1), EDN::Map(m2)) => m1 == m2,
             (EDN::Set(s1), EDN::Set(s2)) => s1 == s2,
+            (EDN::SortedMap(m1), EDN::SortedMap(m2)) => m1 == m2,
+            (EDN::SortedSet(s1), EDN::SortedSet(s2)) => s1 == s2,
+            (EDN::Queue(q1), EDN::Queue(q2)) => q1 == q2,
+            (EDN::RrbVector(v1), EDN::RrbVector(v2)) => v1 == v2,
+            (EDN::RrbVector(rrb), EDN::Vector(v)) | (EDN::Vector(v), EDN::RrbVector(rrb)) => {
+                rrb == v
+            }
+            // Lists, seqs and vectors are all sequential, and equal when
+            // their items are.
+            (EDN::List(l), v) | (v, EDN::List(l)) if v.is_vector() => v
+                .vector_items()
+                .is_some_and(|items| items.len() == l.len() && items.eq(l.iter())),
+            (EDN::Seq(s), v) | (v, EDN::Seq(s)) if v.is_vector() => v
+                .vector_items()
+                .is_some_and(|items| s.iter().eq(items.cloned().map(Ok))),
+            // Sorted and hashed collections are equal when their contents are.
+            (EDN::Map(m), EDN::SortedMap(sm)) | (EDN::SortedMap(sm), EDN::Map(m)) => {
+                m.len() == sm.len() && sm.iter().all(|(k, v)| m.get(k) == Some(v))
+            }
+            (EDN::Set(s), EDN::SortedSet(ss)) | (EDN::SortedSet(ss), EDN::Set(s)) => {
+                s.len() == ss.len() && ss.iter().all(|item| s.contains(item))
+            }
             _ => false,
         }
     }
@@ -103,7 +605,7 @@ impl Hash for EDN {
                 state.write_u8(4);
                 s.hash(state);
             }
-            EDN::Symbol(s) => {
+            EDN::Symbol(s, _) => {
                 state.write_u8(5);
                 s.hash(state);
             }
@@ -117,24 +619,43 @@ impl Hash for EDN {
                     item.hash(state);
                 }
             }
+            EDN::Seq(s) => {
+                state.write_u8(7);
+                s.hash(state);
+            }
+            // Hashed like lists, as they compare equal.
             EDN::Vector(v) => {
-                state.write_u8(8);
+                state.write_u8(7);
+                for item in v {
+                    item.hash(state);
+                }
+            }
+            EDN::RrbVector(v) => {
+                state.write_u8(7);
                 for item in v {
                     item.hash(state);
                 }
             }
             EDN::Map(m) => {
                 state.write_u8(9);
-                for (k, v) in m {
-                    k.hash(state);
-                    v.hash(state);
-                }
+                m.hash(state);
             }
             EDN::Set(s) => {
                 state.write_u8(10);
-                for item in s {
-                    item.hash(state);
-                }
+                s.hash(state);
+            }
+            // Hashed like their unsorted counterparts, as they compare equal.
+            EDN::SortedMap(m) => {
+                state.write_u8(9);
+                m.hash(state);
+            }
+            EDN::SortedSet(s) => {
+                state.write_u8(10);
+                s.hash(state);
+            }
+            EDN::Queue(q) => {
+                state.write_u8(11);
+                q.hash(state);
             }
         }
     }
@@ -142,75 +663,122 @@ impl Hash for EDN {
 
 impl fmt::Display for EDN {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            EDN::Nil => write!(f, "nil"),
-            EDN::Bool(b) => write!(f, "{}", b),
-            EDN::Integer(i) => write!(f, "{}", i),
-            EDN::Float(d) => write!(f, "{}", d.to_string()),
-            EDN::String(s) => write!(f, "\"{}\"", s),
-            EDN::Symbol(sym) => write!(f, "{}", sym),
-            EDN::Keyword(k) => write!(f, "{}", k),
-            EDN::List(l) => {
-                write!(f, "(")?;
-                for (i, item) in l.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                write!(f, ")")
-            }
-            EDN::Vector(v) => {
-                write!(f, "[")?;
-                for (i, item) in v.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                write!(f, "]")
-            }
-            EDN::Map(m) => {
-                write!(f, "{{")?;
-                for (i, (k, v)) in m.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, ":{} {}", k, v)?;
-                }
-                write!(f, "}}")
+        write_edn(f, self, false)
+    }
+}
+
+/// How many realized items of a lazy seq a preview shows.
+const PREVIEW_ITEMS: usize = 10;
+
+/// Displays a value without realizing any lazy seq in it; see `EDN::preview`.
+pub struct Preview<'a>(&'a EDN);
+
+impl EDN {
+    /// A rendering for diagnostics such as the evaluation trace. It prints
+    /// like `Display`, except that a lazy seq shows at most its first few
+    /// realized items followed by `...`, or `#<lazy-seq>` if nothing is
+    /// realized yet, so infinite seqs are safe to show.
+    pub fn preview(&self) -> Preview<'_> {
+        Preview(self)
+    }
+}
+
+impl fmt::Display for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_edn(f, self.0, true)
+    }
+}
+
+fn write_edn(f: &mut fmt::Formatter, edn: &EDN, preview: bool) -> fmt::Result {
+    match edn {
+        EDN::Nil => write!(f, "nil"),
+        EDN::Bool(b) => write!(f, "{}", b),
+        EDN::Integer(i) => write!(f, "{}", i),
+        EDN::Float(d) => write!(f, "{}", d),
+        EDN::String(s) => write!(f, "\"{}\"", s),
+        EDN::Symbol(sym, _) => write!(f, "{}", sym),
+        EDN::Keyword(k) => write!(f, "{}", k),
+        EDN::List(l) => write_seq(f, "(", l.iter(), ")", preview),
+        EDN::Seq(s) if preview => {
+            let (items, more) = s.realized_prefix(PREVIEW_ITEMS);
+            if items.is_empty() && more {
+                return write!(f, "#<lazy-seq>");
             }
-            EDN::Set(s) => {
-                write!(f, "#{{")?;
-                for (i, item) in s.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                write!(f, "}}")
+            write_seq(f, "(", items.iter(), "", true)?;
+            if more {
+                write!(f, " ...)")
+            } else {
+                write!(f, ")")
             }
         }
+        EDN::Seq(s) => write!(f, "{}", s),
+        EDN::Vector(v) => write_seq(f, "[", v.iter(), "]", preview),
+        EDN::RrbVector(v) => write_seq(f, "[", v.iter(), "]", preview),
+        EDN::Map(m) => write_map(f, m.iter(), preview),
+        EDN::Set(s) => write_seq(f, "#{", s.iter(), "}", preview),
+        EDN::SortedMap(m) => write_map(f, m.iter(), preview),
+        EDN::SortedSet(s) => write_seq(f, "#{", s.iter(), "}", preview),
+        EDN::Queue(q) => write_seq(f, "#queue [", q.iter(), "]", preview),
     }
 }
 
-fn vec_to_set(items: Vec<EDN>) -> EDN {
-    let mut set = HashSet::new();
-    for item in items {
-        set.insert(item);
+fn write_seq<'a>(
+    f: &mut fmt::Formatter,
+    opening: &str,
+    items: impl Iterator<Item = &'a EDN>,
+    closing: &str,
+    preview: bool,
+) -> fmt::Result {
+    write!(f, "{}", opening)?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write_edn(f, item, preview)?;
     }
-    EDN::Set(set)
+    write!(f, "{}", closing)
 }
 
-fn vec_to_map<V>(v: Vec<V>) -> HashMap<V, V>
-where
-    V: Eq + Hash + Clone,
-{
-    let mut map = HashMap::new();
-    for pair in v.chunks(2) {
-        if let [key, value] = pair {
-            map.insert(key.clone(), value.clone());
+fn write_map<'a>(
+    f: &mut fmt::Formatter,
+    entries: impl Iterator<Item = (&'a EDN, &'a EDN)>,
+    preview: bool,
+) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, (k, v)) in entries.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
         }
+        write_edn(f, k, preview)?;
+        write!(f, " ")?;
+        write_edn(f, v, preview)?;
+    }
+    write!(f, "}}")
+}
+
+// The reader's constructors build through transients, so a literal of n
+// elements costs n in-place edits rather than n path copies.
+pub(crate) fn vec_to_vector(items: Vec<EDN>) -> EDN {
+    let mut v = TransientVector::new();
+    for item in items {
+        v.conj(item);
+    }
+    EDN::Vector(v.persistent())
+}
+
+pub(crate) fn vec_to_set(items: Vec<EDN>) -> EDN {
+    let mut set = TransientHashSet::new();
+    for item in items {
+        set.conj(item);
+    }
+    EDN::Set(set.persistent())
+}
+
+pub(crate) fn vec_to_map(items: Vec<EDN>) -> PersistentHashMap<EDN, EDN> {
+    let mut map = TransientHashMap::new();
+    let mut items = items.into_iter();
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        map.assoc(key, value);
     }
-    map
+    map.persistent()
 }
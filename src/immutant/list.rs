@@ -1,67 +1,93 @@
+use crate::immutant::map::{Meta, MetaMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum List<T> {
-    Cons(T, Arc<List<T>>),
-    Nil,
+// A persistent singly linked list. Nodes are shared between lists, and the
+// list itself carries its length so `len` never walks the chain.
+#[derive(Clone)]
+pub struct List<T> {
+    head: Option<Arc<Node<T>>>,
+    count: usize,
+    meta: Meta,
+}
+
+struct Node<T> {
+    value: T,
+    next: Option<Arc<Node<T>>>,
 }
 
 pub struct ListIter<'a, T: Clone> {
-    current: Option<&'a List<T>>,
+    current: Option<&'a Node<T>>,
+    remaining: usize,
 }
 
 impl<'a, T: Clone> Iterator for ListIter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let current = self.current?;
-        match current {
-            List::Nil => {
-                self.current = None;
-                None
-            }
-            List::Cons(head, tail) => {
-                self.current = Some(&*tail);
-                Some(head)
-            }
-        }
+        let node = self.current?;
+        self.current = node.next.as_deref();
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Clone> ExactSizeIterator for ListIter<'a, T> {}
+
+impl<T: Clone> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl<T: Clone> List<T> {
     // Create a new empty list
     pub fn new() -> Self {
-        List::Nil
+        List {
+            head: None,
+            count: 0,
+            meta: None,
+        }
     }
 
     // Create a new list with a single element
     pub fn singleton(element: T) -> Self {
-        List::Cons(element, Arc::new(List::Nil))
+        List::new().prepend(element)
     }
 
-    // Static method to create a new list with an element in front
-    pub fn cons(item: T, list: List<T>) -> Self {
-        List::Cons(item, Arc::new(list))
+    // Return a new list with an element in front, like Clojure's cons
+    pub fn cons(&self, item: T) -> Self {
+        self.prepend(item)
     }
 
-    // Instance method to add an element to thepf front
+    // Instance method to add an element to the front
     pub fn prepend(&self, element: T) -> Self {
-        List::Cons(element, Arc::new(self.clone()))
+        List {
+            head: Some(Arc::new(Node {
+                value: element,
+                next: self.head.clone(),
+            })),
+            count: self.count + 1,
+            meta: self.meta.clone(),
+        }
     }
 
+    // Return a new list with an element at the end. This copies every node.
     pub fn append(&self, element: T) -> Self {
-        match self {
-            List::Nil => List::singleton(element),
-            List::Cons(head, tail) => List::Cons(head.clone(), Arc::new(tail.append(element))),
-        }
+        let mut items = self.to_vec();
+        items.push(element);
+        List::from_vec(items)
     }
 
     // Get the head (first element) of the list
     pub fn head(&self) -> Option<&T> {
-        match self {
-            List::Cons(head, _) => Some(head),
-            List::Nil => None,
-        }
+        self.head.as_ref().map(|node| &node.value)
     }
 
     pub fn first(&self) -> Option<&T> {
@@ -70,66 +96,192 @@ impl<T: Clone> List<T> {
 
     // Get the tail (rest) of the list
     pub fn tail(&self) -> Option<Arc<List<T>>> {
-        match self {
-            List::Cons(_, tail) => Some(Arc::clone(tail)),
-            List::Nil => None,
+        self.head.as_ref().map(|node| {
+            Arc::new(List {
+                head: node.next.clone(),
+                count: self.count - 1,
+                meta: None,
+            })
+        })
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    // Return a copy sharing this list's nodes with `meta` attached
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        List {
+            head: self.head.clone(),
+            count: self.count,
+            meta: meta.map(Arc::new),
         }
     }
 
     // Check if the list is empty
     pub fn is_empty(&self) -> bool {
-        matches!(self, List::Nil)
+        self.head.is_none()
     }
 
     // Get the length of the list
     pub fn len(&self) -> usize {
-        match self {
-            List::Nil => 0,
-            List::Cons(_, tail) => 1 + tail.len(),
-        }
+        self.count
     }
 
     pub fn from_vec(v: Vec<T>) -> Self {
-        let mut list = List::Nil;
+        let mut list = List::new();
         for item in v.into_iter().rev() {
-            list = List::Cons(item, Arc::new(list));
+            list = list.prepend(item);
         }
         list
     }
 
     pub fn to_vec(&self) -> Vec<T> {
-        let mut result = Vec::new();
-        let mut current = self;
-        while let List::Cons(head, tail) = current {
-            result.push(head.clone());
-            current = tail;
-        }
-        result
+        self.iter().cloned().collect()
     }
 
     /// Returns an iterator over references to the elements of the list
     pub fn iter(&self) -> ListIter<'_, T> {
         ListIter {
-            current: Some(self),
+            current: self.head.as_deref(),
+            remaining: self.count,
         }
     }
 
     pub fn rest(&self) -> Self {
-        match self {
-            List::Cons(_, tail) => (**tail).clone(),
-            List::Nil => List::Nil,
-        }
+        self.tail().map_or_else(List::new, |tail| (*tail).clone())
     }
 
     pub fn reverse(&self) -> Self {
-        let mut current = self;
-        let mut reversed = List::Nil;
+        self.iter()
+            .fold(List::new(), |reversed, item| reversed.prepend(item.clone()))
+    }
+}
+
+// Dropping the nodes one at a time keeps a long chain from recursing once
+// per element. The walk stops at the first node another list still shares.
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
 
-        while let List::Cons(head, tail) = current {
-            reversed = List::Cons(head.clone(), Arc::new(reversed));
-            current = tail;
+impl<T: Clone + PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.count != other.count {
+            return false;
+        }
+        let (mut a, mut b) = (self.head.as_ref(), other.head.as_ref());
+        while let (Some(x), Some(y)) = (a, b) {
+            if Arc::ptr_eq(x, y) {
+                // The rest of both lists is the same nodes.
+                return true;
+            }
+            if x.value != y.value {
+                return false;
+            }
+            a = x.next.as_ref();
+            b = y.next.as_ref();
         }
+        true
+    }
+}
+
+impl<T: Clone + Eq> Eq for List<T> {}
+
+impl<T: Clone + Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.count);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + fmt::Display> fmt::Display for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T: Clone> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        List::from_vec(iter.into_iter().collect())
+    }
+}
+
+// Extending adds the new items at the end, in order, as `Vec` does.
+impl<T: Clone> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut items = self.to_vec();
+        items.extend(iter);
+        *self = List::from_vec(items);
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = ListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<T> {
+    list: List<T>,
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.list.head.take()?;
+        self.list.count -= 1;
+        // Elements of unshared nodes are moved out; shared ones are cloned.
+        match Arc::try_unwrap(node) {
+            Ok(node) => {
+                self.list.head = node.next;
+                Some(node.value)
+            }
+            Err(node) => {
+                self.list.head = node.next.clone();
+                Some(node.value.clone())
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.count, Some(self.list.count))
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+
+impl<T: Clone> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
 
-        reversed
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
     }
 }
@@ -0,0 +1,954 @@
+use crate::edn::EDN;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::sync::Arc;
+
+const BITS: u32 = 5;
+const MASK: u64 = 0x1f;
+
+/// A persistent hash map: a hash array mapped trie as in Clojure. Each level
+/// consumes five bits of the key's 64-bit hash; keys whose hashes are fully
+/// equal share a collision node. Updates copy only the path to the changed
+/// entry and share every other node with the original map.
+#[derive(Clone)]
+pub struct PersistentHashMap<K, V> {
+    count: usize,
+    root: Arc<Node<K, V>>,
+    meta: Meta,
+}
+
+/// A metadata map, as set by Clojure's `with-meta`. Its keys and values are
+/// EDN whatever the collection carrying it holds.
+pub type MetaMap = PersistentHashMap<EDN, EDN>;
+
+/// The metadata a collection or symbol may carry. It takes no part in
+/// equality or hashing.
+pub type Meta = Option<Arc<MetaMap>>;
+
+#[derive(Debug, Clone)]
+enum Node<K, V> {
+    Bitmap {
+        bitmap: u32,
+        entries: Vec<Entry<K, V>>,
+    },
+    Collision {
+        hash: u64,
+        pairs: Vec<(K, V)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Entry<K, V> {
+    KeyValue(K, V),
+    Node(Arc<Node<K, V>>),
+}
+
+pub(crate) fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes a collection so that the result does not depend on iteration
+/// order. Maps and sets of every flavour hash this way, which keeps equal
+/// collections hashing equally.
+pub(crate) fn hash_unordered<T: Hash, H: Hasher>(
+    len: usize,
+    items: impl Iterator<Item = T>,
+    state: &mut H,
+) {
+    let sum = items.fold(0u64, |acc, item| acc.wrapping_add(hash_of(&item)));
+    state.write_usize(len);
+    state.write_u64(sum);
+}
+
+fn bit_for(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+fn index_for(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+fn empty_node<K, V>() -> Node<K, V> {
+    Node::Bitmap {
+        bitmap: 0,
+        entries: Vec::new(),
+    }
+}
+
+// Builds the smallest subtree at `shift` holding two distinct keys.
+fn create_node<K, V>(shift: u32, k1: K, v1: V, h1: u64, k2: K, v2: V, h2: u64) -> Node<K, V> {
+    if h1 == h2 {
+        return Node::Collision {
+            hash: h1,
+            pairs: vec![(k1, v1), (k2, v2)],
+        };
+    }
+
+    let (b1, b2) = (bit_for(h1, shift), bit_for(h2, shift));
+    if b1 == b2 {
+        let child = create_node(shift + BITS, k1, v1, h1, k2, v2, h2);
+        Node::Bitmap {
+            bitmap: b1,
+            entries: vec![Entry::Node(Arc::new(child))],
+        }
+    } else {
+        let (first, second) = if b1 < b2 {
+            (Entry::KeyValue(k1, v1), Entry::KeyValue(k2, v2))
+        } else {
+            (Entry::KeyValue(k2, v2), Entry::KeyValue(k1, v1))
+        };
+        Node::Bitmap {
+            bitmap: b1 | b2,
+            entries: vec![first, second],
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Node<K, V> {
+    fn get(&self, shift: u32, hash: u64, key: &K) -> Option<(&K, &V)> {
+        match self {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit_for(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                match &entries[index_for(*bitmap, bit)] {
+                    Entry::KeyValue(k, v) if k == key => Some((k, v)),
+                    Entry::KeyValue(_, _) => None,
+                    Entry::Node(child) => child.get(shift + BITS, hash, key),
+                }
+            }
+            Node::Collision { pairs, .. } => {
+                pairs.iter().find(|(k, _)| k == key).map(|(k, v)| (k, v))
+            }
+        }
+    }
+
+    // Returns the new node and whether a new key was added.
+    fn assoc(&self, shift: u32, hash: u64, key: K, value: V) -> (Node<K, V>, bool) {
+        match self {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit_for(hash, shift);
+                let idx = index_for(*bitmap, bit);
+                let mut new_entries = entries.clone();
+
+                if bitmap & bit == 0 {
+                    new_entries.insert(idx, Entry::KeyValue(key, value));
+                    let node = Node::Bitmap {
+                        bitmap: bitmap | bit,
+                        entries: new_entries,
+                    };
+                    return (node, true);
+                }
+
+                let added = match &entries[idx] {
+                    Entry::KeyValue(k, _) if *k == key => {
+                        new_entries[idx] = Entry::KeyValue(key, value);
+                        false
+                    }
+                    Entry::KeyValue(k, v) => {
+                        let child = create_node(
+                            shift + BITS,
+                            k.clone(),
+                            v.clone(),
+                            hash_of(k),
+                            key,
+                            value,
+                            hash,
+                        );
+                        new_entries[idx] = Entry::Node(Arc::new(child));
+                        true
+                    }
+                    Entry::Node(child) => {
+                        let (child, added) = child.assoc(shift + BITS, hash, key, value);
+                        new_entries[idx] = Entry::Node(Arc::new(child));
+                        added
+                    }
+                };
+                let node = Node::Bitmap {
+                    bitmap: *bitmap,
+                    entries: new_entries,
+                };
+                (node, added)
+            }
+            Node::Collision {
+                hash: collision_hash,
+                pairs,
+            } => {
+                if hash != *collision_hash {
+                    // A different hash that shares this prefix: push the
+                    // collision node one level down and retry.
+                    let wrapper = Node::Bitmap {
+                        bitmap: bit_for(*collision_hash, shift),
+                        entries: vec![Entry::Node(Arc::new(self.clone()))],
+                    };
+                    return wrapper.assoc(shift, hash, key, value);
+                }
+
+                let mut new_pairs = pairs.clone();
+                let added = match new_pairs.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        new_pairs[i].1 = value;
+                        false
+                    }
+                    None => {
+                        new_pairs.push((key, value));
+                        true
+                    }
+                };
+                let node = Node::Collision {
+                    hash,
+                    pairs: new_pairs,
+                };
+                (node, added)
+            }
+        }
+    }
+
+    // `None` when the key is absent, `Some(None)` when the node is left
+    // empty, otherwise the replacement node.
+    fn dissoc(&self, shift: u32, hash: u64, key: &K) -> Option<Option<Node<K, V>>> {
+        match self {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit_for(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let idx = index_for(*bitmap, bit);
+                let replacement = match &entries[idx] {
+                    Entry::KeyValue(k, _) if k == key => None,
+                    Entry::KeyValue(_, _) => return None,
+                    Entry::Node(child) => {
+                        child.dissoc(shift + BITS, hash, key)?.map(Node::into_entry)
+                    }
+                };
+
+                let mut new_entries = entries.clone();
+                let new_bitmap = match replacement {
+                    Some(entry) => {
+                        new_entries[idx] = entry;
+                        *bitmap
+                    }
+                    None => {
+                        new_entries.remove(idx);
+                        bitmap & !bit
+                    }
+                };
+                if new_entries.is_empty() {
+                    return Some(None);
+                }
+                Some(Some(Node::Bitmap {
+                    bitmap: new_bitmap,
+                    entries: new_entries,
+                }))
+            }
+            Node::Collision { hash, pairs } => {
+                let i = pairs.iter().position(|(k, _)| k == key)?;
+                let mut new_pairs = pairs.clone();
+                new_pairs.remove(i);
+                Some(Some(Node::Collision {
+                    hash: *hash,
+                    pairs: new_pairs,
+                }))
+            }
+        }
+    }
+
+    // In-place counterpart of `assoc` for transients. Children still shared
+    // with a persistent map are copied on first write by `Arc::make_mut`.
+    fn assoc_mut(&mut self, shift: u32, hash: u64, key: K, value: V) -> bool {
+        match self {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit_for(hash, shift);
+                let idx = index_for(*bitmap, bit);
+                if *bitmap & bit == 0 {
+                    entries.insert(idx, Entry::KeyValue(key, value));
+                    *bitmap |= bit;
+                    return true;
+                }
+
+                let split = match &mut entries[idx] {
+                    Entry::KeyValue(k, v) if *k == key => {
+                        *v = value;
+                        return false;
+                    }
+                    Entry::KeyValue(k, v) => create_node(
+                        shift + BITS,
+                        k.clone(),
+                        v.clone(),
+                        hash_of(k),
+                        key,
+                        value,
+                        hash,
+                    ),
+                    Entry::Node(child) => {
+                        return Arc::make_mut(child).assoc_mut(shift + BITS, hash, key, value)
+                    }
+                };
+                entries[idx] = Entry::Node(Arc::new(split));
+                true
+            }
+            Node::Collision {
+                hash: collision_hash,
+                pairs,
+            } => {
+                if hash != *collision_hash {
+                    *self = Node::Bitmap {
+                        bitmap: bit_for(*collision_hash, shift),
+                        entries: vec![Entry::Node(Arc::new(self.clone()))],
+                    };
+                    return self.assoc_mut(shift, hash, key, value);
+                }
+                match pairs.iter_mut().find(|(k, _)| *k == key) {
+                    Some(pair) => {
+                        pair.1 = value;
+                        false
+                    }
+                    None => {
+                        pairs.push((key, value));
+                        true
+                    }
+                }
+            }
+        }
+    }
+
+    // In-place counterpart of `dissoc` for a key known to be present.
+    // Returns whether the node is left empty.
+    fn dissoc_mut(&mut self, shift: u32, hash: u64, key: &K) -> bool {
+        match self {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit_for(hash, shift);
+                let idx = index_for(*bitmap, bit);
+                let remove = match &mut entries[idx] {
+                    Entry::KeyValue(_, _) => true,
+                    Entry::Node(child) => {
+                        let child = Arc::make_mut(child);
+                        if child.dissoc_mut(shift + BITS, hash, key) {
+                            true
+                        } else {
+                            if let Some(single) = child.single_entry() {
+                                entries[idx] = single;
+                            }
+                            false
+                        }
+                    }
+                };
+                if remove {
+                    entries.remove(idx);
+                    *bitmap &= !bit;
+                }
+                entries.is_empty()
+            }
+            Node::Collision { pairs, .. } => {
+                pairs.retain(|(k, _)| k != key);
+                pairs.is_empty()
+            }
+        }
+    }
+
+    fn single_entry(&self) -> Option<Entry<K, V>> {
+        match self {
+            Node::Bitmap { entries, .. } if entries.len() == 1 => match &entries[0] {
+                entry @ Entry::KeyValue(_, _) => Some(entry.clone()),
+                Entry::Node(_) => None,
+            },
+            Node::Collision { pairs, .. } if pairs.len() == 1 => {
+                Some(Entry::KeyValue(pairs[0].0.clone(), pairs[0].1.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    // A child left holding a single key is pulled up into its parent, so
+    // dissoc returns the trie to the shape assoc would have built.
+    fn into_entry(self) -> Entry<K, V> {
+        match self {
+            Node::Bitmap { entries, .. }
+                if entries.len() == 1 && matches!(entries[0], Entry::KeyValue(_, _)) =>
+            {
+                entries.into_iter().next().unwrap()
+            }
+            Node::Collision { mut pairs, .. } if pairs.len() == 1 => {
+                let (k, v) = pairs.pop().unwrap();
+                Entry::KeyValue(k, v)
+            }
+            node => Entry::Node(Arc::new(node)),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> PersistentHashMap<K, V> {
+    pub fn new() -> Self {
+        PersistentHashMap {
+            count: 0,
+            root: Arc::new(empty_node()),
+            meta: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Returns the stored key along with its value.
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.root.get(0, hash_of(key), key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns a copy with `key` mapped to `value`.
+    pub fn assoc(&self, key: K, value: V) -> Self {
+        let (root, added) = self.root.assoc(0, hash_of(&key), key, value);
+        PersistentHashMap {
+            count: if added { self.count + 1 } else { self.count },
+            root: Arc::new(root),
+            meta: self.meta.clone(),
+        }
+    }
+
+    /// Returns a copy without `key`, or a clone sharing everything when the
+    /// key is absent.
+    pub fn dissoc(&self, key: &K) -> Self {
+        match self.root.dissoc(0, hash_of(key), key) {
+            None => self.clone(),
+            Some(root) => PersistentHashMap {
+                count: self.count - 1,
+                root: Arc::new(root.unwrap_or_else(empty_node)),
+                meta: self.meta.clone(),
+            },
+        }
+    }
+
+    /// Entries of `other` win over entries of `self`, as in Clojure's merge.
+    pub fn merge(&self, other: &Self) -> Self {
+        other
+            .iter()
+            .fold(self.clone(), |m, (k, v)| m.assoc(k.clone(), v.clone()))
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this map's entries with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentHashMap {
+            count: self.count,
+            root: self.root.clone(),
+            meta: meta.map(Arc::new),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: vec![(self.root.as_ref(), 0)],
+            remaining: self.count,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// A cursor at the first entry, or `None` for an empty map.
+    pub fn cursor(&self) -> Option<Cursor<K, V>> {
+        Cursor::settle(vec![(self.root.clone(), 0)])
+    }
+
+    /// Returns an editable copy for batch updates. It shares this map's
+    /// nodes and copies each one only the first time it is changed.
+    pub fn transient(&self) -> TransientHashMap<K, V> {
+        TransientHashMap {
+            count: self.count,
+            root: self.root.clone(),
+        }
+    }
+}
+
+/// The editable counterpart of `PersistentHashMap`, as Clojure's transient
+/// maps. See `TransientVector` for how edits and sharing work.
+#[derive(Clone)]
+pub struct TransientHashMap<K, V> {
+    count: usize,
+    root: Arc<Node<K, V>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TransientHashMap<K, V> {
+    pub fn new() -> Self {
+        PersistentHashMap::new().transient()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.root.get(0, hash_of(key), key).map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn assoc(&mut self, key: K, value: V) -> &mut Self {
+        let hash = hash_of(&key);
+        if Arc::make_mut(&mut self.root).assoc_mut(0, hash, key, value) {
+            self.count += 1;
+        }
+        self
+    }
+
+    pub fn dissoc(&mut self, key: &K) -> &mut Self {
+        // Checking first avoids copying shared nodes for a missing key.
+        if self.contains_key(key) {
+            Arc::make_mut(&mut self.root).dissoc_mut(0, hash_of(key), key);
+            self.count -= 1;
+        }
+        self
+    }
+
+    /// Freezes the edits into a persistent map.
+    pub fn persistent(self) -> PersistentHashMap<K, V> {
+        PersistentHashMap {
+            count: self.count,
+            root: self.root,
+            meta: None,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for TransientHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for TransientHashMap<K, V>
+where
+    K: Hash + Eq + Clone + fmt::Debug,
+    V: Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(Iter {
+                stack: vec![(self.root.as_ref(), 0)],
+                remaining: self.count,
+            })
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for PersistentHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for PersistentHashMap<K, V>
+where
+    K: Hash + Eq + Clone + fmt::Debug,
+    V: Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone + PartialEq> PartialEq for PersistentHashMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone + Eq> Eq for PersistentHashMap<K, V> {}
+
+impl<K: Hash + Eq + Clone, V: Clone + Hash> Hash for PersistentHashMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_unordered(self.count, self.iter(), state);
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Index<&K> for PersistentHashMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("Key not found in map")
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> FromIterator<(K, V)> for PersistentHashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut m = TransientHashMap::new();
+        for (k, v) in iter {
+            m.assoc(k, v);
+        }
+        m.persistent()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    stack: Vec<(&'a Node<K, V>, usize)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, i)) = self.stack.pop() {
+            match node {
+                Node::Bitmap { entries, .. } => {
+                    if i >= entries.len() {
+                        continue;
+                    }
+                    self.stack.push((node, i + 1));
+                    match &entries[i] {
+                        Entry::KeyValue(k, v) => {
+                            self.remaining -= 1;
+                            return Some((k, v));
+                        }
+                        Entry::Node(child) => self.stack.push((child, 0)),
+                    }
+                }
+                Node::Collision { pairs, .. } => {
+                    if let Some((k, v)) = pairs.get(i) {
+                        self.stack.push((node, i + 1));
+                        self.remaining -= 1;
+                        return Some((k, v));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K: Hash + Eq + Clone, V: Clone> IntoIterator for &'a PersistentHashMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An owned position in a map's iteration order, for walking it without
+/// borrowing it, as a seq does. It holds the path from the root to the
+/// current entry, so stepping costs at most the depth of the trie and the
+/// entries are never copied out.
+pub struct Cursor<K, V> {
+    // Each node with the index of the entry being visited in it. The last
+    // one always indexes a key-value pair.
+    path: Vec<(Arc<Node<K, V>>, usize)>,
+}
+
+impl<K, V> Clone for Cursor<K, V> {
+    fn clone(&self) -> Self {
+        Cursor {
+            path: self.path.clone(),
+        }
+    }
+}
+
+impl<K, V> Cursor<K, V> {
+    // Moves down, or up and along, to the first pair at or after the end of
+    // `path`.
+    fn settle(mut path: Vec<(Arc<Node<K, V>>, usize)>) -> Option<Self> {
+        loop {
+            let (node, i) = path.last()?;
+            let (node, i) = (node.clone(), *i);
+            let child = match node.as_ref() {
+                Node::Bitmap { entries, .. } => match entries.get(i) {
+                    Some(Entry::KeyValue(_, _)) => return Some(Cursor { path }),
+                    Some(Entry::Node(child)) => Some(child.clone()),
+                    None => None,
+                },
+                Node::Collision { pairs, .. } if i < pairs.len() => return Some(Cursor { path }),
+                Node::Collision { .. } => None,
+            };
+            match child {
+                Some(child) => path.push((child, 0)),
+                None => {
+                    path.pop();
+                    if let Some((_, i)) = path.last_mut() {
+                        *i += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn entry(&self) -> (&K, &V) {
+        let (node, i) = self.path.last().expect("cursor has an entry");
+        match node.as_ref() {
+            Node::Bitmap { entries, .. } => match &entries[*i] {
+                Entry::KeyValue(k, v) => (k, v),
+                Entry::Node(_) => unreachable!("cursor rests on a pair"),
+            },
+            Node::Collision { pairs, .. } => (&pairs[*i].0, &pairs[*i].1),
+        }
+    }
+
+    /// The cursor at the following entry, or `None` after the last one.
+    pub fn next(&self) -> Option<Self> {
+        let mut path = self.path.clone();
+        if let Some((_, i)) = path.last_mut() {
+            *i += 1;
+        }
+        Cursor::settle(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // A key whose hash is chosen by the test, to force collisions.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Colliding(u64, &'static str);
+
+    impl Hash for Colliding {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let m: PersistentHashMap<i32, i32> = PersistentHashMap::new();
+        assert!(m.is_empty());
+        assert_eq!(m.get(&1), None);
+        assert_eq!(m.iter().count(), 0);
+        assert!(m.dissoc(&1).is_empty());
+    }
+
+    #[test]
+    fn test_assoc_get() {
+        let m1 = PersistentHashMap::new().assoc("a", 1).assoc("b", 2);
+        let m2 = m1.assoc("a", 10);
+        assert_eq!(m1.len(), 2);
+        assert_eq!(m2.len(), 2);
+        assert_eq!(m1.get(&"a"), Some(&1));
+        assert_eq!(m2.get(&"a"), Some(&10));
+        assert_eq!(m2[&"b"], 2);
+        assert!(m2.contains_key(&"b"));
+        assert!(!m2.contains_key(&"c"));
+    }
+
+    #[test]
+    fn test_many_keys() {
+        let n = 20_000;
+        let m: PersistentHashMap<usize, usize> = (0..n).map(|i| (i, i * 2)).collect();
+        assert_eq!(m.len(), n);
+        for i in 0..n {
+            assert_eq!(m.get(&i), Some(&(i * 2)));
+        }
+        assert_eq!(m.iter().count(), n);
+        assert_eq!(m.iter().len(), n);
+
+        let evens_removed = (0..n).step_by(2).fold(m.clone(), |m, i| m.dissoc(&i));
+        assert_eq!(evens_removed.len(), n / 2);
+        for i in 0..n {
+            assert_eq!(evens_removed.contains_key(&i), i % 2 == 1);
+        }
+        assert_eq!(m.len(), n);
+
+        let emptied = (0..n).fold(m, |m, i| m.dissoc(&i));
+        assert!(emptied.is_empty());
+        assert_eq!(emptied.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_matches_std_hashmap() {
+        let mut expected = HashMap::new();
+        let mut m = PersistentHashMap::new();
+        for i in 0..5000u64 {
+            let key = (i * 7919) % 1000;
+            if i % 3 == 0 {
+                expected.remove(&key);
+                m = m.dissoc(&key);
+            } else {
+                expected.insert(key, i);
+                m = m.assoc(key, i);
+            }
+        }
+        assert_eq!(m.len(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(m.get(k), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_collisions() {
+        let a = Colliding(42, "a");
+        let b = Colliding(42, "b");
+        let c = Colliding(42, "c");
+        let other = Colliding(42 + (1 << 40), "other");
+
+        let m = PersistentHashMap::new()
+            .assoc(a.clone(), 1)
+            .assoc(b.clone(), 2)
+            .assoc(c.clone(), 3)
+            .assoc(other.clone(), 4);
+        assert_eq!(m.len(), 4);
+        assert_eq!(m.get(&a), Some(&1));
+        assert_eq!(m.get(&b), Some(&2));
+        assert_eq!(m.get(&c), Some(&3));
+        assert_eq!(m.get(&other), Some(&4));
+        assert_eq!(m.iter().count(), 4);
+
+        let m2 = m.assoc(b.clone(), 20).dissoc(&a);
+        assert_eq!(m2.len(), 3);
+        assert_eq!(m2.get(&a), None);
+        assert_eq!(m2.get(&b), Some(&20));
+
+        let m3 = m2.dissoc(&b).dissoc(&c);
+        assert_eq!(m3.len(), 1);
+        assert_eq!(m3.get(&other), Some(&4));
+        assert_eq!(m3.dissoc(&c), m3);
+    }
+
+    #[test]
+    fn test_structural_sharing() {
+        let m: PersistentHashMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+        let m2 = m.assoc(0, 100);
+        let (old, new) = match (m.root.as_ref(), m2.root.as_ref()) {
+            (Node::Bitmap { entries: old, .. }, Node::Bitmap { entries: new, .. }) => (old, new),
+            _ => panic!("Expected bitmap roots"),
+        };
+        let shared = old
+            .iter()
+            .zip(new.iter())
+            .filter(|(a, b)| match (a, b) {
+                (Entry::Node(a), Entry::Node(b)) => Arc::ptr_eq(a, b),
+                _ => false,
+            })
+            .count();
+        assert_eq!(shared, old.len() - 1);
+    }
+
+    #[test]
+    fn test_eq_hash_independent_of_order() {
+        let m1: PersistentHashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let m2: PersistentHashMap<i32, i32> = (0..100).rev().map(|i| (i, i)).collect();
+        let m3 = m1.assoc(1000, 0).dissoc(&1000);
+        assert_eq!(m1, m2);
+        assert_eq!(m1, m3);
+        assert_eq!(hash_of(&m1), hash_of(&m2));
+        assert_eq!(hash_of(&m1), hash_of(&m3));
+        assert_ne!(m1, m1.assoc(0, 1));
+    }
+
+    #[test]
+    fn test_meta_survives_updates_but_not_equality() {
+        let meta = MetaMap::new().assoc(EDN::from("doc"), EDN::from("a map"));
+        let plain = PersistentHashMap::new().assoc("x", "1");
+        let m = plain.with_meta(Some(meta.clone()));
+        assert_eq!(m.meta(), Some(&meta));
+        assert_eq!(m.assoc("y", "2").dissoc(&"x").meta(), Some(&meta));
+        assert_eq!(m.with_meta(None).meta(), None);
+        assert_eq!(m, plain);
+        assert_eq!(hash_of(&m), hash_of(&plain));
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = PersistentHashMap::new().assoc("x", 1).assoc("y", 2);
+        let b = PersistentHashMap::new().assoc("y", 20).assoc("z", 30);
+        let merged = a.merge(&b);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[&"x"], 1);
+        assert_eq!(merged[&"y"], 20);
+        assert_eq!(merged[&"z"], 30);
+    }
+
+    #[test]
+    fn test_transient_matches_std_hashmap() {
+        let mut expected = HashMap::new();
+        let mut t = TransientHashMap::new();
+        for i in 0..20_000u64 {
+            let key = (i * 7919) % 3000;
+            if i % 3 == 0 {
+                expected.remove(&key);
+                t.dissoc(&key);
+            } else {
+                expected.insert(key, i);
+                t.assoc(key, i);
+            }
+        }
+        assert_eq!(t.len(), expected.len());
+        let m = t.persistent();
+        assert_eq!(m.len(), expected.len());
+        assert_eq!(m.iter().count(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(m.get(k), Some(v));
+        }
+        let rebuilt: PersistentHashMap<u64, u64> = expected.into_iter().collect();
+        assert_eq!(m, rebuilt);
+    }
+
+    #[test]
+    fn test_transient_leaves_original_untouched() {
+        let m1: PersistentHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        let mut t = m1.transient();
+        t.assoc(1, 100).assoc(5000, 5000).dissoc(&2).dissoc(&9999);
+        let m2 = t.persistent();
+
+        assert_eq!(m1.len(), 1000);
+        assert_eq!(m1.get(&1), Some(&1));
+        assert_eq!(m1.get(&2), Some(&2));
+        assert_eq!(m1.get(&5000), None);
+        assert_eq!(m2.len(), 1000);
+        assert_eq!(m2.get(&1), Some(&100));
+        assert_eq!(m2.get(&2), None);
+        assert_eq!(m2.get(&5000), Some(&5000));
+    }
+
+    #[test]
+    fn test_transient_collisions() {
+        let a = Colliding(7, "a");
+        let b = Colliding(7, "b");
+        let other = Colliding(7 + (1 << 30), "other");
+        let mut t = TransientHashMap::new();
+        t.assoc(a.clone(), 1)
+            .assoc(b.clone(), 2)
+            .assoc(other.clone(), 3);
+        assert_eq!(t.len(), 3);
+        t.dissoc(&a);
+        assert_eq!(t.get(&b), Some(&2));
+        let m = t.persistent();
+        assert_eq!(m, PersistentHashMap::new().assoc(b, 2).assoc(other, 3));
+    }
+}
@@ -1 +1,7 @@
 pub mod list;
+pub mod map;
+pub mod queue;
+pub mod rrb;
+pub mod set;
+pub mod sorted;
+pub mod vector;
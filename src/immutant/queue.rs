@@ -0,0 +1,264 @@
+use crate::immutant::list::{List, ListIter};
+use crate::immutant::map::{Meta, MetaMap};
+use crate::immutant::vector::{PersistentVector, PersistentVectorIter};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{Chain, FromIterator};
+use std::sync::Arc;
+
+/// A persistent FIFO queue laid out as in Clojure: items are popped from the
+/// `front` list and conjed onto the `rear` vector. When the front runs out,
+/// the rear becomes the new front in one pass, so each item is copied at most
+/// once.
+#[derive(Clone)]
+pub struct PersistentQueue<T> {
+    count: usize,
+    front: List<T>,
+    rear: PersistentVector<T>,
+    meta: Meta,
+}
+
+impl<T: Clone> PersistentQueue<T> {
+    pub fn new() -> Self {
+        PersistentQueue {
+            count: 0,
+            front: List::new(),
+            rear: PersistentVector::new(),
+            meta: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this queue's items with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentQueue {
+            meta: meta.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    /// Adds `item` at the back of the queue.
+    pub fn conj(&self, item: T) -> Self {
+        if self.front.is_empty() {
+            PersistentQueue {
+                count: 1,
+                front: List::singleton(item),
+                rear: PersistentVector::new(),
+                meta: self.meta.clone(),
+            }
+        } else {
+            PersistentQueue {
+                count: self.count + 1,
+                front: self.front.clone(),
+                rear: self.rear.conj(item),
+                meta: self.meta.clone(),
+            }
+        }
+    }
+
+    /// Returns the item at the front of the queue.
+    pub fn peek(&self) -> Option<&T> {
+        self.front.head()
+    }
+
+    /// Returns the queue without its front item. Popping an empty queue
+    /// returns it unchanged, as in Clojure.
+    pub fn pop(&self) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let front = self.front.rest();
+        if front.is_empty() {
+            PersistentQueue {
+                count: self.count - 1,
+                front: self.rear.iter().cloned().collect(),
+                rear: PersistentVector::new(),
+                meta: self.meta.clone(),
+            }
+        } else {
+            PersistentQueue {
+                count: self.count - 1,
+                front,
+                rear: self.rear.clone(),
+                meta: self.meta.clone(),
+            }
+        }
+    }
+
+    /// The front list and the rear vector, which together hold the items
+    /// in order.
+    pub(crate) fn parts(&self) -> (&List<T>, &PersistentVector<T>) {
+        (&self.front, &self.rear)
+    }
+
+    /// Iterates from the front of the queue to the back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.front.iter().chain(self.rear.iter()),
+        }
+    }
+}
+
+impl<T: Clone> Default for PersistentQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for PersistentQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + fmt::Display> fmt::Display for PersistentQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#queue [")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for PersistentQueue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for PersistentQueue<T> {}
+
+impl<T: Clone + Hash> Hash for PersistentQueue<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.count.hash(state);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let front: List<T> = iter.into_iter().collect();
+        PersistentQueue {
+            count: front.len(),
+            front,
+            rear: PersistentVector::new(),
+            meta: None,
+        }
+    }
+}
+
+pub struct Iter<'a, T: Clone> {
+    inner: Chain<ListIter<'a, T>, PersistentVectorIter<'a, T>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: Clone> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T: Clone> IntoIterator for &'a PersistentQueue<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edn::EDN;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_fifo_order() {
+        let q: PersistentQueue<i32> = PersistentQueue::new().conj(1).conj(2).conj(3);
+        assert_eq!(q.len(), 3);
+        assert_eq!(q.peek(), Some(&1));
+        let q = q.pop();
+        assert_eq!(q.peek(), Some(&2));
+        let q = q.conj(4).pop().pop();
+        assert_eq!(q.peek(), Some(&4));
+        assert_eq!(q.len(), 1);
+        let q = q.pop();
+        assert!(q.is_empty());
+        assert_eq!(q.peek(), None);
+        assert!(q.pop().is_empty());
+    }
+
+    #[test]
+    fn test_matches_vecdeque() {
+        let mut q = PersistentQueue::new();
+        let mut expected = VecDeque::new();
+        let mut snapshots = Vec::new();
+        for i in 0..2000 {
+            if i % 3 == 2 {
+                q = q.pop();
+                expected.pop_front();
+            } else {
+                q = q.conj(i);
+                expected.push_back(i);
+            }
+            assert_eq!(q.len(), expected.len());
+            assert_eq!(q.peek(), expected.front());
+            if i % 500 == 0 {
+                snapshots.push((q.clone(), expected.clone()));
+            }
+        }
+        assert!(q.iter().eq(expected.iter()));
+        for (q, expected) in snapshots {
+            assert!(q.iter().eq(expected.iter()));
+        }
+    }
+
+    #[test]
+    fn test_equality_ignores_layout() {
+        let a: PersistentQueue<i32> = (1..=3).collect();
+        let b = PersistentQueue::new().conj(0).conj(1).conj(2).conj(3).pop();
+        assert_eq!(a, b);
+        assert_ne!(a, b.pop());
+        assert_eq!(a.to_string(), "#queue [1 2 3]");
+        assert_eq!(b.to_string(), "#queue [1 2 3]");
+        assert_eq!(a.iter().len(), 3);
+    }
+
+    #[test]
+    fn test_meta_survives_updates_but_not_equality() {
+        let meta = MetaMap::new().assoc(EDN::from(0i64), EDN::from(1i64));
+        let plain: PersistentQueue<i32> = (1..=3).collect();
+        let q = plain.with_meta(Some(meta.clone()));
+        assert_eq!(q.meta(), Some(&meta));
+        assert_eq!(q.conj(4).meta(), Some(&meta));
+        assert_eq!(q.pop().pop().pop().meta(), Some(&meta));
+        assert_eq!(q.pop().pop().conj(4).pop().meta(), Some(&meta));
+        assert_eq!(plain.meta(), None);
+        assert_eq!(q, plain);
+    }
+}
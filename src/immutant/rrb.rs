@@ -0,0 +1,742 @@
+use crate::immutant::map::{Meta, MetaMap};
+use crate::immutant::vector::PersistentVector;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+use std::sync::Arc;
+
+// A relaxed radix balanced (RRB) vector. Unlike `PersistentVector`, nodes
+// need not be full: every branch keeps a table of cumulative child sizes, so
+// lookups search that table instead of shifting the index. All leaves sit at
+// the same depth and every node holds at most MAX entries. Nodes other than
+// the root also hold at least MIN, which keeps the height logarithmic; the
+// root may hold fewer, as the root of a split-off half usually does. `join`
+// keeps this by merging a short tree's root into a node on the taller tree's
+// edge, which already holds MIN, and `pack` splits an overflowing node into
+// halves of at least MIN. Concatenation only rebuilds the nodes along the
+// edge where the two trees meet.
+
+const MAX: usize = 32;
+const MIN: usize = MAX / 2;
+
+enum Node<T> {
+    Leaf(Vec<T>),
+    Branch {
+        sizes: Vec<usize>,
+        children: Vec<Arc<Node<T>>>,
+    },
+}
+
+impl<T: Clone> Node<T> {
+    fn branch(children: Vec<Arc<Node<T>>>) -> Self {
+        let mut total = 0;
+        let sizes = children
+            .iter()
+            .map(|child| {
+                total += child.len();
+                total
+            })
+            .collect();
+        Node::Branch { sizes, children }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(items) => items.len(),
+            Node::Branch { sizes, .. } => sizes.last().copied().unwrap_or(0),
+        }
+    }
+
+    // Finds the child holding `index` and the index within that child.
+    fn child_for(sizes: &[usize], index: usize) -> (usize, usize) {
+        let slot = sizes.partition_point(|&size| size <= index);
+        let offset = if slot == 0 { 0 } else { sizes[slot - 1] };
+        (slot, index - offset)
+    }
+
+    fn get(&self, mut index: usize) -> &T {
+        let mut node = self;
+        loop {
+            match node {
+                Node::Leaf(items) => return &items[index],
+                Node::Branch { sizes, children } => {
+                    let (slot, sub_index) = Self::child_for(sizes, index);
+                    node = &children[slot];
+                    index = sub_index;
+                }
+            }
+        }
+    }
+
+    fn assoc(&self, index: usize, value: T) -> Self {
+        match self {
+            Node::Leaf(items) => {
+                let mut items = items.clone();
+                items[index] = value;
+                Node::Leaf(items)
+            }
+            Node::Branch { sizes, children } => {
+                let (slot, sub_index) = Self::child_for(sizes, index);
+                let mut children = children.clone();
+                children[slot] = Arc::new(children[slot].assoc(sub_index, value));
+                Node::Branch {
+                    sizes: sizes.clone(),
+                    children,
+                }
+            }
+        }
+    }
+}
+
+// Splits `items` into one node's worth, or two halves when they overflow.
+// Either half of an overflowing node holds at least MIN entries.
+fn pack<X, T: Clone>(mut items: Vec<X>, make: fn(Vec<X>) -> Node<T>) -> Vec<Arc<Node<T>>> {
+    if items.len() <= MAX {
+        vec![Arc::new(make(items))]
+    } else {
+        let right = items.split_off(items.len() / 2);
+        vec![Arc::new(make(items)), Arc::new(make(right))]
+    }
+}
+
+// Cuts `items` into chunks of MAX, evening out the last two chunks when the
+// final one would hold fewer than MIN.
+fn chunk<X>(items: Vec<X>) -> Vec<Vec<X>> {
+    let mut chunks = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(MAX).collect::<Vec<_>>());
+    }
+    if chunks.len() > 1 && chunks[chunks.len() - 1].len() < MIN {
+        let last = chunks.pop().unwrap();
+        let mut combined = chunks.pop().unwrap();
+        combined.extend(last);
+        let right = combined.split_off(combined.len() / 2);
+        chunks.push(combined);
+        chunks.push(right);
+    }
+    chunks
+}
+
+// Joins two trees of heights `ha` and `hb`, returning one or two nodes of
+// the greater height. The shorter tree is merged into the edge of the taller
+// one, so only the nodes along that edge are copied.
+fn join<T: Clone>(a: &Arc<Node<T>>, ha: usize, b: &Arc<Node<T>>, hb: usize) -> Vec<Arc<Node<T>>> {
+    match (a.as_ref(), b.as_ref()) {
+        (Node::Leaf(left), Node::Leaf(right)) => {
+            let mut items = left.clone();
+            items.extend(right.iter().cloned());
+            pack(items, Node::Leaf)
+        }
+        (
+            Node::Branch { children: left, .. },
+            Node::Branch {
+                children: right, ..
+            },
+        ) if ha == hb => {
+            let mut children = left.clone();
+            children.extend(right.iter().cloned());
+            pack(children, Node::branch)
+        }
+        (Node::Branch { children, .. }, _) if ha > hb => {
+            let mut children = children.clone();
+            let last = children.pop().unwrap();
+            children.extend(join(&last, ha - 1, b, hb));
+            pack(children, Node::branch)
+        }
+        (_, Node::Branch { children, .. }) => {
+            let first = &children[0];
+            let mut joined = join(a, ha, first, hb - 1);
+            joined.extend(children[1..].iter().cloned());
+            pack(joined, Node::branch)
+        }
+        (Node::Branch { .. }, Node::Leaf(_)) => unreachable!("leaf taller than branch"),
+    }
+}
+
+/// A persistent vector with O(log n) `concat`, `split_at` and `insert_at`,
+/// for workloads that slice and join large vectors.
+pub struct RrbVector<T> {
+    count: usize,
+    height: usize,
+    root: Arc<Node<T>>,
+    meta: Meta,
+}
+
+impl<T> Clone for RrbVector<T> {
+    fn clone(&self) -> Self {
+        RrbVector {
+            count: self.count,
+            height: self.height,
+            root: Arc::clone(&self.root),
+            meta: self.meta.clone(),
+        }
+    }
+}
+
+impl<T: Clone> RrbVector<T> {
+    pub fn new() -> Self {
+        RrbVector {
+            count: 0,
+            height: 0,
+            root: Arc::new(Node::Leaf(Vec::new())),
+            meta: None,
+        }
+    }
+
+    // Builds a vector from sibling nodes of height `height`, dropping
+    // single-child roots so the tree is no taller than it needs to be.
+    fn from_nodes(mut nodes: Vec<Arc<Node<T>>>, mut height: usize) -> Self {
+        let mut root = match nodes.len() {
+            0 => return RrbVector::new(),
+            1 => nodes.pop().unwrap(),
+            _ => {
+                height += 1;
+                Arc::new(Node::branch(nodes))
+            }
+        };
+        while let Node::Branch { children, .. } = root.as_ref() {
+            if children.len() != 1 {
+                break;
+            }
+            root = Arc::clone(&children[0]);
+            height -= 1;
+        }
+        RrbVector {
+            count: root.len(),
+            height,
+            root,
+            meta: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this vector's nodes with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        RrbVector {
+            meta: meta.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    // Gives `v` this vector's metadata, for results that stand in for it.
+    fn keep_meta(&self, mut v: Self) -> Self {
+        v.meta = self.meta.clone();
+        v
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.count {
+            Some(self.root.get(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.count.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn conj(&self, item: T) -> Self {
+        self.concat(&RrbVector::from_nodes(
+            vec![Arc::new(Node::Leaf(vec![item]))],
+            0,
+        ))
+    }
+
+    /// Returns the vector without its last element, or `None` when empty.
+    pub fn pop(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.keep_meta(self.split_at(self.count - 1).0))
+    }
+
+    /// Replaces the element at `index`. As with `PersistentVector`,
+    /// `index == len()` appends and any larger index is `None`.
+    pub fn assoc(&self, index: usize, value: T) -> Option<Self> {
+        if index == self.count {
+            return Some(self.conj(value));
+        }
+        if index > self.count {
+            return None;
+        }
+        Some(RrbVector {
+            root: Arc::new(self.root.assoc(index, value)),
+            ..self.clone()
+        })
+    }
+
+    /// Returns the elements of `self` followed by those of `other`, with
+    /// the metadata of `self`.
+    pub fn concat(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return self.keep_meta(other.clone());
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+        let nodes = join(&self.root, self.height, &other.root, other.height);
+        self.keep_meta(RrbVector::from_nodes(nodes, self.height.max(other.height)))
+    }
+
+    /// Splits into the elements before `index` and those from it on. An
+    /// index past the end leaves everything on the left. Neither half keeps
+    /// the metadata.
+    pub fn split_at(&self, index: usize) -> (Self, Self) {
+        if index == 0 {
+            return (RrbVector::new(), self.with_meta(None));
+        }
+        if index >= self.count {
+            return (self.with_meta(None), RrbVector::new());
+        }
+        Self::split_node(&self.root, self.height, index)
+    }
+
+    fn split_node(node: &Arc<Node<T>>, height: usize, index: usize) -> (Self, Self) {
+        match node.as_ref() {
+            Node::Leaf(items) => {
+                let leaf = |items: &[T]| {
+                    RrbVector::from_nodes(vec![Arc::new(Node::Leaf(items.to_vec()))], 0)
+                };
+                (leaf(&items[..index]), leaf(&items[index..]))
+            }
+            Node::Branch { sizes, children } => {
+                let (slot, sub_index) = Node::<T>::child_for(sizes, index);
+                let before = RrbVector::from_nodes(children[..slot].to_vec(), height - 1);
+                if sub_index == 0 {
+                    let after = RrbVector::from_nodes(children[slot..].to_vec(), height - 1);
+                    return (before, after);
+                }
+                let after = RrbVector::from_nodes(children[slot + 1..].to_vec(), height - 1);
+                let (left, right) = Self::split_node(&children[slot], height - 1, sub_index);
+                (before.concat(&left), right.concat(&after))
+            }
+        }
+    }
+
+    /// Returns a copy with `item` inserted before `index`, or `None` when
+    /// `index` is past the end.
+    pub fn insert_at(&self, index: usize, item: T) -> Option<Self> {
+        if index > self.count {
+            return None;
+        }
+        let (left, right) = self.split_at(index);
+        Some(self.keep_meta(left.conj(item).concat(&right)))
+    }
+
+    /// Returns the elements from `start` up to but not including `end`.
+    pub fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        if start > end || end > self.count {
+            return None;
+        }
+        Some(self.split_at(end).0.split_at(start).1)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (leaf, stack) = match self.root.as_ref() {
+            Node::Leaf(items) => (items.iter(), Vec::new()),
+            Node::Branch { children, .. } => ([].iter(), vec![children.iter()]),
+        };
+        Iter {
+            stack,
+            leaf,
+            remaining: self.count,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    pub fn to_vector(&self) -> PersistentVector<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: Clone> Default for RrbVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for RrbVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + fmt::Display> fmt::Display for RrbVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for RrbVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for RrbVector<T> {}
+
+impl<T: Clone + PartialEq> PartialEq<PersistentVector<T>> for RrbVector<T> {
+    fn eq(&self, other: &PersistentVector<T>) -> bool {
+        self.count == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+// Hashes like `PersistentVector`, so equal vectors hash alike.
+impl<T: Clone + Hash> Hash for RrbVector<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.count.hash(state);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: Clone> Index<usize> for RrbVector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index).expect("Index out of bounds")
+    }
+}
+
+// Builds the tree bottom up, one level of full nodes at a time.
+impl<T: Clone> FromIterator<T> for RrbVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut nodes: Vec<Arc<Node<T>>> = chunk(iter.into_iter().collect())
+            .into_iter()
+            .map(|items| Arc::new(Node::Leaf(items)))
+            .collect();
+        let mut height = 0;
+        while nodes.len() > 1 {
+            nodes = chunk(nodes)
+                .into_iter()
+                .map(|children| Arc::new(Node::branch(children)))
+                .collect();
+            height += 1;
+        }
+        RrbVector::from_nodes(nodes, height)
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for RrbVector<T> {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+impl<T: Clone> From<&PersistentVector<T>> for RrbVector<T> {
+    fn from(v: &PersistentVector<T>) -> Self {
+        v.iter().cloned().collect()
+    }
+}
+
+pub struct Iter<'a, T> {
+    stack: Vec<slice::Iter<'a, Arc<Node<T>>>>,
+    leaf: slice::Iter<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(item);
+            }
+            let child = loop {
+                match self.stack.last_mut()?.next() {
+                    Some(child) => break child,
+                    None => {
+                        self.stack.pop();
+                    }
+                }
+            };
+            match child.as_ref() {
+                Node::Leaf(items) => self.leaf = items.iter(),
+                Node::Branch { children, .. } => self.stack.push(children.iter()),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T: Clone> IntoIterator for &'a RrbVector<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edn::EDN;
+
+    // Checks sizes, leaf depth and node occupancy, returning the item count.
+    fn check_node<T: Clone>(node: &Node<T>, height: usize, is_root: bool) -> usize {
+        let width = match node {
+            Node::Leaf(items) => {
+                assert_eq!(height, 0, "leaf above the bottom level");
+                items.len()
+            }
+            Node::Branch { sizes, children } => {
+                assert!(height > 0, "branch at the leaf level");
+                assert_eq!(sizes.len(), children.len());
+                let mut total = 0;
+                for (size, child) in sizes.iter().zip(children) {
+                    total += check_node(child, height - 1, false);
+                    assert_eq!(*size, total);
+                }
+                children.len()
+            }
+        };
+        assert!(width <= MAX, "node of {} entries", width);
+        if !is_root {
+            assert!(width >= MIN, "node of {} entries", width);
+        }
+        node.len()
+    }
+
+    fn check<T: Clone>(v: &RrbVector<T>) {
+        assert_eq!(check_node(&v.root, v.height, true), v.count);
+        if let Node::Branch { children, .. } = v.root.as_ref() {
+            assert!(children.len() > 1, "single-child root");
+        }
+    }
+
+    fn range(start: usize, end: usize) -> RrbVector<usize> {
+        (start..end).collect()
+    }
+
+    // A small linear congruential generator, so the tests are repeatable.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % n.max(1)
+        }
+    }
+
+    #[test]
+    fn test_from_iter_and_get() {
+        for n in [0, 1, 31, 32, 33, 1000, 1024, 1025, 40_000] {
+            let v = range(0, n);
+            check(&v);
+            assert_eq!(v.len(), n);
+            assert!((0..n).all(|i| v[i] == i));
+            assert_eq!(v.get(n), None);
+            assert!(v.iter().copied().eq(0..n));
+            assert_eq!(v.iter().len(), n);
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        for (a, b) in [
+            (0, 5),
+            (5, 0),
+            (1, 1),
+            (10, 3000),
+            (3000, 10),
+            (1025, 33),
+            (20_000, 20_000),
+        ] {
+            let v = range(0, a).concat(&range(a, a + b));
+            check(&v);
+            assert_eq!(v.len(), a + b);
+            assert!(v.iter().copied().eq(0..a + b));
+        }
+    }
+
+    #[test]
+    fn test_repeated_small_concats_stay_balanced() {
+        let mut v = RrbVector::new();
+        for i in 0..2000 {
+            v = v.concat(&range(i * 3, i * 3 + 3));
+        }
+        check(&v);
+        assert!(v.iter().copied().eq(0..6000));
+        assert!(v.height <= 3);
+    }
+
+    #[test]
+    fn test_split_at() {
+        let v = range(0, 5000);
+        for index in [0, 1, 31, 32, 33, 1024, 2500, 4999, 5000, 6000] {
+            let (left, right) = v.split_at(index);
+            check(&left);
+            check(&right);
+            let index = index.min(5000);
+            assert!(left.iter().copied().eq(0..index));
+            assert!(right.iter().copied().eq(index..5000));
+            assert_eq!(left.concat(&right), v);
+        }
+    }
+
+    #[test]
+    fn test_insert_slice_assoc_pop() {
+        let v = range(0, 100);
+        let w = v.insert_at(50, 1000).unwrap();
+        check(&w);
+        assert_eq!(w.len(), 101);
+        assert_eq!(w[50], 1000);
+        assert_eq!(w[51], 50);
+        assert!(v.insert_at(101, 0).is_none());
+        assert_eq!(v.slice(10, 20).unwrap(), range(10, 20));
+        assert!(v.slice(20, 10).is_none());
+        assert_eq!(v.assoc(3, 0).unwrap()[3], 0);
+        assert_eq!(v[3], 3);
+        assert_eq!(v.assoc(100, 100).unwrap(), range(0, 101));
+        assert!(v.assoc(101, 0).is_none());
+        assert_eq!(v.pop().unwrap(), range(0, 99));
+        assert_eq!(v.peek(), Some(&99));
+        assert!(RrbVector::<usize>::new().pop().is_none());
+    }
+
+    #[test]
+    fn test_matches_vec_under_random_edits() {
+        let mut rng = Lcg(7);
+        let mut v = range(0, 500);
+        let mut model: Vec<usize> = (0..500).collect();
+        for step in 0..400 {
+            match rng.below(5) {
+                0 => {
+                    let i = rng.below(model.len() + 1);
+                    v = v.insert_at(i, step).unwrap();
+                    model.insert(i, step);
+                }
+                1 => {
+                    // Copy a short run of elements to a random position.
+                    let start = rng.below(model.len());
+                    let end = (start + rng.below(40)).min(model.len());
+                    let run = v.slice(start, end).unwrap();
+                    let at = rng.below(model.len() + 1);
+                    let (left, right) = v.split_at(at);
+                    v = left.concat(&run).concat(&right);
+                    let copied = model[start..end].to_vec();
+                    model.splice(at..at, copied);
+                }
+                2 => {
+                    let i = rng.below(model.len() + 1);
+                    let (left, right) = v.split_at(i);
+                    v = right.concat(&left);
+                    model.rotate_left(i);
+                }
+                3 => {
+                    let start = rng.below(model.len());
+                    let end = (start + rng.below(40)).min(model.len());
+                    v = v
+                        .slice(0, start)
+                        .unwrap()
+                        .concat(&v.slice(end, v.len()).unwrap());
+                    model.drain(start..end);
+                }
+                _ => {
+                    v = v.conj(step);
+                    model.push(step);
+                }
+            }
+            check(&v);
+            assert_eq!(v.to_vec(), model);
+        }
+    }
+
+    #[test]
+    fn test_random_splits_and_concats_keep_nodes_balanced() {
+        let mut rng = Lcg(11);
+        let mut pool: Vec<RrbVector<usize>> = vec![range(0, 1)];
+        for _ in 0..5000 {
+            let a = pool[rng.below(pool.len())].clone();
+            let b = pool[rng.below(pool.len())].clone();
+            let v = match rng.below(4) {
+                0 if a.len() + b.len() <= 100_000 => a.concat(&b),
+                1 => a.split_at(rng.below(a.len() + 1)).0,
+                2 => a.split_at(rng.below(a.len() + 1)).1,
+                _ => {
+                    let max = [40, 3000][rng.below(2)];
+                    range(0, rng.below(max))
+                }
+            };
+            check(&v);
+            pool.push(v);
+            if pool.len() > 50 {
+                pool.swap_remove(rng.below(50));
+            }
+        }
+    }
+
+    #[test]
+    fn test_persistent_vector_interop() {
+        let pv: PersistentVector<usize> = (0..100).collect();
+        let rrb = RrbVector::from(&pv);
+        assert_eq!(rrb, pv);
+        assert_eq!(rrb.to_vector(), pv);
+        let mut h1 = std::collections::hash_map::DefaultHasher::new();
+        let mut h2 = std::collections::hash_map::DefaultHasher::new();
+        rrb.hash(&mut h1);
+        pv.hash(&mut h2);
+        assert_eq!(h1.finish(), h2.finish());
+    }
+
+    #[test]
+    fn test_meta_survives_updates_but_not_equality() {
+        let meta = MetaMap::new().assoc(EDN::from(0i64), EDN::from(1i64));
+        let plain = range(0, 100);
+        let v = plain.with_meta(Some(meta.clone()));
+        assert_eq!(v.meta(), Some(&meta));
+        assert_eq!(v.conj(100).meta(), Some(&meta));
+        assert_eq!(v.pop().unwrap().meta(), Some(&meta));
+        assert_eq!(v.assoc(0, 9).unwrap().meta(), Some(&meta));
+        assert_eq!(v.insert_at(5, 9).unwrap().meta(), Some(&meta));
+        assert_eq!(v.concat(&plain).meta(), Some(&meta));
+        assert_eq!(
+            RrbVector::new()
+                .with_meta(Some(meta.clone()))
+                .concat(&plain)
+                .meta(),
+            Some(&meta)
+        );
+        assert_eq!(plain.concat(&v).meta(), None);
+        assert_eq!(v.split_at(50).0.meta(), None);
+        assert_eq!(v.slice(0, 100).unwrap().meta(), None);
+        assert_eq!(v, plain);
+    }
+}
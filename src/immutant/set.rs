@@ -0,0 +1,324 @@
+use crate::immutant::map::{
+    self, hash_unordered, Meta, MetaMap, PersistentHashMap, TransientHashMap,
+};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+/// A persistent hash set: a `PersistentHashMap` whose values are all `()`,
+/// so it shares the map's structural sharing and collision handling.
+#[derive(Clone)]
+pub struct PersistentHashSet<T> {
+    map: PersistentHashMap<T, ()>,
+    meta: Meta,
+}
+
+impl<T: Hash + Eq + Clone> PersistentHashSet<T> {
+    pub fn new() -> Self {
+        PersistentHashSet {
+            map: PersistentHashMap::new(),
+            meta: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.map.contains_key(item)
+    }
+
+    /// Returns the stored element equal to `item`, as Clojure's `get` on a set.
+    pub fn get(&self, item: &T) -> Option<&T> {
+        self.map.get_key_value(item).map(|(k, _)| k)
+    }
+
+    pub fn conj(&self, item: T) -> Self {
+        PersistentHashSet {
+            map: self.map.assoc(item, ()),
+            meta: self.meta.clone(),
+        }
+    }
+
+    pub fn disj(&self, item: &T) -> Self {
+        PersistentHashSet {
+            map: self.map.dissoc(item),
+            meta: self.meta.clone(),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        // Add the smaller set into the larger one.
+        let (big, small) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small
+            .iter()
+            .fold(big.clone(), |s, item| s.conj(item.clone()))
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (big, small) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small
+            .iter()
+            .filter(|item| !big.contains(item))
+            .fold(small.clone(), |s, item| s.disj(item))
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        if self.len() <= other.len() {
+            self.iter()
+                .filter(|item| other.contains(item))
+                .fold(self.clone(), |s, item| s.disj(item))
+        } else {
+            other.iter().fold(self.clone(), |s, item| s.disj(item))
+        }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|item| other.contains(item))
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this set's items with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentHashSet {
+            map: self.map.clone(),
+            meta: meta.map(Arc::new),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+
+    /// A cursor at the first item, or `None` for an empty set.
+    pub fn cursor(&self) -> Option<Cursor<T>> {
+        self.map.cursor().map(Cursor)
+    }
+
+    /// Returns an editable copy for batch updates; see `TransientHashMap`.
+    pub fn transient(&self) -> TransientHashSet<T> {
+        TransientHashSet {
+            map: self.map.transient(),
+        }
+    }
+}
+
+/// The editable counterpart of `PersistentHashSet`.
+#[derive(Clone)]
+pub struct TransientHashSet<T> {
+    map: TransientHashMap<T, ()>,
+}
+
+impl<T: Hash + Eq + Clone> TransientHashSet<T> {
+    pub fn new() -> Self {
+        PersistentHashSet::new().transient()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.map.contains_key(item)
+    }
+
+    pub fn conj(&mut self, item: T) -> &mut Self {
+        self.map.assoc(item, ());
+        self
+    }
+
+    pub fn disj(&mut self, item: &T) -> &mut Self {
+        self.map.dissoc(item);
+        self
+    }
+
+    /// Freezes the edits into a persistent set.
+    pub fn persistent(self) -> PersistentHashSet<T> {
+        PersistentHashSet {
+            map: self.map.persistent(),
+            meta: None,
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for TransientHashSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone + fmt::Debug> fmt::Debug for TransientHashSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TransientHashSet({:?})", self.map)
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for PersistentHashSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone + fmt::Debug> fmt::Debug for PersistentHashSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq + Clone> PartialEq for PersistentHashSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<T: Hash + Eq + Clone> Eq for PersistentHashSet<T> {}
+
+impl<T: Hash + Eq + Clone> Hash for PersistentHashSet<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_unordered(self.len(), self.iter(), state);
+    }
+}
+
+impl<T: Hash + Eq + Clone> FromIterator<T> for PersistentHashSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = TransientHashSet::new();
+        for item in iter {
+            s.conj(item);
+        }
+        s.persistent()
+    }
+}
+
+pub struct Iter<'a, T> {
+    inner: map::Iter<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T: Hash + Eq + Clone> IntoIterator for &'a PersistentHashSet<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An owned position in a set's iteration order; see `map::Cursor`.
+#[derive(Clone)]
+pub struct Cursor<T>(map::Cursor<T, ()>);
+
+impl<T> Cursor<T> {
+    pub fn item(&self) -> &T {
+        self.0.entry().0
+    }
+
+    /// The cursor at the following item, or `None` after the last one.
+    pub fn next(&self) -> Option<Self> {
+        self.0.next().map(Cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::immutant::map::hash_of;
+
+    fn set(items: &[i32]) -> PersistentHashSet<i32> {
+        items.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_conj_disj_contains() {
+        let s1 = set(&[1, 2, 3]);
+        let s2 = s1.conj(4).conj(1);
+        let s3 = s2.disj(&2).disj(&99);
+        assert_eq!(s1.len(), 3);
+        assert_eq!(s2.len(), 4);
+        assert_eq!(s3.len(), 3);
+        assert!(s2.contains(&4));
+        assert!(!s1.contains(&4));
+        assert!(!s3.contains(&2));
+        assert_eq!(s3.get(&3), Some(&3));
+        assert_eq!(s3.get(&2), None);
+    }
+
+    #[test]
+    fn test_algebra() {
+        let a = set(&[1, 2, 3, 4]);
+        let b = set(&[3, 4, 5]);
+        assert_eq!(a.union(&b), set(&[1, 2, 3, 4, 5]));
+        assert_eq!(b.union(&a), set(&[1, 2, 3, 4, 5]));
+        assert_eq!(a.intersection(&b), set(&[3, 4]));
+        assert_eq!(b.intersection(&a), set(&[3, 4]));
+        assert_eq!(a.difference(&b), set(&[1, 2]));
+        assert_eq!(b.difference(&a), set(&[5]));
+        assert_eq!(a.difference(&set(&[1])), set(&[2, 3, 4]));
+        assert!(set(&[3, 4]).is_subset(&a));
+        assert!(!b.is_subset(&a));
+        assert!(a.is_superset(&set(&[])));
+    }
+
+    #[test]
+    fn test_eq_hash_independent_of_order() {
+        let s1: PersistentHashSet<i32> = (0..500).collect();
+        let s2: PersistentHashSet<i32> = (0..500).rev().collect();
+        let s3 = s1.conj(1000).disj(&1000);
+        assert_eq!(s1, s2);
+        assert_eq!(hash_of(&s1), hash_of(&s2));
+        assert_eq!(hash_of(&s1), hash_of(&s3));
+        assert_ne!(s1, s1.disj(&0));
+        assert_eq!(s1.iter().len(), 500);
+    }
+
+    #[test]
+    fn test_transient() {
+        let s1 = set(&[1, 2, 3]);
+        let mut t = s1.transient();
+        t.conj(4).conj(1).disj(&2).disj(&99);
+        assert_eq!(t.len(), 3);
+        assert!(t.contains(&4));
+        let s2 = t.persistent();
+        assert_eq!(s2, set(&[1, 3, 4]));
+        assert_eq!(s1, set(&[1, 2, 3]));
+    }
+}
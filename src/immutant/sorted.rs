@@ -0,0 +1,944 @@
+use crate::clojure::EvalError;
+use crate::immutant::map::{hash_unordered, Meta, MetaMap};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+/// Orders two keys. Comparisons may fail, as Clojure's `compare` does on
+/// values of unrelated types or a user comparator does on bad input, so every
+/// operation that compares keys returns a `Result`.
+pub type Comparator<K> = Arc<dyn Fn(&K, &K) -> Result<Ordering, EvalError> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    Red,
+    Black,
+}
+
+type Tree<K, V> = Option<Arc<Node<K, V>>>;
+
+struct Node<K, V> {
+    color: Color,
+    left: Tree<K, V>,
+    key: K,
+    value: V,
+    right: Tree<K, V>,
+}
+
+fn node<K, V>(color: Color, left: Tree<K, V>, key: K, value: V, right: Tree<K, V>) -> Tree<K, V> {
+    Some(Arc::new(Node {
+        color,
+        left,
+        key,
+        value,
+        right,
+    }))
+}
+
+fn red<K, V>(tree: &Tree<K, V>) -> Option<Arc<Node<K, V>>> {
+    tree.as_ref().filter(|n| n.color == Color::Red).cloned()
+}
+
+fn black<K, V>(tree: &Tree<K, V>) -> Option<Arc<Node<K, V>>> {
+    tree.as_ref().filter(|n| n.color == Color::Black).cloned()
+}
+
+// The rebalancing below follows Kahrs, "Red-black trees with types" (2001):
+// Okasaki's insertion plus a deletion that restores the invariants on the
+// way back up, which suits path copying.
+impl<K: Clone, V: Clone> Node<K, V> {
+    fn with_color(&self, color: Color) -> Tree<K, V> {
+        node(
+            color,
+            self.left.clone(),
+            self.key.clone(),
+            self.value.clone(),
+            self.right.clone(),
+        )
+    }
+}
+
+fn blacken<K: Clone, V: Clone>(tree: Tree<K, V>) -> Tree<K, V> {
+    match red(&tree) {
+        Some(n) => n.with_color(Color::Black),
+        None => tree,
+    }
+}
+
+fn balance<K: Clone, V: Clone>(l: Tree<K, V>, k: K, v: V, r: Tree<K, V>) -> Tree<K, V> {
+    use Color::*;
+    if let (Some(ln), Some(rn)) = (red(&l), red(&r)) {
+        return node(Red, ln.with_color(Black), k, v, rn.with_color(Black));
+    }
+    if let Some(ln) = red(&l) {
+        if let Some(lln) = red(&ln.left) {
+            return node(
+                Red,
+                lln.with_color(Black),
+                ln.key.clone(),
+                ln.value.clone(),
+                node(Black, ln.right.clone(), k, v, r),
+            );
+        }
+        if let Some(lrn) = red(&ln.right) {
+            return node(
+                Red,
+                node(
+                    Black,
+                    ln.left.clone(),
+                    ln.key.clone(),
+                    ln.value.clone(),
+                    lrn.left.clone(),
+                ),
+                lrn.key.clone(),
+                lrn.value.clone(),
+                node(Black, lrn.right.clone(), k, v, r),
+            );
+        }
+    }
+    if let Some(rn) = red(&r) {
+        if let Some(rrn) = red(&rn.right) {
+            return node(
+                Red,
+                node(Black, l, k, v, rn.left.clone()),
+                rn.key.clone(),
+                rn.value.clone(),
+                rrn.with_color(Black),
+            );
+        }
+        if let Some(rln) = red(&rn.left) {
+            return node(
+                Red,
+                node(Black, l, k, v, rln.left.clone()),
+                rln.key.clone(),
+                rln.value.clone(),
+                node(
+                    Black,
+                    rln.right.clone(),
+                    rn.key.clone(),
+                    rn.value.clone(),
+                    rn.right.clone(),
+                ),
+            );
+        }
+    }
+    node(Black, l, k, v, r)
+}
+
+// Turns a black node red, shortening its black height by one.
+fn redden<K: Clone, V: Clone>(tree: &Tree<K, V>) -> Tree<K, V> {
+    match black(tree) {
+        Some(n) => n.with_color(Color::Red),
+        None => unreachable!("Red-black invariant violated"),
+    }
+}
+
+fn balance_left<K: Clone, V: Clone>(l: Tree<K, V>, k: K, v: V, r: Tree<K, V>) -> Tree<K, V> {
+    use Color::*;
+    if let Some(ln) = red(&l) {
+        return node(Red, ln.with_color(Black), k, v, r);
+    }
+    if let Some(rn) = black(&r) {
+        return balance(l, k, v, rn.with_color(Red));
+    }
+    match (red(&r), r.as_ref().and_then(|rn| black(&rn.left))) {
+        (Some(rn), Some(rln)) => node(
+            Red,
+            node(Black, l, k, v, rln.left.clone()),
+            rln.key.clone(),
+            rln.value.clone(),
+            balance(
+                rln.right.clone(),
+                rn.key.clone(),
+                rn.value.clone(),
+                redden(&rn.right),
+            ),
+        ),
+        _ => unreachable!("Red-black invariant violated"),
+    }
+}
+
+fn balance_right<K: Clone, V: Clone>(l: Tree<K, V>, k: K, v: V, r: Tree<K, V>) -> Tree<K, V> {
+    use Color::*;
+    if let Some(rn) = red(&r) {
+        return node(Red, l, k, v, rn.with_color(Black));
+    }
+    if let Some(ln) = black(&l) {
+        return balance(ln.with_color(Red), k, v, r);
+    }
+    match (red(&l), l.as_ref().and_then(|ln| black(&ln.right))) {
+        (Some(ln), Some(lrn)) => node(
+            Red,
+            balance(
+                redden(&ln.left),
+                ln.key.clone(),
+                ln.value.clone(),
+                lrn.left.clone(),
+            ),
+            lrn.key.clone(),
+            lrn.value.clone(),
+            node(Black, lrn.right.clone(), k, v, r),
+        ),
+        _ => unreachable!("Red-black invariant violated"),
+    }
+}
+
+// Joins the two subtrees of a removed node.
+fn append<K: Clone, V: Clone>(l: &Tree<K, V>, r: &Tree<K, V>) -> Tree<K, V> {
+    use Color::*;
+    let (ln, rn) = match (l, r) {
+        (None, _) => return r.clone(),
+        (_, None) => return l.clone(),
+        (Some(ln), Some(rn)) => (ln, rn),
+    };
+    match (ln.color, rn.color) {
+        (Red, Red) => {
+            let middle = append(&ln.right, &rn.left);
+            match red(&middle) {
+                Some(m) => node(
+                    Red,
+                    node(
+                        Red,
+                        ln.left.clone(),
+                        ln.key.clone(),
+                        ln.value.clone(),
+                        m.left.clone(),
+                    ),
+                    m.key.clone(),
+                    m.value.clone(),
+                    node(
+                        Red,
+                        m.right.clone(),
+                        rn.key.clone(),
+                        rn.value.clone(),
+                        rn.right.clone(),
+                    ),
+                ),
+                None => node(
+                    Red,
+                    ln.left.clone(),
+                    ln.key.clone(),
+                    ln.value.clone(),
+                    node(
+                        Red,
+                        middle,
+                        rn.key.clone(),
+                        rn.value.clone(),
+                        rn.right.clone(),
+                    ),
+                ),
+            }
+        }
+        (Black, Black) => {
+            let middle = append(&ln.right, &rn.left);
+            match red(&middle) {
+                Some(m) => node(
+                    Red,
+                    node(
+                        Black,
+                        ln.left.clone(),
+                        ln.key.clone(),
+                        ln.value.clone(),
+                        m.left.clone(),
+                    ),
+                    m.key.clone(),
+                    m.value.clone(),
+                    node(
+                        Black,
+                        m.right.clone(),
+                        rn.key.clone(),
+                        rn.value.clone(),
+                        rn.right.clone(),
+                    ),
+                ),
+                None => balance_left(
+                    ln.left.clone(),
+                    ln.key.clone(),
+                    ln.value.clone(),
+                    node(
+                        Black,
+                        middle,
+                        rn.key.clone(),
+                        rn.value.clone(),
+                        rn.right.clone(),
+                    ),
+                ),
+            }
+        }
+        (Black, Red) => node(
+            Red,
+            append(l, &rn.left),
+            rn.key.clone(),
+            rn.value.clone(),
+            rn.right.clone(),
+        ),
+        (Red, Black) => node(
+            Red,
+            ln.left.clone(),
+            ln.key.clone(),
+            ln.value.clone(),
+            append(&ln.right, r),
+        ),
+    }
+}
+
+/// A persistent sorted map: a red-black tree ordered by a comparator, as
+/// Clojure's `sorted-map` and `sorted-map-by`. Updates copy the path from the
+/// root to the changed node and share everything else.
+#[derive(Clone)]
+pub struct PersistentTreeMap<K, V> {
+    count: usize,
+    root: Tree<K, V>,
+    comparator: Comparator<K>,
+    meta: Meta,
+}
+
+impl<K: Ord + Clone, V: Clone> PersistentTreeMap<K, V> {
+    /// An empty map ordered by `K`'s natural ordering.
+    pub fn new() -> Self {
+        Self::with_comparator(Arc::new(|a: &K, b: &K| Ok(a.cmp(b))))
+    }
+}
+
+impl<K: Clone, V: Clone> PersistentTreeMap<K, V> {
+    pub fn with_comparator(comparator: Comparator<K>) -> Self {
+        PersistentTreeMap {
+            count: 0,
+            root: None,
+            comparator,
+            meta: None,
+        }
+    }
+
+    /// An empty map with the same comparator and metadata.
+    pub fn empty(&self) -> Self {
+        PersistentTreeMap {
+            meta: self.meta.clone(),
+            ..Self::with_comparator(self.comparator.clone())
+        }
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this map's tree with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentTreeMap {
+            meta: meta.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    pub fn comparator(&self) -> &Comparator<K> {
+        &self.comparator
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<&V>, EvalError> {
+        Ok(self.get_key_value(key)?.map(|(_, v)| v))
+    }
+
+    /// Returns the stored key along with its value.
+    pub fn get_key_value(&self, key: &K) -> Result<Option<(&K, &V)>, EvalError> {
+        let mut current = &self.root;
+        while let Some(n) = current {
+            current = match (self.comparator)(key, &n.key)? {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Ok(Some((&n.key, &n.value))),
+            };
+        }
+        Ok(None)
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool, EvalError> {
+        Ok(self.get_key_value(key)?.is_some())
+    }
+
+    /// Returns a copy with `key` mapped to `value`. An existing equal key is
+    /// kept and only its value replaced.
+    pub fn assoc(&self, key: K, value: V) -> Result<Self, EvalError> {
+        let (root, added) = self.insert(&self.root, key, value)?;
+        Ok(PersistentTreeMap {
+            count: if added { self.count + 1 } else { self.count },
+            root: blacken(root),
+            comparator: self.comparator.clone(),
+            meta: self.meta.clone(),
+        })
+    }
+
+    fn insert(&self, tree: &Tree<K, V>, key: K, value: V) -> Result<(Tree<K, V>, bool), EvalError> {
+        let n = match tree {
+            None => return Ok((node(Color::Red, None, key, value, None), true)),
+            Some(n) => n,
+        };
+        let rebuild = |l, r| match n.color {
+            Color::Black => balance(l, n.key.clone(), n.value.clone(), r),
+            Color::Red => node(Color::Red, l, n.key.clone(), n.value.clone(), r),
+        };
+        Ok(match (self.comparator)(&key, &n.key)? {
+            Ordering::Less => {
+                let (l, added) = self.insert(&n.left, key, value)?;
+                (rebuild(l, n.right.clone()), added)
+            }
+            Ordering::Greater => {
+                let (r, added) = self.insert(&n.right, key, value)?;
+                (rebuild(n.left.clone(), r), added)
+            }
+            Ordering::Equal => {
+                let replaced = node(
+                    n.color,
+                    n.left.clone(),
+                    n.key.clone(),
+                    value,
+                    n.right.clone(),
+                );
+                (replaced, false)
+            }
+        })
+    }
+
+    /// Returns a copy without `key`, or a clone sharing everything when the
+    /// key is absent.
+    pub fn dissoc(&self, key: &K) -> Result<Self, EvalError> {
+        if !self.contains_key(key)? {
+            return Ok(self.clone());
+        }
+        Ok(PersistentTreeMap {
+            count: self.count - 1,
+            root: blacken(self.remove(&self.root, key)?),
+            comparator: self.comparator.clone(),
+            meta: self.meta.clone(),
+        })
+    }
+
+    fn remove(&self, tree: &Tree<K, V>, key: &K) -> Result<Tree<K, V>, EvalError> {
+        let n = match tree {
+            None => return Ok(None),
+            Some(n) => n,
+        };
+        Ok(match (self.comparator)(key, &n.key)? {
+            Ordering::Less => {
+                let l = self.remove(&n.left, key)?;
+                if black(&n.left).is_some() {
+                    balance_left(l, n.key.clone(), n.value.clone(), n.right.clone())
+                } else {
+                    node(
+                        Color::Red,
+                        l,
+                        n.key.clone(),
+                        n.value.clone(),
+                        n.right.clone(),
+                    )
+                }
+            }
+            Ordering::Greater => {
+                let r = self.remove(&n.right, key)?;
+                if black(&n.right).is_some() {
+                    balance_right(n.left.clone(), n.key.clone(), n.value.clone(), r)
+                } else {
+                    node(
+                        Color::Red,
+                        n.left.clone(),
+                        n.key.clone(),
+                        n.value.clone(),
+                        r,
+                    )
+                }
+            }
+            Ordering::Equal => append(&n.left, &n.right),
+        })
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.rseq().next()
+    }
+
+    /// Iterates in ascending order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(&self.root, true)
+    }
+
+    /// Iterates in descending order.
+    pub fn rseq(&self) -> Iter<'_, K, V> {
+        Iter::new(&self.root, false)
+    }
+
+    /// Iterates from `key` onwards, ascending or descending. The first entry
+    /// is the nearest one on the given side of `key`, or `key` itself when
+    /// present and `inclusive`. This is the primitive behind `subseq`.
+    pub fn seq_from(
+        &self,
+        key: &K,
+        inclusive: bool,
+        ascending: bool,
+    ) -> Result<Iter<'_, K, V>, EvalError> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            ascending,
+        };
+        let mut current = &self.root;
+        while let Some(n) = current {
+            let ord = (self.comparator)(&n.key, key)?;
+            let wanted = if ascending {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+            if ord == wanted || (inclusive && ord == Ordering::Equal) {
+                iter.stack.push(n);
+                current = if ascending { &n.left } else { &n.right };
+            } else {
+                current = if ascending { &n.right } else { &n.left };
+            }
+        }
+        Ok(iter)
+    }
+
+    /// A cursor at the first entry in ascending order, or `None` for an
+    /// empty map.
+    pub fn cursor(&self) -> Option<Cursor<K, V>> {
+        Cursor::settle(Vec::new(), &self.root)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for PersistentTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for PersistentTreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Equal when both hold the same entries, whatever their comparators.
+impl<K: Clone, V: Clone + PartialEq> PartialEq for PersistentTreeMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count
+            && self
+                .iter()
+                .all(|(k, v)| matches!(other.get(k), Ok(Some(ov)) if ov == v))
+    }
+}
+
+impl<K: Clone, V: Clone + Eq> Eq for PersistentTreeMap<K, V> {}
+
+impl<K: Clone + Hash, V: Clone + Hash> Hash for PersistentTreeMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_unordered(self.count, self.iter(), state);
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for PersistentTreeMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(PersistentTreeMap::new(), |m, (k, v)| {
+                m.assoc(k, v).expect("Natural ordering cannot fail")
+            })
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+    ascending: bool,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn new(root: &'a Tree<K, V>, ascending: bool) -> Self {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            ascending,
+        };
+        iter.push_edge(root);
+        iter
+    }
+
+    // Pushes the path to the first node of `tree` in iteration order.
+    fn push_edge(&mut self, mut tree: &'a Tree<K, V>) {
+        while let Some(n) = tree {
+            self.stack.push(n);
+            tree = if self.ascending { &n.left } else { &n.right };
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.stack.pop()?;
+        self.push_edge(if self.ascending { &n.right } else { &n.left });
+        Some((&n.key, &n.value))
+    }
+}
+
+impl<'a, K: Clone, V: Clone> IntoIterator for &'a PersistentTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An owned position in a sorted map's ascending order, for walking it
+/// without borrowing it, as a seq does. Like `Iter` it keeps the stack of
+/// nodes whose entries are still to come, so stepping costs at most the
+/// height of the tree.
+pub struct Cursor<K, V> {
+    // The top of the stack is the current entry.
+    stack: Vec<Arc<Node<K, V>>>,
+}
+
+impl<K, V> Clone for Cursor<K, V> {
+    fn clone(&self) -> Self {
+        Cursor {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+impl<K, V> Cursor<K, V> {
+    // Pushes the path to the first node of `tree` onto `stack`.
+    fn settle(mut stack: Vec<Arc<Node<K, V>>>, mut tree: &Tree<K, V>) -> Option<Self> {
+        while let Some(n) = tree {
+            stack.push(n.clone());
+            tree = &n.left;
+        }
+        (!stack.is_empty()).then_some(Cursor { stack })
+    }
+
+    pub fn entry(&self) -> (&K, &V) {
+        let n = self.stack.last().expect("cursor has an entry");
+        (&n.key, &n.value)
+    }
+
+    /// The cursor at the following entry, or `None` after the last one.
+    pub fn next(&self) -> Option<Self> {
+        let mut stack = self.stack.clone();
+        let n = stack.pop()?;
+        Cursor::settle(stack, &n.right)
+    }
+}
+
+/// A persistent sorted set: a `PersistentTreeMap` whose values are all `()`.
+#[derive(Clone)]
+pub struct PersistentTreeSet<T> {
+    map: PersistentTreeMap<T, ()>,
+    meta: Meta,
+}
+
+impl<T: Ord + Clone> PersistentTreeSet<T> {
+    pub fn new() -> Self {
+        PersistentTreeSet {
+            map: PersistentTreeMap::new(),
+            meta: None,
+        }
+    }
+}
+
+impl<T: Clone> PersistentTreeSet<T> {
+    pub fn with_comparator(comparator: Comparator<T>) -> Self {
+        PersistentTreeSet {
+            map: PersistentTreeMap::with_comparator(comparator),
+            meta: None,
+        }
+    }
+
+    pub fn empty(&self) -> Self {
+        PersistentTreeSet {
+            map: self.map.empty(),
+            meta: self.meta.clone(),
+        }
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this set's tree with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentTreeSet {
+            map: self.map.clone(),
+            meta: meta.map(Arc::new),
+        }
+    }
+
+    pub fn comparator(&self) -> &Comparator<T> {
+        self.map.comparator()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, item: &T) -> Result<bool, EvalError> {
+        self.map.contains_key(item)
+    }
+
+    /// Returns the stored element equal to `item`.
+    pub fn get(&self, item: &T) -> Result<Option<&T>, EvalError> {
+        Ok(self.map.get_key_value(item)?.map(|(k, _)| k))
+    }
+
+    pub fn conj(&self, item: T) -> Result<Self, EvalError> {
+        Ok(PersistentTreeSet {
+            map: self.map.assoc(item, ())?,
+            meta: self.meta.clone(),
+        })
+    }
+
+    pub fn disj(&self, item: &T) -> Result<Self, EvalError> {
+        Ok(PersistentTreeSet {
+            map: self.map.dissoc(item)?,
+            meta: self.meta.clone(),
+        })
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.map.first().map(|(k, _)| k)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.map.last().map(|(k, _)| k)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.map.keys()
+    }
+
+    pub fn rseq(&self) -> impl Iterator<Item = &T> {
+        self.map.rseq().map(|(k, _)| k)
+    }
+
+    /// A cursor at the first item in ascending order, or `None` for an
+    /// empty set.
+    pub fn cursor(&self) -> Option<SetCursor<T>> {
+        self.map.cursor().map(SetCursor)
+    }
+
+    /// See `PersistentTreeMap::seq_from`.
+    pub fn seq_from(
+        &self,
+        item: &T,
+        inclusive: bool,
+        ascending: bool,
+    ) -> Result<impl Iterator<Item = &T>, EvalError> {
+        Ok(self
+            .map
+            .seq_from(item, inclusive, ascending)?
+            .map(|(k, _)| k))
+    }
+}
+
+impl<T: Ord + Clone> Default for PersistentTreeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for PersistentTreeSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> PartialEq for PersistentTreeSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T: Clone> Eq for PersistentTreeSet<T> {}
+
+impl<T: Clone + Hash> Hash for PersistentTreeSet<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_unordered(self.len(), self.iter(), state);
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for PersistentTreeSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PersistentTreeSet {
+            map: iter.into_iter().map(|item| (item, ())).collect(),
+            meta: None,
+        }
+    }
+}
+
+/// An owned position in a sorted set's ascending order; see `Cursor`.
+#[derive(Clone)]
+pub struct SetCursor<T>(Cursor<T, ()>);
+
+impl<T> SetCursor<T> {
+    pub fn item(&self) -> &T {
+        self.0.entry().0
+    }
+
+    /// The cursor at the following item, or `None` after the last one.
+    pub fn next(&self) -> Option<Self> {
+        self.0.next().map(SetCursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks the red-black invariants and returns the black height.
+    fn check<K: Ord, V>(tree: &Tree<K, V>, parent_red: bool) -> usize {
+        match tree {
+            None => 1,
+            Some(n) => {
+                let is_red = n.color == Color::Red;
+                assert!(!(parent_red && is_red), "Red node with a red parent");
+                if let Some(l) = &n.left {
+                    assert!(l.key < n.key);
+                }
+                if let Some(r) = &n.right {
+                    assert!(r.key > n.key);
+                }
+                let lh = check(&n.left, is_red);
+                let rh = check(&n.right, is_red);
+                assert_eq!(lh, rh, "Unequal black heights");
+                lh + if is_red { 0 } else { 1 }
+            }
+        }
+    }
+
+    fn check_map<K: Ord, V>(m: &PersistentTreeMap<K, V>) {
+        assert!(m.root.as_ref().is_none_or(|n| n.color == Color::Black));
+        check(&m.root, false);
+    }
+
+    // A fixed shuffle of 0..n, so the tests see non-sequential inserts.
+    fn shuffled(n: u64) -> Vec<u64> {
+        (0..n).map(|i| (i * 7919) % n).collect()
+    }
+
+    #[test]
+    fn test_assoc_get_ordered() {
+        let keys = shuffled(1000);
+        let m: PersistentTreeMap<u64, u64> = keys.iter().map(|&k| (k, k * 10)).collect();
+        check_map(&m);
+        assert_eq!(m.len(), 1000);
+        assert_eq!(m.get(&500).unwrap(), Some(&5000));
+        assert_eq!(m.get(&1000).unwrap(), None);
+        assert!(m.keys().cloned().eq(0..1000));
+        assert!(m.rseq().map(|(k, _)| *k).eq((0..1000).rev()));
+        assert_eq!(m.first(), Some((&0, &0)));
+        assert_eq!(m.last(), Some((&999, &9990)));
+
+        let m2 = m.assoc(500, 1).unwrap();
+        assert_eq!(m2.len(), 1000);
+        assert_eq!(m2.get(&500).unwrap(), Some(&1));
+        assert_eq!(m.get(&500).unwrap(), Some(&5000));
+    }
+
+    #[test]
+    fn test_dissoc_keeps_invariants() {
+        let keys = shuffled(500);
+        let full: PersistentTreeMap<u64, u64> = keys.iter().map(|&k| (k, k)).collect();
+        let mut m = full.clone();
+        for (i, k) in keys.iter().enumerate() {
+            if i % 3 != 0 {
+                m = m.dissoc(k).unwrap();
+                check_map(&m);
+            }
+        }
+        assert_eq!(m.len(), keys.iter().step_by(3).count());
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(m.contains_key(k).unwrap(), i % 3 == 0);
+        }
+        assert_eq!(m.dissoc(&10_000).unwrap(), m);
+        assert_eq!(full.len(), 500);
+
+        let emptied = keys.iter().fold(full, |m, k| m.dissoc(k).unwrap());
+        assert!(emptied.is_empty());
+        assert!(emptied.root.is_none());
+    }
+
+    #[test]
+    fn test_seq_from() {
+        let m: PersistentTreeMap<u64, ()> = (0..20).step_by(2).map(|k| (k, ())).collect();
+        let from = |k, inclusive, ascending| -> Vec<u64> {
+            m.seq_from(&k, inclusive, ascending)
+                .unwrap()
+                .map(|(k, _)| *k)
+                .collect()
+        };
+        assert_eq!(from(14, true, true), vec![14, 16, 18]);
+        assert_eq!(from(14, false, true), vec![16, 18]);
+        assert_eq!(from(15, true, true), vec![16, 18]);
+        assert_eq!(from(4, true, false), vec![4, 2, 0]);
+        assert_eq!(from(4, false, false), vec![2, 0]);
+        assert_eq!(from(5, false, false), vec![4, 2, 0]);
+        assert!(from(19, true, true).is_empty());
+    }
+
+    #[test]
+    fn test_custom_comparator() {
+        let reverse: Comparator<i32> = Arc::new(|a: &i32, b: &i32| Ok(b.cmp(a)));
+        let s = (1..=5).fold(PersistentTreeSet::with_comparator(reverse), |s, i| {
+            s.conj(i).unwrap()
+        });
+        assert!(s.iter().cloned().eq((1..=5).rev()));
+        assert_eq!(s.first(), Some(&5));
+        assert_eq!(
+            s.empty().conj(1).unwrap().conj(2).unwrap().first(),
+            Some(&2)
+        );
+        assert_eq!(s, (1..=5).collect::<PersistentTreeSet<i32>>());
+    }
+
+    #[test]
+    fn test_comparator_errors() {
+        let picky: Comparator<i32> = Arc::new(|a: &i32, b: &i32| {
+            if *a < 0 || *b < 0 {
+                Err(EvalError::Native(
+                    "Negative keys are not comparable".to_string(),
+                ))
+            } else {
+                Ok(a.cmp(b))
+            }
+        });
+        let s = PersistentTreeSet::with_comparator(picky)
+            .conj(1)
+            .unwrap()
+            .conj(2)
+            .unwrap();
+        assert!(s.conj(-1).is_err());
+        assert!(s.contains(&-1).is_err());
+        assert!(s.disj(&-1).is_err());
+        assert_eq!(s.len(), 2);
+    }
+}
@@ -0,0 +1,1185 @@
+use crate::immutant::map::{Meta, MetaMap};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, Rev};
+use std::ops::Index;
+use std::sync::Arc;
+
+const BRANCH_FACTOR: usize = 32;
+const BITS: usize = 5; // log2(32)
+const MASK: usize = BRANCH_FACTOR - 1;
+
+/// A persistent vector: a 32-way trie of leaves plus a tail buffer, as in
+/// Clojure. Updates copy only the path to the changed leaf and share the rest
+/// with the original.
+#[derive(Clone)]
+pub struct PersistentVector<T> {
+    count: usize,
+    shift: usize,
+    root: Arc<Node<T>>,
+    tail: Arc<Vec<T>>,
+    meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+enum Node<T> {
+    Internal(Vec<Arc<Node<T>>>),
+    Leaf(Vec<T>),
+}
+
+fn empty_node<T>() -> Arc<Node<T>> {
+    Arc::new(Node::Internal(Vec::new()))
+}
+
+// Wraps `node` in single-child internal nodes until it sits `level` bits
+// below the node that will hold the result.
+fn new_path<T>(level: usize, node: Arc<Node<T>>) -> Arc<Node<T>> {
+    if level == 0 {
+        node
+    } else {
+        Arc::new(Node::Internal(vec![new_path(level - BITS, node)]))
+    }
+}
+
+fn tail_offset(count: usize) -> usize {
+    if count < BRANCH_FACTOR {
+        0
+    } else {
+        ((count - 1) >> BITS) << BITS
+    }
+}
+
+// The leaf of the trie under `root` that holds `index`.
+fn leaf_for<T>(root: &Arc<Node<T>>, shift: usize, index: usize) -> &[T] {
+    let mut node = root;
+    let mut level = shift;
+    while level > 0 {
+        match node.as_ref() {
+            Node::Internal(children) => node = &children[(index >> level) & MASK],
+            Node::Leaf(_) => break,
+        }
+        level -= BITS;
+    }
+
+    match node.as_ref() {
+        Node::Leaf(arr) => arr,
+        Node::Internal(_) => panic!("Malformed vector trie at index {}", index),
+    }
+}
+
+impl<T: Clone> PersistentVector<T> {
+    pub fn new() -> Self {
+        PersistentVector {
+            count: 0,
+            shift: BITS,
+            root: empty_node(),
+            tail: Arc::new(Vec::new()),
+            meta: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn meta(&self) -> Option<&MetaMap> {
+        self.meta.as_deref()
+    }
+
+    /// Returns a copy sharing this vector's nodes with `meta` attached.
+    pub fn with_meta(&self, meta: Option<MetaMap>) -> Self {
+        PersistentVector {
+            meta: meta.map(Arc::new),
+            ..self.clone()
+        }
+    }
+
+    pub fn conj(&self, item: T) -> Self {
+        if self.tail.len() < BRANCH_FACTOR {
+            // Room in tail, just add to it
+            let mut new_tail = (*self.tail).clone();
+            new_tail.push(item);
+
+            return PersistentVector {
+                count: self.count + 1,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Arc::new(new_tail),
+                meta: self.meta.clone(),
+            };
+        }
+
+        // Tail is full, push it into the tree. When the tree is already full
+        // at this depth, grow a new root above the old one.
+        let tail_node = Arc::new(Node::Leaf((*self.tail).clone()));
+        let (root, shift) = if (self.count >> BITS) > (1 << self.shift) {
+            let new_root = Node::Internal(vec![self.root.clone(), new_path(self.shift, tail_node)]);
+            (Arc::new(new_root), self.shift + BITS)
+        } else {
+            (
+                self.push_tail(self.shift, &self.root, tail_node),
+                self.shift,
+            )
+        };
+
+        PersistentVector {
+            count: self.count + 1,
+            shift,
+            root,
+            tail: Arc::new(vec![item]),
+            meta: self.meta.clone(),
+        }
+    }
+
+    fn push_tail(&self, level: usize, parent: &Node<T>, tail_node: Arc<Node<T>>) -> Arc<Node<T>> {
+        let subidx = ((self.count - 1) >> level) & MASK;
+        let children = match parent {
+            Node::Internal(children) => children,
+            Node::Leaf(_) => panic!("Cannot push tail to leaf node"),
+        };
+
+        let node_to_insert = if level == BITS {
+            tail_node
+        } else if let Some(child) = children.get(subidx) {
+            self.push_tail(level - BITS, child, tail_node)
+        } else {
+            new_path(level - BITS, tail_node)
+        };
+
+        let mut new_children = children.clone();
+        if subidx < new_children.len() {
+            new_children[subidx] = node_to_insert;
+        } else {
+            new_children.push(node_to_insert);
+        }
+        Arc::new(Node::Internal(new_children))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.count {
+            return None;
+        }
+        self.array_for(index).get(index & MASK)
+    }
+
+    fn tail_offset(&self) -> usize {
+        tail_offset(self.count)
+    }
+
+    // The leaf (or tail) holding `index`, which must be in bounds.
+    fn array_for(&self, index: usize) -> &[T] {
+        if index >= self.tail_offset() {
+            return &self.tail;
+        }
+        leaf_for(&self.root, self.shift, index)
+    }
+
+    /// Returns the vector without its last element, or `None` when empty.
+    pub fn pop(&self) -> Option<Self> {
+        if self.count == 0 {
+            return None;
+        }
+
+        if self.count == 1 {
+            return Some(PersistentVector {
+                meta: self.meta.clone(),
+                ..PersistentVector::new()
+            });
+        }
+
+        if self.tail.len() > 1 {
+            // Remove from tail
+            let mut new_tail = (*self.tail).clone();
+            new_tail.pop();
+
+            return Some(PersistentVector {
+                count: self.count - 1,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Arc::new(new_tail),
+                meta: self.meta.clone(),
+            });
+        }
+
+        // The last leaf of the tree becomes the new tail. If that leaves the
+        // root with a single child, that child becomes the root.
+        let new_tail = self.array_for(self.count - 2).to_vec();
+        let mut root = self
+            .pop_tail(self.shift, &self.root)
+            .unwrap_or_else(empty_node);
+        let mut shift = self.shift;
+        if shift > BITS {
+            if let Node::Internal(children) = root.as_ref() {
+                if children.len() == 1 {
+                    root = children[0].clone();
+                    shift -= BITS;
+                }
+            }
+        }
+
+        Some(PersistentVector {
+            count: self.count - 1,
+            shift,
+            root,
+            tail: Arc::new(new_tail),
+            meta: self.meta.clone(),
+        })
+    }
+
+    fn pop_tail(&self, level: usize, node: &Node<T>) -> Option<Arc<Node<T>>> {
+        let subidx = ((self.count - 2) >> level) & MASK;
+        let children = match node {
+            Node::Internal(children) => children,
+            Node::Leaf(_) => return None,
+        };
+
+        if level > BITS {
+            let new_child = self.pop_tail(level - BITS, &children[subidx]);
+            if new_child.is_none() && subidx == 0 {
+                return None;
+            }
+            let mut new_children = children.clone();
+            match new_child {
+                Some(child) => new_children[subidx] = child,
+                None => new_children.truncate(subidx),
+            }
+            Some(Arc::new(Node::Internal(new_children)))
+        } else if subidx == 0 {
+            None
+        } else {
+            let mut new_children = children.clone();
+            new_children.truncate(subidx);
+            Some(Arc::new(Node::Internal(new_children)))
+        }
+    }
+
+    /// Returns a copy with `index` set to `value`, copying only the path to
+    /// the leaf that holds it. As in Clojure, `index == len()` appends; any
+    /// larger index is `None`.
+    pub fn assoc(&self, index: usize, value: T) -> Option<Self> {
+        if index == self.count {
+            return Some(self.conj(value));
+        }
+        if index > self.count {
+            return None;
+        }
+
+        if index >= self.tail_offset() {
+            let mut new_tail = (*self.tail).clone();
+            new_tail[index & MASK] = value;
+            return Some(PersistentVector {
+                tail: Arc::new(new_tail),
+                ..self.clone()
+            });
+        }
+
+        Some(PersistentVector {
+            root: Self::do_assoc(self.shift, &self.root, index, value),
+            ..self.clone()
+        })
+    }
+
+    fn do_assoc(level: usize, node: &Node<T>, index: usize, value: T) -> Arc<Node<T>> {
+        match node {
+            Node::Leaf(arr) => {
+                let mut new_arr = arr.clone();
+                new_arr[index & MASK] = value;
+                Arc::new(Node::Leaf(new_arr))
+            }
+            Node::Internal(children) => {
+                let subidx = (index >> level) & MASK;
+                let mut new_children = children.clone();
+                new_children[subidx] =
+                    Self::do_assoc(level - BITS, &children[subidx], index, value);
+                Arc::new(Node::Internal(new_children))
+            }
+        }
+    }
+
+    /// Replaces the element at `index` with `f` applied to it, or `None` when
+    /// `index` is out of bounds.
+    pub fn update<F>(&self, index: usize, f: F) -> Option<Self>
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let value = f(self.get(index)?);
+        self.assoc(index, value)
+    }
+
+    /// The last element, which is where `conj` adds and `pop` removes.
+    pub fn peek(&self) -> Option<&T> {
+        self.tail.last()
+    }
+
+    pub fn contains_index(&self, index: usize) -> bool {
+        index < self.count
+    }
+
+    /// An O(1) view of the elements in `start..end` that shares this
+    /// vector's storage, or `None` if the range is out of bounds.
+    pub fn subvec(&self, start: usize, end: usize) -> Option<SubVector<T>> {
+        if start > end || end > self.count {
+            return None;
+        }
+        Some(SubVector {
+            vector: self.clone(),
+            start,
+            end,
+        })
+    }
+
+    pub fn iter(&self) -> PersistentVectorIter<'_, T> {
+        self.range_iter(0, self.count)
+    }
+
+    fn range_iter(&self, start: usize, end: usize) -> PersistentVectorIter<'_, T> {
+        PersistentVectorIter {
+            vector: self,
+            front: start,
+            back: end,
+            front_chunk: &[],
+            back_chunk: &[],
+        }
+    }
+
+    /// Iterates from the last element to the first.
+    pub fn rseq(&self) -> Rev<PersistentVectorIter<'_, T>> {
+        self.iter().rev()
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    /// Returns an editable copy for batch updates. It shares this vector's
+    /// trie and copies each node only the first time it is changed.
+    pub fn transient(&self) -> TransientVector<T> {
+        TransientVector {
+            count: self.count,
+            shift: self.shift,
+            root: self.root.clone(),
+            tail: (*self.tail).clone(),
+        }
+    }
+}
+
+/// The editable counterpart of `PersistentVector`, as Clojure's transient
+/// vectors. Edits happen in place on nodes it owns alone; nodes still shared
+/// with a persistent vector are copied on first write. Ownership rules out
+/// use after `persistent`.
+#[derive(Clone)]
+pub struct TransientVector<T> {
+    count: usize,
+    shift: usize,
+    root: Arc<Node<T>>,
+    tail: Vec<T>,
+}
+
+impl<T: Clone> TransientVector<T> {
+    pub fn new() -> Self {
+        PersistentVector::new().transient()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.count {
+            None
+        } else if index >= tail_offset(self.count) {
+            self.tail.get(index & MASK)
+        } else {
+            leaf_for(&self.root, self.shift, index).get(index & MASK)
+        }
+    }
+
+    pub fn conj(&mut self, item: T) -> &mut Self {
+        if self.tail.len() == BRANCH_FACTOR {
+            let full_tail = std::mem::replace(&mut self.tail, Vec::with_capacity(BRANCH_FACTOR));
+            let tail_node = Arc::new(Node::Leaf(full_tail));
+            if (self.count >> BITS) > (1 << self.shift) {
+                let old_root = self.root.clone();
+                self.root = Arc::new(Node::Internal(vec![
+                    old_root,
+                    new_path(self.shift, tail_node),
+                ]));
+                self.shift += BITS;
+            } else {
+                Self::push_tail(
+                    self.count,
+                    self.shift,
+                    Arc::make_mut(&mut self.root),
+                    tail_node,
+                );
+            }
+        }
+        self.tail.push(item);
+        self.count += 1;
+        self
+    }
+
+    fn push_tail(count: usize, level: usize, parent: &mut Node<T>, tail_node: Arc<Node<T>>) {
+        let subidx = ((count - 1) >> level) & MASK;
+        let children = match parent {
+            Node::Internal(children) => children,
+            Node::Leaf(_) => panic!("Cannot push tail to leaf node"),
+        };
+
+        if level == BITS {
+            if subidx < children.len() {
+                children[subidx] = tail_node;
+            } else {
+                children.push(tail_node);
+            }
+        } else if let Some(child) = children.get_mut(subidx) {
+            Self::push_tail(count, level - BITS, Arc::make_mut(child), tail_node);
+        } else {
+            children.push(new_path(level - BITS, tail_node));
+        }
+    }
+
+    /// Sets `index` to `value`; `index == len()` appends. Returns `None`
+    /// without changing anything when `index` is out of bounds.
+    pub fn assoc(&mut self, index: usize, value: T) -> Option<&mut Self> {
+        if index == self.count {
+            return Some(self.conj(value));
+        }
+        if index > self.count {
+            return None;
+        }
+
+        if index >= tail_offset(self.count) {
+            self.tail[index & MASK] = value;
+        } else {
+            let mut node = Arc::make_mut(&mut self.root);
+            let mut level = self.shift;
+            loop {
+                match node {
+                    Node::Internal(children) => {
+                        node = Arc::make_mut(&mut children[(index >> level) & MASK]);
+                        level -= BITS;
+                    }
+                    Node::Leaf(arr) => {
+                        arr[index & MASK] = value;
+                        break;
+                    }
+                }
+            }
+        }
+        Some(self)
+    }
+
+    /// Removes and returns the last element.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.count == 0 {
+            return None;
+        }
+        if self.tail.len() > 1 || self.count == 1 {
+            self.count -= 1;
+            return self.tail.pop();
+        }
+
+        // The tail is about to empty, so refill it from the trie the way the
+        // persistent pop does. This happens once every 32 pops.
+        let current = std::mem::take(self).persistent();
+        let item = current.peek().cloned();
+        *self = current.pop()?.transient();
+        item
+    }
+
+    /// Freezes the edits into a persistent vector.
+    pub fn persistent(self) -> PersistentVector<T> {
+        PersistentVector {
+            count: self.count,
+            shift: self.shift,
+            root: self.root,
+            tail: Arc::new(self.tail),
+            meta: None,
+        }
+    }
+}
+
+impl<T: Clone> Default for TransientVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for TransientVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.count).filter_map(|i| self.get(i)))
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for PersistentVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for PersistentVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: fmt::Display + Clone> fmt::Display for PersistentVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for PersistentVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for PersistentVector<T> {}
+
+// Clojure orders vectors by length first, then element by element.
+impl<T: Clone + PartialOrd> PartialOrd for PersistentVector<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.count.cmp(&other.count) {
+            Ordering::Equal => self.iter().partial_cmp(other.iter()),
+            ord => Some(ord),
+        }
+    }
+}
+
+impl<T: Clone + Ord> Ord for PersistentVector<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.count
+            .cmp(&other.count)
+            .then_with(|| self.iter().cmp(other.iter()))
+    }
+}
+
+impl<T: Clone + Hash> Hash for PersistentVector<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.count.hash(state);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: Clone> Index<usize> for PersistentVector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).unwrap_or_else(|| {
+            panic!(
+                "Index {} out of bounds for vector of length {}",
+                index, self.count
+            )
+        })
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = TransientVector::new();
+        for item in iter {
+            v.conj(item);
+        }
+        v.persistent()
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for PersistentVector<T> {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+pub struct PersistentVectorIter<'a, T> {
+    vector: &'a PersistentVector<T>,
+    front: usize,
+    back: usize,
+    front_chunk: &'a [T],
+    back_chunk: &'a [T],
+}
+
+// Both ends walk the trie once per leaf rather than once per element.
+impl<'a, T: Clone> Iterator for PersistentVectorIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        if self.front & MASK == 0 || self.front_chunk.is_empty() {
+            self.front_chunk = self.vector.array_for(self.front);
+        }
+        let item = &self.front_chunk[self.front & MASK];
+        self.front += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: Clone> DoubleEndedIterator for PersistentVectorIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        if self.back & MASK == MASK || self.back_chunk.is_empty() {
+            self.back_chunk = self.vector.array_for(self.back);
+        }
+        Some(&self.back_chunk[self.back & MASK])
+    }
+}
+
+impl<'a, T: Clone> ExactSizeIterator for PersistentVectorIter<'a, T> {}
+
+impl<'a, T: Clone> IntoIterator for &'a PersistentVector<T> {
+    type Item = &'a T;
+    type IntoIter = PersistentVectorIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<T> {
+    vector: PersistentVector<T>,
+    index: usize,
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let item = self.vector.get(self.index).cloned();
+        self.index += 1;
+        item
+    }
+}
+
+impl<T: Clone> IntoIterator for PersistentVector<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            vector: self,
+            index: 0,
+        }
+    }
+}
+
+/// A view of a contiguous range of a `PersistentVector`, made by
+/// `PersistentVector::subvec`. Like Clojure's subvec it keeps the whole
+/// underlying vector alive.
+#[derive(Clone)]
+pub struct SubVector<T> {
+    vector: PersistentVector<T>,
+    start: usize,
+    end: usize,
+}
+
+impl<T: Clone> SubVector<T> {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.vector.get(self.start + index)
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn contains_index(&self, index: usize) -> bool {
+        index < self.len()
+    }
+
+    pub fn conj(&self, item: T) -> Self {
+        SubVector {
+            vector: self.vector.assoc(self.end, item).unwrap(),
+            start: self.start,
+            end: self.end + 1,
+        }
+    }
+
+    pub fn assoc(&self, index: usize, value: T) -> Option<Self> {
+        if index > self.len() {
+            return None;
+        }
+        Some(SubVector {
+            vector: self.vector.assoc(self.start + index, value)?,
+            start: self.start,
+            end: self.end.max(self.start + index + 1),
+        })
+    }
+
+    pub fn pop(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(SubVector {
+            end: self.end - 1,
+            ..self.clone()
+        })
+    }
+
+    /// A narrower view over the same underlying vector.
+    pub fn subvec(&self, start: usize, end: usize) -> Option<SubVector<T>> {
+        if start > end || end > self.len() {
+            return None;
+        }
+        Some(SubVector {
+            vector: self.vector.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        })
+    }
+
+    pub fn iter(&self) -> PersistentVectorIter<'_, T> {
+        self.vector.range_iter(self.start, self.end)
+    }
+
+    pub fn rseq(&self) -> Rev<PersistentVectorIter<'_, T>> {
+        self.iter().rev()
+    }
+
+    /// Copies the viewed elements into a vector of their own.
+    pub fn to_vector(&self) -> PersistentVector<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for SubVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for SubVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq<PersistentVector<T>> for SubVector<T> {
+    fn eq(&self, other: &PersistentVector<T>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a SubVector<T> {
+    type Item = &'a T;
+    type IntoIter = PersistentVectorIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edn::EDN;
+    use crate::immutant::map::hash_of;
+
+    #[test]
+    fn test_empty_vector() {
+        let v: PersistentVector<i32> = PersistentVector::new();
+        assert_eq!(v.len(), 0);
+        assert!(v.is_empty());
+        assert_eq!(v.get(0), None);
+        assert!(v.pop().is_none());
+    }
+
+    #[test]
+    fn test_conj() {
+        let v1 = PersistentVector::new();
+        let v2 = v1.conj(1);
+        let v3 = v2.conj(2);
+
+        assert_eq!(v1.len(), 0);
+        assert_eq!(v2.len(), 1);
+        assert_eq!(v3.len(), 2);
+
+        assert_eq!(v2.get(0), Some(&1));
+        assert_eq!(v3.get(0), Some(&1));
+        assert_eq!(v3.get(1), Some(&2));
+    }
+
+    #[test]
+    fn test_large_vector() {
+        let mut v = PersistentVector::new();
+        for i in 0..1000 {
+            v = v.conj(i);
+        }
+
+        assert_eq!(v.len(), 1000);
+        for i in 0..1000 {
+            assert_eq!(v.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn test_pop() {
+        let v1 = PersistentVector::new().conj(1).conj(2).conj(3);
+        let v2 = v1.pop().unwrap();
+        let v3 = v2.pop().unwrap();
+
+        assert_eq!(v1.len(), 3);
+        assert_eq!(v2.len(), 2);
+        assert_eq!(v3.len(), 1);
+
+        assert_eq!(v1.get(2), Some(&3));
+        assert_eq!(v2.get(1), Some(&2));
+        assert_eq!(v3.get(0), Some(&1));
+    }
+
+    #[test]
+    fn test_structural_sharing() {
+        let v1 = PersistentVector::new().conj(1).conj(2);
+        let v2 = v1.conj(3);
+        let v3 = v1.conj(4);
+
+        // v2 and v3 should share structure with v1
+        assert_eq!(v1.len(), 2);
+        assert_eq!(v2.len(), 3);
+        assert_eq!(v3.len(), 3);
+
+        assert_eq!(v2.get(2), Some(&3));
+        assert_eq!(v3.get(2), Some(&4));
+
+        // v1 should be unchanged
+        assert_eq!(v1.get(0), Some(&1));
+        assert_eq!(v1.get(1), Some(&2));
+    }
+
+    #[test]
+    fn test_shares_tree_nodes() {
+        let v1: PersistentVector<usize> = (0..100).collect();
+        let v2 = v1.conj(100);
+        assert!(Arc::ptr_eq(&v1.root, &v2.root));
+    }
+
+    #[test]
+    fn depth_grow_boundary_33() {
+        // crosses one full tail
+        let mut v = PersistentVector::new();
+        for i in 0..33 {
+            v = v.conj(i);
+        }
+        for i in 0..33 {
+            assert_eq!(v.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn depth_grow_boundary_1025() {
+        // forces second depth level
+        let mut v = PersistentVector::new();
+        for i in 0..1025 {
+            v = v.conj(i);
+        }
+        for i in [0, 31, 32, 33, 1024] {
+            assert_eq!(v.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn root_overflow_past_1056() {
+        // 32 full leaves plus a full tail: the next conj must grow the root
+        let mut v = PersistentVector::new();
+        for i in 0..1056 {
+            v = v.conj(i);
+        }
+        assert_eq!(v.shift, BITS);
+        for i in 1056..1200 {
+            v = v.conj(i);
+        }
+        assert_eq!(v.shift, 2 * BITS);
+        for i in 0..1200 {
+            assert_eq!(v.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn three_levels() {
+        let n = 32 * 32 * 32 + 32 + 1;
+        let v: PersistentVector<usize> = (0..n).collect();
+        assert_eq!(v.shift, 3 * BITS);
+        assert!(v.iter().copied().eq(0..n));
+        assert_eq!(v.get(n - 1), Some(&(n - 1)));
+        assert_eq!(v.get(n), None);
+    }
+
+    #[test]
+    fn pop_shrinks_depth() {
+        let mut v = PersistentVector::new();
+        for i in 0..1025 {
+            v = v.conj(i);
+        }
+        for _ in 0..(1025 - 32) {
+            v = v.pop().unwrap(); // pop until only tail remains
+        }
+        assert_eq!(v.len(), 32);
+        // should be back to a shallow tree (shift == BITS or tail-only)
+        assert_eq!(v.shift, BITS);
+        assert_eq!(v.get(31), Some(&31));
+    }
+
+    #[test]
+    fn pop_back_through_every_boundary() {
+        let n = 32 * 32 * 2 + 70;
+        let full: PersistentVector<usize> = (0..n).collect();
+        let mut v = full.clone();
+        for len in (0..n).rev() {
+            v = v.pop().unwrap();
+            assert_eq!(v.len(), len);
+            if len > 0 {
+                assert_eq!(v.get(len - 1), Some(&(len - 1)));
+            }
+            if len == 32 * 32 + 32 {
+                assert_eq!(v.shift, BITS);
+            }
+        }
+        assert!(v.is_empty());
+        assert_eq!(full.len(), n);
+
+        let regrown: PersistentVector<usize> = (0..n).fold(v, |v, i| v.conj(i));
+        assert_eq!(regrown, full);
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        let a: PersistentVector<i32> = (0..50).collect();
+        let b = PersistentVector::from((0..50).collect::<Vec<_>>());
+        assert_eq!(a, b);
+        assert_ne!(a, b.pop().unwrap());
+
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn test_meta_survives_updates_but_not_equality() {
+        let meta = MetaMap::new().assoc(EDN::from(0i64), EDN::from(1i64));
+        let plain: PersistentVector<i32> = (0..40).collect();
+        let v = plain.with_meta(Some(meta.clone()));
+        assert_eq!(v.meta(), Some(&meta));
+        assert_eq!(v.conj(40).meta(), Some(&meta));
+        assert_eq!(v.pop().unwrap().meta(), Some(&meta));
+        assert_eq!(v.assoc(0, 9).unwrap().meta(), Some(&meta));
+        assert_eq!(plain.meta(), None);
+        assert_eq!(v, plain);
+
+        assert_eq!(hash_of(&v), hash_of(&plain));
+        assert_eq!(v.transient().persistent().meta(), None);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let v: PersistentVector<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let borrowed: Vec<&String> = (&v).into_iter().collect();
+        assert_eq!(borrowed.len(), 3);
+        let owned: Vec<String> = v.into_iter().collect();
+        assert_eq!(owned, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_display() {
+        let v: PersistentVector<i32> = (1..4).collect();
+        assert_eq!(v.to_string(), "[1 2 3]");
+        assert_eq!(format!("{:?}", v), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn test_assoc() {
+        let v: PersistentVector<usize> = (0..2000).collect();
+        for i in [0, 31, 32, 1000, 1055, 1056, 1999] {
+            let updated = v.assoc(i, 9999).unwrap();
+            assert_eq!(updated.get(i), Some(&9999));
+            assert_eq!(v.get(i), Some(&i));
+            assert_eq!(updated.len(), 2000);
+        }
+        let appended = v.assoc(2000, 2000).unwrap();
+        assert_eq!(appended.len(), 2001);
+        assert_eq!(appended.peek(), Some(&2000));
+        assert!(v.assoc(2001, 0).is_none());
+    }
+
+    #[test]
+    fn test_assoc_copies_only_one_path() {
+        let v: PersistentVector<usize> = (0..2000).collect();
+        let updated = v.assoc(5, 0).unwrap();
+        if let (Node::Internal(old), Node::Internal(new)) = (v.root.as_ref(), updated.root.as_ref())
+        {
+            assert!(!Arc::ptr_eq(&old[0], &new[0]));
+            assert!(Arc::ptr_eq(&old[1], &new[1]));
+        } else {
+            panic!("Expected internal roots");
+        }
+        assert!(Arc::ptr_eq(&v.tail, &updated.tail));
+    }
+
+    #[test]
+    fn test_update() {
+        let v: PersistentVector<i32> = (0..40).collect();
+        let v2 = v.update(35, |x| x * 10).unwrap();
+        assert_eq!(v2.get(35), Some(&350));
+        assert_eq!(v2.get(34), Some(&34));
+        assert!(v.update(40, |x| *x).is_none());
+    }
+
+    #[test]
+    fn test_peek_and_contains_index() {
+        let empty: PersistentVector<i32> = PersistentVector::new();
+        assert_eq!(empty.peek(), None);
+        assert!(!empty.contains_index(0));
+
+        let v: PersistentVector<i32> = (0..33).collect();
+        assert_eq!(v.peek(), Some(&32));
+        assert_eq!(v.pop().unwrap().peek(), Some(&31));
+        assert!(v.contains_index(32));
+        assert!(!v.contains_index(33));
+    }
+
+    #[test]
+    fn test_rseq_and_double_ended() {
+        let v: PersistentVector<usize> = (0..100).collect();
+        assert!(v.rseq().copied().eq((0..100).rev()));
+        assert_eq!(v.iter().len(), 100);
+
+        let mut it = v.iter();
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next_back(), Some(&99));
+        assert_eq!(it.len(), 98);
+        let middle: Vec<usize> = it.copied().collect();
+        assert_eq!(middle, (1..99).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_subvec() {
+        let v: PersistentVector<usize> = (0..100).collect();
+        let s = v.subvec(30, 70).unwrap();
+        assert_eq!(s.len(), 40);
+        assert_eq!(s.get(0), Some(&30));
+        assert_eq!(s.get(40), None);
+        assert_eq!(s.peek(), Some(&69));
+        assert!(s.iter().copied().eq(30..70));
+        assert!(s.rseq().copied().eq((30..70).rev()));
+        assert!(Arc::ptr_eq(&s.vector.root, &v.root));
+
+        let nested = s.subvec(5, 10).unwrap();
+        assert!(nested.iter().copied().eq(35..40));
+        assert!(s.subvec(10, 5).is_none());
+        assert!(v.subvec(0, 101).is_none());
+
+        // conj writes past the end of the view, leaving the source alone
+        let grown = nested.conj(0);
+        assert_eq!(grown.len(), 6);
+        assert_eq!(grown.peek(), Some(&0));
+        assert_eq!(v.get(40), Some(&40));
+
+        assert_eq!(nested.assoc(0, 7).unwrap().get(0), Some(&7));
+        assert_eq!(nested.pop().unwrap().to_vector(), (35..39).collect());
+        assert_eq!(v.subvec(3, 3).unwrap().pop(), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let a: PersistentVector<i32> = vec![1, 2, 3].into();
+        let b: PersistentVector<i32> = vec![1, 2, 4].into();
+        let shorter: PersistentVector<i32> = vec![9, 9].into();
+        assert!(a < b);
+        assert!(shorter < a);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+        let mut vs = vec![b.clone(), a.clone(), shorter.clone()];
+        vs.sort();
+        assert_eq!(vs, vec![shorter, a, b]);
+    }
+
+    #[test]
+    fn test_transient_conj_matches_persistent() {
+        let mut t = TransientVector::new();
+        for i in 0..100_000 {
+            t.conj(i);
+        }
+        assert_eq!(t.len(), 100_000);
+        assert_eq!(t.get(33_000), Some(&33_000));
+        let v = t.persistent();
+
+        let mut expected = PersistentVector::new();
+        for i in 0..2000 {
+            expected = expected.conj(i);
+        }
+        assert_eq!(v.len(), 100_000);
+        assert!(v.iter().cloned().eq(0..100_000));
+        assert_eq!(v.subvec(0, 2000).unwrap().to_vector(), expected);
+        assert_eq!(v.pop().unwrap().len(), 99_999);
+    }
+
+    #[test]
+    fn test_transient_leaves_original_untouched() {
+        let v1: PersistentVector<usize> = (0..1100).collect();
+        let mut t = v1.transient();
+        t.assoc(5, 500).unwrap().assoc(1099, 0).unwrap().conj(1100);
+        assert!(t.assoc(5000, 0).is_none());
+        let v2 = t.persistent();
+
+        assert_eq!(v1.get(5), Some(&5));
+        assert_eq!(v1.get(1099), Some(&1099));
+        assert_eq!(v1.len(), 1100);
+        assert_eq!(v2.get(5), Some(&500));
+        assert_eq!(v2.get(1099), Some(&0));
+        assert_eq!(v2.get(1100), Some(&1100));
+    }
+
+    #[test]
+    fn test_transient_pop() {
+        let mut t: TransientVector<usize> = (0..1100).collect::<PersistentVector<_>>().transient();
+        for i in (40..1100).rev() {
+            assert_eq!(t.pop(), Some(i));
+        }
+        assert_eq!(t.len(), 40);
+        assert_eq!(t.get(39), Some(&39));
+        t.conj(40);
+        let v = t.persistent();
+        assert!(v.iter().cloned().eq(0..41));
+
+        let mut t = v.transient();
+        while t.pop().is_some() {}
+        assert!(t.is_empty());
+        assert!(t.persistent().is_empty());
+    }
+}
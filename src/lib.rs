@@ -2,4 +2,11 @@ pub mod clojure;
 pub mod core;
 pub mod edn;
 pub mod immutant;
+pub mod namespace;
+pub mod pattern;
 pub mod repl;
+pub mod schema;
+pub mod seq;
+pub mod trace;
+pub mod var;
+pub mod zip;
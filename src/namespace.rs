@@ -0,0 +1,500 @@
+use crate::clojure::{eval, read_all, Environment, EvalError, Value};
+use crate::edn::EDN;
+use crate::var::{self, Var};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+// Namespaces. The global table an environment is created with is the `user`
+// namespace; every other namespace has a table of its own. The core
+// functions are interned in `clojure.core` alone, and every namespace,
+// `user` included, falls back to it for names it doesn't map or refer, so
+// it sees the core functions but not what another namespace defines. The
+// current namespace is stored under `*ns*` in the global table, and from
+// there reaches the registry of all namespaces.
+
+pub const USER: &str = "user";
+pub const CORE: &str = "clojure.core";
+
+#[derive(Clone)]
+pub struct Namespace(Arc<NsData>);
+
+struct NsData {
+    name: String,
+    // `None` for `user`, whose mappings are the global table
+    mappings: Option<Environment>,
+    aliases: RwLock<HashMap<String, Namespace>>,
+    refers: RwLock<HashMap<String, (Namespace, String)>>,
+    // Vars defined `^:dynamic`
+    dynamic: RwLock<HashSet<String>>,
+    // Vars interned without a root value, as by `(def x)`
+    unbound: RwLock<HashSet<String>>,
+    registry: Arc<Registry>,
+}
+
+struct Registry {
+    namespaces: RwLock<HashMap<String, Namespace>>,
+    loading: RwLock<HashSet<String>>,
+    source_path: RwLock<Vec<PathBuf>>,
+}
+
+impl Namespace {
+    /// The current namespace of `globals`, or `None` while it is still the
+    /// implicit `user` namespace.
+    pub fn current(globals: &Environment) -> Option<Namespace> {
+        match globals.read().unwrap().get("*ns*") {
+            Some(Value::Namespace(ns)) => Some(ns.clone()),
+            _ => None,
+        }
+    }
+
+    /// The current namespace of `globals`, storing `user` as current if no
+    /// namespace has been entered yet.
+    pub fn installed(globals: &Environment) -> Namespace {
+        Namespace::current(globals).unwrap_or_else(|| {
+            let user = Namespace::user();
+            user.enter(globals);
+            user
+        })
+    }
+
+    /// Makes `user` the current namespace of `globals`, and creates
+    /// `clojure.core` with `core` as its mappings.
+    pub fn install_core(globals: &Environment, core: Environment) -> Namespace {
+        let user = Namespace::installed(globals);
+        Namespace::create(CORE, Some(core), &user.0.registry)
+    }
+
+    /// The `user` namespace of `globals`, whose mappings are the global table.
+    pub fn root(globals: &Environment) -> Namespace {
+        let current = Namespace::installed(globals);
+        current.find(USER).unwrap_or(current)
+    }
+
+    // A fresh `user` namespace, the only one in its registry.
+    fn user() -> Namespace {
+        let source_path = match std::env::var("YINYANG_PATH") {
+            Ok(path) => std::env::split_paths(&path).collect(),
+            Err(_) => vec![PathBuf::from(".")],
+        };
+        let registry = Arc::new(Registry {
+            namespaces: RwLock::new(HashMap::new()),
+            loading: RwLock::new(HashSet::new()),
+            source_path: RwLock::new(source_path),
+        });
+        Namespace::create(USER, None, &registry)
+    }
+
+    fn create(name: &str, mappings: Option<Environment>, registry: &Arc<Registry>) -> Namespace {
+        let ns = Namespace(Arc::new(NsData {
+            name: name.to_string(),
+            mappings,
+            aliases: RwLock::new(HashMap::new()),
+            refers: RwLock::new(HashMap::new()),
+            dynamic: RwLock::new(HashSet::new()),
+            unbound: RwLock::new(HashSet::new()),
+            registry: registry.clone(),
+        }));
+        let mut namespaces = registry.namespaces.write().unwrap();
+        namespaces.insert(name.to_string(), ns.clone());
+        ns
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The namespace called `name`, if it has been created.
+    pub fn find(&self, name: &str) -> Option<Namespace> {
+        self.0
+            .registry
+            .namespaces
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+
+    /// The namespace called `name`, created empty if it doesn't exist.
+    pub fn find_or_create(&self, name: &str) -> Namespace {
+        self.find(name).unwrap_or_else(|| {
+            let mappings = Arc::new(RwLock::new(HashMap::new()));
+            Namespace::create(name, Some(mappings), &self.0.registry)
+        })
+    }
+
+    fn table<'a>(&'a self, globals: &'a Environment) -> &'a Environment {
+        self.0.mappings.as_ref().unwrap_or(globals)
+    }
+
+    /// Maps `name` to `value` in this namespace.
+    pub fn intern(&self, globals: &Environment, name: String, value: Value) {
+        self.0.unbound.write().unwrap().remove(&name);
+        self.table(globals).write().unwrap().insert(name, value);
+    }
+
+    /// Maps `name` to `value` if `expected` holds for its current mapping,
+    /// checking and replacing it under one lock.
+    pub fn intern_if(
+        &self,
+        globals: &Environment,
+        name: &str,
+        value: Value,
+        expected: impl FnOnce(Option<&Value>) -> bool,
+    ) -> bool {
+        let mut table = self.table(globals).write().unwrap();
+        if !expected(table.get(name)) {
+            return false;
+        }
+        self.0.unbound.write().unwrap().remove(name);
+        table.insert(name.to_string(), value);
+        true
+    }
+
+    /// Interns `name` without a root value unless it already has one.
+    pub fn declare(&self, globals: &Environment, name: &str) {
+        if self.mapping(globals, name).is_none() {
+            self.0.unbound.write().unwrap().insert(name.to_string());
+        }
+    }
+
+    /// Removes the root value of `name`, leaving it interned.
+    pub fn unbind(&self, globals: &Environment, name: &str) {
+        self.table(globals).write().unwrap().remove(name);
+        self.0.unbound.write().unwrap().insert(name.to_string());
+    }
+
+    pub fn set_dynamic(&self, name: &str, dynamic: bool) {
+        let mut names = self.0.dynamic.write().unwrap();
+        if dynamic {
+            names.insert(name.to_string());
+        } else {
+            names.remove(name);
+        }
+    }
+
+    pub fn is_dynamic(&self, name: &str) -> bool {
+        self.0.dynamic.read().unwrap().contains(name)
+    }
+
+    /// Resolves a global name as seen from this namespace: `alias/name` and
+    /// `ns/name` in the namespace they name, anything else through the
+    /// namespace's own mappings, then its refers, then `clojure.core`.
+    pub fn lookup(&self, globals: &Environment, name: &str) -> Option<Value> {
+        if name == "*ns*" {
+            return Some(Value::Namespace(self.clone()));
+        }
+        if var::has_thread_bindings() {
+            let var = Var::resolve(globals, self, name);
+            if let Some(value) = var.and_then(|var| var.thread_binding()) {
+                return Some(value);
+            }
+        }
+        if let Some((prefix, local)) = qualified(name) {
+            let target = self.resolve_alias(prefix).or_else(|| self.find(prefix));
+            return target.and_then(|ns| ns.mapping(globals, local));
+        }
+        self.mapping(globals, name)
+            .or_else(|| {
+                let refers = self.0.refers.read().unwrap();
+                let (ns, name) = refers.get(name)?;
+                ns.mapping(globals, name)
+            })
+            .or_else(|| self.find(CORE)?.mapping(globals, name))
+    }
+
+    /// Finds the namespace that interns what `name` refers to from this
+    /// one, in the same order as `lookup`, with the name it has there.
+    pub fn resolve(&self, globals: &Environment, name: &str) -> Option<(Namespace, String)> {
+        if let Some((prefix, local)) = qualified(name) {
+            let target = self.resolve_alias(prefix).or_else(|| self.find(prefix));
+            return target
+                .filter(|ns| ns.interns(globals, local))
+                .map(|ns| (ns, local.to_string()));
+        }
+        if self.interns(globals, name) {
+            return Some((self.clone(), name.to_string()));
+        }
+        if let Some(refer) = self.0.refers.read().unwrap().get(name) {
+            return Some(refer.clone());
+        }
+        let core = self.find(CORE)?;
+        core.interns(globals, name)
+            .then(|| (core, name.to_string()))
+    }
+
+    fn interns(&self, globals: &Environment, name: &str) -> bool {
+        self.table(globals).read().unwrap().contains_key(name)
+            || self.0.unbound.read().unwrap().contains(name)
+    }
+
+    pub(crate) fn mapping(&self, globals: &Environment, name: &str) -> Option<Value> {
+        self.table(globals).read().unwrap().get(name).cloned()
+    }
+
+    fn resolve_alias(&self, alias: &str) -> Option<Namespace> {
+        self.0.aliases.read().unwrap().get(alias).cloned()
+    }
+
+    /// Makes `alias/x` refer to `x` in `target`.
+    pub fn alias(&self, alias: &str, target: &Namespace) {
+        let mut aliases = self.0.aliases.write().unwrap();
+        aliases.insert(alias.to_string(), target.clone());
+    }
+
+    /// Makes `names` of `target`, or all of its current mappings, resolve
+    /// unqualified in this namespace.
+    pub fn refer(
+        &self,
+        globals: &Environment,
+        target: &Namespace,
+        names: Option<Vec<String>>,
+    ) -> Result<(), String> {
+        let names = match names {
+            Some(names) => names,
+            None => {
+                let table = target.table(globals).read().unwrap();
+                table
+                    .keys()
+                    .filter(|name| *name != "*ns*")
+                    .cloned()
+                    .collect()
+            }
+        };
+        let mut refers = self.0.refers.write().unwrap();
+        for name in names {
+            if target.mapping(globals, &name).is_none() {
+                return Err(format!("{} does not exist in {}", name, target.name()));
+            }
+            refers.insert(name.clone(), (target.clone(), name));
+        }
+        Ok(())
+    }
+
+    /// Makes this the current namespace of `globals`.
+    pub fn enter(&self, globals: &Environment) {
+        let mut globals = globals.write().unwrap();
+        globals.insert("*ns*".to_string(), Value::Namespace(self.clone()));
+    }
+
+    /// Replaces the directories `require` searches for source files.
+    pub fn set_source_path(&self, path: Vec<PathBuf>) {
+        *self.0.registry.source_path.write().unwrap() = path;
+    }
+
+    pub fn ptr_eq(&self, other: &Namespace) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#namespace[{}]", self.name())
+    }
+}
+
+impl fmt::Debug for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// The namespace part and the name of `ns/name`, but not of `/`.
+fn qualified(name: &str) -> Option<(&str, &str)> {
+    name.split_once('/')
+        .filter(|(prefix, local)| !prefix.is_empty() && !local.is_empty())
+}
+
+// Enters the namespace `name`, creating it if needed.
+fn enter(globals: &Environment, name: &str) -> Namespace {
+    let ns = Namespace::installed(globals).find_or_create(name);
+    ns.enter(globals);
+    ns
+}
+
+/// `(in-ns 'name)`
+pub fn in_ns(globals: &Environment, args: &[EDN]) -> Result<Value, EvalError> {
+    match args {
+        [EDN::Symbol(name, _)] => Ok(Value::Namespace(enter(globals, name))),
+        _ => Err(EvalError::Native("in-ns requires a symbol".to_string())),
+    }
+}
+
+/// `(require spec*)`, each spec as in the `:require` clause of `ns`.
+pub fn require_all(globals: &Environment, specs: &[EDN]) -> Result<Value, EvalError> {
+    for spec in specs {
+        require(globals, spec)?;
+    }
+    Ok(Value::EDN(EDN::Nil))
+}
+
+/// `(refer 'ns)` or `(refer 'ns :only '[names])`
+pub fn refer(globals: &Environment, args: &[EDN]) -> Result<Value, EvalError> {
+    let (name, names) = match args {
+        [EDN::Symbol(name, _)] => (name, None),
+        [EDN::Symbol(name, _), EDN::Keyword(only), EDN::Vector(names)] if only == ":only" => {
+            (name, Some(symbol_names(names.iter())?))
+        }
+        _ => {
+            return Err(EvalError::Native(
+                "refer requires a namespace and optionally :only [names]".to_string(),
+            ))
+        }
+    };
+    let current = Namespace::installed(globals);
+    let target = current
+        .find(name)
+        .ok_or_else(|| EvalError::Native(format!("No namespace: {}", name)))?;
+    current.refer(globals, &target, names)?;
+    Ok(Value::EDN(EDN::Nil))
+}
+
+/// `(alias 'alias 'ns)`
+pub fn alias(globals: &Environment, args: &[EDN]) -> Result<Value, EvalError> {
+    match args {
+        [EDN::Symbol(alias, _), EDN::Symbol(name, _)] => {
+            let current = Namespace::installed(globals);
+            let target = current
+                .find(name)
+                .ok_or_else(|| EvalError::Native(format!("No namespace: {}", name)))?;
+            current.alias(alias, &target);
+            Ok(Value::EDN(EDN::Nil))
+        }
+        _ => Err(EvalError::Native(
+            "alias requires an alias and a namespace symbol".to_string(),
+        )),
+    }
+}
+
+/// `(ns name docstring? (:require spec*)*)`: enters `name`, then loads and
+/// refers what its `:require` clauses ask for.
+pub fn ns_form(globals: &Environment, args: &[EDN]) -> Result<Value, EvalError> {
+    let (name, clauses) = match args.split_first() {
+        Some((EDN::Symbol(name, _), clauses)) => (name, clauses),
+        _ => return Err(EvalError::Syntax("'ns' requires a symbol name".to_string())),
+    };
+    let clauses = match clauses.first() {
+        Some(EDN::String(_)) => &clauses[1..],
+        _ => clauses,
+    };
+    // Check every clause before switching, so a bad form leaves *ns* alone
+    let mut requires = vec![];
+    for clause in clauses {
+        let items = match clause {
+            EDN::List(l) => l.to_vec(),
+            _ => return Err(EvalError::Syntax(format!("Invalid ns clause: {}", clause))),
+        };
+        match items.split_first() {
+            Some((EDN::Keyword(k), specs)) if k == ":require" => requires.extend_from_slice(specs),
+            Some((EDN::Keyword(k), _)) if k == ":refer-clojure" || k == ":gen-class" => {}
+            _ => {
+                return Err(EvalError::Syntax(format!(
+                    "Unsupported ns clause: {}",
+                    clause
+                )))
+            }
+        }
+    }
+    enter(globals, name);
+    require_all(globals, &requires)?;
+    Ok(Value::EDN(EDN::Nil))
+}
+
+/// Loads the namespace a spec names, unless it is already loaded, then
+/// aliases and refers it in the current namespace as the spec asks:
+/// `foo.bar` or `[foo.bar :as fb :refer [x y]]`, with `:refer :all`.
+fn require(globals: &Environment, spec: &EDN) -> Result<(), EvalError> {
+    let (name, options) = match spec {
+        EDN::Symbol(name, _) => (name.clone(), vec![]),
+        EDN::Vector(v) => match v.to_vec().split_first() {
+            Some((EDN::Symbol(name, _), options)) => (name.clone(), options.to_vec()),
+            _ => return Err(invalid_spec(spec)),
+        },
+        _ => return Err(invalid_spec(spec)),
+    };
+    if options.len() % 2 != 0 {
+        return Err(invalid_spec(spec));
+    }
+
+    let current = Namespace::installed(globals);
+    let target = load(globals, &current, &name)?;
+
+    for option in options.chunks(2) {
+        match (&option[0], &option[1]) {
+            (EDN::Keyword(k), EDN::Symbol(alias, _)) if k == ":as" => current.alias(alias, &target),
+            (EDN::Keyword(k), EDN::Keyword(all)) if k == ":refer" && all == ":all" => {
+                current.refer(globals, &target, None)?
+            }
+            (EDN::Keyword(k), EDN::Vector(names)) if k == ":refer" => {
+                let names = symbol_names(names.iter())?;
+                current.refer(globals, &target, Some(names))?
+            }
+            _ => return Err(invalid_spec(spec)),
+        }
+    }
+    Ok(())
+}
+
+fn invalid_spec(spec: &EDN) -> EvalError {
+    EvalError::Syntax(format!("Invalid require spec: {}", spec))
+}
+
+fn symbol_names<'a>(items: impl Iterator<Item = &'a EDN>) -> Result<Vec<String>, String> {
+    items
+        .map(|item| match item {
+            EDN::Symbol(name, _) => Ok(name.clone()),
+            _ => Err(format!("Expected a symbol, got {}", item)),
+        })
+        .collect()
+}
+
+// Loads `foo-bar.baz` from `foo_bar/baz.clj` on the source path, restoring
+// the current namespace afterwards. Namespaces that already exist are not
+// loaded again.
+fn load(globals: &Environment, current: &Namespace, name: &str) -> Result<Namespace, EvalError> {
+    let registry = &current.0.registry;
+    if registry.loading.read().unwrap().contains(name) {
+        return Err(EvalError::Native(format!(
+            "Cyclic load dependency on {}",
+            name
+        )));
+    }
+    if let Some(ns) = current.find(name) {
+        return Ok(ns);
+    }
+
+    let relative = format!("{}.clj", name.replace('-', "_").replace('.', "/"));
+    let path = registry
+        .source_path
+        .read()
+        .unwrap()
+        .iter()
+        .map(|dir| dir.join(&relative))
+        .find(|path| path.is_file());
+    let path = path.ok_or_else(|| {
+        EvalError::Native(format!("Could not locate {} on the source path", relative))
+    })?;
+
+    registry.loading.write().unwrap().insert(name.to_string());
+    let result = load_file(globals, &path);
+    registry.loading.write().unwrap().remove(name);
+    current.enter(globals);
+    result?;
+
+    current
+        .find(name)
+        .ok_or_else(|| EvalError::Native(format!("{} did not define namespace {}", relative, name)))
+}
+
+fn load_file(globals: &Environment, path: &PathBuf) -> Result<(), EvalError> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| EvalError::Native(format!("Error reading {}: {}", path.display(), e)))?;
+    let forms = read_all(&source)
+        .map_err(|e| EvalError::Native(format!("Error parsing {}: {:?}", path.display(), e)))?;
+    for form in forms {
+        eval(form, globals)?;
+    }
+    Ok(())
+}
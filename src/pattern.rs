@@ -0,0 +1,283 @@
+use crate::clojure::{eval_in, EvalError, Scope, Value};
+use crate::edn::EDN;
+use crate::immutant::list::List;
+use crate::immutant::map::PersistentHashMap;
+use crate::seq::{self, ISeq, Seqable};
+use std::fmt;
+
+// Binding patterns, the destructuring shared by `let`, `fn`, `loop` and
+// `doseq`. A binding form is compiled once into a `Pattern`, which then
+// binds values by extending a scope:
+//
+//   x                                  the whole value
+//   [a b & more :as all]               positions of a seq, the rest, the whole
+//   {a :a [b] :b :as m}                values under keys, themselves patterns
+//   {:keys [a ns/b] :strs [c] :syms [d] :or {a 1}}
+//                                      names looked up as keywords, strings or
+//                                      symbols, with defaults when missing
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Bind(String),
+    Seq {
+        items: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+        whole: Option<String>,
+    },
+    Map {
+        entries: Vec<(Pattern, EDN)>,
+        defaults: Vec<(String, EDN)>,
+        whole: Option<String>,
+    },
+}
+
+fn malformed<T>(msg: impl fmt::Display) -> Result<T, EvalError> {
+    Err(EvalError::Syntax(msg.to_string()))
+}
+
+impl Pattern {
+    /// Compiles a binding form, rejecting malformed ones.
+    pub fn compile(form: &EDN) -> Result<Pattern, EvalError> {
+        match form {
+            EDN::Symbol(name, _) if name == "&" => malformed("'&' is not a valid binding name"),
+            EDN::Symbol(name, _) if name.contains('/') => {
+                malformed(format!("Can't bind qualified name: {}", name))
+            }
+            EDN::Symbol(name, _) => Ok(Pattern::Bind(name.clone())),
+            EDN::Vector(v) => compile_seq(&v.to_vec()),
+            EDN::Map(m) => compile_map(m),
+            _ => malformed(format!("Unsupported binding form: {}", form)),
+        }
+    }
+
+    /// Binds `value` to the pattern in frames over `scope`. `:or` defaults
+    /// are evaluated there, so they can see names bound before them.
+    pub fn bind(&self, value: Value, scope: &Scope) -> Result<Scope, EvalError> {
+        match self {
+            Pattern::Bind(name) => Ok(scope.extend(vec![(name.clone(), value)])),
+            Pattern::Seq { items, rest, whole } => {
+                if let Value::Args(args) = value {
+                    return bind_args(items, rest, whole, args, scope);
+                }
+                let coll = destructured(value)?;
+                let mut scope = bind_whole(whole, &coll, scope);
+                let mut current = seq::to_seq(&coll)?;
+                for item in items {
+                    let (first, next) = match current.seq()? {
+                        Some(s) => (s.first()?, s.rest()?),
+                        None => (EDN::Nil, current),
+                    };
+                    scope = item.bind(Value::EDN(first), &scope)?;
+                    current = next;
+                }
+                match rest {
+                    Some(rest) => {
+                        let tail = current.seq()?.map_or(EDN::Nil, EDN::Seq);
+                        rest.bind(Value::EDN(tail), &scope)
+                    }
+                    None => Ok(scope),
+                }
+            }
+            Pattern::Map {
+                entries,
+                defaults,
+                whole,
+            } => {
+                let coll = as_map(destructured(value)?)?;
+                let mut scope = bind_whole(whole, &coll, scope);
+                for (pattern, key) in entries {
+                    let value = match (coll.get(key), pattern) {
+                        (Some(value), _) => Value::EDN(value.clone()),
+                        (None, Pattern::Bind(name)) => match default_for(defaults, name) {
+                            Some(expr) => eval_in(expr.clone(), &scope)?,
+                            None => Value::EDN(EDN::Nil),
+                        },
+                        (None, _) => Value::EDN(EDN::Nil),
+                    };
+                    scope = pattern.bind(value, &scope)?;
+                }
+                Ok(scope)
+            }
+        }
+    }
+}
+
+fn destructured(value: Value) -> Result<EDN, EvalError> {
+    match value {
+        Value::EDN(edn) => Ok(edn),
+        other => Err(EvalError::Type(format!("Can't destructure {}", other))),
+    }
+}
+
+// Binds a seq pattern to the rest arguments of a variadic fn that holds
+// values other than EDN, such as fns, walking them as they are.
+fn bind_args(
+    items: &[Pattern],
+    rest: &Option<Box<Pattern>>,
+    whole: &Option<String>,
+    args: List<Value>,
+    scope: &Scope,
+) -> Result<Scope, EvalError> {
+    let mut scope = match whole {
+        Some(name) => scope.extend(vec![(name.clone(), Value::Args(args.clone()))]),
+        None => scope.clone(),
+    };
+    let mut current = args;
+    for item in items {
+        let first = current.first().cloned().unwrap_or(Value::EDN(EDN::Nil));
+        scope = item.bind(first, &scope)?;
+        current = current.rest();
+    }
+    match rest {
+        Some(rest) if current.is_empty() => rest.bind(Value::EDN(EDN::Nil), &scope),
+        Some(rest) => rest.bind(Value::Args(current), &scope),
+        None => Ok(scope),
+    }
+}
+
+fn bind_whole(whole: &Option<String>, coll: &EDN, scope: &Scope) -> Scope {
+    match whole {
+        Some(name) => scope.extend(vec![(name.clone(), Value::EDN(coll.clone()))]),
+        None => scope.clone(),
+    }
+}
+
+fn default_for<'a>(defaults: &'a [(String, EDN)], name: &str) -> Option<&'a EDN> {
+    defaults
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, expr)| expr)
+}
+
+// A seq is read as key/value pairs, so `& {:keys [a]}` takes keyword
+// arguments; a seq holding just a map is that map.
+fn as_map(coll: EDN) -> Result<EDN, EvalError> {
+    if !matches!(coll, EDN::Seq(_) | EDN::List(_)) {
+        return Ok(coll);
+    }
+    let items = seq::to_seq(&coll)?
+        .iter()
+        .collect::<Result<Vec<EDN>, EvalError>>()?;
+    if let [map @ EDN::Map(_)] = items.as_slice() {
+        return Ok(map.clone());
+    }
+    if items.len() % 2 != 0 {
+        return Err(EvalError::Type(format!(
+            "No value supplied for key: {}",
+            items[items.len() - 1]
+        )));
+    }
+    let map = items.chunks(2).fold(PersistentHashMap::new(), |m, kv| {
+        m.assoc(kv[0].clone(), kv[1].clone())
+    });
+    Ok(EDN::Map(map))
+}
+
+fn compile_seq(forms: &[EDN]) -> Result<Pattern, EvalError> {
+    let mut items = Vec::new();
+    let mut rest = None;
+    let mut whole = None;
+    let mut forms = forms.iter();
+    while let Some(form) = forms.next() {
+        if whole.is_some() {
+            return malformed(format!("Unexpected {} after :as in binding vector", form));
+        }
+        match form {
+            EDN::Symbol(s, _) if s == "&" => {
+                if rest.is_some() {
+                    return malformed("Only one '&' is allowed in a binding vector");
+                }
+                match forms.next() {
+                    Some(EDN::Keyword(k)) if k == ":as" => {
+                        return malformed("'&' must be followed by a binding form")
+                    }
+                    Some(form) => rest = Some(Box::new(Pattern::compile(form)?)),
+                    None => return malformed("'&' must be followed by a binding form"),
+                }
+            }
+            EDN::Keyword(k) if k == ":as" => match forms.next().map(Pattern::compile) {
+                Some(Ok(Pattern::Bind(name))) => whole = Some(name),
+                _ => return malformed(":as must be followed by a symbol"),
+            },
+            _ if rest.is_some() => {
+                return malformed(format!("Unexpected {} after the rest binding", form))
+            }
+            _ => items.push(Pattern::compile(form)?),
+        }
+    }
+    Ok(Pattern::Seq { items, rest, whole })
+}
+
+fn compile_map(map: &PersistentHashMap<EDN, EDN>) -> Result<Pattern, EvalError> {
+    let mut entries = Vec::new();
+    let mut defaults = Vec::new();
+    let mut whole = None;
+    for (key, value) in map.iter() {
+        match key {
+            EDN::Keyword(k) if k == ":as" => match value {
+                EDN::Symbol(name, _) => whole = Some(name.clone()),
+                _ => return malformed(":as must be followed by a symbol"),
+            },
+            EDN::Keyword(k) if k == ":or" => match value {
+                EDN::Map(m) => {
+                    for (name, expr) in m.iter() {
+                        match name {
+                            EDN::Symbol(name, _) => defaults.push((name.clone(), expr.clone())),
+                            _ => {
+                                return malformed(format!(":or keys must be symbols, got {}", name))
+                            }
+                        }
+                    }
+                }
+                _ => return malformed(":or must be followed by a map"),
+            },
+            EDN::Keyword(k) => entries.extend(compile_names(k, value)?),
+            _ => entries.push((Pattern::compile(key)?, value.clone())),
+        }
+    }
+    Ok(Pattern::Map {
+        entries,
+        defaults,
+        whole,
+    })
+}
+
+// Expands `:keys`, `:strs` and `:syms`, optionally namespaced as in
+// `:ns/keys`, into a name and lookup key per entry.
+fn compile_names(option: &str, names: &EDN) -> Result<Vec<(Pattern, EDN)>, EvalError> {
+    let (ns, kind) = match option[1..].rsplit_once('/') {
+        Some((ns, kind)) => (Some(ns), kind),
+        None => (None, &option[1..]),
+    };
+    if !matches!(kind, "keys" | "strs" | "syms") || (ns.is_some() && kind == "strs") {
+        return malformed(format!("Unknown destructuring option: {}", option));
+    }
+    let names = match names {
+        EDN::Vector(v) => v.to_vec(),
+        _ => return malformed(format!("{} must be followed by a vector", option)),
+    };
+    names
+        .iter()
+        .map(|name| {
+            let full = match name {
+                EDN::Symbol(s, _) => s.as_str(),
+                EDN::Keyword(k) if kind == "keys" => &k[1..],
+                _ => return malformed(format!("Invalid name in {}: {}", option, name)),
+            };
+            let local = full.rsplit_once('/').map_or(full, |(_, local)| local);
+            let full = match ns {
+                Some(ns) if !full.contains('/') => format!("{}/{}", ns, full),
+                _ => full.to_string(),
+            };
+            let key = match kind {
+                "keys" => EDN::keyword(&full),
+                "strs" => EDN::String(full),
+                _ => EDN::Symbol(full, None),
+            };
+            Ok((
+                Pattern::compile(&EDN::Symbol(local.to_string(), None))?,
+                key,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+(defmacro defn [name & fdecl]
+  (let [fdecl (if (string? (first fdecl)) (next fdecl) fdecl)]
+    (list 'def name (cons 'fn (cons name fdecl)))))
+
+(defmacro when [test & body]
+  (list 'if test (cons 'do body)))
+
+(defmacro cond [& clauses]
+  (if clauses
+    (if (next clauses)
+      (list 'if (first clauses)
+            (first (next clauses))
+            (cons 'cond (next (next clauses))))
+      (throw "cond requires an even number of forms"))))
+
+(defmacro and
+  ([] true)
+  ([x] x)
+  ([x & more]
+   (let [g (gensym "and__")]
+     (list 'let (conj (conj [] g) x) (list 'if g (cons 'and more) g)))))
+
+(defmacro or
+  ([] nil)
+  ([x] x)
+  ([x & more]
+   (let [g (gensym "or__")]
+     (list 'let (conj (conj [] g) x) (list 'if g g (cons 'or more))))))
+
+(defmacro -> [x & forms]
+  (loop [x x
+         forms forms]
+    (if forms
+      (let [form (first forms)]
+        (recur (if (seq? form)
+                 (cons (first form) (cons x (rest form)))
+                 (list form x))
+               (next forms)))
+      x)))
+
+(defmacro ->> [x & forms]
+  (loop [x x
+         forms forms]
+    (if forms
+      (let [form (first forms)]
+        (recur (if (seq? form)
+                 (concat form (list x))
+                 (list form x))
+               (next forms)))
+      x)))
+
+(defmacro if-let
+  ([bindings then] (list 'if-let bindings then nil))
+  ([bindings then else]
+   (let [[form test] bindings
+         temp (gensym "temp__")]
+     (list 'let (conj (conj [] temp) test)
+           (list 'if temp
+                 (list 'let (conj (conj [] form) temp) then)
+                 else)))))
@@ -2,23 +2,28 @@ use crate::clojure::*;
 use crate::core::register_native_fn;
 use crate::core::*;
 use crate::edn::*;
+use crate::immutant::queue::PersistentQueue;
+use crate::namespace;
+use crate::trace;
+use crate::var;
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, BufRead, Write};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 
-fn read_string_wrapper(args: Vec<Value>) -> Result<Value, String> {
+fn read_string_wrapper(args: Vec<Value>) -> Result<Value, EvalError> {
     if args.len() != 1 {
-        return Err("read-string requires exactly 1 argument".to_string());
+        return Err("read-string requires exactly 1 argument".into());
     }
 
     let s = match &args[0] {
         Value::EDN(EDN::String(s)) => s,
-        _ => return Err("read-string argument must be a string".to_string()),
+        _ => return Err("read-string argument must be a string".into()),
     };
 
-    let v = read_string(s).unwrap();
-    Ok(Value::EDN(v))
+    read_string(s)
+        .map(Value::EDN)
+        .map_err(|e| EvalError::Native(format!("read-string failed: {:?}", e)))
 }
 
 /// Function to check if parentheses, brackets, and braces are balanced
@@ -28,20 +33,14 @@ fn is_form_complete(input: &str) -> bool {
     for c in input.chars() {
         match c {
             '(' | '[' | '{' => stack.push(c),
-            ')' => {
-                if stack.pop() != Some('(') {
-                    return false;
-                }
+            ')' if stack.pop() != Some('(') => {
+                return false;
             }
-            ']' => {
-                if stack.pop() != Some('[') {
-                    return false;
-                }
+            ']' if stack.pop() != Some('[') => {
+                return false;
             }
-            '}' => {
-                if stack.pop() != Some('{') {
-                    return false;
-                }
+            '}' if stack.pop() != Some('{') => {
+                return false;
             }
             _ => {}
         }
@@ -50,44 +49,13 @@ fn is_form_complete(input: &str) -> bool {
     stack.is_empty()
 }
 
-/// Read multiple forms from a string into a vector of EDN
-fn read_forms(input: &str) -> Result<Vec<EDN>, ParseError> {
-    let mut forms = Vec::new();
-    let mut current = String::new();
-    let mut paren_count = 0;
-
-    for ch in input.chars() {
-        match ch {
-            '(' => {
-                paren_count += 1;
-                current.push(ch);
-            }
-            ')' => {
-                paren_count -= 1;
-                current.push(ch);
-                if paren_count == 0 && !current.trim().is_empty() {
-                    match read_string(&current) {
-                        Ok(form) => forms.push(form),
-                        Err(e) => return Err(e),
-                    }
-                    current.clear();
-                }
-            }
-            _ => {
-                if paren_count > 0 || !ch.is_whitespace() {
-                    current.push(ch);
-                }
-            }
-        }
-    }
-
-    Ok(forms)
-}
-
 pub fn repl(environment: &Environment) {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        run_script_file(&args[1], environment);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--trace") || trace::enabled_by_env() {
+        trace::init();
+    }
+    if let Some(script) = args.iter().find(|a| !a.starts_with("--")) {
+        run_script_file(script, environment);
         return;
     }
 
@@ -135,9 +103,9 @@ pub fn repl(environment: &Environment) {
         match ast {
             Ok(ast) => {
                 // Pass the Environment reference directly to eval
-                match eval(ast, environment) {
+                match eval(ast, environment).and_then(|val| val.force().map(|_| val)) {
                     Ok(val) => println!("{}", val),
-                    Err(e) => eprintln!("Error: {}", e),
+                    Err(e) => eprintln!("Error: {}", e.stack_trace()),
                 }
             }
             Err(e) => eprintln!("Parse error: {:?}", e),
@@ -147,13 +115,13 @@ pub fn repl(environment: &Environment) {
 
 fn run_script_file(filename: &str, environment: &Environment) {
     match fs::read_to_string(filename) {
-        Ok(content) => match read_forms(&content) {
+        Ok(content) => match read_all(&content) {
             Ok(forms) => {
                 for form in forms {
-                    match eval(form, environment) {
+                    match eval(form, environment).and_then(|val| val.force().map(|_| val)) {
                         Ok(val) => println!("{}", val),
                         Err(e) => {
-                            eprintln!("Evaluation error: {}", e);
+                            eprintln!("Evaluation error: {}", e.stack_trace());
                             return;
                         }
                     }
@@ -169,40 +137,225 @@ pub fn create_env() -> Environment {
     let env = Arc::new(RwLock::new(HashMap::new()));
     let env_clone = env.clone();
 
+    // The core functions are interned in `clojure.core` only; `user` and
+    // every other namespace fall back to it.
+    let mut core = HashMap::new();
     {
-        let mut env_write = env.write().unwrap();
-
-        let eval_wrapper = move |args: Vec<Value>| -> Result<Value, String> {
+        let eval_wrapper = move |args: Vec<Value>| -> Result<Value, EvalError> {
             if args.len() != 1 {
-                return Err("eval requires exactly 1 argument".to_string());
+                return Err("eval requires exactly 1 argument".into());
             }
 
             let expr = match &args[0] {
                 Value::EDN(edn) => edn.clone(),
-                _ => return Err("eval argument must be an EDN value".to_string()),
+                _ => return Err("eval argument must be an EDN value".into()),
             };
 
             // Pass the thread-safe environment clone
             eval(expr, &env_clone)
         };
 
+        let macro_env = env.clone();
+        let macroexpand_1_wrapper = move |args: Vec<Value>| -> Result<Value, EvalError> {
+            match &args[..] {
+                [Value::EDN(form)] => Ok(Value::EDN(macroexpand_1(
+                    form.clone(),
+                    &Scope::new(&macro_env),
+                )?)),
+                _ => Err("macroexpand-1 requires exactly 1 form".into()),
+            }
+        };
+        let macro_env = env.clone();
+        let macroexpand_wrapper = move |args: Vec<Value>| -> Result<Value, EvalError> {
+            match &args[..] {
+                [Value::EDN(form)] => Ok(Value::EDN(macroexpand(
+                    form.clone(),
+                    &Scope::new(&macro_env),
+                )?)),
+                _ => Err("macroexpand requires exactly 1 form".into()),
+            }
+        };
+
+        let ns_native = |f: fn(&Environment, &[EDN]) -> Result<Value, EvalError>| {
+            let env = env.clone();
+            move |args: Vec<Value>| -> Result<Value, EvalError> {
+                let args = args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        Value::EDN(edn) => Ok(edn),
+                        other => Err(format!("Expected a symbol or spec, got {}", other).into()),
+                    })
+                    .collect::<Result<Vec<EDN>, EvalError>>()?;
+                f(&env, &args)
+            }
+        };
+        register_native_fn(&mut core, "in-ns", ns_native(namespace::in_ns));
+        register_native_fn(&mut core, "require", ns_native(namespace::require_all));
+        register_native_fn(&mut core, "refer", ns_native(namespace::refer));
+        register_native_fn(&mut core, "alias", ns_native(namespace::alias));
+
+        let var_native = |f: fn(&Environment, Vec<Value>) -> Result<Value, EvalError>| {
+            let env = env.clone();
+            move |args: Vec<Value>| f(&env, args)
+        };
+        register_native_fn(&mut core, "var-get", var_native(var::var_get));
+        register_native_fn(&mut core, "deref", var_native(var::deref));
+        register_native_fn(&mut core, "alter-var-root", var_native(var::alter_var_root));
+        register_native_fn(&mut core, "bound?", var_native(var::is_bound));
+
         // Register core functions
-        register_native_fn(&mut env_write, "+", add);
-        register_native_fn(&mut env_write, "-", subtract);
-        register_native_fn(&mut env_write, "*", multiply);
-        register_native_fn(&mut env_write, "/", divide);
-        register_native_fn(&mut env_write, "prn", println_fn);
-        register_native_fn(&mut env_write, "print", println_fn);
-        register_native_fn(&mut env_write, "println", println_fn);
-        register_native_fn(&mut env_write, "read-string", read_string_wrapper);
-        register_native_fn(&mut env_write, "eval", eval_wrapper);
-        register_native_fn(&mut env_write, "slurp", slurp_wrapper);
-        register_native_fn(&mut env_write, "=", equal);
-        register_native_fn(&mut env_write, "<", less_than);
-        register_native_fn(&mut env_write, "<=", less_than_equal);
-        register_native_fn(&mut env_write, ">", greater_than);
-        register_native_fn(&mut env_write, ">=", greater_than_equal);
+        register_native_fn(&mut core, "+", add);
+        register_native_fn(&mut core, "-", subtract);
+        register_native_fn(&mut core, "*", multiply);
+        register_native_fn(&mut core, "/", divide);
+        register_native_fn(&mut core, "prn", println_fn);
+        register_native_fn(&mut core, "print", println_fn);
+        register_native_fn(&mut core, "println", println_fn);
+        register_native_fn(&mut core, "read-string", read_string_wrapper);
+        register_native_fn(&mut core, "eval", eval_wrapper);
+        register_native_fn(&mut core, "macroexpand-1", macroexpand_1_wrapper);
+        register_native_fn(&mut core, "macroexpand", macroexpand_wrapper);
+        register_native_fn(&mut core, "gensym", gensym);
+        register_native_fn(&mut core, "slurp", slurp_wrapper);
+        register_native_fn(&mut core, "=", equal);
+        register_native_fn(&mut core, "<", less_than);
+        register_native_fn(&mut core, "<=", less_than_equal);
+        register_native_fn(&mut core, ">", greater_than);
+        register_native_fn(&mut core, ">=", greater_than_equal);
+
+        register_native_fn(&mut core, "seq", seq);
+        register_native_fn(&mut core, "first", first);
+        register_native_fn(&mut core, "rest", rest);
+        register_native_fn(&mut core, "next", next);
+        register_native_fn(&mut core, "cons", cons);
+        register_native_fn(&mut core, "list", list);
+        register_native_fn(&mut core, "concat", concat);
+        register_native_fn(&mut core, "seq?", is_seq);
+        register_native_fn(&mut core, "var?", is_var);
+        register_native_fn(&mut core, "string?", is_string);
+        register_native_fn(&mut core, "count", count);
+        register_native_fn(&mut core, "empty?", is_empty);
+        register_native_fn(&mut core, "range", range);
+        register_native_fn(&mut core, "iterate", iterate);
+        register_native_fn(&mut core, "repeat", repeat);
+        register_native_fn(&mut core, "cycle", cycle);
+        register_native_fn(&mut core, "map", map);
+        register_native_fn(&mut core, "filter", filter);
+        register_native_fn(&mut core, "take", take_fn);
+        register_native_fn(&mut core, "drop", drop_fn);
+        register_native_fn(&mut core, "doall", doall);
+        register_native_fn(&mut core, "dorun", dorun);
+        register_native_fn(&mut core, "realized?", is_realized);
+
+        register_native_fn(&mut core, "meta", meta);
+        register_native_fn(&mut core, "with-meta", with_meta);
+        register_native_fn(&mut core, "vary-meta", vary_meta);
+
+        register_native_fn(&mut core, "hash-map", hash_map);
+        register_native_fn(&mut core, "assoc", assoc);
+        register_native_fn(&mut core, "dissoc", dissoc);
+        register_native_fn(&mut core, "merge", merge);
+        register_native_fn(&mut core, "get", get);
+        register_native_fn(&mut core, "contains?", contains);
+        register_native_fn(&mut core, "hash-set", hash_set);
+        register_native_fn(&mut core, "conj", conj);
+        register_native_fn(&mut core, "disj", disj);
+        register_native_fn(&mut core, "peek", peek);
+        register_native_fn(&mut core, "pop", pop);
+        register_native_fn(&mut core, "catvec", catvec);
+        register_native_fn(&mut core, "subvec", subvec);
+
+        register_native_fn(&mut core, "transient", transient);
+        register_native_fn(&mut core, "persistent!", persistent);
+        register_native_fn(&mut core, "conj!", conj_bang);
+        register_native_fn(&mut core, "assoc!", assoc_bang);
+        register_native_fn(&mut core, "dissoc!", dissoc_bang);
+        register_native_fn(&mut core, "disj!", disj_bang);
+        register_native_fn(&mut core, "pop!", pop_bang);
+
+        register_native_fn(&mut core, "compare", compare);
+        register_native_fn(&mut core, "sorted-map", sorted_map);
+        register_native_fn(&mut core, "sorted-map-by", sorted_map_by);
+        register_native_fn(&mut core, "sorted-set", sorted_set);
+        register_native_fn(&mut core, "sorted-set-by", sorted_set_by);
+        register_native_fn(&mut core, "subseq", subseq);
+        register_native_fn(&mut core, "rsubseq", rsubseq);
     }
 
+    // Libraries of natives, each interned in a namespace of its own that
+    // `user` has the usual alias for
+    let mut set = HashMap::new();
+    register_native_fn(&mut set, "union", set_union);
+    register_native_fn(&mut set, "intersection", set_intersection);
+    register_native_fn(&mut set, "difference", set_difference);
+    register_native_fn(&mut set, "subset?", set_is_subset);
+    register_native_fn(&mut set, "superset?", set_is_superset);
+
+    let mut zip = HashMap::new();
+    register_native_fn(&mut zip, "edn-zip", zip_edn_zip);
+    register_native_fn(&mut zip, "vector-zip", zip_vector_zip);
+    register_native_fn(&mut zip, "seq-zip", zip_seq_zip);
+    register_native_fn(&mut zip, "node", zip_node);
+    register_native_fn(&mut zip, "branch?", zip_is_branch);
+    register_native_fn(&mut zip, "children", zip_children);
+    register_native_fn(&mut zip, "end?", zip_is_end);
+    register_native_fn(&mut zip, "root", zip_root);
+    register_native_fn(&mut zip, "up", zip_up);
+    register_native_fn(&mut zip, "down", zip_down);
+    register_native_fn(&mut zip, "left", zip_left);
+    register_native_fn(&mut zip, "right", zip_right);
+    register_native_fn(&mut zip, "leftmost", zip_leftmost);
+    register_native_fn(&mut zip, "rightmost", zip_rightmost);
+    register_native_fn(&mut zip, "next", zip_next);
+    register_native_fn(&mut zip, "prev", zip_prev);
+    register_native_fn(&mut zip, "replace", zip_replace);
+    register_native_fn(&mut zip, "edit", zip_edit);
+    register_native_fn(&mut zip, "insert-left", zip_insert_left);
+    register_native_fn(&mut zip, "insert-right", zip_insert_right);
+    register_native_fn(&mut zip, "insert-child", zip_insert_child);
+    register_native_fn(&mut zip, "append-child", zip_append_child);
+    register_native_fn(&mut zip, "remove", zip_remove);
+
+    let mut schema = HashMap::new();
+    register_native_fn(&mut schema, "validate", schema_validate);
+    register_native_fn(&mut schema, "explain", schema_explain);
+    register_native_fn(&mut schema, "coerce", schema_coerce);
+
+    // The empty queue, as Clojure's static field
+    let mut queue = HashMap::new();
+    queue.insert(
+        "EMPTY".to_string(),
+        Value::EDN(EDN::Queue(PersistentQueue::new())),
+    );
+
+    let core = namespace::Namespace::install_core(&env, Arc::new(RwLock::new(core)));
+    let user = namespace::Namespace::root(&env);
+    for (name, alias, natives) in [
+        ("clojure.set", "set", set),
+        ("clojure.zip", "zip", zip),
+        ("yinyang.schema", "schema", schema),
+        ("clojure.lang.PersistentQueue", "PersistentQueue", queue),
+    ] {
+        let lib = core.find_or_create(name);
+        for (name, value) in natives {
+            lib.intern(&env, name, value);
+        }
+        user.alias(alias, &lib);
+    }
+
+    // The core macros are written in yinyang itself; they are read once
+    // and evaluated in clojure.core
+    core.enter(&env);
+    static PRELUDE_FORMS: OnceLock<Vec<EDN>> = OnceLock::new();
+    let prelude = PRELUDE_FORMS.get_or_init(|| read_all(PRELUDE).expect("prelude parses"));
+    for form in prelude {
+        if let Err(e) = eval(form.clone(), &env) {
+            panic!("prelude failed to load: {}", e.stack_trace());
+        }
+    }
+    namespace::Namespace::root(&env).enter(&env);
+
     env
 }
+
+const PRELUDE: &str = include_str!("prelude.clj");
@@ -0,0 +1,657 @@
+use crate::edn::EDN;
+use crate::immutant::map::PersistentHashMap;
+use crate::immutant::vector::PersistentVector;
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use std::fmt;
+use std::str::FromStr;
+
+// Malli-style schemas written as EDN data, e.g.
+//
+//   [:map [:port int?] [:host string?] [:tags {:optional true} [:vector keyword?]]]
+//
+// Leaf schemas are predicate symbols (`int?`, `string?`, ...) or type
+// keywords (`:int`, `:string`, ...). Type keywords may take a property map
+// with `:min`/`:max`, which bound numbers by value and strings and
+// collections by count.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Any,
+    Some,
+    Nil,
+    Boolean,
+    Int,
+    PosInt,
+    NegInt,
+    NatInt,
+    Double,
+    Number,
+    String,
+    Keyword,
+    Symbol,
+    Map,
+    Vector,
+    List,
+    Set,
+    Coll,
+}
+
+#[derive(Debug, Clone)]
+struct MapEntry {
+    key: EDN,
+    optional: bool,
+    schema: Schema,
+}
+
+#[derive(Debug, Clone)]
+enum SchemaType {
+    Leaf {
+        kind: Kind,
+        min: Option<BigDecimal>,
+        max: Option<BigDecimal>,
+    },
+    Map {
+        entries: Vec<MapEntry>,
+        closed: bool,
+    },
+    MapOf(Box<Schema>, Box<Schema>),
+    Vector(Box<Schema>),
+    Sequential(Box<Schema>),
+    Set(Box<Schema>),
+    Tuple(Vec<Schema>),
+    Maybe(Box<Schema>),
+    Or(Vec<Schema>),
+    And(Vec<Schema>),
+    Enum(Vec<EDN>),
+    Equals(EDN),
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    form: EDN,
+    schema_type: SchemaType,
+}
+
+/// One failed check: where in the value it happened, the value found there,
+/// the sub-schema it failed and a human readable reason.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: Vec<EDN>,
+    pub value: EDN,
+    pub schema: EDN,
+    pub message: String,
+}
+
+impl SchemaError {
+    pub fn to_edn(&self) -> EDN {
+        let map = PersistentHashMap::new()
+            .assoc(EDN::keyword("path"), EDN::Vector(self.path.clone().into()))
+            .assoc(EDN::keyword("value"), self.value.clone())
+            .assoc(EDN::keyword("schema"), self.schema.clone())
+            .assoc(EDN::keyword("message"), EDN::String(self.message.clone()));
+        EDN::Map(map)
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, key) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        write!(f, "] {}: {}", self.value, self.message)
+    }
+}
+
+fn kind_from_predicate(name: &str) -> Option<Kind> {
+    let kind = match name {
+        "any?" => Kind::Any,
+        "some?" => Kind::Some,
+        "nil?" => Kind::Nil,
+        "boolean?" => Kind::Boolean,
+        "int?" | "integer?" => Kind::Int,
+        "pos-int?" => Kind::PosInt,
+        "neg-int?" => Kind::NegInt,
+        "nat-int?" => Kind::NatInt,
+        "double?" | "float?" => Kind::Double,
+        "number?" => Kind::Number,
+        "string?" => Kind::String,
+        "keyword?" => Kind::Keyword,
+        "symbol?" => Kind::Symbol,
+        "map?" => Kind::Map,
+        "vector?" => Kind::Vector,
+        "list?" | "seq?" => Kind::List,
+        "set?" => Kind::Set,
+        "coll?" => Kind::Coll,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn kind_from_keyword(name: &str) -> Option<Kind> {
+    let kind = match name {
+        ":any" => Kind::Any,
+        ":some" => Kind::Some,
+        ":nil" => Kind::Nil,
+        ":boolean" => Kind::Boolean,
+        ":int" => Kind::Int,
+        ":double" => Kind::Double,
+        ":number" => Kind::Number,
+        ":string" => Kind::String,
+        ":keyword" => Kind::Keyword,
+        ":symbol" => Kind::Symbol,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn kind_description(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Any => "anything",
+        Kind::Some => "a non-nil value",
+        Kind::Nil => "nil",
+        Kind::Boolean => "a boolean",
+        Kind::Int => "an int",
+        Kind::PosInt => "a positive int",
+        Kind::NegInt => "a negative int",
+        Kind::NatInt => "a non-negative int",
+        Kind::Double => "a double",
+        Kind::Number => "a number",
+        Kind::String => "a string",
+        Kind::Keyword => "a keyword",
+        Kind::Symbol => "a symbol",
+        Kind::Map => "a map",
+        Kind::Vector => "a vector",
+        Kind::List => "a list",
+        Kind::Set => "a set",
+        Kind::Coll => "a collection",
+    }
+}
+
+fn kind_matches(kind: Kind, value: &EDN) -> bool {
+    let zero = BigInt::from(0);
+    match (kind, value) {
+        (Kind::Any, _) => true,
+        (Kind::Some, v) => !v.is_nil(),
+        (Kind::Nil, EDN::Nil) => true,
+        (Kind::Boolean, EDN::Bool(_)) => true,
+        (Kind::Int, EDN::Integer(_)) => true,
+        (Kind::PosInt, EDN::Integer(i)) => *i > zero,
+        (Kind::NegInt, EDN::Integer(i)) => *i < zero,
+        (Kind::NatInt, EDN::Integer(i)) => *i >= zero,
+        (Kind::Double, EDN::Float(_)) => true,
+        (Kind::Number, EDN::Integer(_) | EDN::Float(_)) => true,
+        (Kind::String, EDN::String(_)) => true,
+        (Kind::Keyword, EDN::Keyword(_)) => true,
+        (Kind::Symbol, EDN::Symbol(_, _)) => true,
+        (Kind::Map, v) => v.is_map(),
+        (Kind::Vector, v) => v.is_vector(),
+        (Kind::List, EDN::List(_)) => true,
+        (Kind::Set, v) => v.is_set(),
+        (Kind::Coll, v) => v.is_coll(),
+        _ => false,
+    }
+}
+
+// The quantity `:min`/`:max` apply to: numbers by value, everything else by
+// count.
+fn measure(value: &EDN) -> Option<BigDecimal> {
+    match value {
+        EDN::Integer(i) => Some(BigDecimal::from(i.clone())),
+        EDN::Float(f) => Some(f.clone()),
+        EDN::String(s) => Some(BigDecimal::from(s.chars().count() as u64)),
+        EDN::Vector(v) => Some(BigDecimal::from(v.len() as u64)),
+        EDN::RrbVector(v) => Some(BigDecimal::from(v.len() as u64)),
+        EDN::List(l) => Some(BigDecimal::from(l.len() as u64)),
+        EDN::Map(m) => Some(BigDecimal::from(m.len() as u64)),
+        EDN::SortedMap(m) => Some(BigDecimal::from(m.len() as u64)),
+        EDN::Set(s) => Some(BigDecimal::from(s.len() as u64)),
+        EDN::SortedSet(s) => Some(BigDecimal::from(s.len() as u64)),
+        EDN::Queue(q) => Some(BigDecimal::from(q.len() as u64)),
+        _ => None,
+    }
+}
+
+fn bound(props: &EDN, key: &str) -> Result<Option<BigDecimal>, String> {
+    match &props[&EDN::keyword(key)] {
+        EDN::Nil => Ok(None),
+        EDN::Integer(i) => Ok(Some(BigDecimal::from(i.clone()))),
+        EDN::Float(f) => Ok(Some(f.clone())),
+        other => Err(format!(
+            "Schema property {} must be a number, got {}",
+            key, other
+        )),
+    }
+}
+
+fn sequence_items(value: &EDN) -> Option<Vec<&EDN>> {
+    match value {
+        EDN::Vector(v) => Some(v.iter().collect()),
+        EDN::RrbVector(v) => Some(v.iter().collect()),
+        EDN::List(l) => Some(l.iter().collect()),
+        EDN::Queue(q) => Some(q.iter().collect()),
+        _ => None,
+    }
+}
+
+impl Schema {
+    pub fn parse(form: &EDN) -> Result<Schema, String> {
+        let schema_type = match form {
+            EDN::Symbol(name, _) => SchemaType::Leaf {
+                kind: kind_from_predicate(name)
+                    .ok_or_else(|| format!("Unknown schema predicate: {}", name))?,
+                min: None,
+                max: None,
+            },
+            EDN::Keyword(name) => match name.as_str() {
+                ":map" => SchemaType::Map {
+                    entries: Vec::new(),
+                    closed: false,
+                },
+                _ => SchemaType::Leaf {
+                    kind: kind_from_keyword(name)
+                        .ok_or_else(|| format!("Unknown schema type: {}", name))?,
+                    min: None,
+                    max: None,
+                },
+            },
+            EDN::Vector(items) => Self::parse_compound(form, &items.to_vec())?,
+            _ => return Err(format!("Invalid schema: {}", form)),
+        };
+        Ok(Schema {
+            form: form.clone(),
+            schema_type,
+        })
+    }
+
+    fn parse_compound(form: &EDN, items: &[EDN]) -> Result<SchemaType, String> {
+        let (head, rest) = items
+            .split_first()
+            .ok_or_else(|| "Empty schema vector".to_string())?;
+        let (props, children) = match rest.split_first() {
+            Some((props @ EDN::Map(_), children)) => (props.clone(), children),
+            _ => (EDN::Nil, rest),
+        };
+        let one_child = || match children {
+            [child] => Schema::parse(child).map(Box::new),
+            _ => Err(format!("{} takes exactly one child schema: {}", head, form)),
+        };
+        let all_children = || {
+            children
+                .iter()
+                .map(Schema::parse)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let schema_type = match head.as_keyword() {
+            Some(":map") => SchemaType::Map {
+                entries: children
+                    .iter()
+                    .map(Self::parse_map_entry)
+                    .collect::<Result<_, _>>()?,
+                closed: props[&EDN::keyword("closed")].as_bool().unwrap_or(false),
+            },
+            Some(":map-of") => match children {
+                [k, v] => {
+                    SchemaType::MapOf(Box::new(Schema::parse(k)?), Box::new(Schema::parse(v)?))
+                }
+                _ => return Err(format!(":map-of takes a key and a value schema: {}", form)),
+            },
+            Some(":vector") => SchemaType::Vector(one_child()?),
+            Some(":sequential") => SchemaType::Sequential(one_child()?),
+            Some(":set") => SchemaType::Set(one_child()?),
+            Some(":maybe") => SchemaType::Maybe(one_child()?),
+            Some(":tuple") => SchemaType::Tuple(all_children()?),
+            Some(":or") => SchemaType::Or(all_children()?),
+            Some(":and") => SchemaType::And(all_children()?),
+            Some(":enum") => SchemaType::Enum(children.to_vec()),
+            Some(":=") => match children {
+                [v] => SchemaType::Equals(v.clone()),
+                _ => return Err(format!(":= takes exactly one value: {}", form)),
+            },
+            Some(name) => match kind_from_keyword(name) {
+                Some(kind) if children.is_empty() => SchemaType::Leaf {
+                    kind,
+                    min: bound(&props, ":min")?,
+                    max: bound(&props, ":max")?,
+                },
+                _ => return Err(format!("Invalid schema: {}", form)),
+            },
+            None => return Err(format!("Invalid schema: {}", form)),
+        };
+
+        // Collection schemas accept :min/:max too, checked against count.
+        match (&schema_type, props.is_nil()) {
+            (SchemaType::Vector(_) | SchemaType::Sequential(_) | SchemaType::Set(_), false) => {
+                Ok(SchemaType::And(vec![
+                    Schema {
+                        form: form.clone(),
+                        schema_type,
+                    },
+                    Schema {
+                        form: form.clone(),
+                        schema_type: SchemaType::Leaf {
+                            kind: Kind::Any,
+                            min: bound(&props, ":min")?,
+                            max: bound(&props, ":max")?,
+                        },
+                    },
+                ]))
+            }
+            _ => Ok(schema_type),
+        }
+    }
+
+    fn parse_map_entry(entry: &EDN) -> Result<MapEntry, String> {
+        match entry.as_vector().map(PersistentVector::to_vec).as_deref() {
+            Some([key, schema]) => Ok(MapEntry {
+                key: key.clone(),
+                optional: false,
+                schema: Schema::parse(schema)?,
+            }),
+            Some([key, props @ EDN::Map(_), schema]) => Ok(MapEntry {
+                key: key.clone(),
+                optional: props[&EDN::keyword("optional")].as_bool().unwrap_or(false),
+                schema: Schema::parse(schema)?,
+            }),
+            _ => Err(format!(
+                "Map schema entries must be [key schema] or [key props schema], got {}",
+                entry
+            )),
+        }
+    }
+
+    pub fn form(&self) -> &EDN {
+        &self.form
+    }
+
+    pub fn validate(&self, value: &EDN) -> bool {
+        self.explain(value).is_empty()
+    }
+
+    /// Returns every reason `value` does not conform, or an empty vector when
+    /// it does.
+    pub fn explain(&self, value: &EDN) -> Vec<SchemaError> {
+        let mut errors = Vec::new();
+        self.explain_at(value, &mut Vec::new(), &mut errors);
+        errors
+    }
+
+    fn error(&self, path: &[EDN], value: &EDN, message: String, errors: &mut Vec<SchemaError>) {
+        errors.push(SchemaError {
+            path: path.to_vec(),
+            value: value.clone(),
+            schema: self.form.clone(),
+            message,
+        });
+    }
+
+    fn explain_at(&self, value: &EDN, path: &mut Vec<EDN>, errors: &mut Vec<SchemaError>) {
+        match &self.schema_type {
+            SchemaType::Leaf { kind, min, max } => {
+                if !kind_matches(*kind, value) {
+                    let message = format!("should be {}", kind_description(*kind));
+                    return self.error(path, value, message, errors);
+                }
+                let size = measure(value);
+                if let (Some(min), Some(size)) = (min, &size) {
+                    if size < min {
+                        let message = format!("should be at least {}", min);
+                        self.error(path, value, message, errors);
+                    }
+                }
+                if let (Some(max), Some(size)) = (max, &size) {
+                    if size > max {
+                        let message = format!("should be at most {}", max);
+                        self.error(path, value, message, errors);
+                    }
+                }
+            }
+            SchemaType::Map { entries, closed } => {
+                let map = match value.map_entries() {
+                    Some(map) => map,
+                    None => return self.error(path, value, "should be a map".into(), errors),
+                };
+                for entry in entries {
+                    path.push(entry.key.clone());
+                    match value.get(&entry.key) {
+                        Some(v) => entry.schema.explain_at(v, path, errors),
+                        None if !entry.optional => {
+                            let message = "missing required key".to_string();
+                            self.error(path, &EDN::Nil, message, errors);
+                        }
+                        None => {}
+                    }
+                    path.pop();
+                }
+                if *closed {
+                    for (key, v) in map {
+                        if !entries.iter().any(|e| &e.key == key) {
+                            path.push(key.clone());
+                            self.error(path, v, "disallowed key".into(), errors);
+                            path.pop();
+                        }
+                    }
+                }
+            }
+            SchemaType::MapOf(key_schema, value_schema) => {
+                let map = match value.map_entries() {
+                    Some(map) => map,
+                    None => return self.error(path, value, "should be a map".into(), errors),
+                };
+                for (k, v) in map {
+                    path.push(k.clone());
+                    key_schema.explain_at(k, path, errors);
+                    value_schema.explain_at(v, path, errors);
+                    path.pop();
+                }
+            }
+            SchemaType::Vector(item) | SchemaType::Sequential(item) => {
+                let items = match (&self.schema_type, value) {
+                    (SchemaType::Vector(_), v) if v.is_vector() => sequence_items(value),
+                    (SchemaType::Sequential(_), _) => sequence_items(value),
+                    _ => None,
+                };
+                let items = match items {
+                    Some(items) => items,
+                    None => {
+                        let message = match self.schema_type {
+                            SchemaType::Vector(_) => "should be a vector",
+                            _ => "should be a sequence",
+                        };
+                        return self.error(path, value, message.into(), errors);
+                    }
+                };
+                for (i, v) in items.into_iter().enumerate() {
+                    path.push(EDN::from(i));
+                    item.explain_at(v, path, errors);
+                    path.pop();
+                }
+            }
+            SchemaType::Set(item) => match value.set_items() {
+                Some(set) => {
+                    for v in set {
+                        item.explain_at(v, path, errors);
+                    }
+                }
+                None => self.error(path, value, "should be a set".into(), errors),
+            },
+            SchemaType::Tuple(items) => match sequence_items(value) {
+                Some(values) if values.len() == items.len() => {
+                    for (i, (schema, v)) in items.iter().zip(values).enumerate() {
+                        path.push(EDN::from(i));
+                        schema.explain_at(v, path, errors);
+                        path.pop();
+                    }
+                }
+                _ => {
+                    let message = format!("should be a tuple of {}", items.len());
+                    self.error(path, value, message, errors)
+                }
+            },
+            SchemaType::Maybe(inner) => {
+                if !value.is_nil() {
+                    inner.explain_at(value, path, errors);
+                }
+            }
+            SchemaType::Or(alternatives) => {
+                if !alternatives.iter().any(|s| s.validate(value)) {
+                    let message = "should match one of the alternatives".to_string();
+                    self.error(path, value, message, errors);
+                }
+            }
+            SchemaType::And(all) => {
+                for schema in all {
+                    let before = errors.len();
+                    schema.explain_at(value, path, errors);
+                    if errors.len() > before {
+                        break;
+                    }
+                }
+            }
+            SchemaType::Enum(options) => {
+                if !options.contains(value) {
+                    let options: Vec<String> = options.iter().map(EDN::to_string).collect();
+                    let message = format!("should be one of {}", options.join(", "));
+                    self.error(path, value, message, errors);
+                }
+            }
+            SchemaType::Equals(expected) => {
+                if value != expected {
+                    let message = format!("should be {}", expected);
+                    self.error(path, value, message, errors);
+                }
+            }
+        }
+    }
+
+    /// Converts `value` towards the schema where an obvious conversion
+    /// exists (strings to numbers, keywords, symbols and booleans; ints to
+    /// doubles), then validates the result.
+    pub fn coerce(&self, value: &EDN) -> Result<EDN, Vec<SchemaError>> {
+        let coerced = self.transform(value);
+        let errors = self.explain(&coerced);
+        if errors.is_empty() {
+            Ok(coerced)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn transform(&self, value: &EDN) -> EDN {
+        match (&self.schema_type, value) {
+            (SchemaType::Leaf { kind, .. }, _) => coerce_leaf(*kind, value),
+            (SchemaType::Map { entries, .. }, map) if map.is_map() => {
+                let mut map = map.clone();
+                for entry in entries {
+                    if let Some(v) = map.get(&entry.key) {
+                        let v = entry.schema.transform(v);
+                        if let Ok(m) = map.assoc(entry.key.clone(), v) {
+                            map = m;
+                        }
+                    }
+                }
+                map
+            }
+            (SchemaType::MapOf(key_schema, value_schema), EDN::Map(map)) => EDN::Map(
+                map.iter()
+                    .map(|(k, v)| (key_schema.transform(k), value_schema.transform(v)))
+                    .collect(),
+            ),
+            (SchemaType::MapOf(key_schema, value_schema), EDN::SortedMap(map)) => map
+                .iter()
+                .try_fold(EDN::SortedMap(map.empty()), |m, (k, v)| {
+                    m.assoc(key_schema.transform(k), value_schema.transform(v))
+                })
+                .unwrap_or_else(|_| value.clone()),
+            (SchemaType::Vector(item) | SchemaType::Sequential(item), EDN::Vector(v)) => {
+                EDN::Vector(v.iter().map(|x| item.transform(x)).collect())
+            }
+            (SchemaType::Vector(item) | SchemaType::Sequential(item), EDN::RrbVector(v)) => {
+                EDN::RrbVector(v.iter().map(|x| item.transform(x)).collect())
+            }
+            (SchemaType::Set(item), EDN::Set(s)) => {
+                EDN::Set(s.iter().map(|x| item.transform(x)).collect())
+            }
+            (SchemaType::Set(item), EDN::SortedSet(s)) => s
+                .iter()
+                .try_fold(EDN::SortedSet(s.empty()), |set, x| {
+                    set.conj(item.transform(x))
+                })
+                .unwrap_or_else(|_| value.clone()),
+            (SchemaType::Tuple(items), EDN::Vector(v)) if v.len() == items.len() => EDN::Vector(
+                items
+                    .iter()
+                    .zip(v)
+                    .map(|(schema, x)| schema.transform(x))
+                    .collect(),
+            ),
+            (SchemaType::Tuple(items), EDN::RrbVector(v)) if v.len() == items.len() => {
+                EDN::RrbVector(
+                    items
+                        .iter()
+                        .zip(v)
+                        .map(|(schema, x)| schema.transform(x))
+                        .collect(),
+                )
+            }
+            (SchemaType::Maybe(_), EDN::Nil) => EDN::Nil,
+            (SchemaType::Maybe(inner), _) => inner.transform(value),
+            (SchemaType::Or(alternatives), _) => alternatives
+                .iter()
+                .map(|s| (s, s.transform(value)))
+                .find(|(s, v)| s.validate(v))
+                .map_or_else(|| value.clone(), |(_, v)| v),
+            (SchemaType::And(all), _) => all.iter().fold(value.clone(), |v, s| s.transform(&v)),
+            (SchemaType::Enum(options), _) => options
+                .iter()
+                .find(|o| coerce_like(o, value).as_ref() == Some(o))
+                .cloned()
+                .unwrap_or_else(|| value.clone()),
+            (SchemaType::Equals(expected), _) => match coerce_like(expected, value) {
+                Some(v) if &v == expected => v,
+                _ => value.clone(),
+            },
+            _ => value.clone(),
+        }
+    }
+}
+
+fn coerce_leaf(kind: Kind, value: &EDN) -> EDN {
+    let coerced = match (kind, value) {
+        (Kind::Int | Kind::PosInt | Kind::NegInt | Kind::NatInt, EDN::String(s)) => {
+            BigInt::from_str(s.trim()).ok().map(EDN::Integer)
+        }
+        (Kind::Double | Kind::Number, EDN::String(s)) => {
+            BigDecimal::from_str(s.trim()).ok().map(EDN::Float)
+        }
+        (Kind::Double, EDN::Integer(i)) => Some(EDN::Float(BigDecimal::from(i.clone()))),
+        (Kind::Boolean, EDN::String(s)) => match s.as_str() {
+            "true" => Some(EDN::Bool(true)),
+            "false" => Some(EDN::Bool(false)),
+            _ => None,
+        },
+        (Kind::Keyword, EDN::String(s)) => Some(EDN::keyword(s)),
+        (Kind::Symbol, EDN::String(s)) => Some(EDN::Symbol(s.clone(), None)),
+        (Kind::String, EDN::Keyword(k)) => Some(EDN::String(k.trim_start_matches(':').into())),
+        _ => None,
+    };
+    coerced.unwrap_or_else(|| value.clone())
+}
+
+// Coerces a string `value` to the type of `example`, used by :enum and :=.
+fn coerce_like(example: &EDN, value: &EDN) -> Option<EDN> {
+    let kind = match example {
+        EDN::Integer(_) => Kind::Int,
+        EDN::Float(_) => Kind::Double,
+        EDN::Bool(_) => Kind::Boolean,
+        EDN::Keyword(_) => Kind::Keyword,
+        EDN::Symbol(_, _) => Kind::Symbol,
+        _ => return Some(value.clone()),
+    };
+    Some(coerce_leaf(kind, value))
+}
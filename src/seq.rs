@@ -0,0 +1,773 @@
+use crate::clojure::EvalError;
+use crate::edn::EDN;
+use crate::immutant::list::List;
+use crate::immutant::rrb::RrbVector;
+use crate::immutant::vector::PersistentVector;
+use crate::immutant::{map, set, sorted};
+use bigdecimal::BigDecimal;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+// The seq abstraction: anything `Seqable` can produce a `Seq`, a persistent
+// cursor whose `first` and `rest` work the same way whatever collection it
+// walks. Natives that only need to step through a collection go through this
+// layer instead of matching every `EDN` variant.
+//
+// Seqs may be lazy: a `LazySeq` runs its thunk the first time it is walked
+// and caches the result. Producers such as `range` and `map` hand out
+// chunks of up to CHUNK_SIZE items at a time, as Clojure's chunked seqs do.
+
+const CHUNK_SIZE: usize = 32;
+
+/// Values that can be viewed as a sequence. `seq` is `None` for empty
+/// collections and `nil`, as in Clojure.
+pub trait Seqable {
+    fn seq(&self) -> Result<Option<Seq>, EvalError>;
+}
+
+/// A sequence. An empty seq has a `nil` first and an empty rest.
+pub trait ISeq {
+    fn first(&self) -> Result<EDN, EvalError>;
+
+    fn rest(&self) -> Result<Seq, EvalError>;
+
+    /// Like `rest`, but `None` rather than an empty seq at the end.
+    fn next(&self) -> Result<Option<Seq>, EvalError> {
+        self.rest()?.seq()
+    }
+
+    fn cons(&self, item: EDN) -> Seq;
+}
+
+#[derive(Clone)]
+pub struct Seq(Cursor);
+
+// Every cursor other than `Empty` has at least one item left; `Seq::make`
+// keeps it that way.
+#[derive(Clone)]
+enum Cursor {
+    Empty,
+    List(List<EDN>),
+    Vector(PersistentVector<EDN>, usize),
+    RrbVector(RrbVector<EDN>, usize),
+    // Maps and sets are walked in place; each map item is a [key value]
+    // entry.
+    HashMap(map::Cursor<EDN, EDN>),
+    HashSet(set::Cursor<EDN>),
+    SortedMap(sorted::Cursor<EDN, EDN>),
+    SortedSet(sorted::SetCursor<EDN>),
+    // A queue's front list, then its rear vector from the index.
+    Queue(List<EDN>, PersistentVector<EDN>, usize),
+    // Items collected into a seq, as by `FromIterator`.
+    Items(Arc<Vec<EDN>>, usize),
+    // Byte offset into the string; each item is a one-character string.
+    Chars(Arc<str>, usize),
+    Cons(Arc<(EDN, Seq)>),
+    // An offset into a run of computed items, followed by the rest.
+    Chunk(Arc<Chunk>, usize),
+    // May turn out to be empty once realized.
+    Lazy(LazySeq),
+}
+
+struct Chunk {
+    items: Vec<EDN>,
+    rest: Seq,
+}
+
+type Thunk = Box<dyn FnOnce() -> Result<Seq, EvalError> + Send>;
+
+enum LazyState {
+    Pending(Thunk),
+    // The thunk is being run by this thread.
+    Running(ThreadId),
+    // Always holds a realized (non-lazy) seq or the error the thunk gave.
+    Done(Result<Seq, EvalError>),
+}
+
+struct Lazy {
+    state: Mutex<LazyState>,
+    // Signalled when the state becomes `Done`.
+    done: Condvar,
+}
+
+/// A seq computed on first use. The result, or the error, is cached.
+/// Threads that reach it while another thread runs the thunk wait for that
+/// result.
+#[derive(Clone)]
+pub struct LazySeq(Arc<Lazy>);
+
+impl LazySeq {
+    pub fn new<F>(thunk: F) -> Self
+    where
+        F: FnOnce() -> Result<Seq, EvalError> + Send + 'static,
+    {
+        LazySeq(Arc::new(Lazy {
+            state: Mutex::new(LazyState::Pending(Box::new(thunk))),
+            done: Condvar::new(),
+        }))
+    }
+
+    pub fn is_realized(&self) -> bool {
+        matches!(*self.0.state.lock().unwrap(), LazyState::Done(_))
+    }
+
+    // The cached result, if the thunk has already run.
+    fn value(&self) -> Option<Result<Seq, EvalError>> {
+        match &*self.0.state.lock().unwrap() {
+            LazyState::Done(result) => Some(result.clone()),
+            _ => None,
+        }
+    }
+
+    // Runs the thunk if it has not run yet, and says whether it did. The
+    // result may itself be lazy; the caller must `finish` a seq it ran.
+    fn step(&self) -> (Result<Seq, EvalError>, bool) {
+        let me = thread::current().id();
+        let mut state = self.0.state.lock().unwrap();
+        let thunk = loop {
+            match &*state {
+                LazyState::Done(result) => return (result.clone(), false),
+                LazyState::Running(owner) if *owner == me => {
+                    let e =
+                        EvalError::Native("lazy seq needs its own value to realize".to_string());
+                    return (Err(e), false);
+                }
+                LazyState::Running(_) => state = self.0.done.wait(state).unwrap(),
+                LazyState::Pending(_) => match mem::replace(&mut *state, LazyState::Running(me)) {
+                    LazyState::Pending(thunk) => break thunk,
+                    _ => unreachable!(),
+                },
+            }
+        };
+        mem::drop(state);
+        (thunk(), true)
+    }
+
+    fn finish(&self, result: Result<Seq, EvalError>) {
+        *self.0.state.lock().unwrap() = LazyState::Done(result);
+        self.0.done.notify_all();
+    }
+
+    // A lazy seq whose thunk returns another lazy seq is followed in a loop
+    // rather than by recursion, so long chains cannot overflow the stack.
+    // Every seq whose thunk ran here caches the final result.
+    fn realize(&self) -> Result<Seq, EvalError> {
+        let mut ran = Vec::new();
+        let mut next = self.clone();
+        let result = loop {
+            let (result, did_run) = next.step();
+            if did_run {
+                ran.push(next.clone());
+            }
+            match result {
+                Ok(seq) => match &seq.0 {
+                    Cursor::Lazy(lazy) => next = lazy.clone(),
+                    _ => break Ok(seq),
+                },
+                Err(e) => break Err(e),
+            }
+        };
+        for lazy in ran {
+            lazy.finish(result.clone());
+        }
+        result
+    }
+}
+
+impl Seq {
+    pub fn empty() -> Self {
+        Seq(Cursor::Empty)
+    }
+
+    pub fn lazy<F>(thunk: F) -> Self
+    where
+        F: FnOnce() -> Result<Seq, EvalError> + Send + 'static,
+    {
+        Seq(Cursor::Lazy(LazySeq::new(thunk)))
+    }
+
+    /// `items` followed by `rest`.
+    pub fn chunked(items: Vec<EDN>, rest: Seq) -> Self {
+        if items.is_empty() {
+            rest
+        } else {
+            Seq(Cursor::Chunk(Arc::new(Chunk { items, rest }), 0))
+        }
+    }
+
+    fn make(cursor: Cursor) -> Self {
+        let exhausted = match &cursor {
+            Cursor::Empty => true,
+            Cursor::List(l) => l.is_empty(),
+            Cursor::Vector(v, i) => *i >= v.len(),
+            Cursor::RrbVector(v, i) => *i >= v.len(),
+            Cursor::Queue(front, rear, i) => front.is_empty() && *i >= rear.len(),
+            Cursor::Items(items, i) => *i >= items.len(),
+            Cursor::Chars(s, i) => *i >= s.len(),
+            Cursor::HashMap(_)
+            | Cursor::HashSet(_)
+            | Cursor::SortedMap(_)
+            | Cursor::SortedSet(_)
+            | Cursor::Cons(_)
+            | Cursor::Chunk(..)
+            | Cursor::Lazy(_) => false,
+        };
+        if exhausted {
+            Seq::empty()
+        } else {
+            Seq(cursor)
+        }
+    }
+
+    // Realizes a lazy head, so the cursor is never `Lazy`.
+    fn realized(&self) -> Result<Seq, EvalError> {
+        match &self.0 {
+            Cursor::Lazy(lazy) => lazy.realize(),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// False for a lazy seq whose thunk has not run yet.
+    pub fn is_realized(&self) -> bool {
+        match &self.0 {
+            Cursor::Lazy(lazy) => lazy.is_realized(),
+            _ => true,
+        }
+    }
+
+    /// Up to `limit` items that can be read without running any thunk, and
+    /// whether the seq may go on past them.
+    pub fn realized_prefix(&self, limit: usize) -> (Vec<EDN>, bool) {
+        let mut items = Vec::new();
+        let mut seq = self.clone();
+        loop {
+            if let Cursor::Lazy(lazy) = &seq.0 {
+                match lazy.value() {
+                    Some(Ok(realized)) => seq = realized,
+                    _ => return (items, true),
+                }
+            }
+            if matches!(seq.0, Cursor::Empty) {
+                return (items, false);
+            }
+            if items.len() == limit {
+                return (items, true);
+            }
+            // The head is not lazy, so neither of these runs a thunk
+            match (seq.first(), seq.rest()) {
+                (Ok(item), Ok(rest)) => {
+                    items.push(item);
+                    seq = rest;
+                }
+                _ => return (items, true),
+            }
+        }
+    }
+
+    /// Returns the items that are ready together, such as a whole chunk or up
+    /// to CHUNK_SIZE items of a vector, and the seq after them. `None` at the
+    /// end.
+    pub fn chunk(&self) -> Result<Option<(Vec<EDN>, Seq)>, EvalError> {
+        let seq = self.realized()?;
+        let end = |i: usize, len: usize| ((i / CHUNK_SIZE + 1) * CHUNK_SIZE).min(len);
+        Ok(Some(match &seq.0 {
+            Cursor::Empty => return Ok(None),
+            Cursor::Chunk(chunk, i) => (chunk.items[*i..].to_vec(), chunk.rest.clone()),
+            Cursor::Vector(v, i) => {
+                let end = end(*i, v.len());
+                let items = (*i..end).map(|j| v[j].clone()).collect();
+                (items, Seq::make(Cursor::Vector(v.clone(), end)))
+            }
+            Cursor::RrbVector(v, i) => {
+                let end = end(*i, v.len());
+                let items = (*i..end).map(|j| v[j].clone()).collect();
+                (items, Seq::make(Cursor::RrbVector(v.clone(), end)))
+            }
+            Cursor::Items(items, i) => {
+                let end = end(*i, items.len());
+                let chunk = items[*i..end].to_vec();
+                (chunk, Seq::make(Cursor::Items(items.clone(), end)))
+            }
+            // Stepping these never runs a thunk, so a chunk is cheap to take
+            Cursor::HashMap(_)
+            | Cursor::HashSet(_)
+            | Cursor::SortedMap(_)
+            | Cursor::SortedSet(_) => {
+                let mut items = Vec::with_capacity(CHUNK_SIZE);
+                let mut rest = seq;
+                while items.len() < CHUNK_SIZE && !matches!(rest.0, Cursor::Empty) {
+                    items.push(rest.first()?);
+                    rest = rest.rest()?;
+                }
+                (items, rest)
+            }
+            _ => (vec![seq.first()?], seq.rest()?),
+        }))
+    }
+
+    /// Realizes the first `limit` items, or all of them, as `doall` does.
+    pub fn realize_all(&self, limit: Option<usize>) -> Result<(), EvalError> {
+        self.iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .try_for_each(|item| item.map(|_| ()))
+    }
+
+    /// Iterates the items from here to the end.
+    pub fn iter(&self) -> SeqIter {
+        SeqIter {
+            current: self.clone(),
+        }
+    }
+
+    /// Walks the whole seq, so this is linear in its length.
+    pub fn count(&self) -> Result<usize, EvalError> {
+        self.iter().try_fold(0, |n, item| item.map(|_| n + 1))
+    }
+}
+
+impl From<List<EDN>> for Seq {
+    fn from(l: List<EDN>) -> Self {
+        Seq::make(Cursor::List(l))
+    }
+}
+
+impl FromIterator<EDN> for Seq {
+    fn from_iter<I: IntoIterator<Item = EDN>>(iter: I) -> Self {
+        Seq::make(Cursor::Items(Arc::new(iter.into_iter().collect()), 0))
+    }
+}
+
+impl Seqable for Seq {
+    fn seq(&self) -> Result<Option<Seq>, EvalError> {
+        let seq = self.realized()?;
+        Ok(match seq.0 {
+            Cursor::Empty => None,
+            _ => Some(seq),
+        })
+    }
+}
+
+impl ISeq for Seq {
+    fn first(&self) -> Result<EDN, EvalError> {
+        let seq = self.realized()?;
+        Ok(match &seq.0 {
+            Cursor::Empty => EDN::Nil,
+            Cursor::List(l) => l.head().cloned().unwrap_or(EDN::Nil),
+            Cursor::Vector(v, i) => v[*i].clone(),
+            Cursor::RrbVector(v, i) => v[*i].clone(),
+            Cursor::HashMap(c) => map_entry(c.entry()),
+            Cursor::HashSet(c) => c.item().clone(),
+            Cursor::SortedMap(c) => map_entry(c.entry()),
+            Cursor::SortedSet(c) => c.item().clone(),
+            Cursor::Queue(front, rear, i) => match front.head() {
+                Some(item) => item.clone(),
+                None => rear[*i].clone(),
+            },
+            Cursor::Items(items, i) => items[*i].clone(),
+            Cursor::Chars(s, i) => s[*i..]
+                .chars()
+                .next()
+                .map_or(EDN::Nil, |c| EDN::String(c.to_string())),
+            Cursor::Cons(cell) => cell.0.clone(),
+            Cursor::Chunk(chunk, i) => chunk.items[*i].clone(),
+            Cursor::Lazy(_) => unreachable!("realized seq is lazy"),
+        })
+    }
+
+    fn rest(&self) -> Result<Seq, EvalError> {
+        let seq = self.realized()?;
+        Ok(match &seq.0 {
+            Cursor::Empty => Seq::empty(),
+            Cursor::List(l) => Seq::make(Cursor::List(l.rest())),
+            Cursor::Vector(v, i) => Seq::make(Cursor::Vector(v.clone(), i + 1)),
+            Cursor::RrbVector(v, i) => Seq::make(Cursor::RrbVector(v.clone(), i + 1)),
+            Cursor::HashMap(c) => c
+                .next()
+                .map_or_else(Seq::empty, |c| Seq(Cursor::HashMap(c))),
+            Cursor::HashSet(c) => c
+                .next()
+                .map_or_else(Seq::empty, |c| Seq(Cursor::HashSet(c))),
+            Cursor::SortedMap(c) => c
+                .next()
+                .map_or_else(Seq::empty, |c| Seq(Cursor::SortedMap(c))),
+            Cursor::SortedSet(c) => c
+                .next()
+                .map_or_else(Seq::empty, |c| Seq(Cursor::SortedSet(c))),
+            Cursor::Queue(front, rear, i) if front.is_empty() => {
+                Seq::make(Cursor::Queue(List::new(), rear.clone(), i + 1))
+            }
+            Cursor::Queue(front, rear, i) => {
+                Seq::make(Cursor::Queue(front.rest(), rear.clone(), *i))
+            }
+            Cursor::Items(items, i) => Seq::make(Cursor::Items(items.clone(), i + 1)),
+            Cursor::Chars(s, i) => {
+                let width = s[*i..].chars().next().map_or(1, char::len_utf8);
+                Seq::make(Cursor::Chars(s.clone(), i + width))
+            }
+            Cursor::Cons(cell) => cell.1.clone(),
+            Cursor::Chunk(chunk, i) if i + 1 < chunk.items.len() => {
+                Seq(Cursor::Chunk(chunk.clone(), i + 1))
+            }
+            Cursor::Chunk(chunk, _) => chunk.rest.clone(),
+            Cursor::Lazy(_) => unreachable!("realized seq is lazy"),
+        })
+    }
+
+    fn cons(&self, item: EDN) -> Seq {
+        Seq(Cursor::Cons(Arc::new((item, self.clone()))))
+    }
+}
+
+fn map_entry((k, v): (&EDN, &EDN)) -> EDN {
+    EDN::Vector(vec![k.clone(), v.clone()].into())
+}
+
+impl Seqable for EDN {
+    fn seq(&self) -> Result<Option<Seq>, EvalError> {
+        let cursor = match self {
+            EDN::Nil => Cursor::Empty,
+            EDN::List(l) => Cursor::List((**l).clone()),
+            EDN::Vector(v) => Cursor::Vector(v.clone(), 0),
+            EDN::RrbVector(v) => Cursor::RrbVector(v.clone(), 0),
+            EDN::Map(m) => m.cursor().map_or(Cursor::Empty, Cursor::HashMap),
+            EDN::SortedMap(m) => m.cursor().map_or(Cursor::Empty, Cursor::SortedMap),
+            EDN::Set(s) => s.cursor().map_or(Cursor::Empty, Cursor::HashSet),
+            EDN::SortedSet(s) => s.cursor().map_or(Cursor::Empty, Cursor::SortedSet),
+            EDN::Queue(q) => {
+                let (front, rear) = q.parts();
+                Cursor::Queue(front.clone(), rear.clone(), 0)
+            }
+            EDN::String(s) => Cursor::Chars(Arc::from(s.as_str()), 0),
+            EDN::Seq(s) => return s.seq(),
+            _ => return Err(format!("Don't know how to create a seq from {}", self).into()),
+        };
+        Seq::make(cursor).seq()
+    }
+}
+
+/// Views `coll` as a seq without realizing it if it is already a lazy seq.
+pub fn to_seq(coll: &EDN) -> Result<Seq, EvalError> {
+    match coll {
+        EDN::Seq(s) => Ok(s.clone()),
+        _ => Ok(coll.seq()?.unwrap_or_else(Seq::empty)),
+    }
+}
+
+/// Counts the items of a collection: in constant time for the persistent
+/// collections, by walking for seqs and strings.
+pub fn count(coll: &EDN) -> Result<usize, EvalError> {
+    Ok(match coll {
+        EDN::Nil => 0,
+        EDN::String(s) => s.chars().count(),
+        EDN::List(l) => l.len(),
+        EDN::Vector(v) => v.len(),
+        EDN::RrbVector(v) => v.len(),
+        EDN::Map(m) => m.len(),
+        EDN::SortedMap(m) => m.len(),
+        EDN::Set(s) => s.len(),
+        EDN::SortedSet(s) => s.len(),
+        EDN::Queue(q) => q.len(),
+        EDN::Seq(s) => s.count()?,
+        _ => return Err(format!("count not supported on {}", coll).into()),
+    })
+}
+
+/// Realizes every lazy seq in `x`, however deeply nested, passing up the
+/// first error a thunk raises. Printing, comparing or hashing a value that
+/// has been forced runs no thunk, so those can't fail.
+pub fn force(x: &EDN) -> Result<(), EvalError> {
+    match x {
+        EDN::Seq(s) => s.iter().try_for_each(|item| force(&item?)),
+        EDN::List(l) => l.iter().try_for_each(force),
+        EDN::Vector(v) => v.iter().try_for_each(force),
+        EDN::RrbVector(v) => v.iter().try_for_each(force),
+        EDN::Queue(q) => q.iter().try_for_each(force),
+        EDN::Set(s) => s.iter().try_for_each(force),
+        EDN::SortedSet(s) => s.iter().try_for_each(force),
+        EDN::Map(m) => m
+            .iter()
+            .try_for_each(|(k, v)| force(k).and_then(|_| force(v))),
+        EDN::SortedMap(m) => m
+            .iter()
+            .try_for_each(|(k, v)| force(k).and_then(|_| force(v))),
+        _ => Ok(()),
+    }
+}
+
+pub struct SeqIter {
+    current: Seq,
+}
+
+impl Iterator for SeqIter {
+    type Item = Result<EDN, EvalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = match self.current.seq() {
+            Ok(None) => return None,
+            Ok(Some(seq)) => seq.first().and_then(|item| Ok((item, seq.rest()?))),
+            Err(e) => Err(e),
+        };
+        match step {
+            Ok((item, rest)) => {
+                self.current = rest;
+                Some(Ok(item))
+            }
+            Err(e) => {
+                self.current = Seq::empty();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// Equality and hashing can't report a failing thunk, so `=` and the hashed
+// collections `force` seqs before they get here. An item that fails anyway
+// makes the seqs unequal, and ends what is hashed.
+impl PartialEq for Seq {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl PartialEq<List<EDN>> for Seq {
+    fn eq(&self, other: &List<EDN>) -> bool {
+        self.iter().eq(other.iter().cloned().map(Ok))
+    }
+}
+
+// Hashes the items the way `EDN::List` does, so a seq and a list with the
+// same items hash alike.
+impl Hash for Seq {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for item in self.iter().map_while(Result::ok) {
+            item.hash(state);
+        }
+    }
+}
+
+// Unlike Display, Debug does not force a lazy head, so an infinite seq can
+// still be debug-printed before it is walked.
+impl fmt::Debug for Seq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_realized() {
+            f.debug_list().entries(self.iter()).finish()
+        } else {
+            write!(f, "<lazy-seq>")
+        }
+    }
+}
+
+// Unlinks cells one at a time so a long realized chain is dropped without
+// recursing once per cell. Cells another seq still shares are left alone.
+impl Drop for Seq {
+    fn drop(&mut self) {
+        let mut cursor = mem::replace(&mut self.0, Cursor::Empty);
+        loop {
+            let mut rest = match cursor {
+                Cursor::Cons(cell) => match Arc::try_unwrap(cell) {
+                    Ok((_, rest)) => rest,
+                    Err(_) => break,
+                },
+                Cursor::Chunk(chunk, _) => match Arc::try_unwrap(chunk) {
+                    Ok(chunk) => chunk.rest,
+                    Err(_) => break,
+                },
+                Cursor::Lazy(lazy) => match Arc::try_unwrap(lazy.0).map(|l| l.state.into_inner()) {
+                    Ok(Ok(LazyState::Done(Ok(rest)))) => rest,
+                    _ => break,
+                },
+                _ => break,
+            };
+            cursor = mem::replace(&mut rest.0, Cursor::Empty);
+        }
+    }
+}
+
+// Whoever prints a value `force`s it first and reports the error, so a
+// failing thunk only shows up here through Display used for diagnostics.
+// It is written in place of the item rather than failing the formatter.
+impl fmt::Display for Seq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match item {
+                Ok(item) => write!(f, "{}", item)?,
+                Err(e) => write!(f, "#<error {}>", e)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// A function the seq combinators apply, such as a yinyang fn.
+pub type SeqFn = Arc<dyn Fn(&[EDN]) -> Result<EDN, EvalError> + Send + Sync>;
+
+fn is_truthy(value: &EDN) -> bool {
+    !matches!(value, EDN::Nil | EDN::Bool(false))
+}
+
+fn add_numbers(a: &EDN, b: &EDN) -> Result<EDN, String> {
+    let decimal = |n: &EDN| match n {
+        EDN::Integer(i) => Ok(BigDecimal::from(i.clone())),
+        EDN::Float(f) => Ok(f.clone()),
+        _ => Err(format!("Expected a number, got {}", n)),
+    };
+    match (a, b) {
+        (EDN::Integer(x), EDN::Integer(y)) => Ok(EDN::Integer(x + y)),
+        _ => Ok(EDN::Float(decimal(a)? + decimal(b)?)),
+    }
+}
+
+/// Numbers from `start` by `step` up to but not including `end`, or forever
+/// without an `end`. Produced a chunk at a time.
+pub fn range(start: EDN, end: Option<EDN>, step: EDN) -> Result<Seq, EvalError> {
+    for n in [&start, &step].into_iter().chain(end.as_ref()) {
+        add_numbers(n, n)?;
+    }
+    Ok(range_from(start, end, step))
+}
+
+fn range_from(start: EDN, end: Option<EDN>, step: EDN) -> Seq {
+    Seq::lazy(move || {
+        let direction = step.compare(&EDN::from(0i64))?;
+        let mut items = Vec::with_capacity(CHUNK_SIZE);
+        let mut x = start;
+        while items.len() < CHUNK_SIZE {
+            if let Some(end) = &end {
+                let more = match direction {
+                    Ordering::Greater => x.compare(end)? == Ordering::Less,
+                    Ordering::Less => x.compare(end)? == Ordering::Greater,
+                    // A zero step repeats `start` forever, unless it is `end`.
+                    Ordering::Equal => x.compare(end)? != Ordering::Equal,
+                };
+                if !more {
+                    return Ok(Seq::chunked(items, Seq::empty()));
+                }
+            }
+            let next = add_numbers(&x, &step)?;
+            items.push(mem::replace(&mut x, next));
+        }
+        Ok(Seq::chunked(items, range_from(x, end, step)))
+    })
+}
+
+/// `x`, `f(x)`, `f(f(x))` and so on.
+pub fn iterate(f: SeqFn, x: EDN) -> Seq {
+    let current = x.clone();
+    Seq::lazy(move || Ok(iterate(f.clone(), f(&[current])?))).cons(x)
+}
+
+/// `x` repeated `n` times, or forever.
+pub fn repeat(x: EDN, n: Option<usize>) -> Seq {
+    Seq::lazy(move || {
+        let len = n.map_or(CHUNK_SIZE, |n| n.min(CHUNK_SIZE));
+        let rest = match n {
+            Some(n) if n == len => Seq::empty(),
+            _ => repeat(x.clone(), n.map(|n| n - len)),
+        };
+        Ok(Seq::chunked(vec![x; len], rest))
+    })
+}
+
+/// The items of `first` followed by those of `second`, lazily.
+pub fn concat(first: Seq, second: Seq) -> Seq {
+    Seq::lazy(move || match first.chunk()? {
+        Some((items, rest)) => Ok(Seq::chunked(items, concat(rest, second))),
+        None => Ok(second),
+    })
+}
+
+/// The items of `coll` over and over; empty if `coll` is.
+pub fn cycle(coll: Seq) -> Seq {
+    Seq::lazy(move || match coll.seq()? {
+        Some(_) => Ok(concat(coll.clone(), cycle(coll))),
+        None => Ok(Seq::empty()),
+    })
+}
+
+/// `f` applied to the first items of every coll, then the second items, and
+/// so on until one runs out. A single coll is mapped a chunk at a time.
+pub fn map(f: SeqFn, colls: Vec<Seq>) -> Seq {
+    Seq::lazy(move || {
+        if let [coll] = &colls[..] {
+            return match coll.chunk()? {
+                Some((items, rest)) => {
+                    let mapped = items
+                        .iter()
+                        .map(|item| f(std::slice::from_ref(item)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Seq::chunked(mapped, map(f, vec![rest])))
+                }
+                None => Ok(Seq::empty()),
+            };
+        }
+        let mut firsts = Vec::with_capacity(colls.len());
+        let mut rests = Vec::with_capacity(colls.len());
+        for coll in &colls {
+            match coll.seq()? {
+                Some(seq) => {
+                    firsts.push(seq.first()?);
+                    rests.push(seq.rest()?);
+                }
+                None => return Ok(Seq::empty()),
+            }
+        }
+        let item = f(&firsts)?;
+        Ok(map(f, rests).cons(item))
+    })
+}
+
+/// The items of `coll` for which `pred` is truthy. Runs of rejected items
+/// are skipped in a loop, so a long gap between matches is stack safe.
+pub fn filter(pred: SeqFn, coll: Seq) -> Seq {
+    Seq::lazy(move || {
+        let mut current = coll;
+        while let Some((items, rest)) = current.chunk()? {
+            let mut kept = Vec::new();
+            for item in items {
+                if is_truthy(&pred(std::slice::from_ref(&item))?) {
+                    kept.push(item);
+                }
+            }
+            if !kept.is_empty() {
+                return Ok(Seq::chunked(kept, filter(pred, rest)));
+            }
+            current = rest;
+        }
+        Ok(Seq::empty())
+    })
+}
+
+/// The first `n` items of `coll`.
+pub fn take(n: usize, coll: Seq) -> Seq {
+    Seq::lazy(move || {
+        if n == 0 {
+            return Ok(Seq::empty());
+        }
+        match coll.seq()? {
+            Some(seq) => Ok(take(n - 1, seq.rest()?).cons(seq.first()?)),
+            None => Ok(Seq::empty()),
+        }
+    })
+}
+
+/// `coll` without its first `n` items.
+pub fn drop(n: usize, coll: Seq) -> Seq {
+    Seq::lazy(move || {
+        let mut current = coll;
+        for _ in 0..n {
+            match current.seq()? {
+                Some(seq) => current = seq.rest()?,
+                None => break,
+            }
+        }
+        Ok(current)
+    })
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+// Evaluation trace mode. The evaluator opens a `call` span for every function
+// call and records its result as an event; `CallTree` renders those as an
+// indented tree:
+//
+//   (f 1)
+//     (+ 1 1)
+//     => 2
+//   => 2
+
+/// True when `YINYANG_TRACE` is set to anything but "" or "0".
+pub fn enabled_by_env() -> bool {
+    std::env::var("YINYANG_TRACE").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Installs a `CallTree` on stderr as the global subscriber.
+pub fn init() {
+    let subscriber = tracing_subscriber::registry().with(CallTree::new(io::stderr));
+    // Another subscriber may already be installed; keep it.
+    let _ = tracing::subscriber::set_global_default(subscriber);
+}
+
+/// A layer that writes function calls and their results as an indented tree.
+pub struct CallTree {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl CallTree {
+    pub fn new<W, F>(make_writer: F) -> Self
+    where
+        W: Write + Send + 'static,
+        F: FnOnce() -> W,
+    {
+        CallTree {
+            out: Mutex::new(Box::new(make_writer())),
+        }
+    }
+
+    /// A tree written into a shared buffer, for reading back in tests.
+    pub fn buffered() -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let tree = CallTree::new(|| SharedBuffer(buffer.clone()));
+        (tree, buffer)
+    }
+
+    fn line(&self, depth: usize, text: fmt::Arguments) {
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}{}", "  ".repeat(depth), text);
+    }
+}
+
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Collects the fields the evaluator records on calls and results.
+#[derive(Default)]
+struct Fields {
+    name: String,
+    args: String,
+    result: Option<String>,
+    error: Option<String>,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let text = format!("{:?}", value);
+        match field.name() {
+            "name" => self.name = text,
+            "args" => self.args = text,
+            "result" => self.result = Some(text),
+            "error" => self.error = Some(text),
+            _ => {}
+        }
+    }
+}
+
+impl<S> Layer<S> for CallTree
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "call" {
+            return;
+        }
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        let depth = ctx.span(id).map_or(0, |span| span.scope().count() - 1);
+        if fields.args.is_empty() {
+            self.line(depth, format_args!("({})", fields.name));
+        } else {
+            self.line(depth, format_args!("({} {})", fields.name, fields.args));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let depth = ctx
+            .event_scope(event)
+            .map_or(0, |scope| scope.count().saturating_sub(1));
+        if let Some(result) = fields.result {
+            self.line(depth, format_args!("=> {}", result));
+        } else if let Some(error) = fields.error {
+            self.line(depth, format_args!("!! {}", error));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::clojure::{Environment, EvalError, Value};
+use crate::edn::EDN;
+use crate::namespace::Namespace;
+use std::cell::RefCell;
+use std::fmt;
+
+// Vars. A var is a name interned in a namespace, and its root value is the
+// namespace's mapping for that name, so everything that refers to the var
+// sees it redefined. Vars defined `^:dynamic` can also be rebound with
+// `binding`, which pushes a frame of values onto a stack private to the
+// current thread; the innermost binding of a var hides its root.
+
+#[derive(Clone)]
+pub struct Var {
+    ns: Namespace,
+    name: String,
+}
+
+thread_local! {
+    static FRAMES: RefCell<Vec<Vec<(Var, Value)>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Var {
+    /// The var `name` refers to as seen from `ns`, if any.
+    pub fn resolve(globals: &Environment, ns: &Namespace, name: &str) -> Option<Var> {
+        ns.resolve(globals, name).map(|(ns, name)| Var { ns, name })
+    }
+
+    /// The var a `#'ns/name` value stands for.
+    pub fn of(globals: &Environment, value: &Value) -> Result<Var, String> {
+        match value {
+            Value::Var { ns, name, .. } => Namespace::installed(globals)
+                .find(ns)
+                .map(|ns| Var {
+                    ns,
+                    name: name.clone(),
+                })
+                .ok_or_else(|| format!("No namespace: {}", ns)),
+            other => Err(format!("Expected a var, got {}", other)),
+        }
+    }
+
+    pub fn root(&self, globals: &Environment) -> Option<Value> {
+        self.ns.mapping(globals, &self.name)
+    }
+
+    pub fn set_root(&self, globals: &Environment, value: Value) {
+        self.ns.intern(globals, self.name.clone(), value);
+    }
+
+    /// Sets the root to `value` if it is still `expected`, the very value
+    /// rather than an equal one where values have an identity.
+    pub fn compare_and_set_root(
+        &self,
+        globals: &Environment,
+        expected: &Option<Value>,
+        value: Value,
+    ) -> bool {
+        self.ns.intern_if(globals, &self.name, value, |current| {
+            match (current, expected) {
+                (Some(current), Some(expected)) => same_value(current, expected),
+                (None, None) => true,
+                _ => false,
+            }
+        })
+    }
+
+    /// Leaves the var interned but without a root value.
+    pub fn unbind_root(&self, globals: &Environment) {
+        self.ns.unbind(globals, &self.name);
+    }
+
+    /// The innermost binding of the var on this thread.
+    pub fn thread_binding(&self) -> Option<Value> {
+        FRAMES.with(|frames| {
+            frames
+                .borrow()
+                .iter()
+                .rev()
+                .flat_map(|frame| frame.iter().rev())
+                .find(|(var, _)| var.same(self))
+                .map(|(_, value)| value.clone())
+        })
+    }
+
+    /// The thread binding of the var if it has one, else its root.
+    pub fn get(&self, globals: &Environment) -> Option<Value> {
+        self.thread_binding().or_else(|| self.root(globals))
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.ns.is_dynamic(&self.name)
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Var {
+            ns: self.ns.name().to_string(),
+            name: self.name.clone(),
+        }
+    }
+
+    fn same(&self, other: &Var) -> bool {
+        self.name == other.name && self.ns.ptr_eq(&other.ns)
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.ns.name(), self.name)
+    }
+}
+
+/// Whether any var is rebound on this thread, so that resolving a name has
+/// to look past the namespaces.
+pub fn has_thread_bindings() -> bool {
+    FRAMES.with(|frames| !frames.borrow().is_empty())
+}
+
+/// Rebinds each var to its value on this thread until the returned guard is
+/// dropped.
+pub fn push_thread_bindings(frame: Vec<(Var, Value)>) -> ThreadBindings {
+    FRAMES.with(|frames| frames.borrow_mut().push(frame));
+    ThreadBindings(())
+}
+
+pub struct ThreadBindings(());
+
+impl Drop for ThreadBindings {
+    fn drop(&mut self) {
+        FRAMES.with(|frames| frames.borrow_mut().pop());
+    }
+}
+
+/// `(var-get v)`
+pub fn var_get(globals: &Environment, args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [v] => {
+            let var = Var::of(globals, v)?;
+            var.get(globals)
+                .ok_or_else(|| format!("Var {} is unbound", var).into())
+        }
+        _ => Err("var-get requires exactly 1 argument".into()),
+    }
+}
+
+/// `(deref ref)`; vars are the only references so far.
+pub fn deref(globals: &Environment, args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [v @ Value::Var { .. }] => var_get(globals, vec![v.clone()]),
+        [other] => Err(format!("Can't deref {}", other).into()),
+        _ => Err("deref requires exactly 1 argument".into()),
+    }
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Function(f), Value::Function(g)) | (Value::Macro(f), Value::Macro(g)) => {
+            f.ptr_eq(g)
+        }
+        _ => a == b,
+    }
+}
+
+/// `(alter-var-root v f & args)` sets the root of `v` to `(apply f root args)`
+/// and returns it. Thread bindings of the var are left alone. `f` runs
+/// without a lock held and is retried if another thread changed the root
+/// meanwhile, so concurrent updates are not lost.
+pub fn alter_var_root(globals: &Environment, args: Vec<Value>) -> Result<Value, EvalError> {
+    match &args[..] {
+        [v, Value::Function(f), rest @ ..] => {
+            let var = Var::of(globals, v)?;
+            loop {
+                let old = var.root(globals);
+                let mut f_args = vec![old.clone().unwrap_or(Value::EDN(EDN::Nil))];
+                f_args.extend(rest.iter().cloned());
+                let root = f.call(f_args)?;
+                if var.compare_and_set_root(globals, &old, root.clone()) {
+                    return Ok(root);
+                }
+            }
+        }
+        [_, other, ..] => Err(format!("alter-var-root requires a function, got {}", other).into()),
+        _ => Err("alter-var-root requires a var and a function".into()),
+    }
+}
+
+/// `(bound? & vars)` is true when every var has a root or a thread binding.
+pub fn is_bound(globals: &Environment, args: Vec<Value>) -> Result<Value, EvalError> {
+    for v in &args {
+        if Var::of(globals, v)?.get(globals).is_none() {
+            return Ok(Value::EDN(EDN::Bool(false)));
+        }
+    }
+    Ok(Value::EDN(EDN::Bool(true)))
+}
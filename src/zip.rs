@@ -0,0 +1,559 @@
+use crate::edn::EDN;
+use crate::immutant::list::List;
+use crate::immutant::map::PersistentHashMap;
+use crate::immutant::vector::PersistentVector;
+use std::sync::Arc;
+
+// A clojure.zip style zipper over EDN forms. Which nodes are branches
+// depends on the zipper's `Kind`; for `Kind::Edn`, lists, vectors, maps and
+// sets all are, and the children of a map are its entries as [key value]
+// vectors.
+
+/// The branch rule of a zipper, as chosen by `zip/edn-zip`, `zip/vector-zip`
+/// or `zip/seq-zip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Every collection is a branch.
+    Edn,
+    /// Only vectors are branches.
+    Vector,
+    /// Only lists are branches.
+    Seq,
+}
+
+impl Kind {
+    pub fn is_branch(self, node: &EDN) -> bool {
+        match self {
+            Kind::Edn => is_branch(node),
+            Kind::Vector => matches!(node, EDN::Vector(_) | EDN::RrbVector(_)),
+            Kind::Seq => matches!(node, EDN::List(_)),
+        }
+    }
+
+    pub fn children(self, node: &EDN) -> Option<Vec<EDN>> {
+        if self.is_branch(node) {
+            children(node)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Edn => ":edn",
+            Kind::Vector => ":vector",
+            Kind::Seq => ":seq",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Kind> {
+        [Kind::Edn, Kind::Vector, Kind::Seq]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Path {
+    left: Vec<EDN>,
+    right: Vec<EDN>,
+    parent: EDN,
+    parent_path: Option<Arc<Path>>,
+    changed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loc {
+    node: EDN,
+    path: Option<Arc<Path>>,
+    end: bool,
+    kind: Kind,
+}
+
+pub fn is_branch(node: &EDN) -> bool {
+    matches!(
+        node,
+        EDN::List(_) | EDN::Vector(_) | EDN::RrbVector(_) | EDN::Map(_) | EDN::Set(_)
+    )
+}
+
+pub fn children(node: &EDN) -> Option<Vec<EDN>> {
+    match node {
+        EDN::List(l) => Some(l.to_vec()),
+        EDN::Vector(v) => Some(v.to_vec()),
+        EDN::RrbVector(v) => Some(v.to_vec()),
+        EDN::Map(m) => Some(
+            m.iter()
+                .map(|(k, v)| EDN::Vector(vec![k.clone(), v.clone()].into()))
+                .collect(),
+        ),
+        EDN::Set(s) => Some(s.iter().cloned().collect()),
+        _ => None,
+    }
+}
+
+// Rebuilds a branch of the same kind as `node` from `children`. Edits check
+// map entries with `check_child` and refuse to change the size of an entry
+// (see `in_map_entry`), so every child of a map is a [key value] vector here.
+fn make_node(node: &EDN, children: Vec<EDN>) -> EDN {
+    match node {
+        EDN::List(_) => EDN::List(Box::new(List::from_vec(children))),
+        EDN::Vector(_) => EDN::Vector(children.into()),
+        EDN::RrbVector(_) => EDN::RrbVector(children.into()),
+        EDN::Map(_) => EDN::Map(
+            children
+                .iter()
+                .filter_map(|entry| match entry.as_vector()?.to_vec()[..] {
+                    [ref key, ref value] => Some((key.clone(), value.clone())),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        EDN::Set(_) => EDN::Set(children.into_iter().collect()),
+        _ => node.clone(),
+    }
+}
+
+fn check_child(parent: &EDN, child: &EDN) -> Result<(), String> {
+    match (parent, child) {
+        (EDN::Map(_), EDN::Vector(pair)) if pair.len() == 2 => Ok(()),
+        (EDN::Map(_), _) => Err(format!(
+            "Map entries must be [key value] vectors, got {}",
+            child
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Whether the location is inside a [key value] entry of a map, whose size
+// must not change.
+fn in_map_entry(path: &Path) -> bool {
+    matches!(
+        path.parent_path.as_deref(),
+        Some(Path {
+            parent: EDN::Map(_),
+            ..
+        })
+    )
+}
+
+fn check_entry_size(path: &Path) -> Result<(), String> {
+    if in_map_entry(path) {
+        Err("Map entries must stay [key value] vectors".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+impl Loc {
+    /// Creates a zipper positioned at `root` in which every collection is a
+    /// branch.
+    pub fn new(root: EDN) -> Self {
+        Loc::with_kind(root, Kind::Edn)
+    }
+
+    pub fn with_kind(root: EDN, kind: Kind) -> Self {
+        Loc {
+            node: root,
+            path: None,
+            end: false,
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn node(&self) -> &EDN {
+        &self.node
+    }
+
+    pub fn is_branch(&self) -> bool {
+        self.kind.is_branch(&self.node)
+    }
+
+    pub fn children(&self) -> Option<Vec<EDN>> {
+        self.kind.children(&self.node)
+    }
+
+    pub fn is_end(&self) -> bool {
+        self.end
+    }
+
+    pub fn lefts(&self) -> &[EDN] {
+        self.path.as_ref().map_or(&[], |p| &p.left)
+    }
+
+    pub fn rights(&self) -> &[EDN] {
+        self.path.as_ref().map_or(&[], |p| &p.right)
+    }
+
+    pub fn down(&self) -> Option<Loc> {
+        let mut children = self.children()?;
+        if children.is_empty() {
+            return None;
+        }
+        let right = children.split_off(1);
+        Some(Loc {
+            node: children.pop().unwrap(),
+            path: Some(Arc::new(Path {
+                left: Vec::new(),
+                right,
+                parent: self.node.clone(),
+                parent_path: self.path.clone(),
+                changed: false,
+            })),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    pub fn up(&self) -> Option<Loc> {
+        let path = self.path.as_ref()?;
+        if !path.changed {
+            return Some(Loc {
+                node: path.parent.clone(),
+                path: path.parent_path.clone(),
+                end: false,
+                kind: self.kind,
+            });
+        }
+
+        let mut children = path.left.clone();
+        children.push(self.node.clone());
+        children.extend(path.right.iter().cloned());
+        Some(Loc {
+            node: make_node(&path.parent, children),
+            path: path.parent_path.as_ref().map(|pp| {
+                Arc::new(Path {
+                    changed: true,
+                    ..(**pp).clone()
+                })
+            }),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    /// Zips all the way up and returns the root node with every edit applied.
+    pub fn root(&self) -> EDN {
+        if self.end {
+            return self.node.clone();
+        }
+        let mut loc = self.clone();
+        while let Some(parent) = loc.up() {
+            loc = parent;
+        }
+        loc.node
+    }
+
+    pub fn right(&self) -> Option<Loc> {
+        let path = self.path.as_ref()?;
+        let (next, rest) = path.right.split_first()?;
+        let mut left = path.left.clone();
+        left.push(self.node.clone());
+        Some(Loc {
+            node: next.clone(),
+            path: Some(Arc::new(Path {
+                left,
+                right: rest.to_vec(),
+                ..(**path).clone()
+            })),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    pub fn left(&self) -> Option<Loc> {
+        let path = self.path.as_ref()?;
+        let (prev, rest) = path.left.split_last()?;
+        let mut right = vec![self.node.clone()];
+        right.extend(path.right.iter().cloned());
+        Some(Loc {
+            node: prev.clone(),
+            path: Some(Arc::new(Path {
+                left: rest.to_vec(),
+                right,
+                ..(**path).clone()
+            })),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    pub fn rightmost(&self) -> Loc {
+        let mut loc = self.clone();
+        while let Some(right) = loc.right() {
+            loc = right;
+        }
+        loc
+    }
+
+    pub fn leftmost(&self) -> Loc {
+        let mut loc = self.clone();
+        while let Some(left) = loc.left() {
+            loc = left;
+        }
+        loc
+    }
+
+    /// Moves to the next location in a depth-first walk. Past the last node
+    /// this returns an end location whose node is the root; see `is_end`.
+    pub fn next(&self) -> Loc {
+        if self.end {
+            return self.clone();
+        }
+        if let Some(down) = self.down() {
+            return down;
+        }
+        if let Some(right) = self.right() {
+            return right;
+        }
+
+        let mut loc = self.clone();
+        while let Some(parent) = loc.up() {
+            if let Some(right) = parent.right() {
+                return right;
+            }
+            loc = parent;
+        }
+        Loc {
+            node: loc.node,
+            path: None,
+            end: true,
+            kind: self.kind,
+        }
+    }
+
+    /// Moves to the previous location in a depth-first walk, or `None` at
+    /// the root.
+    pub fn prev(&self) -> Option<Loc> {
+        match self.left() {
+            Some(left) => {
+                let mut loc = left;
+                while let Some(child) = loc.down() {
+                    loc = child.rightmost();
+                }
+                Some(loc)
+            }
+            None => self.up(),
+        }
+    }
+
+    pub fn replace(&self, node: EDN) -> Result<Loc, String> {
+        let path = match &self.path {
+            Some(path) => {
+                check_child(&path.parent, &node)?;
+                Some(Arc::new(Path {
+                    changed: true,
+                    ..(**path).clone()
+                }))
+            }
+            None => None,
+        };
+        Ok(Loc {
+            node,
+            path,
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    pub fn edit<F>(&self, f: F) -> Result<Loc, String>
+    where
+        F: FnOnce(&EDN) -> Result<EDN, String>,
+    {
+        self.replace(f(&self.node)?)
+    }
+
+    pub fn insert_left(&self, item: EDN) -> Result<Loc, String> {
+        let path = self.path.as_ref().ok_or("Insert at top")?;
+        check_child(&path.parent, &item)?;
+        check_entry_size(path)?;
+        let mut left = path.left.clone();
+        left.push(item);
+        Ok(Loc {
+            node: self.node.clone(),
+            path: Some(Arc::new(Path {
+                left,
+                changed: true,
+                ..(**path).clone()
+            })),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    pub fn insert_right(&self, item: EDN) -> Result<Loc, String> {
+        let path = self.path.as_ref().ok_or("Insert at top")?;
+        check_child(&path.parent, &item)?;
+        check_entry_size(path)?;
+        let mut right = vec![item];
+        right.extend(path.right.iter().cloned());
+        Ok(Loc {
+            node: self.node.clone(),
+            path: Some(Arc::new(Path {
+                right,
+                changed: true,
+                ..(**path).clone()
+            })),
+            end: false,
+            kind: self.kind,
+        })
+    }
+
+    /// Inserts `item` as the leftmost child of this node, without moving.
+    pub fn insert_child(&self, item: EDN) -> Result<Loc, String> {
+        let mut children = self
+            .children()
+            .ok_or_else(|| format!("Cannot insert a child into {}", self.node))?;
+        check_child(&self.node, &item)?;
+        children.insert(0, item);
+        self.replace(make_node(&self.node, children))
+    }
+
+    /// Inserts `item` as the rightmost child of this node, without moving.
+    pub fn append_child(&self, item: EDN) -> Result<Loc, String> {
+        let mut children = self
+            .children()
+            .ok_or_else(|| format!("Cannot append a child to {}", self.node))?;
+        check_child(&self.node, &item)?;
+        children.push(item);
+        self.replace(make_node(&self.node, children))
+    }
+
+    /// Removes this node and moves to the location that preceded it in a
+    /// depth-first walk.
+    pub fn remove(&self) -> Result<Loc, String> {
+        let path = self.path.as_ref().ok_or("Remove at top")?;
+        check_entry_size(path)?;
+        match path.left.split_last() {
+            Some((prev, rest)) => {
+                let mut loc = Loc {
+                    node: prev.clone(),
+                    path: Some(Arc::new(Path {
+                        left: rest.to_vec(),
+                        changed: true,
+                        ..(**path).clone()
+                    })),
+                    end: false,
+                    kind: self.kind,
+                };
+                while let Some(child) = loc.down() {
+                    loc = child.rightmost();
+                }
+                Ok(loc)
+            }
+            None => Ok(Loc {
+                node: make_node(&path.parent, path.right.clone()),
+                path: path.parent_path.as_ref().map(|pp| {
+                    Arc::new(Path {
+                        changed: true,
+                        ..(**pp).clone()
+                    })
+                }),
+                end: false,
+                kind: self.kind,
+            }),
+        }
+    }
+
+    /// Encodes the location as EDN the way clojure.zip does: a vector of the
+    /// node and its path map (`nil` at the top, `:end` after a full walk).
+    /// The zipper's kind goes in the vector's metadata, as `:zip/kind`.
+    pub fn to_edn(&self) -> EDN {
+        let path = if self.end {
+            EDN::keyword("end")
+        } else {
+            self.path.as_deref().map_or(EDN::Nil, path_to_edn)
+        };
+        let meta = PersistentHashMap::new()
+            .assoc(EDN::keyword("zip/kind"), EDN::keyword(self.kind.name()));
+        EDN::Vector(PersistentVector::from(vec![self.node.clone(), path]).with_meta(Some(meta)))
+    }
+
+    pub fn from_edn(edn: &EDN) -> Result<Loc, String> {
+        let invalid = || format!("Not a zipper location: {}", edn);
+        let kind = match &edn.meta()[&EDN::keyword("zip/kind")] {
+            EDN::Nil => Kind::Edn,
+            EDN::Keyword(name) => Kind::from_name(name).ok_or_else(invalid)?,
+            _ => return Err(invalid()),
+        };
+        match edn.as_vector().map(PersistentVector::to_vec).as_deref() {
+            Some([node, EDN::Nil]) => Ok(Loc::with_kind(node.clone(), kind)),
+            Some([node, EDN::Keyword(k)]) if k == ":end" => Ok(Loc {
+                node: node.clone(),
+                path: None,
+                end: true,
+                kind,
+            }),
+            Some([node, path]) => {
+                let path = path_from_edn(path).ok_or_else(invalid)?;
+                check_path(&path, node).map_err(|e| format!("{}: {}", invalid(), e))?;
+                Ok(Loc {
+                    node: node.clone(),
+                    path: Some(Arc::new(path)),
+                    end: false,
+                    kind,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// Checks that a decoded path could have been built by the zipper's own
+// edits, so rebuilding its parents can't go wrong.
+fn check_path(path: &Path, node: &EDN) -> Result<(), String> {
+    let siblings = path
+        .left
+        .iter()
+        .chain(std::iter::once(node))
+        .chain(&path.right);
+    for child in siblings {
+        check_child(&path.parent, child)?;
+    }
+    if in_map_entry(path) && path.left.len() + path.right.len() != 1 {
+        return Err("Map entries must stay [key value] vectors".to_string());
+    }
+    match path.parent_path.as_deref() {
+        Some(parent_path) => check_path(parent_path, &path.parent),
+        None => Ok(()),
+    }
+}
+
+fn path_to_edn(path: &Path) -> EDN {
+    let mut pnodes = Vec::new();
+    let mut current = Some(path);
+    while let Some(p) = current {
+        pnodes.push(p.parent.clone());
+        current = p.parent_path.as_deref();
+    }
+    pnodes.reverse();
+
+    let map = PersistentHashMap::new()
+        .assoc(EDN::keyword("l"), EDN::Vector(path.left.clone().into()))
+        .assoc(EDN::keyword("r"), EDN::Vector(path.right.clone().into()))
+        .assoc(EDN::keyword("pnodes"), EDN::Vector(pnodes.into()))
+        .assoc(
+            EDN::keyword("ppath"),
+            path.parent_path.as_deref().map_or(EDN::Nil, path_to_edn),
+        )
+        .assoc(EDN::keyword("changed?"), EDN::Bool(path.changed));
+    EDN::Map(map)
+}
+
+fn path_from_edn(edn: &EDN) -> Option<Path> {
+    let parent_path = match &edn[&EDN::keyword("ppath")] {
+        EDN::Nil => None,
+        ppath => Some(Arc::new(path_from_edn(ppath)?)),
+    };
+    Some(Path {
+        left: edn[&EDN::keyword("l")].as_vector()?.to_vec(),
+        right: edn[&EDN::keyword("r")].as_vector()?.to_vec(),
+        parent: edn[&EDN::keyword("pnodes")].as_vector()?.peek()?.clone(),
+        parent_path,
+        changed: edn[&EDN::keyword("changed?")].as_bool().unwrap_or(false),
+    })
+}
@@ -0,0 +1,30 @@
+// Helpers shared by the integration tests. Each test crate uses its own
+// subset of them.
+#![allow(dead_code)]
+
+use yinyang::clojure::{eval, read_string, Environment, EvalError, Value};
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+
+pub fn edn(s: &str) -> EDN {
+    read_string(s).unwrap()
+}
+
+/// Evaluates `s` in a fresh environment.
+pub fn run(s: &str) -> Result<Value, EvalError> {
+    let env = create_env();
+    eval(read_string(s).unwrap(), &env)
+}
+
+pub fn run_in(env: &Environment, s: &str) -> Result<Value, EvalError> {
+    eval(read_string(s).unwrap(), env)
+}
+
+/// Evaluates `s` in a fresh environment and prints the result.
+pub fn run_str(s: &str) -> String {
+    run(s).unwrap().to_string()
+}
+
+pub fn run_str_in(env: &Environment, s: &str) -> String {
+    run_in(env, s).unwrap().to_string()
+}
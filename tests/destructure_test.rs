@@ -0,0 +1,155 @@
+use yinyang::clojure::EvalError;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{run, run_in, run_str};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(s: &str) -> String {
+        match run(s).map_err(|e| e.cause().clone()) {
+            Err(EvalError::Syntax(msg)) => msg,
+            other => panic!("expected a syntax error from {}, got {:?}", s, other),
+        }
+    }
+
+    #[test]
+    fn test_sequential() {
+        assert_eq!(
+            run_str("(let [[a b] [1 2 3]] (conj (conj [] a) b))"),
+            "[1 2]"
+        );
+        assert_eq!(
+            run_str("(let [[a b c] (quote (1 2))] (conj (conj (conj [] a) b) c))"),
+            "[1 2 nil]"
+        );
+        assert_eq!(
+            run_str("(let [[a & more :as all] [1 2 3]] (conj (conj (conj [] a) more) all))"),
+            "[1 (2 3) [1 2 3]]"
+        );
+        assert_eq!(run_str("(let [[a & more] [1]] more)"), "nil");
+        assert_eq!(
+            run_str("(let [[[a b] c] [[1 2] 3]] (conj (conj (conj [] a) b) c))"),
+            "[1 2 3]"
+        );
+        assert_eq!(run_str("(let [[a b] nil] b)"), "nil");
+        assert_eq!(run_str("(let [[_ b] (range)] b)"), "1");
+    }
+
+    #[test]
+    fn test_associative() {
+        assert_eq!(
+            run_str(
+                "(let [{a :a [b] :b :as m} {:a 1 :b [2]}] (conj (conj (conj [] a) b) (count m)))"
+            ),
+            "[1 2 2]"
+        );
+        assert_eq!(
+            run_str("(let [{:keys [a b] :or {b 5}} {:a 1}] (conj (conj [] a) b))"),
+            "[1 5]"
+        );
+        assert_eq!(
+            run_str("(let [{:strs [a] :syms [b]} {\"a\" 1 b 2}] (conj (conj [] a) b))"),
+            "[1 2]"
+        );
+        assert_eq!(
+            run_str("(let [{:keys [x/a :x/b] :y/keys [c]} {:x/a 1 :x/b 2 :y/c 3}] (conj (conj (conj [] a) b) c))"),
+            "[1 2 3]"
+        );
+        assert_eq!(run_str("(let [{:keys [a] :or {a 1}} nil] a)"), "1");
+        // A default is only evaluated when the key is missing
+        assert_eq!(
+            run_str("(let [{:keys [a] :or {a (undefined)}} {:a false}] a)"),
+            "false"
+        );
+    }
+
+    #[test]
+    fn test_fn_params() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(def f (fn [{:keys [x y]} [a & _]] (conj (conj (conj [] x) y) a)))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "(f {:x 1 :y 2} [3 4])").unwrap().to_string(),
+            "[1 2 3]"
+        );
+
+        // Keyword arguments through a map rest pattern
+        run_in(
+            &env,
+            "(def g (fn [a & {:keys [scale] :or {scale 10}}] (* a scale)))",
+        )
+        .unwrap();
+        assert_eq!(run_in(&env, "(g 2)").unwrap().to_string(), "20");
+        assert_eq!(run_in(&env, "(g 2 :scale 3)").unwrap().to_string(), "6");
+        assert!(run_in(&env, "(g 2 :scale)").is_err());
+    }
+
+    #[test]
+    fn test_loop_and_doseq() {
+        assert_eq!(
+            run_str("(loop [[x & xs] [1 2 3] acc 0] (if x (recur xs (+ acc x)) acc))"),
+            "6"
+        );
+
+        let env = create_env();
+        run_in(&env, "(def seen [])").unwrap();
+        run_in(
+            &env,
+            "(doseq [[k v] {:a 1} n [1 2]] (def seen (conj seen (conj (conj (conj [] k) v) n))))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "seen").unwrap().to_string(),
+            "[[:a 1 1] [:a 1 2]]"
+        );
+        assert_eq!(run_str("(doseq [x []] (undefined))"), "nil");
+        assert!(run("(doseq [x [1]] (recur x))").is_err());
+    }
+
+    #[test]
+    fn test_malformed_patterns() {
+        assert_eq!(syntax_error("(let [1 2] 3)"), "Unsupported binding form: 1");
+        assert_eq!(
+            syntax_error("(let [[a &] [1]] a)"),
+            "'&' must be followed by a binding form"
+        );
+        assert_eq!(
+            syntax_error("(let [[a & b c] [1]] a)"),
+            "Unexpected c after the rest binding"
+        );
+        assert_eq!(
+            syntax_error("(let [[a :as] [1]] a)"),
+            ":as must be followed by a symbol"
+        );
+        assert_eq!(
+            syntax_error("(let [[:as a b] [1]] a)"),
+            "Unexpected b after :as in binding vector"
+        );
+        assert_eq!(
+            syntax_error("(let [{:keys a} {}] a)"),
+            ":keys must be followed by a vector"
+        );
+        assert_eq!(
+            syntax_error("(let [{:kees [a]} {}] a)"),
+            "Unknown destructuring option: :kees"
+        );
+        assert_eq!(
+            syntax_error("(let [{:or [a 1]} {}] 1)"),
+            ":or must be followed by a map"
+        );
+        assert_eq!(
+            syntax_error("(fn [a & b & c] a)"),
+            "'&' must be followed by exactly one parameter"
+        );
+        assert!(matches!(
+            run("(let [[a] 1] a)").map_err(|e| e.cause().clone()),
+            Err(EvalError::Native(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use num_bigint::BigInt;
+use yinyang::clojure::read_string;
+use yinyang::edn::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EDN {
+        read_string("{:server {:host \"localhost\" :ports [80 443]} :debug true}").unwrap()
+    }
+
+    #[test]
+    fn test_index() {
+        let c = config();
+        assert_eq!(c[&EDN::keyword("debug")], EDN::Bool(true));
+        assert_eq!(
+            c[&EDN::keyword("server")][&EDN::keyword("host")].as_str(),
+            Some("localhost")
+        );
+        assert_eq!(
+            c[&EDN::keyword("server")][&EDN::keyword("ports")][1],
+            EDN::from(443i64)
+        );
+        assert!(c[&EDN::keyword("missing")][&EDN::keyword("deeper")].is_nil());
+        assert!(c[&EDN::keyword("server")][&EDN::keyword("ports")][5].is_nil());
+    }
+
+    #[test]
+    fn test_string_keys() {
+        let m = read_string("{\"name\" \"yinyang\"}").unwrap();
+        assert_eq!(m[&EDN::from("name")].as_str(), Some("yinyang"));
+        assert!(m[&EDN::keyword("name")].is_nil());
+    }
+
+    #[test]
+    fn test_lists_index_by_position_but_get_is_nil() {
+        let l = read_string("(:a :b)").unwrap();
+        assert_eq!(l[1], EDN::keyword("b"));
+        assert!(l[2].is_nil());
+        // As with Clojure's get, lists have no keys
+        assert_eq!(l.get(&EDN::from(0usize)), None);
+        assert!(l[&EDN::from(0usize)].is_nil());
+        assert_eq!(
+            read_string("[(:a :b)]")
+                .unwrap()
+                .get_in(&[EDN::from(0usize), EDN::from(1usize)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_in() {
+        let c = config();
+        let path = [
+            EDN::keyword("server"),
+            EDN::keyword("ports"),
+            EDN::from(0usize),
+        ];
+        assert_eq!(
+            c.get_in(&path).and_then(EDN::as_bigint),
+            Some(&BigInt::from(80))
+        );
+        assert_eq!(c.get_in(&[]), Some(&c));
+        assert_eq!(c.get_in(&[EDN::keyword("nope"), EDN::keyword("x")]), None);
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let c = config();
+        assert_eq!(c[&EDN::keyword("debug")].as_bool(), Some(true));
+        assert_eq!(c[&EDN::keyword("debug")].as_str(), None);
+        assert_eq!(
+            c[&EDN::keyword("server")][&EDN::keyword("ports")][0].as_i64(),
+            Some(80)
+        );
+        assert_eq!(
+            c[&EDN::keyword("server")][&EDN::keyword("ports")]
+                .as_vector()
+                .map(|v| v.len()),
+            Some(2)
+        );
+        assert_eq!(c.as_map().map(|m| m.len()), Some(2));
+        assert_eq!(EDN::keyword(":a").as_keyword(), Some(":a"));
+        assert_eq!(read_string("sym").unwrap().as_symbol(), Some("sym"));
+    }
+
+    #[test]
+    fn test_assoc_in_does_not_mutate() {
+        let c = config();
+        let path = [EDN::keyword("server"), EDN::keyword("host")];
+        let updated = c.assoc_in(&path, EDN::from("example.com")).unwrap();
+
+        assert_eq!(
+            updated[&EDN::keyword("server")][&EDN::keyword("host")].as_str(),
+            Some("example.com")
+        );
+        assert_eq!(
+            c[&EDN::keyword("server")][&EDN::keyword("host")].as_str(),
+            Some("localhost")
+        );
+        assert_eq!(
+            updated[&EDN::keyword("server")][&EDN::keyword("ports")],
+            c[&EDN::keyword("server")][&EDN::keyword("ports")]
+        );
+    }
+
+    #[test]
+    fn test_assoc_in_creates_maps() {
+        let path = [EDN::keyword("a"), EDN::keyword("b")];
+        let m = EDN::Nil.assoc_in(&path, EDN::from(1i64)).unwrap();
+        assert_eq!(m, read_string("{:a {:b 1}}").unwrap());
+    }
+
+    #[test]
+    fn test_assoc_vector() {
+        let v = read_string("[1 2]").unwrap();
+        assert_eq!(
+            v.assoc(EDN::from(0usize), EDN::from(9i64)).unwrap(),
+            read_string("[9 2]").unwrap()
+        );
+        assert_eq!(
+            v.assoc(EDN::from(2usize), EDN::from(3i64)).unwrap(),
+            read_string("[1 2 3]").unwrap()
+        );
+        assert!(v.assoc(EDN::from(3usize), EDN::Nil).is_err());
+        assert!(EDN::from(1i64).assoc(EDN::Nil, EDN::Nil).is_err());
+    }
+
+    #[test]
+    fn test_update_in() {
+        let c = config();
+        let path = [
+            EDN::keyword("server"),
+            EDN::keyword("ports"),
+            EDN::from(0usize),
+        ];
+        let updated = c
+            .update_in(&path, |port| {
+                EDN::Integer(port.as_bigint().unwrap() + BigInt::from(8000))
+            })
+            .unwrap();
+        assert_eq!(updated.get_in(&path).and_then(EDN::as_i64), Some(8080));
+        assert_eq!(c.get_in(&path).and_then(EDN::as_i64), Some(80));
+
+        let counted = EDN::Nil
+            .update_in(&[EDN::keyword("n")], |n| {
+                assert!(n.is_nil());
+                EDN::from(1i64)
+            })
+            .unwrap();
+        assert_eq!(counted[&EDN::keyword("n")], EDN::from(1i64));
+    }
+
+    #[test]
+    fn test_dissoc_in() {
+        let c = config();
+        let path = [EDN::keyword("server"), EDN::keyword("host")];
+        let removed = c.dissoc_in(&path).unwrap();
+
+        assert!(removed.get_in(&path).is_none());
+        assert!(removed[&EDN::keyword("server")][&EDN::keyword("ports")]
+            .as_vector()
+            .is_some());
+        assert!(c.get_in(&path).is_some());
+
+        let missing = [EDN::keyword("nope"), EDN::keyword("host")];
+        assert_eq!(c.dissoc_in(&missing).unwrap(), c);
+    }
+}
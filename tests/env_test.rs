@@ -0,0 +1,97 @@
+use yinyang::clojure::Value;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run_in};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closure_sees_later_defs() {
+        let env = create_env();
+        run_in(&env, "(def f (fn [x] (g x)))").unwrap();
+        run_in(&env, "(def g (fn [x] (conj [] x)))").unwrap();
+        assert_eq!(run_in(&env, "(f 1)"), Ok(Value::EDN(edn("[1]"))));
+
+        run_in(&env, "(def g (fn [x] (conj [:new] x)))").unwrap();
+        assert_eq!(run_in(&env, "(f 1)"), Ok(Value::EDN(edn("[:new 1]"))));
+    }
+
+    #[test]
+    fn test_recursive_fn() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(def countdown (fn [n acc] (if (< n 1) acc (countdown (- n 1) (conj acc n)))))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "(countdown 3 [])"),
+            Ok(Value::EDN(edn("[3 2 1]")))
+        );
+    }
+
+    #[test]
+    fn test_let_body_is_an_implicit_do() {
+        let env = create_env();
+        assert_eq!(
+            run_in(&env, "(let [a 1] (println a) a)"),
+            Ok(Value::EDN(edn("1")))
+        );
+        assert_eq!(
+            run_in(&env, "(let [a 1] (def seen a) (conj [] a seen))"),
+            Ok(Value::EDN(edn("[1 1]")))
+        );
+        assert_eq!(run_in(&env, "(let [a 1])"), Ok(Value::EDN(edn("nil"))));
+        assert_eq!(
+            run_in(
+                &env,
+                "(loop [n 3] (let [m (- n 1)] (def last-n n) (if (< m 0) last-n (recur m))))"
+            ),
+            Ok(Value::EDN(edn("0")))
+        );
+        assert!(run_in(&env, "(let)").is_err());
+    }
+
+    #[test]
+    fn test_lexical_scoping() {
+        let env = create_env();
+        run_in(&env, "(def x :global)").unwrap();
+        assert_eq!(
+            run_in(&env, "(let [x :outer f (fn [] x) x :inner] (f))"),
+            Ok(Value::EDN(edn(":outer")))
+        );
+        assert_eq!(
+            run_in(&env, "(let [x 1 y (conj [] x)] y)"),
+            Ok(Value::EDN(edn("[1]")))
+        );
+        assert_eq!(
+            run_in(&env, "(let [f (fn [x] x)] (f :param))"),
+            Ok(Value::EDN(edn(":param")))
+        );
+        // Neither let bindings nor parameters leak out of their scope.
+        assert_eq!(run_in(&env, "x"), Ok(Value::EDN(edn(":global"))));
+        run_in(&env, "(let [f (fn [y] y)] (f 1))").unwrap();
+        assert!(run_in(&env, "y").is_err());
+    }
+
+    #[test]
+    fn test_closures_capture_their_own_frames() {
+        let env = create_env();
+        run_in(&env, "(def adder (fn [n] (fn [x] (+ x n))))").unwrap();
+        run_in(&env, "(def add1 (adder 1))").unwrap();
+        run_in(&env, "(def add10 (adder 10))").unwrap();
+        assert_eq!(run_in(&env, "(add1 1)"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run_in(&env, "(add10 1)"), Ok(Value::EDN(edn("11"))));
+    }
+
+    #[test]
+    fn test_def_inside_fn_is_global() {
+        let env = create_env();
+        run_in(&env, "(def setter (fn [v] (def answer v)))").unwrap();
+        run_in(&env, "(setter 42)").unwrap();
+        assert_eq!(run_in(&env, "answer"), Ok(Value::EDN(edn("42"))));
+    }
+}
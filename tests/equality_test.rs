@@ -0,0 +1,90 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use yinyang::clojure::Value;
+use yinyang::core::divide;
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_of(x: &EDN) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_integer_arithmetic_stays_integral() {
+        assert_eq!(run("(+ 1 1)"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run("(- 10 3 2)"), Ok(Value::EDN(edn("5"))));
+        assert_eq!(run("(* 2 3)"), Ok(Value::EDN(edn("6"))));
+        assert_eq!(run("(- 1)"), Ok(Value::EDN(edn("-1"))));
+        // A float anywhere makes the result a float
+        assert_eq!(run("(+ 1 1.5)"), Ok(Value::EDN(edn("2.5"))));
+        assert_eq!(run("(* 2 1.0)"), Ok(Value::EDN(edn("2.0"))));
+
+        // Exact quotients stay integers
+        let quotient = |a: i64, b: i64| divide(vec![Value::EDN(a.into()), Value::EDN(b.into())]);
+        assert_eq!(quotient(6, 3), Ok(Value::EDN(edn("2"))));
+        assert_eq!(quotient(1, 2), Ok(Value::EDN(edn("0.5"))));
+        assert!(quotient(1, 0).is_err());
+        assert_eq!(
+            divide(vec![Value::EDN(4i64.into())]),
+            Ok(Value::EDN(edn("0.25")))
+        );
+
+        assert!(run("(- 1 :a)").is_err());
+        assert!(run("(+ 1 nil)").is_err());
+    }
+
+    #[test]
+    fn test_integers_and_floats_are_not_equal() {
+        let yes = Ok(Value::EDN(EDN::Bool(true)));
+        let no = Ok(Value::EDN(EDN::Bool(false)));
+        assert_eq!(run("(= (+ 1 1) 2)"), yes);
+        assert_eq!(run("(= (+ 1 1) 3)"), no);
+        assert_eq!(run("(= 1 1.0)"), no);
+        assert_eq!(run("(= (+ 1 1.0) 2.0)"), yes);
+        assert_eq!(run("(= 0 :zero)"), no);
+        assert_eq!(run("(get {2 :x} (+ 1 1))"), Ok(Value::EDN(edn(":x"))));
+        assert_eq!(run("(get {2 :x} 2.0)"), Ok(Value::EDN(EDN::Nil)));
+    }
+
+    #[test]
+    fn test_sequentials_compare_by_items() {
+        let yes = Ok(Value::EDN(EDN::Bool(true)));
+        let no = Ok(Value::EDN(EDN::Bool(false)));
+        assert_eq!(run("(= [0] (quote (0)))"), yes);
+        assert_eq!(run("(= (conj [] (- 1 1)) [0])"), yes);
+        assert_eq!(run("(= [0 1] (quote (0)))"), no);
+        assert_eq!(run("(= [0 1] (range 2))"), yes);
+        assert_eq!(run("(= (quote (0 1)) (range 2))"), yes);
+        assert_eq!(run("(= [] (quote ()))"), yes);
+        // Maps and sets are not sequential
+        assert_eq!(run("(= [] {})"), no);
+        assert_eq!(run("(= [1] #{1})"), no);
+    }
+
+    #[test]
+    fn test_equal_sequentials_hash_alike() {
+        let vector = edn("[1 [2] 3]");
+        let list = edn("(1 (2) 3)");
+        assert_eq!(vector, list);
+        assert_eq!(hash_of(&vector), hash_of(&list));
+        assert_ne!(hash_of(&edn("[1 2]")), hash_of(&edn("[2 1]")));
+
+        assert_eq!(run("(get {[0] :x} (quote (0)))"), Ok(Value::EDN(edn(":x"))));
+        assert_eq!(
+            run("(count (hash-set [1 2] (quote (1 2))))"),
+            Ok(Value::EDN(edn("1")))
+        );
+        assert_eq!(
+            run("(contains? (hash-set (quote (1))) [1])"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use yinyang::clojure::{eval, EvalError, Value};
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_special_forms_report_their_own_errors() {
+        let err = run("(if true (undefined-fn 1) 2)").unwrap_err();
+        assert_eq!(err.cause(), &EvalError::Unbound("undefined-fn".to_string()));
+        assert_eq!(err.to_string(), "Unable to resolve symbol: undefined-fn");
+
+        let err = run("(let [x] x)").unwrap_err();
+        assert!(matches!(err.cause(), EvalError::Syntax(_)));
+
+        let err = run("(def 1 2)").unwrap_err();
+        assert_eq!(
+            err.cause(),
+            &EvalError::Syntax("First argument to 'def' must be a symbol".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let env = create_env();
+        eval(edn("(def f (fn [x] x))"), &env).unwrap();
+        let err = eval(edn("(f 1 2)"), &env).unwrap_err();
+        assert_eq!(
+            err.cause(),
+            &EvalError::Arity {
+                name: "fn".to_string(),
+                expected: "1".to_string(),
+                got: 2
+            }
+        );
+
+        assert!(matches!(
+            run("(:k 1)").unwrap_err().cause(),
+            EvalError::Type(_)
+        ));
+        assert!(matches!(
+            run("(get)").unwrap_err().cause(),
+            EvalError::Native(_)
+        ));
+        assert_eq!(
+            run("(throw [:oops 1])").unwrap_err().cause(),
+            &EvalError::Thrown(Value::EDN(edn("[:oops 1]")))
+        );
+    }
+
+    #[test]
+    fn test_trace_names_forms_and_positions() {
+        let env = create_env();
+        eval(edn("(def g (fn [x] (conj x (missing x))))"), &env).unwrap();
+        let err = eval(edn("(let [v []]\n  (g v))"), &env).unwrap_err();
+
+        let frames = err.trace();
+        let names: Vec<_> = frames.iter().map(|f| f.name.as_deref()).collect();
+        assert_eq!(
+            names,
+            vec![Some("missing"), Some("conj"), Some("g"), Some("let")]
+        );
+        assert_eq!((frames[2].line, frames[2].column), (Some(2), Some(3)));
+        assert_eq!((frames[3].line, frames[3].column), (Some(1), Some(1)));
+        assert_eq!(frames[2].form, edn("(g v)"));
+
+        let trace = err.stack_trace();
+        assert!(trace.starts_with("Unable to resolve symbol: missing\n  at missing"));
+        assert!(trace.contains("at g (2:3) (g v)"));
+    }
+
+    #[test]
+    fn test_errors_pass_through_natives() {
+        let env = create_env();
+        eval(edn("(def boom (fn [& _] (throw [:boom 1])))"), &env).unwrap();
+        eval(edn("(def v 1)"), &env).unwrap();
+        let thrown = EvalError::Thrown(Value::EDN(edn("[:boom 1]")));
+        for form in [
+            "(doall (map boom [1 2]))",
+            "(first (filter boom [1]))",
+            "(sorted-map-by boom 1 :a 2 :b)",
+            "(conj (sorted-set-by boom 1) 2)",
+            "(alter-var-root (var v) boom)",
+            "(vary-meta [] boom)",
+            "(zip/edit (zip/down (zip/vector-zip [1])) boom)",
+        ] {
+            let err = eval(edn(form), &env).unwrap_err();
+            assert_eq!(err.cause(), &thrown, "{}", form);
+            let names: Vec<_> = err.trace().iter().map(|f| f.name.clone()).collect();
+            assert!(names.contains(&Some("throw".to_string())), "{}", form);
+        }
+
+        eval(edn("(def bad (fn [x] (missing x)))"), &env).unwrap();
+        let err = eval(edn("(doall (map bad [1]))"), &env).unwrap_err();
+        assert_eq!(err.cause(), &EvalError::Unbound("missing".to_string()));
+    }
+}
@@ -0,0 +1,176 @@
+use yinyang::clojure::{EvalError, Value};
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run, run_in, run_str_in};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_fn_refers_to_itself() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(def fact (fn f [n] (if (< n 1) 1 (* n (f (- n 1))))))",
+        )
+        .unwrap();
+        assert_eq!(run_str_in(&env, "(fact 5)"), "120");
+
+        // The name is local to the fn
+        assert_eq!(
+            run("(let [g (fn f [] 1)] f)").map_err(|e| e.cause().clone()),
+            Err(EvalError::Unbound("f".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_multi_arity() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(def f (fn ([] :none) ([x] (conj [] x)) ([x y] (conj (conj [] x) y))))",
+        )
+        .unwrap();
+        assert_eq!(run_str_in(&env, "(f)"), ":none");
+        assert_eq!(run_str_in(&env, "(f 1)"), "[1]");
+        assert_eq!(run_str_in(&env, "(f 1 2)"), "[1 2]");
+
+        // Arities can call each other through the fn's name
+        run_in(&env, "(def g (fn g ([x] (g x 10)) ([x y] (+ x y))))").unwrap();
+        assert_eq!(run_str_in(&env, "(g 1)"), "11");
+    }
+
+    #[test]
+    fn test_variadic() {
+        let env = create_env();
+        run_in(&env, "(def f (fn [a & more] (conj (conj [] a) more)))").unwrap();
+        assert_eq!(run_str_in(&env, "(f 1)"), "[1 nil]");
+        assert_eq!(run_str_in(&env, "(f 1 2 3)"), "[1 (2 3)]");
+
+        run_in(&env, "(def g (fn ([] 0) ([x & xs] (count xs))))").unwrap();
+        assert_eq!(run_str_in(&env, "(g)"), "0");
+        assert_eq!(run_str_in(&env, "(g 1 2 3)"), "2");
+
+        // recur passes the rest parameter as a single seq
+        assert_eq!(
+            run("(let [f (fn [n & xs] (if (< n 1) xs (recur (- n 1) (cons n xs))))] (f 2))")
+                .unwrap()
+                .to_string(),
+            "(1 2)"
+        );
+    }
+
+    #[test]
+    fn test_fns_pass_through_rest_args() {
+        let env = create_env();
+        run_in(&env, "(defn f [& fs] (count fs))").unwrap();
+        assert_eq!(run_str_in(&env, "(f + -)"), "2");
+        run_in(
+            &env,
+            "(defn call-all [x & fs] (loop [fs fs acc []] (if (empty? fs) acc (let [g (first fs)] (recur (rest fs) (conj acc (g x)))))))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_str_in(&env, "(let [n 10] (call-all 2 + (fn [y] (* y n))))"),
+            "[2 20]"
+        );
+        run_in(&env, "(defn second-fn [& [_ g & more]] (g (count more)))").unwrap();
+        assert_eq!(
+            run_str_in(&env, "(second-fn + (fn [n] (+ n 100)) 1 2)"),
+            "102"
+        );
+        assert_eq!(run_str_in(&env, "(next (rest (call-all 1)))"), "nil");
+        assert_eq!(
+            run_str_in(&env, "(let [f (fn [& xs] xs)] (first (next (f + -))))"),
+            "#<function>"
+        );
+        assert_eq!(
+            run_str_in(&env, "(let [f (fn [& xs] xs)] (f 1 :a))"),
+            "(1 :a)"
+        );
+    }
+
+    #[test]
+    fn test_rest_args_of_fns_are_seqs() {
+        let env = create_env();
+        run_in(&env, "(defn fns [& fs] fs)").unwrap();
+        run_in(&env, "(def fs (fns + -))").unwrap();
+        assert_eq!(run_str_in(&env, "(map (fn [g] (g 1 2)) fs)"), "(3 -1)");
+        assert_eq!(
+            run_str_in(&env, "(map (fn [g x] (g x 1)) fs [10 20 30])"),
+            "(11 19)"
+        );
+        assert_eq!(run_str_in(&env, "(map (fn [g n] n) fs (range))"), "(0 1)");
+        assert_eq!(run_str_in(&env, "(count fs)"), "2");
+        assert_eq!(run_str_in(&env, "(seq? fs)"), "true");
+        assert_eq!(run_str_in(&env, "(count (conj fs 1))"), "3");
+        assert_eq!(run_str_in(&env, "(first (conj fs 1))"), "1");
+        assert_eq!(run_str_in(&env, "(count (conj (quote (1 2)) +))"), "3");
+        assert_eq!(run_str_in(&env, "(count (list fs))"), "1");
+        assert_eq!(run_str_in(&env, "(count (list + -))"), "2");
+        assert_eq!(run_str_in(&env, "(count (cons + fs))"), "3");
+        assert_eq!(
+            run_str_in(&env, "(count (filter (fn [g] (= 3 (g 1 2))) fs))"),
+            "1"
+        );
+        assert_eq!(
+            run_str_in(&env, "(let [g (first (drop 1 fs))] (g 5 1))"),
+            "4"
+        );
+        assert_eq!(run_str_in(&env, "(count (take 1 fs))"), "1");
+        assert_eq!(
+            run_str_in(&env, "(let [[f g] (list + -)] (g 5 (f 1 1)))"),
+            "3"
+        );
+    }
+
+    #[test]
+    fn test_multi_expression_body() {
+        let env = create_env();
+        run_in(&env, "(def f (fn [x] (def seen x) (conj [] x)))").unwrap();
+        assert_eq!(run_str_in(&env, "(f 1)"), "[1]");
+        assert_eq!(run_str_in(&env, "seen"), "1");
+        assert_eq!(run("(let [f (fn [])] (f))"), Ok(Value::EDN(EDN::Nil)));
+    }
+
+    #[test]
+    fn test_arity_errors_name_the_function() {
+        let env = create_env();
+        run_in(&env, "(def f (fn add ([x] x) ([x y & zs] y)))").unwrap();
+        let err = run_in(&env, "(f)").unwrap_err();
+        assert_eq!(
+            err.cause(),
+            &EvalError::Arity {
+                name: "add".to_string(),
+                expected: "1 or at least 2".to_string(),
+                got: 0
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Wrong number of args (0) passed to add, expected 1 or at least 2"
+        );
+        assert_eq!(run_in(&env, "(f 1 2 3)"), Ok(Value::EDN(edn("2"))));
+    }
+
+    #[test]
+    fn test_invalid_fn_forms() {
+        let syntax = |s: &str| {
+            matches!(
+                run(s).map_err(|e| e.cause().clone()),
+                Err(EvalError::Syntax(_))
+            )
+        };
+        assert!(syntax("(fn)"));
+        assert!(syntax("(fn f)"));
+        assert!(syntax("(fn [a &] a)"));
+        assert!(syntax("(fn [a & b c] a)"));
+        assert!(syntax("(fn ([a] a) ([b] b))"));
+        assert!(syntax("(fn ([& a] a) ([& b] b))"));
+        assert!(syntax("(fn ([a b c] a) ([a & b] b))"));
+        assert!(syntax("(fn ([a] a) [b])"));
+    }
+}
@@ -0,0 +1,187 @@
+use yinyang::clojure::{EvalError, Value};
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+use yinyang::seq::{self, ISeq, Seq, Seqable};
+
+mod common;
+use common::{edn, run, run_in, run_str};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_range() {
+        assert_eq!(run_str("(range 5)"), "(0 1 2 3 4)");
+        assert_eq!(run_str("(range 2 5)"), "(2 3 4)");
+        assert_eq!(run_str("(range 10 0 -3)"), "(10 7 4 1)");
+        assert_eq!(run_str("(range 0)"), "()");
+        assert_eq!(run_str("(take 3 (range))"), "(0 1 2)");
+        assert_eq!(run_str("(take 3 (range 1 1 0))"), "()");
+        assert_eq!(run_str("(take 3 (range 1 2 0))"), "(1 1 1)");
+        assert_eq!(run("(count (range 100))"), Ok(Value::EDN(edn("100"))));
+        assert!(run("(range :a)").is_err());
+    }
+
+    #[test]
+    fn test_infinite_seqs() {
+        assert_eq!(
+            run_str("(take 4 (iterate (fn [v] (conj v 1)) []))"),
+            "([] [1] [1 1] [1 1 1])"
+        );
+        assert_eq!(run_str("(take 3 (repeat :x))"), "(:x :x :x)");
+        assert_eq!(run_str("(repeat 2 :x)"), "(:x :x)");
+        assert_eq!(run_str("(take 5 (cycle [1 2]))"), "(1 2 1 2 1)");
+        assert_eq!(run_str("(cycle [])"), "()");
+        assert_eq!(run_str("(drop 2 (take 4 (range)))"), "(2 3)");
+        assert_eq!(
+            run("(first (drop 1000000 (range)))"),
+            Ok(Value::EDN(edn("1000000")))
+        );
+    }
+
+    #[test]
+    fn test_map_and_filter() {
+        assert_eq!(
+            run_str("(map (fn [a b] (conj (conj [] a) b)) [1 2 3] (range))"),
+            "([1 0] [2 1] [3 2])"
+        );
+        assert_eq!(
+            run_str("(take 2 (filter (fn [x] (< 100 x)) (range)))"),
+            "(101 102)"
+        );
+        assert_eq!(
+            run_str("(map (fn [x] (get {1 :a 2 :b} x)) (filter (fn [x] (< 0 x)) [0 1 2]))"),
+            "(:a :b)"
+        );
+        assert_eq!(run_str("(filter (fn [x] nil) [1 2])"), "()");
+    }
+
+    #[test]
+    fn test_realization_is_deferred_and_cached() {
+        let env = create_env();
+        run_in(&env, "(def xs (map (fn [x] (conj [] x)) (range 3)))").unwrap();
+        assert_eq!(
+            run_in(&env, "(realized? xs)"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+        assert_eq!(run_in(&env, "(dorun xs)"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(
+            run_in(&env, "(realized? xs)"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run_in(&env, "(doall xs)").unwrap().to_string(),
+            "([0] [1] [2])"
+        );
+
+        // Errors surface when the seq is walked, not when it is built.
+        run_in(&env, "(def bad (map (fn [x] (undefined x)) [1]))").unwrap();
+        assert!(run_in(&env, "(doall bad)").is_err());
+        assert!(run_in(&env, "(first bad)").is_err());
+    }
+
+    #[test]
+    fn test_lazy_seq_form() {
+        assert_eq!(run_str("(lazy-seq [1 2])"), "(1 2)");
+        assert_eq!(run_str("(lazy-seq nil)"), "()");
+        assert_eq!(run_str("(cons 0 (lazy-seq (range 1 3)))"), "(0 1 2)");
+        assert_eq!(
+            run("(realized? (lazy-seq [1]))"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+        assert!(run("(doall (lazy-seq 1))").is_err());
+        assert!(run("(cons 0 (lazy-seq (undefined)))").is_ok());
+    }
+
+    #[test]
+    fn test_deep_lazy_chain_is_stack_safe() {
+        let mut s = Seq::empty();
+        for _ in 0..1_000_000 {
+            let inner = s;
+            s = Seq::lazy(move || Ok(inner));
+        }
+        let s = s.cons(EDN::from(1i64));
+        assert_eq!(s.rest().unwrap().seq(), Ok(None));
+        assert_eq!(s.count(), Ok(1));
+
+        let nested = (0..1_000_000).fold(Seq::empty(), |tail, _| {
+            Seq::lazy(move || Ok(tail.cons(EDN::Nil)))
+        });
+        assert_eq!(nested.count(), Ok(1_000_000));
+
+        let numbers = seq::range(
+            EDN::from(0i64),
+            Some(EDN::from(1_000_000i64)),
+            EDN::from(1i64),
+        );
+        let numbers = numbers.unwrap();
+        assert_eq!(numbers.count(), Ok(1_000_000));
+        let tail = seq::drop(999_999, numbers).first();
+        assert_eq!(tail, Ok(EDN::from(999_999i64)));
+    }
+
+    #[test]
+    fn test_threads_share_a_lazy_seq() {
+        // The first thread holds the thunk until the second one is waiting
+        // on it.
+        let barrier = Arc::new(Barrier::new(2));
+        let gate = barrier.clone();
+        let slow = Seq::lazy(move || {
+            gate.wait();
+            thread::sleep(Duration::from_millis(50));
+            Ok(Seq::empty().cons(EDN::from(1i64)))
+        });
+        let other = slow.clone();
+        let first = thread::spawn(move || other.first());
+        barrier.wait();
+        assert_eq!(slow.first(), Ok(EDN::from(1i64)));
+        assert_eq!(first.join().unwrap(), Ok(EDN::from(1i64)));
+
+        let env = create_env();
+        run_in(&env, "(def xs (map (fn [x] (conj [] x)) (range 2000)))").unwrap();
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let env = env.clone();
+                thread::spawn(move || run_in(&env, "(count (doall xs))"))
+            })
+            .collect();
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), Ok(Value::EDN(edn("2000"))));
+        }
+    }
+
+    #[test]
+    fn test_self_reference_is_an_error() {
+        let env = create_env();
+        run_in(&env, "(def xs (lazy-seq (cons (count xs) nil)))").unwrap();
+        let err = run_in(&env, "(doall xs)").unwrap_err();
+        assert!(err.to_string().contains("needs its own value"), "{err}");
+    }
+
+    #[test]
+    fn test_failing_thunks_are_errors_not_panics() {
+        let env = create_env();
+        run_in(&env, "(def xs (map (fn [x] (throw \"boom\")) [1 2]))").unwrap();
+        let thrown = |s: &str| match run_in(&env, s) {
+            Err(e) => matches!(e.cause(), EvalError::Thrown(_)),
+            Ok(_) => false,
+        };
+        assert!(thrown("(println xs)"));
+        assert!(thrown("(= xs [1 2])"));
+        assert!(thrown("(= [1 2] (conj [] xs))"));
+        assert!(thrown("(hash-set xs)"));
+        assert!(thrown("(hash-map xs 1)"));
+        assert!(thrown("(get {} xs)"));
+        assert!(thrown("(conj #{} (conj [] xs))"));
+        assert!(thrown("(assoc {} xs 1)"));
+
+        // A value whose seq fails still displays, with the error in place
+        let value = run_in(&env, "xs").unwrap();
+        assert!(value.force().is_err());
+        assert!(value.to_string().contains("#<error"), "{value}");
+    }
+}
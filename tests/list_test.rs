@@ -3,6 +3,14 @@ use yinyang::immutant::list::List;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
 
     #[test]
     fn test_new() {
@@ -82,4 +90,70 @@ mod tests {
         let list = list.cons(3);
         assert_eq!(list.len(), 3);
     }
+
+    #[test]
+    fn test_append() {
+        let list = List::new().append(1).append(2).append(3);
+        assert_eq!(list.to_vec(), vec![1, 2, 3]);
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        let a: List<i32> = (0..100).collect();
+        let b: List<i32> = (0..100).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, b.rest());
+        assert_ne!(a, b.rest().cons(42));
+        assert_eq!(a.rest(), b.rest());
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn test_traits() {
+        let mut list: List<i32> = vec![1, 2].into_iter().collect();
+        list.extend(vec![3, 4]);
+        assert_eq!(list.to_string(), "(1 2 3 4)");
+        assert_eq!(format!("{:?}", list), "[1, 2, 3, 4]");
+        assert_eq!((&list).into_iter().len(), 4);
+        let shared = list.rest();
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(shared.into_iter().collect::<Vec<_>>(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_million_elements() {
+        let n = 1_000_000;
+        let list: List<usize> = (0..n).collect();
+        assert_eq!(list.len(), n);
+        assert_eq!(list.iter().len(), n);
+        assert_eq!(list.tail().unwrap().len(), n - 1);
+
+        let copy: List<usize> = (0..n).collect();
+        assert_eq!(list, copy);
+        assert_eq!(hash_of(&list), hash_of(&copy));
+        drop(copy);
+
+        let longer = list.append(n);
+        assert_eq!(longer.len(), n + 1);
+        assert_eq!(longer.iter().last(), Some(&n));
+        assert_ne!(list, longer);
+
+        let reversed = list.reverse();
+        assert_eq!(reversed.head(), Some(&(n - 1)));
+        assert_eq!(list.to_string().len(), reversed.to_string().len());
+        assert_eq!(reversed.into_iter().count(), n);
+    }
+
+    #[test]
+    fn test_million_element_cons_chain_drops() {
+        let mut list = List::new();
+        for i in 0..1_000_000 {
+            list = list.cons(i);
+        }
+        let shared = list.rest();
+        drop(list);
+        assert_eq!(shared.len(), 999_999);
+        drop(shared);
+    }
 }
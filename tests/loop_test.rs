@@ -0,0 +1,123 @@
+use yinyang::clojure::{EvalError, Value};
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run, run_in};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop() {
+        assert_eq!(
+            run("(loop [i 0 acc []] (if (< i 3) (recur (+ i 1) (conj acc i)) acc))")
+                .unwrap()
+                .to_string(),
+            "[0 1 2]"
+        );
+        assert_eq!(run("(loop [x :a] x)"), Ok(Value::EDN(edn(":a"))));
+        assert_eq!(run("(loop [])"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(
+            run("(loop [a 1 b a] (conj (conj [] a) b))")
+                .unwrap()
+                .to_string(),
+            "[1 1]"
+        );
+    }
+
+    #[test]
+    fn test_million_iterations_in_constant_stack() {
+        let result = run("(loop [n 1000000] (if (< n 1) :done (recur (- n 1))))");
+        assert_eq!(result, Ok(Value::EDN(edn(":done"))));
+
+        let env = create_env();
+        run_in(
+            &env,
+            "(def count-down (fn [n] (if (< n 1) :done (recur (- n 1)))))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "(count-down 100000)"),
+            Ok(Value::EDN(edn(":done")))
+        );
+    }
+
+    #[test]
+    fn test_equality_as_exit_test() {
+        assert_eq!(
+            run("(loop [i 0] (if (= i 10) i (recur (+ i 1))))"),
+            Ok(Value::EDN(edn("10")))
+        );
+        let env = create_env();
+        run_in(&env, "(defn count-down [n] (if (= n 0) 0 (recur (- n 1))))").unwrap();
+        assert_eq!(
+            run_in(&env, "(count-down 100000)"),
+            Ok(Value::EDN(edn("0")))
+        );
+        assert_eq!(
+            run("(loop [n 3 acc []] (if (= n 0) acc (recur (- n 1) (conj acc n))))")
+                .unwrap()
+                .to_string(),
+            "[3 2 1]"
+        );
+    }
+
+    #[test]
+    fn test_recur_through_let_and_do() {
+        assert_eq!(
+            run("(loop [n 3 acc (quote ())] (let [m (- n 1)] (do (count acc) (if (< n 1) acc (recur m (cons n acc))))))")
+                .unwrap()
+                .to_string(),
+            "(1 2 3)"
+        );
+    }
+
+    #[test]
+    fn test_recur_must_be_in_tail_position() {
+        let tail_error = |s: &str| match run(s) {
+            Err(e) => e.to_string().contains("tail position"),
+            Ok(_) => false,
+        };
+        assert!(tail_error("(loop [n 1] (+ 1 (recur n)))"));
+        assert!(tail_error("(fn [n] (if (recur n) 1 2))"));
+        assert!(tail_error("(loop [n 1] (let [x (recur n)] x))"));
+        assert!(tail_error("(loop [n 1] (recur n) n)"));
+        assert!(tail_error("(loop [n 1] (let [] (recur n) n))"));
+        assert!(tail_error("(recur 1)"));
+
+        // Checked when the form is analysed, not when the recur is reached
+        assert!(tail_error("(fn [n] (if false (+ 1 (recur n)) n))"));
+
+        // A nested fn is its own recur target
+        assert!(run("(loop [n 1] (fn [x] (recur x)))").is_ok());
+    }
+
+    #[test]
+    fn test_recur_arity() {
+        let arity_error = |s: &str, n: usize| {
+            matches!(
+                run(s).map_err(|e| e.cause().clone()),
+                Err(EvalError::Arity { got, .. }) if got == n
+            )
+        };
+        assert!(arity_error("(loop [a 1 b 2] (recur 1))", 1));
+
+        // Checked when the fn or loop is analysed, before it runs
+        assert!(arity_error("(fn [x] (recur x 1))", 2));
+        assert!(arity_error("(fn ([] 0) ([x] (recur)))", 0));
+        assert!(arity_error("(loop [a 1] (if false (recur 1 2) a))", 2));
+        assert!(arity_error("(loop [a 1] (let [b a] (recur)))", 0));
+
+        // The rest parameter takes a single seq
+        assert!(run("(fn [x & more] (recur x more))").is_ok());
+        assert!(arity_error("(fn [x & more] (recur x 1 2))", 3));
+        assert_eq!(
+            run("(let [f (fn [n & acc] (if (< n 1) acc (recur (- n 1) (cons n acc))))] (f 3))")
+                .unwrap()
+                .to_string(),
+            "(1 2 3)"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run, run_in, run_str};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defmacro() {
+        let env = create_env();
+        assert_eq!(
+            run_in(&env, "(defmacro unless [c a b] (list 'if c b a))")
+                .unwrap()
+                .to_string(),
+            "#'user/unless"
+        );
+        assert_eq!(
+            run_in(&env, "(unless false :yes (undefined))"),
+            Ok(Value::EDN(edn(":yes")))
+        );
+
+        // Macros see the call and the local names in scope
+        run_in(&env, "(defmacro whole [& _] (list 'quote &form))").unwrap();
+        assert_eq!(
+            run_in(&env, "(whole 1 2)").unwrap().to_string(),
+            "(whole 1 2)"
+        );
+        run_in(&env, "(defmacro local? [s] (contains? &env s))").unwrap();
+        assert_eq!(
+            run_in(&env, "(let [x 1] (local? x))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(run_in(&env, "(local? x)"), Ok(Value::EDN(EDN::Bool(false))));
+
+        assert!(run_in(&env, "unless")
+            .unwrap_err()
+            .to_string()
+            .contains("Can't take value of a macro"));
+    }
+
+    #[test]
+    fn test_macroexpand() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(defmacro my-when [c & body] (list 'when c (cons 'do body)))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "(macroexpand-1 '(my-when a b))")
+                .unwrap()
+                .to_string(),
+            "(when a (do b))"
+        );
+        assert_eq!(
+            run_in(&env, "(macroexpand '(my-when a b))")
+                .unwrap()
+                .to_string(),
+            "(if a (do (do b)))"
+        );
+        assert_eq!(
+            run_in(&env, "(macroexpand '(+ 1 2))").unwrap().to_string(),
+            "(+ 1 2)"
+        );
+    }
+
+    #[test]
+    fn test_gensym() {
+        let a = run_str("(gensym)");
+        let b = run_str("(gensym)");
+        assert!(a.starts_with("G__"));
+        assert_ne!(a, b);
+        assert!(run_str("(gensym \"tmp\")").starts_with("tmp"));
+    }
+
+    #[test]
+    fn test_core_macros() {
+        let env = create_env();
+        run_in(&env, "(defn add \"Adds.\" [a b] (+ a b))").unwrap();
+        assert_eq!(run_in(&env, "(add 1 2)").unwrap().to_string(), "3");
+        run_in(&env, "(defn f ([] 0) ([x] x))").unwrap();
+        assert_eq!(run_in(&env, "(f 4)").unwrap().to_string(), "4");
+
+        assert_eq!(run_str("(when true 1 2)"), "2");
+        assert_eq!(run_str("(when false (undefined))"), "nil");
+        assert_eq!(run_str("(cond false 1 nil 2 :else 3)"), "3");
+        assert_eq!(run_str("(cond false 1)"), "nil");
+        assert!(run("(cond true)").is_err());
+
+        assert_eq!(run_str("(and)"), "true");
+        assert_eq!(run_str("(and 1 nil (undefined))"), "nil");
+        assert_eq!(run_str("(and 1 2)"), "2");
+        assert_eq!(run_str("(or)"), "nil");
+        assert_eq!(run_str("(or false 2 (undefined))"), "2");
+        // The temporary names don't capture the caller's
+        assert_eq!(run_str("(let [g 5] (or nil g))"), "5");
+
+        assert_eq!(run_str("(-> {} (assoc :a 1) (get :a))"), "1");
+        assert_eq!(
+            run_str("(->> [1 2 3] (map (fn [x] (* x 2))) (take 2))"),
+            "(2 4)"
+        );
+
+        assert_eq!(run_str("(if-let [[a] [1]] a :none)"), "1");
+        assert_eq!(run_str("(if-let [a nil] a :none)"), ":none");
+        assert_eq!(run_str("(if-let [a false] a)"), "nil");
+    }
+
+    #[test]
+    fn test_macros_in_tail_position() {
+        let env = create_env();
+        run_in(
+            &env,
+            "(defn count-down [n] (cond (< n 1) :done :else (recur (- n 1))))",
+        )
+        .unwrap();
+        assert_eq!(
+            run_in(&env, "(count-down 1000)"),
+            Ok(Value::EDN(edn(":done")))
+        );
+        assert!(run("(loop [n 1] (when true (+ 1 (recur n))))")
+            .unwrap_err()
+            .to_string()
+            .contains("tail position"));
+    }
+}
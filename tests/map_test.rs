@@ -0,0 +1,88 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_literals() {
+        let m = edn("{:a 1 :b {:c 2}}");
+        assert_eq!(m.as_map().unwrap().len(), 2);
+        assert_eq!(m, edn("{:b {:c 2} :a 1}"));
+        assert_eq!(m.get_in(&[edn(":b"), edn(":c")]), Some(&edn("2")));
+        assert_eq!(edn("{:a 1}").to_string(), "{:a 1}");
+    }
+
+    #[test]
+    fn test_map_equality_ignores_history() {
+        let built = edn("{}")
+            .assoc(edn(":x"), edn("1"))
+            .unwrap()
+            .assoc(edn(":tmp"), edn("0"))
+            .unwrap()
+            .assoc(edn(":y"), edn("2"))
+            .unwrap()
+            .dissoc(&edn(":tmp"))
+            .unwrap();
+        assert_eq!(built, edn("{:y 2 :x 1}"));
+        assert_eq!(
+            run("(= (assoc {} :x 1 :y 2) {:y 2 :x 1})").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_assoc_dissoc() {
+        assert_eq!(
+            run("(assoc {:a 1} :b 2 :a 3)").unwrap(),
+            Value::EDN(edn("{:a 3 :b 2}"))
+        );
+        assert_eq!(run("(assoc nil :a 1)").unwrap(), Value::EDN(edn("{:a 1}")));
+        assert_eq!(
+            run("(assoc [1 2] 2 3)").unwrap(),
+            Value::EDN(edn("[1 2 3]"))
+        );
+        assert_eq!(
+            run("(dissoc {:a 1 :b 2 :c 3} :a :c :missing)").unwrap(),
+            Value::EDN(edn("{:b 2}"))
+        );
+        assert!(run("(assoc {:a 1} :b)").is_err());
+        assert!(run("(dissoc [1 2] 0)").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            run("(merge {:a 1 :b 2} nil {:b 3 :c 4})").unwrap(),
+            Value::EDN(edn("{:a 1 :b 3 :c 4}"))
+        );
+        assert_eq!(run("(merge)").unwrap(), Value::EDN(EDN::Nil));
+        assert_eq!(run("(merge nil nil)").unwrap(), Value::EDN(EDN::Nil));
+        assert!(run("(merge {:a 1} [1 2])").is_err());
+    }
+
+    #[test]
+    fn test_get_contains() {
+        assert_eq!(
+            run("(get (hash-map :a 1 :b nil) :a)").unwrap(),
+            Value::EDN(edn("1"))
+        );
+        assert_eq!(
+            run("(get {:a 1} :z :default)").unwrap(),
+            Value::EDN(edn(":default"))
+        );
+        assert_eq!(
+            run("(contains? {:a 1 :b nil} :b)").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(contains? {:a 1} :z)").unwrap(),
+            Value::EDN(EDN::Bool(false))
+        );
+        assert!(run("(hash-map :a)").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use yinyang::clojure::{read_string, Value};
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_meta_and_meta() {
+        assert_eq!(
+            run("(meta (with-meta [1 2] {:doc \"v\"}))"),
+            Ok(Value::EDN(edn("{:doc \"v\"}")))
+        );
+        assert_eq!(
+            run("(meta (with-meta (quote (1 2)) {:line 3}))"),
+            Ok(Value::EDN(edn("{:line 3}")))
+        );
+        assert_eq!(
+            run("(meta (with-meta #{1} {:tag :set}))"),
+            Ok(Value::EDN(edn("{:tag :set}")))
+        );
+        assert_eq!(
+            run("(meta (with-meta (sorted-map 1 2) {:a 1}))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(run("(meta [1 2])"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(run("(meta 1)"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(
+            run("(meta (with-meta (with-meta [] {:a 1}) nil))"),
+            Ok(Value::EDN(EDN::Nil))
+        );
+        assert!(run("(with-meta [] [:a 1])").is_err());
+        assert!(run("(with-meta 1 {:a 1})").is_err());
+    }
+
+    #[test]
+    fn test_symbol_meta() {
+        assert_eq!(
+            run("(meta (with-meta (quote x) {:tag 1}))"),
+            Ok(Value::EDN(edn("{:tag 1}")))
+        );
+        assert_eq!(
+            run("(meta (vary-meta (with-meta (quote x) {:a 1}) assoc :b 2))"),
+            Ok(Value::EDN(edn("{:a 1 :b 2}")))
+        );
+        assert_eq!(run("(meta (quote x))"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(
+            run("(= (with-meta (quote x) {:a 1}) (quote x))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(with-meta (quote x) {:a 1})").unwrap().to_string(),
+            "x"
+        );
+    }
+
+    #[test]
+    fn test_meta_is_preserved_by_updates() {
+        assert_eq!(
+            run("(meta (conj (with-meta [1] {:a 1}) 2))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (conj (with-meta (quote (1)) {:a 1}) 0))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (assoc (with-meta {:x 1} {:a 1}) :y 2))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (dissoc (with-meta {:x 1} {:a 1}) :x))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (disj (with-meta #{1 2} {:a 1}) 1))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (conj (with-meta (catvec [1] [2]) {:a 1}) 3))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (assoc (with-meta (subvec [1 2 3] 1) {:a 1}) 0 :x))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (conj (with-meta clojure.lang.PersistentQueue/EMPTY {:a 1}) 1))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(meta (pop (conj (with-meta clojure.lang.PersistentQueue/EMPTY {:a 1}) 1 2)))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+    }
+
+    #[test]
+    fn test_meta_is_ignored_by_equality() {
+        assert_eq!(
+            run("(= (with-meta [1 2] {:a 1}) [1 2])"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(count (hash-set (with-meta {:x 1} {:a 1}) {:x 1}))"),
+            Ok(Value::EDN(edn("1")))
+        );
+        assert_eq!(
+            run("(with-meta {:x 1} {:a 1})").unwrap().to_string(),
+            "{:x 1}"
+        );
+    }
+
+    #[test]
+    fn test_reader_leaves_strings_alone() {
+        assert_eq!(run("\"a ^b\""), Ok(Value::EDN(edn("\"a ^b\""))));
+        assert_eq!(run("(count \"a ^:b c\")"), Ok(Value::EDN(edn("7"))));
+        assert_eq!(run("(count \"#'x\")"), Ok(Value::EDN(edn("3"))));
+        assert_eq!(run("(count \"it's\")"), Ok(Value::EDN(edn("4"))));
+        assert_eq!(
+            run("(meta (quote ^{:doc \"^:x #'y\"} z))"),
+            Ok(Value::EDN(edn("{:doc \"^:x #'y\"}")))
+        );
+        assert_eq!(
+            run("(first (quote (\"a \\\" #'b\" ^:c d)))"),
+            Ok(Value::EDN(EDN::String("a \" #'b".to_string())))
+        );
+        assert_eq!(
+            run("(meta (first (next (quote (\"a \\\" ^:b\" ^:c d)))))"),
+            Ok(Value::EDN(edn("{:c true}")))
+        );
+    }
+
+    #[test]
+    fn test_reader_attaches_meta_to_the_form() {
+        assert_eq!(
+            run("(meta (quote ^:foo sym))"),
+            Ok(Value::EDN(edn("{:foo true}")))
+        );
+        assert_eq!(run("(meta '^:foo sym)"), Ok(Value::EDN(edn("{:foo true}"))));
+        assert_eq!(run("(meta ^{:a 1} [1 2])"), Ok(Value::EDN(edn("{:a 1}"))));
+        assert_eq!(run("^{:a 1} [1 2]"), Ok(Value::EDN(edn("[1 2]"))));
+        assert_eq!(
+            run("(meta '^:a ^{:b 2} ^String x)"),
+            Ok(Value::EDN(edn("{:a true :b 2 :tag String}")))
+        );
+        assert_eq!(run("(let [^:foo s 1] s)"), Ok(Value::EDN(edn("1"))));
+        assert_eq!(
+            run("(let [v (with-meta [] {:a 1})] (meta ^:foo v))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert!(read_string("^:foo 1").is_err());
+        assert!(run("(read-string \"^:foo 1\")").is_err());
+    }
+
+    #[test]
+    fn test_vary_meta() {
+        assert_eq!(
+            run("(meta (vary-meta (with-meta [] {:a 1}) assoc :b 2))"),
+            Ok(Value::EDN(edn("{:a 1 :b 2}")))
+        );
+        assert_eq!(
+            run("(meta (vary-meta [] assoc :b 2))"),
+            Ok(Value::EDN(edn("{:b 2}")))
+        );
+        assert!(run("(vary-meta [] 1)").is_err());
+    }
+}
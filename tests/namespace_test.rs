@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::PathBuf;
+use yinyang::clojure::{Environment, EvalError, Value};
+use yinyang::namespace::Namespace;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run_in, run_str_in};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An environment whose source path is a fresh directory holding `files`.
+    fn env_with_sources(test: &str, files: &[(&str, &str)]) -> Environment {
+        let dir = std::env::temp_dir().join(format!("yinyang-{}-{}", test, std::process::id()));
+        for (path, source) in files {
+            let path: PathBuf = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        let env = create_env();
+        Namespace::installed(&env).set_source_path(vec![dir]);
+        env
+    }
+
+    #[test]
+    fn test_ns_and_in_ns() {
+        let env = create_env();
+        assert_eq!(run_str_in(&env, "*ns*"), "#namespace[user]");
+        assert_eq!(run_str_in(&env, "(def x 0)"), "#'user/x");
+
+        run_in(&env, "(ns foo.bar)").unwrap();
+        assert_eq!(run_str_in(&env, "*ns*"), "#namespace[foo.bar]");
+        assert_eq!(run_str_in(&env, "(def x 1)"), "#'foo.bar/x");
+        // Core functions are visible from every namespace
+        assert_eq!(run_str_in(&env, "(+ x 1)"), "2");
+
+        assert_eq!(run_str_in(&env, "(in-ns 'user)"), "#namespace[user]");
+        assert_eq!(run_in(&env, "x"), Ok(Value::EDN(edn("0"))));
+        assert_eq!(run_in(&env, "foo.bar/x"), Ok(Value::EDN(edn("1"))));
+        assert_eq!(run_in(&env, "user/x"), Ok(Value::EDN(edn("0"))));
+        assert_eq!(
+            run_in(&env, "foo.bar/nope").map_err(|e| e.cause().clone()),
+            Err(EvalError::Unbound("foo.bar/nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_native_libraries_have_namespaces() {
+        let env = create_env();
+        assert_eq!(run_str_in(&env, "(var set/union)"), "#'clojure.set/union");
+        assert_eq!(run_str_in(&env, "(var zip/node)"), "#'clojure.zip/node");
+        assert_eq!(
+            run_str_in(&env, "(var schema/validate)"),
+            "#'yinyang.schema/validate"
+        );
+        assert_eq!(
+            run_str_in(&env, "(clojure.set/union (hash-set 1) (hash-set 2))"),
+            "#{1 2}"
+        );
+        assert_eq!(
+            run_str_in(&env, "(count clojure.lang.PersistentQueue/EMPTY)"),
+            "0"
+        );
+
+        // Other namespaces get the aliases by requiring them
+        run_in(&env, "(ns other)").unwrap();
+        assert!(run_in(&env, "(set/union #{1} #{2})").is_err());
+        assert!(run_in(&env, "union").is_err());
+        run_in(&env, "(require '[clojure.set :as s :refer [difference]])").unwrap();
+        assert_eq!(run_str_in(&env, "(s/union #{1} #{2})"), "#{1 2}");
+        assert_eq!(run_str_in(&env, "(difference #{1 2} #{2})"), "#{1}");
+    }
+
+    #[test]
+    fn test_user_defs_stay_in_user() {
+        let env = create_env();
+        run_in(&env, "(def only-in-user 1)").unwrap();
+        run_in(&env, "(defn count [x] :mine)").unwrap();
+        run_in(&env, "(ns other)").unwrap();
+        assert_eq!(
+            run_in(&env, "only-in-user").map_err(|e| e.cause().clone()),
+            Err(EvalError::Unbound("only-in-user".to_string()))
+        );
+        assert_eq!(run_in(&env, "user/only-in-user"), Ok(Value::EDN(edn("1"))));
+        // Core functions come from clojure.core, not from user's redefinitions
+        assert_eq!(run_str_in(&env, "(count [1 2])"), "2");
+        assert_eq!(run_str_in(&env, "(clojure.core/count [1 2])"), "2");
+        assert_eq!(run_str_in(&env, "(var count)"), "#'clojure.core/count");
+
+        run_in(&env, "(refer 'user :only '[only-in-user])").unwrap();
+        assert_eq!(run_in(&env, "only-in-user"), Ok(Value::EDN(edn("1"))));
+    }
+
+    #[test]
+    fn test_core_vars_live_in_clojure_core() {
+        let env = create_env();
+        assert_eq!(run_str_in(&env, "(var map)"), "#'clojure.core/map");
+        assert_eq!(run_str_in(&env, "(var defn)"), "#'clojure.core/defn");
+        run_in(
+            &env,
+            "(alter-var-root #'clojure.core/count (fn [f] (fn [x] :altered)))",
+        )
+        .unwrap();
+        assert_eq!(run_str_in(&env, "(count [1])"), ":altered");
+        run_in(&env, "(ns other)").unwrap();
+        assert_eq!(run_str_in(&env, "(count [1])"), ":altered");
+    }
+
+    #[test]
+    fn test_functions_resolve_in_their_namespace() {
+        let env = create_env();
+        run_in(&env, "(ns lib)").unwrap();
+        run_in(&env, "(def y :lib)").unwrap();
+        run_in(&env, "(defn get-y [] y)").unwrap();
+        run_in(&env, "(in-ns 'user)").unwrap();
+        run_in(&env, "(def y :user)").unwrap();
+        assert_eq!(run_in(&env, "(lib/get-y)"), Ok(Value::EDN(edn(":lib"))));
+    }
+
+    #[test]
+    fn test_alias_and_refer() {
+        let env = create_env();
+        run_in(&env, "(ns a.long.name)").unwrap();
+        run_in(&env, "(def v 1)").unwrap();
+        run_in(&env, "(def w 2)").unwrap();
+        run_in(&env, "(in-ns 'user)").unwrap();
+
+        run_in(&env, "(alias 'aln 'a.long.name)").unwrap();
+        assert_eq!(run_in(&env, "aln/v"), Ok(Value::EDN(edn("1"))));
+
+        run_in(&env, "(refer 'a.long.name :only '[w])").unwrap();
+        assert_eq!(run_in(&env, "w"), Ok(Value::EDN(edn("2"))));
+        assert!(run_in(&env, "v").is_err());
+        run_in(&env, "(refer 'a.long.name)").unwrap();
+        assert_eq!(run_in(&env, "v"), Ok(Value::EDN(edn("1"))));
+
+        assert!(run_in(&env, "(alias 'x 'missing)").is_err());
+        assert!(run_in(&env, "(refer 'a.long.name :only '[missing])").is_err());
+    }
+
+    #[test]
+    fn test_require_from_source_path() {
+        let env = env_with_sources(
+            "require",
+            &[
+                (
+                    "my_lib/core.clj",
+                    "(ns my-lib.core)\n(def answer 42)\n(defn greet [x] (conj [:hello] x))\n\
+                     (def secret \"a)b\")\n; a (comment\nanswer\n(def after-secret (count secret))\n",
+                ),
+                (
+                    "my_lib/extra.clj",
+                    "(ns my-lib.extra (:require [my-lib.core :as c]))\n(defn double-answer [] (* 2 c/answer))\n",
+                ),
+            ],
+        );
+
+        run_in(
+            &env,
+            "(ns app (:require [my-lib.core :as lib :refer [greet]] [my-lib.extra :refer :all]))",
+        )
+        .unwrap();
+        assert_eq!(run_in(&env, "lib/answer"), Ok(Value::EDN(edn("42"))));
+        assert_eq!(run_str_in(&env, "(greet :you)"), "[:hello :you]");
+        assert_eq!(run_str_in(&env, "(double-answer)"), "84");
+        assert_eq!(run_str_in(&env, "*ns*"), "#namespace[app]");
+        // Strings, comments and bare symbols between forms read as written
+        assert_eq!(run_in(&env, "lib/secret"), Ok(Value::EDN(edn("\"a)b\""))));
+        assert_eq!(run_str_in(&env, "lib/after-secret"), "3");
+
+        // Already loaded namespaces are not loaded again
+        run_in(&env, "(in-ns 'my-lib.core)").unwrap();
+        assert_eq!(
+            run_str_in(&env, "(def my-lib.core/answer 0)"),
+            "#'my-lib.core/answer"
+        );
+        run_in(&env, "(in-ns 'app)").unwrap();
+        run_in(&env, "(require '[my-lib.core :as again])").unwrap();
+        assert_eq!(run_in(&env, "again/answer"), Ok(Value::EDN(edn("0"))));
+        // Other namespaces' vars cannot be defined from here
+        let err = run_in(&env, "(def my-lib.core/answer 1)").unwrap_err();
+        assert!(err.to_string().contains("outside of current ns"), "{err}");
+        assert_eq!(run_in(&env, "lib/answer"), Ok(Value::EDN(edn("0"))));
+    }
+
+    #[test]
+    fn test_require_errors() {
+        let env = env_with_sources(
+            "require-errors",
+            &[
+                ("cyc/a.clj", "(ns cyc.a (:require cyc.b))\n"),
+                ("cyc/b.clj", "(ns cyc.b (:require cyc.a))\n"),
+                ("wrong/name.clj", "(ns something.else)\n"),
+            ],
+        );
+        let error = |s: &str| run_in(&env, s).unwrap_err().to_string();
+        assert!(error("(require 'no.such.lib)").contains("Could not locate no/such/lib.clj"));
+        assert!(error("(require 'cyc.a)").contains("Cyclic load dependency"));
+        assert!(error("(require 'wrong.name)").contains("did not define namespace wrong.name"));
+        assert!(error("(require '[cyc.a :bad])").contains("Invalid require spec"));
+        assert!(error("(ns x (:use y))").contains("Unsupported ns clause"));
+        // A failed require leaves the namespace it was called from current
+        assert_eq!(run_str_in(&env, "*ns*"), "#namespace[user]");
+    }
+}
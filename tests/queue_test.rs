@@ -0,0 +1,75 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_queue() {
+        let q = run("PersistentQueue/EMPTY").unwrap();
+        assert_eq!(q.to_string(), "#queue []");
+        assert_eq!(run("clojure.lang.PersistentQueue/EMPTY"), Ok(q));
+        assert_eq!(
+            run("(peek PersistentQueue/EMPTY)"),
+            Ok(Value::EDN(EDN::Nil))
+        );
+        assert_eq!(
+            run("(pop PersistentQueue/EMPTY)").unwrap().to_string(),
+            "#queue []"
+        );
+    }
+
+    #[test]
+    fn test_conj_peek_pop() {
+        let q = run("(conj PersistentQueue/EMPTY 1 2 3)").unwrap();
+        assert_eq!(q.to_string(), "#queue [1 2 3]");
+        assert_eq!(
+            run("(peek (conj PersistentQueue/EMPTY 1 2 3))"),
+            Ok(Value::EDN(edn("1")))
+        );
+        assert_eq!(
+            run("(pop (conj (pop (conj PersistentQueue/EMPTY 1 2 3)) 4))")
+                .unwrap()
+                .to_string(),
+            "#queue [3 4]"
+        );
+        match run("(conj PersistentQueue/EMPTY :a :b)") {
+            Ok(Value::EDN(q)) => {
+                let q = q.as_queue().unwrap();
+                assert_eq!(q.len(), 2);
+                assert_eq!(
+                    q.iter().cloned().collect::<Vec<_>>(),
+                    vec![edn(":a"), edn(":b")]
+                );
+            }
+            other => panic!("expected a queue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(
+            run("(= (conj PersistentQueue/EMPTY 1 2) (pop (conj PersistentQueue/EMPTY 0 1 2)))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(= (conj PersistentQueue/EMPTY 1 2) (conj PersistentQueue/EMPTY 2 1))"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+    }
+
+    #[test]
+    fn test_peek_pop_on_lists_and_vectors() {
+        assert_eq!(run("(peek [1 2 3])"), Ok(Value::EDN(edn("3"))));
+        assert_eq!(run("(pop [1 2 3])"), Ok(Value::EDN(edn("[1 2]"))));
+        assert_eq!(run("(peek (quote (1 2 3)))"), Ok(Value::EDN(edn("1"))));
+        assert_eq!(run("(pop (quote (1 2 3)))"), Ok(Value::EDN(edn("(2 3)"))));
+        assert_eq!(run("(peek nil)"), Ok(Value::EDN(EDN::Nil)));
+        assert!(run("(pop [])").is_err());
+        assert!(run("(pop {})").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+use yinyang::immutant::rrb::RrbVector;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catvec() {
+        let v = run("(catvec [1 2] [] [3 4 5])").unwrap();
+        assert_eq!(v, Value::EDN(edn("[1 2 3 4 5]")));
+        assert_eq!(v.to_string(), "[1 2 3 4 5]");
+        assert_eq!(run("(catvec)").unwrap().to_string(), "[]");
+        assert_eq!(
+            run("(conj (catvec [1] [2]) 3)"),
+            Ok(Value::EDN(edn("[1 2 3]")))
+        );
+        assert_eq!(run("(get (catvec [1] [2]) 1)"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run("(peek (catvec [1] [2]))"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(
+            run("(assoc (catvec [1] [2]) 0 :a)"),
+            Ok(Value::EDN(edn("[:a 2]")))
+        );
+        assert!(run("(catvec [1] (quote (2)))").is_err());
+    }
+
+    #[test]
+    fn test_subvec() {
+        assert_eq!(
+            run("(subvec [0 1 2 3 4] 1 3)"),
+            Ok(Value::EDN(edn("[1 2]")))
+        );
+        assert_eq!(run("(subvec [0 1 2 3 4] 3)"), Ok(Value::EDN(edn("[3 4]"))));
+        assert_eq!(
+            run("(subvec (catvec [0 1] [2 3]) 1 3)"),
+            Ok(Value::EDN(edn("[1 2]")))
+        );
+        assert!(run("(subvec [0 1 2] 2 1)").is_err());
+        assert!(run("(subvec [0 1 2] 0 4)").is_err());
+        assert!(run("(subvec [0 1 2] -1)").is_err());
+    }
+
+    #[test]
+    fn test_equal_to_plain_vectors() {
+        assert_eq!(
+            run("(= (catvec [1] [2 3]) [1 2 3])"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(= [1 2 3] (subvec [0 1 2 3] 1))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(compare (catvec [1] [2]) [1 3])"),
+            Ok(Value::EDN(edn("-1")))
+        );
+        assert_eq!(
+            run("(get (hash-map [1 2] :found) (catvec [1] [2]))"),
+            Ok(Value::EDN(edn(":found")))
+        );
+    }
+
+    #[test]
+    fn test_treated_as_vectors() {
+        let yes = Ok(Value::EDN(EDN::Bool(true)));
+        assert_eq!(
+            run("(schema/validate (quote [:vector int?]) (catvec [1] [2]))"),
+            yes
+        );
+        assert_eq!(
+            run("(schema/validate (quote [:vector {:max 2} int?]) (catvec [1] [2 3]))"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+        assert_eq!(
+            run("(schema/validate (quote [:tuple int? keyword?]) (subvec [0 1 :a] 1))"),
+            yes
+        );
+        assert_eq!(run("(schema/validate 'vector? (catvec [1] [2]))"), yes);
+        assert_eq!(run("(schema/validate 'coll? (subvec [1 2] 1))"), yes);
+        assert_eq!(
+            run("(schema/coerce (quote [:vector int?]) (catvec [\"1\"] [2]))"),
+            Ok(Value::EDN(edn("[1 2]")))
+        );
+        assert_eq!(
+            run("(persistent! (conj! (transient (catvec [1] [2])) 3))"),
+            Ok(Value::EDN(edn("[1 2 3]")))
+        );
+    }
+
+    #[test]
+    fn test_split_and_join_large_vectors() {
+        let text: RrbVector<EDN> = (0..200_000i64).map(EDN::from).collect();
+        let (head, tail) = text.split_at(123_457);
+        let edited = head
+            .conj(EDN::from(-1i64))
+            .concat(&tail)
+            .insert_at(5, EDN::from(-2i64))
+            .unwrap();
+        assert_eq!(edited.len(), 200_002);
+        assert_eq!(edited[5], EDN::from(-2i64));
+        assert_eq!(edited[123_458], EDN::from(-1i64));
+        assert_eq!(edited[200_001], EDN::from(199_999i64));
+        assert_eq!(text.len(), 200_000);
+    }
+}
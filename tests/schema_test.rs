@@ -0,0 +1,217 @@
+use yinyang::clojure::{eval, read_string, Value};
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+use yinyang::schema::Schema;
+
+mod common;
+use common::edn;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(s: &str) -> Schema {
+        Schema::parse(&edn(s)).unwrap()
+    }
+
+    #[test]
+    fn test_leaf_schemas() {
+        assert!(schema("int?").validate(&edn("42")));
+        assert!(!schema("int?").validate(&edn("\"42\"")));
+        assert!(schema(":string").validate(&edn("\"x\"")));
+        assert!(schema("keyword?").validate(&edn(":k")));
+        assert!(schema("number?").validate(&edn("1.5")));
+        assert!(!schema("pos-int?").validate(&edn("0")));
+        assert!(schema("nat-int?").validate(&edn("0")));
+        assert!(schema("any?").validate(&EDN::Nil));
+        assert!(!schema("some?").validate(&EDN::Nil));
+    }
+
+    #[test]
+    fn test_min_max() {
+        let port = schema("[:int {:min 1 :max 65535}]");
+        assert!(port.validate(&edn("8080")));
+        assert!(!port.validate(&edn("0")));
+        assert!(!port.validate(&edn("70000")));
+
+        let name = schema("[:string {:min 1}]");
+        assert!(!name.validate(&edn("\"\"")));
+
+        let pair = schema("[:vector {:max 2} int?]");
+        assert!(pair.validate(&edn("[1 2]")));
+        assert!(!pair.validate(&edn("[1 2 3]")));
+    }
+
+    #[test]
+    fn test_map_schema() {
+        let s = schema(
+            "[:map [:port int?] [:host string?] [:tags {:optional true} [:vector keyword?]]]",
+        );
+        assert!(s.validate(&edn("{:port 80 :host \"localhost\"}")));
+        assert!(s.validate(&edn(
+            "{:port 80 :host \"localhost\" :tags [:a :b] :extra 1}"
+        )));
+        assert!(!s.validate(&edn("{:port 80}")));
+        assert!(!s.validate(&edn("[:port 80]")));
+
+        let closed = schema("[:map {:closed true} [:a int?]]");
+        assert!(closed.validate(&edn("{:a 1}")));
+        assert!(!closed.validate(&edn("{:a 1 :b 2}")));
+    }
+
+    #[test]
+    fn test_explain_paths() {
+        let s = schema("[:map [:server [:map [:port int?] [:hosts [:vector string?]]]]]");
+        let errors = s.explain(&edn("{:server {:port \"80\" :hosts [\"a\" 1]}}"));
+        assert_eq!(errors.len(), 2);
+
+        let port = errors
+            .iter()
+            .find(|e| e.path == vec![edn(":server"), edn(":port")])
+            .unwrap();
+        assert_eq!(port.value, edn("\"80\""));
+        assert_eq!(port.schema, edn("int?"));
+        assert_eq!(port.message, "should be an int");
+
+        let host = errors
+            .iter()
+            .find(|e| e.path == vec![edn(":server"), edn(":hosts"), edn("1")])
+            .unwrap();
+        assert_eq!(host.value, edn("1"));
+
+        let missing = s.explain(&edn("{:server {:hosts []}}"));
+        assert_eq!(missing[0].path, vec![edn(":server"), edn(":port")]);
+        assert_eq!(missing[0].message, "missing required key");
+    }
+
+    #[test]
+    fn test_combinators() {
+        assert!(schema("[:maybe int?]").validate(&EDN::Nil));
+        assert!(!schema("[:maybe int?]").validate(&edn("\"x\"")));
+        assert!(schema("[:or int? string?]").validate(&edn("\"x\"")));
+        assert!(!schema("[:or int? string?]").validate(&edn(":x")));
+        assert!(!schema("[:and int? [:int {:min 10}]]").validate(&edn("5")));
+        assert!(schema("[:enum :dev :prod]").validate(&edn(":dev")));
+        assert!(!schema("[:enum :dev :prod]").validate(&edn(":test")));
+        assert!(schema("[:= 1]").validate(&edn("1")));
+        assert!(schema("[:tuple int? string?]").validate(&edn("[1 \"a\"]")));
+        assert!(!schema("[:tuple int? string?]").validate(&edn("[1]")));
+        assert!(schema("[:map-of keyword? int?]").validate(&edn("{:a 1 :b 2}")));
+        assert!(!schema("[:map-of keyword? int?]").validate(&edn("{:a \"1\"}")));
+        assert!(schema("[:set int?]").validate(&edn("#{1 2}")));
+        assert!(schema("[:sequential int?]").validate(&edn("(1 2)")));
+    }
+
+    #[test]
+    fn test_coerce() {
+        let s = schema(
+            "[:map [:port int?] [:debug boolean?] [:env [:enum :dev :prod]] [:ratio double?]]",
+        );
+        let coerced = s
+            .coerce(&edn(
+                "{:port \"8080\" :debug \"true\" :env \"prod\" :ratio 1}",
+            ))
+            .unwrap();
+        assert_eq!(coerced[&EDN::keyword("port")], edn("8080"));
+        assert_eq!(coerced[&EDN::keyword("debug")], EDN::Bool(true));
+        assert_eq!(coerced[&EDN::keyword("env")], edn(":prod"));
+        assert!(coerced[&EDN::keyword("ratio")].as_bigdecimal().is_some());
+
+        let errors = s
+            .coerce(&edn("{:port \"eighty\" :debug true :env :dev :ratio 1.0}"))
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, vec![edn(":port")]);
+
+        assert_eq!(
+            schema("[:vector int?]").coerce(&edn("[\"1\" 2]")).unwrap(),
+            edn("[1 2]")
+        );
+        assert_eq!(
+            schema("[:or int? keyword?]").coerce(&edn("\"5\"")).unwrap(),
+            edn("5")
+        );
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(Schema::parse(&edn("bogus?")).is_err());
+        assert!(Schema::parse(&edn("[:vector]")).is_err());
+        assert!(Schema::parse(&edn("[:map [:a]]")).is_err());
+        assert!(Schema::parse(&edn("42")).is_err());
+    }
+
+    #[test]
+    fn test_natives() {
+        let env = create_env();
+        let run = |s: &str| eval(read_string(s).unwrap(), &env);
+
+        assert_eq!(
+            run("(schema/validate (quote [:map [:port int?]]) {:port 1})").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(schema/explain 'int? 1)").unwrap(),
+            Value::EDN(EDN::Nil)
+        );
+        if let Value::EDN(EDN::Vector(errors)) =
+            run("(schema/explain (quote [:map [:port int?]]) {})").unwrap()
+        {
+            assert_eq!(errors[0][&EDN::keyword("path")], edn("[:port]"));
+        } else {
+            panic!("Expected a vector of errors");
+        }
+        assert_eq!(
+            run("(schema/coerce (quote [:map [:port int?]]) {:port \"80\"})").unwrap(),
+            Value::EDN(edn("{:port 80}"))
+        );
+        assert!(run("(schema/coerce 'int? \"x\")").is_err());
+    }
+
+    #[test]
+    fn test_sorted_and_other_collections() {
+        let env = create_env();
+        let valid = |schema: &str, value: &str| {
+            let form = format!("(schema/validate (quote {}) {})", schema, value);
+            eval(read_string(&form).unwrap(), &env).unwrap() == Value::EDN(EDN::Bool(true))
+        };
+
+        assert!(valid("map?", "(sorted-map :a 1)"));
+        assert!(valid("set?", "(sorted-set 1 2)"));
+        assert!(valid("vector?", "(catvec [1] [2])"));
+        assert!(valid("coll?", "(sorted-map :a 1)"));
+        assert!(valid("coll?", "(sorted-set 1)"));
+        assert!(valid(
+            "coll?",
+            "(conj clojure.lang.PersistentQueue/EMPTY 1)"
+        ));
+        assert!(valid("coll?", "(catvec [1] [2])"));
+        assert!(valid("coll?", "(range 3)"));
+        assert!(!valid("map?", "(sorted-set 1)"));
+        assert!(!valid("set?", "(sorted-map :a 1)"));
+
+        assert!(valid("[:map [:a int?]]", "(sorted-map :a 1)"));
+        assert!(!valid("[:map [:a int?]]", "(sorted-map :a :x)"));
+        assert!(!valid(
+            "[:map {:closed true} [:a int?]]",
+            "(sorted-map :a 1 :b 2)"
+        ));
+        assert!(valid("[:map-of keyword? int?]", "(sorted-map :a 1 :b 2)"));
+        assert!(valid("[:set int?]", "(sorted-set 1 2)"));
+        assert!(!valid("[:set int?]", "(sorted-set :a)"));
+
+        let coerce = |schema: &str, value: &str| {
+            let form = format!("(schema/coerce (quote {}) {})", schema, value);
+            eval(read_string(&form).unwrap(), &env).unwrap().to_string()
+        };
+        assert_eq!(
+            coerce("[:map [:port int?]]", "(sorted-map :port \"80\")"),
+            "{:port 80}"
+        );
+        assert_eq!(
+            coerce("[:map-of keyword? int?]", "(sorted-map :b \"2\" :a \"1\")"),
+            "{:a 1, :b 2}"
+        );
+        assert_eq!(coerce("[:set int?]", "(sorted-set \"1\")"), "#{1}");
+    }
+}
@@ -0,0 +1,159 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+use yinyang::immutant::map::PersistentHashMap;
+use yinyang::immutant::queue::PersistentQueue;
+use yinyang::seq::{ISeq, Seqable};
+
+mod common;
+use common::{edn, run, run_str};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_over_collections() {
+        assert_eq!(run_str("(seq [1 2 3])"), "(1 2 3)");
+        assert_eq!(run_str("(seq (quote (1 2)))"), "(1 2)");
+        assert_eq!(run_str("(seq {:a 1})"), "([:a 1])");
+        assert_eq!(run_str("(seq (sorted-set 3 1 2))"), "(1 2 3)");
+        assert_eq!(
+            run_str("(seq \"héllo\")"),
+            "(\"h\" \"é\" \"l\" \"l\" \"o\")"
+        );
+        assert_eq!(run_str("(seq (conj PersistentQueue/EMPTY 1 2))"), "(1 2)");
+        assert_eq!(run_str("(seq (catvec [1] [2]))"), "(1 2)");
+        assert_eq!(run("(seq [])"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(run("(seq nil)"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(run("(seq \"\")"), Ok(Value::EDN(EDN::Nil)));
+        assert!(run("(seq 1)").is_err());
+    }
+
+    #[test]
+    fn test_seq_walks_every_entry() {
+        // Enough entries for several HAMT levels and a deep sorted tree
+        let n: i64 = 2000;
+        let ints = || (0..n).map(|i| EDN::Integer(i.into()));
+        let mut map = PersistentHashMap::new();
+        for i in ints() {
+            map = map.assoc(i.clone(), i);
+        }
+        let sorted = |empty: &str, step: &str| {
+            let src = format!("(loop [c ({empty})] (if (< (count c) {n}) (recur {step}) c))");
+            match run(&src) {
+                Ok(Value::EDN(coll)) => coll,
+                other => panic!("unexpected {other:?}"),
+            }
+        };
+        let queue = ints().collect::<PersistentQueue<_>>().pop();
+        let collections = [
+            (EDN::Map(map), 0),
+            (EDN::Set(ints().collect()), 0),
+            (sorted("sorted-map", "(assoc c (count c) 0)"), 0),
+            (sorted("sorted-set", "(conj c (count c))"), 0),
+            // Items in both the front list and the rear vector
+            (EDN::Queue(queue.conj(EDN::Integer(n.into()))), 1),
+        ];
+        for (coll, start) in collections {
+            let mut seen = coll
+                .seq()
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|item| match item.unwrap() {
+                    EDN::Vector(entry) => entry[0].clone(),
+                    item => item,
+                })
+                .map(|item| match item {
+                    EDN::Integer(i) => i64::try_from(i).unwrap(),
+                    item => panic!("unexpected item {item}"),
+                })
+                .collect::<Vec<_>>();
+            seen.sort();
+            assert_eq!(seen, (start..n + start).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_first_rest_next() {
+        assert_eq!(run("(first [1 2 3])"), Ok(Value::EDN(edn("1"))));
+        assert_eq!(run("(first {:a 1})"), Ok(Value::EDN(edn("[:a 1]"))));
+        assert_eq!(run("(first \"abc\")"), Ok(Value::EDN(edn("\"a\""))));
+        assert_eq!(run("(first nil)"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(run_str("(rest [1 2 3])"), "(2 3)");
+        assert_eq!(run_str("(rest [1])"), "()");
+        assert_eq!(run_str("(rest nil)"), "()");
+        assert_eq!(run_str("(next [1 2 3])"), "(2 3)");
+        assert_eq!(run("(next [1])"), Ok(Value::EDN(EDN::Nil)));
+        assert_eq!(
+            run("(first (rest (rest \"abc\")))"),
+            Ok(Value::EDN(edn("\"c\"")))
+        );
+    }
+
+    #[test]
+    fn test_cons() {
+        assert_eq!(run_str("(cons 0 [1 2])"), "(0 1 2)");
+        assert_eq!(run_str("(cons 0 nil)"), "(0)");
+        assert_eq!(run_str("(cons 0 (cons 1 #{}))"), "(0 1)");
+        assert_eq!(
+            run("(first (rest (cons 0 [1 2])))"),
+            Ok(Value::EDN(edn("1")))
+        );
+        assert_eq!(run_str("(conj (rest [1 2]) 0)"), "(0 2)");
+    }
+
+    #[test]
+    fn test_count_and_empty() {
+        assert_eq!(run("(count [1 2 3])"), Ok(Value::EDN(edn("3"))));
+        assert_eq!(run("(count {:a 1 :b 2})"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run("(count \"héllo\")"), Ok(Value::EDN(edn("5"))));
+        assert_eq!(run("(count nil)"), Ok(Value::EDN(edn("0"))));
+        assert_eq!(
+            run("(count (cons 1 (rest [1 2 3])))"),
+            Ok(Value::EDN(edn("3")))
+        );
+        assert!(run("(count 1)").is_err());
+        assert_eq!(run("(empty? [])"), Ok(Value::EDN(EDN::Bool(true))));
+        assert_eq!(run("(empty? nil)"), Ok(Value::EDN(EDN::Bool(true))));
+        assert_eq!(run("(empty? (rest [1]))"), Ok(Value::EDN(EDN::Bool(true))));
+        assert_eq!(run("(empty? #{1})"), Ok(Value::EDN(EDN::Bool(false))));
+    }
+
+    #[test]
+    fn test_seqs_equal_lists() {
+        assert_eq!(
+            run("(= (rest [1 2 3]) (quote (2 3)))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(= (seq [1 2]) (seq (quote (1 2))))"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run("(= (seq [1 2]) (seq [2 1]))"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+        assert_eq!(
+            run("(get (hash-map (quote (1 2)) :found) (seq [1 2]))"),
+            Ok(Value::EDN(edn(":found")))
+        );
+    }
+
+    #[test]
+    fn test_trait_layer() {
+        let s = edn("[1 2 3]").seq().unwrap().unwrap();
+        assert_eq!(s.first(), Ok(edn("1")));
+        assert_eq!(s.rest().unwrap().first(), Ok(edn("2")));
+        assert_eq!(s.count(), Ok(3));
+        let consed = s.cons(edn("0"));
+        assert_eq!(
+            consed.iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![edn("0"), edn("1"), edn("2"), edn("3")]
+        );
+        assert_eq!(
+            s.next().unwrap().unwrap().next().unwrap().unwrap().next(),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_literals() {
+        let s = edn("#{1 2 3 2}");
+        assert_eq!(s.as_set().unwrap().len(), 3);
+        assert_eq!(s, edn("#{3 2 1}"));
+        assert_eq!(s.get(&edn("2")), Some(&edn("2")));
+        assert_eq!(s.get(&edn("4")), None);
+        assert_eq!(edn("#{:a}").to_string(), "#{:a}");
+    }
+
+    #[test]
+    fn test_conj_disj() {
+        assert_eq!(
+            run("(conj #{1 2} 2 3)").unwrap(),
+            Value::EDN(edn("#{1 2 3}"))
+        );
+        assert_eq!(
+            run("(disj #{1 2 3} 1 4)").unwrap(),
+            Value::EDN(edn("#{2 3}"))
+        );
+        assert_eq!(run("(conj [1] 2)").unwrap(), Value::EDN(edn("[1 2]")));
+        assert_eq!(
+            run("(conj {:a 1} [:b 2])").unwrap(),
+            Value::EDN(edn("{:a 1 :b 2}"))
+        );
+        assert_eq!(
+            run("(= (hash-set 3 1 2) (conj #{} 1 2 3))").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(contains? #{:a :b} :a)").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert!(run("(disj [1 2] 1)").is_err());
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        assert_eq!(
+            run("(set/union #{1 2} #{2 3} nil)").unwrap(),
+            Value::EDN(edn("#{1 2 3}"))
+        );
+        assert_eq!(
+            run("(set/intersection #{1 2 3} #{2 3 4} #{3})").unwrap(),
+            Value::EDN(edn("#{3}"))
+        );
+        assert_eq!(
+            run("(set/difference #{1 2 3} #{2} #{3})").unwrap(),
+            Value::EDN(edn("#{1}"))
+        );
+        assert_eq!(
+            run("(set/subset? #{1} #{1 2})").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(set/superset? #{1} #{1 2})").unwrap(),
+            Value::EDN(EDN::Bool(false))
+        );
+        assert!(run("(set/union #{1} [2])").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(s: &str) -> String {
+        match run(s).unwrap() {
+            Value::EDN(edn) => edn.to_string(),
+            other => panic!("Expected EDN, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(edn("1").compare(&edn("2.5")), Ok(std::cmp::Ordering::Less));
+        assert_eq!(edn("nil").compare(&edn(":a")), Ok(std::cmp::Ordering::Less));
+        assert_eq!(
+            edn("[1 2]").compare(&edn("[1 1 1]")),
+            Ok(std::cmp::Ordering::Less)
+        );
+        assert_eq!(
+            edn("[2 1]").compare(&edn("[1 2]")),
+            Ok(std::cmp::Ordering::Greater)
+        );
+        assert!(edn("1").compare(&edn(":a")).is_err());
+        assert_eq!(printed("(compare \"b\" \"a\")"), "1");
+    }
+
+    #[test]
+    fn test_sorted_printing() {
+        assert_eq!(printed("(sorted-map :c 3 :a 1 :b 2)"), "{:a 1, :b 2, :c 3}");
+        assert_eq!(printed("(sorted-set 5 1 3 1)"), "#{1 3 5}");
+        assert_eq!(
+            printed("(assoc (sorted-map 3 :c 1 :a) 2 :b)"),
+            "{1 :a, 2 :b, 3 :c}"
+        );
+        assert_eq!(printed("(conj (sorted-set 3 1) 2)"), "#{1 2 3}");
+        assert_eq!(printed("(disj (sorted-set 3 1 2) 2)"), "#{1 3}");
+        assert_eq!(printed("(dissoc (sorted-map 1 1 2 2) 1)"), "{2 2}");
+    }
+
+    #[test]
+    fn test_custom_comparators() {
+        assert_eq!(printed("(sorted-set-by > 1 3 2)"), "#{3 2 1}");
+        assert_eq!(
+            printed("(sorted-map-by (fn [a b] (- b a)) 1 :a 3 :c 2 :b)"),
+            "{3 :c, 2 :b, 1 :a}"
+        );
+        assert!(run("(sorted-set-by > 1 :a)").is_err());
+        assert!(run("(sorted-set 1 :a)").is_err());
+    }
+
+    #[test]
+    fn test_equality_with_unsorted() {
+        assert_eq!(
+            run("(= (sorted-map :b 2 :a 1) {:a 1 :b 2})").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(= (sorted-set 1 2) #{2 1})").unwrap(),
+            Value::EDN(EDN::Bool(true))
+        );
+        assert_eq!(
+            run("(get (sorted-map :a 1) :a)").unwrap(),
+            Value::EDN(edn("1"))
+        );
+        assert_eq!(
+            run("(contains? (sorted-set 1 2) 3)").unwrap(),
+            Value::EDN(EDN::Bool(false))
+        );
+    }
+
+    #[test]
+    fn test_lookup_with_incomparable_keys() {
+        let error = |s: &str| run(s).unwrap_err().to_string();
+        assert!(error("(get (sorted-map 1 :a) :x)").contains("Cannot compare"));
+        assert!(error("(get (sorted-map 1 :a) :x :default)").contains("Cannot compare"));
+        assert!(error("(contains? (sorted-set 1 2) :x)").contains("Cannot compare"));
+        assert!(edn("(1)").lookup(&edn(":x")).unwrap().is_none());
+
+        // EDN::get reads a key the comparator rejects as absent
+        let map = match run("(sorted-map 1 :a)").unwrap() {
+            Value::EDN(edn) => edn,
+            other => panic!("Expected EDN, got {}", other),
+        };
+        assert!(map.lookup(&edn(":x")).is_err());
+        assert_eq!(map.get(&edn(":x")), None);
+        assert_eq!(map.get(&edn("1")), Some(&edn(":a")));
+    }
+
+    #[test]
+    fn test_subseq() {
+        let s = "(sorted-set 1 2 3 4 5 6)";
+        assert_eq!(printed(&format!("(subseq {} > 3)", s)), "(4 5 6)");
+        assert_eq!(printed(&format!("(subseq {} >= 3)", s)), "(3 4 5 6)");
+        assert_eq!(printed(&format!("(subseq {} < 3)", s)), "(1 2)");
+        assert_eq!(printed(&format!("(subseq {} >= 2 < 5)", s)), "(2 3 4)");
+        assert_eq!(printed(&format!("(rsubseq {} < 3)", s)), "(2 1)");
+        assert_eq!(printed(&format!("(rsubseq {} >= 5)", s)), "(6 5)");
+        assert_eq!(printed(&format!("(rsubseq {} > 2 <= 4)", s)), "(4 3)");
+        assert_eq!(printed(&format!("(subseq {} > 10)", s)), "()");
+        assert_eq!(
+            printed("(subseq (sorted-map :a 1 :b 2 :c 3) >= :b)"),
+            "([:b 2] [:c 3])"
+        );
+        assert!(run("(subseq #{1 2} > 1)").is_err());
+    }
+}
@@ -2,10 +2,13 @@ use bigdecimal::BigDecimal;
 use num_bigint::BigInt;
 use std::collections::HashMap;
 use std::str::FromStr;
-use yinyang::clojure::{eval, read_string, Value};
+use std::sync::{Arc, RwLock};
+use yinyang::clojure::{eval, read_string, Environment, EvalError, Value};
 use yinyang::core::register_native_fn;
 use yinyang::edn::*;
 use yinyang::immutant::list::List;
+use yinyang::immutant::map::PersistentHashMap;
+use yinyang::immutant::vector::PersistentVector;
 
 #[cfg(test)]
 mod tests {
@@ -32,7 +35,7 @@ mod tests {
         );
         assert_eq!(
             read_string("symbol").unwrap(),
-            EDN::Symbol("symbol".to_string())
+            EDN::Symbol("symbol".to_string(), None)
         );
     }
 
@@ -52,18 +55,17 @@ mod tests {
     fn test_vector_parsing() {
         assert_eq!(
             read_string("[1 2 3]").unwrap(),
-            EDN::Vector(vec![
+            EDN::Vector(PersistentVector::from(vec![
                 EDN::Integer(BigInt::from(1)),
                 EDN::Integer(BigInt::from(2)),
                 EDN::Integer(BigInt::from(3)),
-            ])
+            ]))
         );
     }
 
     #[test]
     fn test_map_parsing() {
-        let mut expected_map = HashMap::new();
-        expected_map.insert(
+        let expected_map = PersistentHashMap::new().assoc(
             EDN::Keyword(":key".to_string()),
             EDN::String("value".to_string()),
         );
@@ -83,21 +85,15 @@ mod tests {
         if let Ok(EDN::Set(set)) = result {
             assert_eq!(set.len(), 2);
 
-            let mut inner_vec = Vec::new();
-            inner_vec.push(EDN::Integer(3.into()));
-            inner_vec.push(EDN::Integer(4.into()));
+            let inner_vec = vec![EDN::Integer(3.into()), EDN::Integer(4.into())];
+            let inner_list = List::from_vec(vec![
+                EDN::Integer(2.into()),
+                EDN::Vector(inner_vec.into()),
+                EDN::Integer(5.into()),
+            ]);
 
-            let mut inner_list = Vec::new();
-            inner_list.push(EDN::Integer(2.into()));
-            inner_list.push(EDN::Vector(inner_vec));
-            inner_list.push(EDN::Integer(5.into()));
-
-        //TODO: fix this test
-        // let mut hset = HashSet::new();
-        // hset.insert(EDN::Integer(1.into()));
-        // hset.insert(EDN::List(Box::new(inner_list)));
-
-        // assert_eq!(hset, set);
+            assert!(set.contains(&EDN::Integer(1.into())));
+            assert!(set.contains(&EDN::List(Box::new(inner_list))));
         } else {
             panic!("Expected Set");
         }
@@ -112,69 +108,52 @@ mod tests {
 
     #[test]
     fn test_special_form_quote() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
         let ast = read_string("(quote a)").unwrap();
-        let a = eval(ast, &mut env).unwrap();
-        assert_eq!(Value::EDN(EDN::Symbol("a".to_string())), a);
+        let a = eval(ast, &env).unwrap();
+        assert_eq!(Value::EDN(EDN::Symbol("a".to_string(), None)), a);
 
         let ast2 = read_string("'a").unwrap();
-        let a2 = eval(ast2, &mut env).unwrap();
-        assert_eq!(Value::EDN(EDN::Symbol("a".to_string())), a2);
+        let a2 = eval(ast2, &env).unwrap();
+        assert_eq!(Value::EDN(EDN::Symbol("a".to_string(), None)), a2);
     }
 
     #[test]
     fn test_special_form_do() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
         let ast = read_string("(do 1 2 3)").unwrap();
-        let result = eval(ast, &mut env).unwrap();
+        let result = eval(ast, &env).unwrap();
         assert_eq!(Value::EDN(EDN::Integer(BigInt::from(3))), result);
     }
 
     #[test]
     fn test_special_form_if() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
         let ast = read_string("(if true 1 2)").unwrap();
-        let result = eval(ast, &mut env).unwrap();
+        let result = eval(ast, &env).unwrap();
         assert_eq!(Value::EDN(EDN::Integer(BigInt::from(1))), result);
     }
 
     #[test]
     fn test_special_form_def() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
 
-        let a_sexp = List::singleton(EDN::Symbol("def".to_string()))
-            .append(EDN::Symbol("pi".to_string()))
+        let a_sexp = List::singleton(EDN::Symbol("def".to_string(), None))
+            .append(EDN::Symbol("pi".to_string(), None))
             .append(EDN::Float(BigDecimal::from_str("3.14").unwrap()));
 
         let def_expr = EDN::List(Box::new(a_sexp));
 
-        let a_var = eval(def_expr, &mut env).unwrap();
+        let a_var = eval(def_expr, &env).unwrap();
 
-        assert!(matches!(
-            a_var,
-            Value::Var {
-                ns: _,
-                name: _,
-                value: _
-            }
-        ));
-
-        if let Value::Var {
-            ref ns,
-            ref name,
-            ref value,
-        } = a_var
-        {
+        assert!(matches!(a_var, Value::Var { ns: _, name: _ }));
+
+        if let Value::Var { ref ns, ref name } = a_var {
             assert_eq!(ns, "user");
             assert_eq!(name, "pi");
-            if let Value::EDN(ref edn) = **value {
-                if let EDN::Float(pi) = edn {
-                    assert_eq!(*pi, BigDecimal::from_str("3.14").unwrap());
-                }
-            }
 
-            assert!(env.contains_key("pi"));
-            if let Some(Value::EDN(EDN::Float(val))) = env.get("pi") {
+            assert!(env.read().unwrap().contains_key("pi"));
+            if let Some(Value::EDN(EDN::Float(val))) = env.read().unwrap().get("pi") {
                 assert_eq!(val, &BigDecimal::from_str("3.14").unwrap());
             } else {
                 panic!("Expected pi to be bound to float 3.14");
@@ -184,10 +163,10 @@ mod tests {
 
     #[test]
     fn test_special_form_let() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
 
         let let_expr = read_string("(let [pi 3.14] pi)").unwrap();
-        let result = eval(let_expr, &mut env).unwrap();
+        let result = eval(let_expr, &env).unwrap();
 
         assert_eq!(
             result,
@@ -195,42 +174,39 @@ mod tests {
         );
 
         // Verify binding was local
-        assert!(env.get("pi").is_none());
+        assert!(env.read().unwrap().get("pi").is_none());
     }
 
     #[test]
     #[allow(unused_variables)]
     fn test_call_lambda() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
         let ast = read_string("(def one (fn [] 1))").unwrap();
-        let one_fn = eval(ast, &mut env).unwrap();
+        let one_fn = eval(ast, &env).unwrap();
         let call_one = read_string("(one)").unwrap();
-        let r = eval(call_one, &mut env).unwrap();
-        if let Value::EDN(edn) = r {
-            match edn {
-                EDN::Integer(i) => {
-                    assert_eq!(BigInt::from(1), i);
-                }
-                _ => {}
-            }
+        let r = eval(call_one, &env).unwrap();
+        if let Value::EDN(EDN::Integer(i)) = r {
+            assert_eq!(BigInt::from(1), i);
         }
     }
 
     #[test]
     fn test_call_native() {
-        let mut env = HashMap::new();
+        let env: Environment = Arc::new(RwLock::new(HashMap::new()));
 
-        let echo: fn(Vec<Value>) -> Result<Value, String> = |args: Vec<Value>| {
+        let echo: fn(Vec<Value>) -> Result<Value, EvalError> = |args: Vec<Value>| {
             if args.len() != 1 {
-                return Err("Expected exactly one argument".to_string());
+                return Err(EvalError::Native(
+                    "Expected exactly one argument".to_string(),
+                ));
             }
             Ok(args[0].clone())
         };
 
-        register_native_fn(&mut env, "echo", echo);
+        register_native_fn(&mut env.write().unwrap(), "echo", echo);
 
         let ast = read_string("(echo 123)").unwrap();
-        let result = eval(ast, &mut env).unwrap();
+        let result = eval(ast, &env).unwrap();
 
         if let Value::EDN(EDN::Integer(i)) = result {
             assert_eq!(BigInt::from(123), i);
@@ -0,0 +1,83 @@
+use yinyang::clojure::{eval, read_string, Value};
+use yinyang::edn::EDN;
+use yinyang::immutant::list::List;
+use yinyang::immutant::map::PersistentHashMap;
+use yinyang::immutant::queue::PersistentQueue;
+use yinyang::immutant::rrb::RrbVector;
+use yinyang::immutant::set::PersistentHashSet;
+use yinyang::immutant::sorted::{PersistentTreeMap, PersistentTreeSet};
+use yinyang::immutant::vector::PersistentVector;
+use yinyang::repl::create_env;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_collections_are_send_and_sync() {
+        assert_send_sync::<List<EDN>>();
+        assert_send_sync::<PersistentVector<EDN>>();
+        assert_send_sync::<RrbVector<EDN>>();
+        assert_send_sync::<PersistentHashMap<EDN, EDN>>();
+        assert_send_sync::<PersistentHashSet<EDN>>();
+        assert_send_sync::<PersistentTreeMap<EDN, EDN>>();
+        assert_send_sync::<PersistentTreeSet<EDN>>();
+        assert_send_sync::<PersistentQueue<EDN>>();
+        assert_send_sync::<EDN>();
+        assert_send_sync::<Value>();
+    }
+
+    #[test]
+    fn test_workers_share_one_value() {
+        let shared: PersistentVector<i64> = (0..10_000).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|worker| {
+                let v = shared.clone();
+                thread::spawn(move || {
+                    let v = v.assoc(0, worker).unwrap().conj(worker);
+                    (v[0], v.len(), v.iter().skip(1).take(9_999).sum::<i64>())
+                })
+            })
+            .collect();
+        for (worker, handle) in handles.into_iter().enumerate() {
+            let (first, len, sum) = handle.join().unwrap();
+            assert_eq!(first, worker as i64);
+            assert_eq!(len, 10_001);
+            assert_eq!(sum, (1..10_000).sum::<i64>());
+        }
+        assert_eq!(shared[0], 0);
+        assert_eq!(shared.len(), 10_000);
+    }
+
+    #[test]
+    fn test_values_cross_threads() {
+        let env = create_env();
+        let value = eval(
+            read_string("(assoc (hash-map :a [1 2]) :b (hash-set 1 2))").unwrap(),
+            &env,
+        )
+        .unwrap();
+        let sent = value.clone();
+        let worker_env = env.clone();
+        let result = thread::spawn(move || {
+            let mut local = worker_env.read().unwrap().clone();
+            local.insert("m".to_string(), sent);
+            let local = std::sync::Arc::new(std::sync::RwLock::new(local));
+            eval(read_string("(get m :a)").unwrap(), &local)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(result, Ok(Value::EDN(read_string("[1 2]").unwrap())));
+        assert_eq!(
+            value,
+            eval(
+                read_string("(assoc (hash-map :a [1 2]) :b (hash-set 1 2))").unwrap(),
+                &env
+            )
+            .unwrap()
+        );
+    }
+}
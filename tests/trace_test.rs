@@ -0,0 +1,60 @@
+use tracing_subscriber::layer::SubscriberExt;
+use yinyang::clojure::{eval, read_string};
+use yinyang::repl::create_env;
+use yinyang::trace::CallTree;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traced(forms: &[&str]) -> String {
+        let env = create_env();
+        let (tree, buffer) = CallTree::buffered();
+        let subscriber = tracing_subscriber::registry().with(tree);
+        tracing::subscriber::with_default(subscriber, || {
+            for form in forms {
+                let _ = eval(read_string(form).unwrap(), &env);
+            }
+        });
+        let output = buffer.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_call_tree() {
+        let output = traced(&["(def inc2 (fn [x] (+ x 2)))", "(inc2 (+ 1 0))"]);
+        assert_eq!(output, "(+ 1 0)\n=> 1\n(inc2 1)\n  (+ 1 2)\n  => 3\n=> 3\n");
+    }
+
+    #[test]
+    fn test_errors_are_traced() {
+        let output = traced(&["(def f (fn [] (throw :boom)))", "(f)"]);
+        assert_eq!(output, "(f)\n!! Uncaught: :boom\n");
+    }
+
+    #[test]
+    fn test_lazy_seqs_are_not_realized() {
+        let output = traced(&[
+            "(def naturals (fn [] (range)))",
+            "(def xs (naturals))",
+            "(first (take 3 xs))",
+            "(take 1 xs)",
+            "(doall (take 2 xs))",
+        ]);
+        assert_eq!(
+            output,
+            "(naturals)\n  (range)\n  => #<lazy-seq>\n=> #<lazy-seq>\n\
+             (take 3 #<lazy-seq>)\n=> #<lazy-seq>\n\
+             (first #<lazy-seq>)\n=> 0\n\
+             (take 1 (0 1 2 3 4 5 6 7 8 9 ...))\n=> #<lazy-seq>\n\
+             (take 2 (0 1 2 3 4 5 6 7 8 9 ...))\n=> #<lazy-seq>\n\
+             (doall #<lazy-seq>)\n=> (0 1)\n"
+        );
+    }
+
+    #[test]
+    fn test_silent_without_subscriber() {
+        let env = create_env();
+        assert!(eval(read_string("(+ 1 2)").unwrap(), &env).is_ok());
+    }
+}
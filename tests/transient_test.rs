@@ -0,0 +1,94 @@
+use yinyang::clojure::{eval, read_string, Value};
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_round_trips() {
+        assert_eq!(
+            run("(persistent! (conj! (transient [1 2]) 3 4))"),
+            Ok(Value::EDN(edn("[1 2 3 4]")))
+        );
+        assert_eq!(
+            run("(persistent! (pop! (assoc! (transient [1 2 3]) 0 :a)))"),
+            Ok(Value::EDN(edn("[:a 2]")))
+        );
+        assert_eq!(
+            run("(persistent! (dissoc! (assoc! (transient {:a 1}) :b 2 :c 3) :a))"),
+            Ok(Value::EDN(edn("{:b 2 :c 3}")))
+        );
+        assert_eq!(
+            run("(persistent! (conj! (transient {}) [:a 1]))"),
+            Ok(Value::EDN(edn("{:a 1}")))
+        );
+        assert_eq!(
+            run("(persistent! (disj! (conj! (transient #{1}) 2 3) 1))"),
+            Ok(Value::EDN(edn("#{2 3}")))
+        );
+    }
+
+    #[test]
+    fn test_original_is_untouched() {
+        assert_eq!(
+            run("(let [v [1 2] t (conj! (transient v) 3)] v)"),
+            Ok(Value::EDN(edn("[1 2]")))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(run("(transient (quote (1 2)))").is_err());
+        assert!(run("(assoc! (transient [1 2]) 5 :x)").is_err());
+        assert!(run("(assoc! (transient #{1}) 0 :x)").is_err());
+        assert!(run("(dissoc! (transient [1]) 0)").is_err());
+        assert!(run("(disj! (transient {:a 1}) :a)").is_err());
+        assert!(run("(pop! (transient []))").is_err());
+        assert!(run("(pop! (transient {}))").is_err());
+        assert!(run("(conj! [1] 2)").is_err());
+    }
+
+    #[test]
+    fn test_use_after_persistent() {
+        let env = create_env();
+        let eval_str = |s: &str| eval(read_string(s).unwrap(), &env);
+        eval_str("(def t (transient [1]))").unwrap();
+        assert_eq!(eval_str("(persistent! t)"), Ok(Value::EDN(edn("[1]"))));
+        let err = eval_str("(conj! t 2)").unwrap_err();
+        assert!(err.to_string().contains("after persistent!"));
+        assert!(eval_str("(persistent! t)").is_err());
+    }
+
+    #[test]
+    fn test_owner_thread_checked() {
+        let t = run("(transient [1 2 3])").unwrap();
+        let env = create_env();
+        env.write().unwrap().insert("t".to_string(), t);
+        let other = env.clone();
+        let result = thread::spawn(move || eval(read_string("(conj! t 4)").unwrap(), &other))
+            .join()
+            .unwrap();
+        assert!(result.unwrap_err().to_string().contains("non-owner thread"));
+        assert_eq!(
+            eval(read_string("(persistent! (conj! t 4))").unwrap(), &env),
+            Ok(Value::EDN(edn("[1 2 3 4]")))
+        );
+    }
+
+    #[test]
+    fn test_large_literals() {
+        let items: Vec<String> = (0..2_000).map(|i| i.to_string()).collect();
+        let v = edn(&format!("[{}]", items.join(" ")));
+        assert_eq!(v.as_vector().unwrap().len(), 2_000);
+        assert_eq!(v.as_vector().unwrap()[1_999], edn("1999"));
+        let s = edn(&format!("#{{{}}}", items.join(" ")));
+        assert_eq!(s.as_set().unwrap().len(), 2_000);
+        let m = edn(&format!("{{{}}}", items.join(" ")));
+        assert_eq!(m.get(&edn("1998")), Some(&edn("1999")));
+    }
+}
@@ -0,0 +1,194 @@
+use std::thread;
+use yinyang::clojure::Value;
+use yinyang::edn::EDN;
+use yinyang::repl::create_env;
+
+mod common;
+use common::{edn, run_in, run_str_in};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_var_references() {
+        let env = create_env();
+        run_in(&env, "(def x 1)").unwrap();
+        assert_eq!(run_str_in(&env, "#'x"), "#'user/x");
+        assert_eq!(
+            run_in(&env, "(= (var x) #'x)"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(run_in(&env, "(var? #'x)"), Ok(Value::EDN(EDN::Bool(true))));
+        assert_eq!(run_in(&env, "(var? x)"), Ok(Value::EDN(EDN::Bool(false))));
+        assert!(run_in(&env, "#'nope")
+            .unwrap_err()
+            .to_string()
+            .contains("Unable to resolve var: nope"));
+
+        // A var reference sees later redefinitions, and so do closures
+        run_in(&env, "(def v #'x)").unwrap();
+        run_in(&env, "(defn get-x [] x)").unwrap();
+        run_in(&env, "(def x 2)").unwrap();
+        assert_eq!(run_in(&env, "(deref v)"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run_in(&env, "(var-get v)"), Ok(Value::EDN(edn("2"))));
+        assert_eq!(run_in(&env, "(get-x)"), Ok(Value::EDN(edn("2"))));
+
+        // Calling a var calls its current value
+        run_in(&env, "(def call-f #'get-x)").unwrap();
+        run_in(&env, "(defn get-x [] :redefined)").unwrap();
+        assert_eq!(run_in(&env, "(call-f)"), Ok(Value::EDN(edn(":redefined"))));
+    }
+
+    #[test]
+    fn test_unbound_vars() {
+        let env = create_env();
+        assert_eq!(run_str_in(&env, "(def later)"), "#'user/later");
+        assert_eq!(
+            run_in(&env, "(bound? #'later)"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+        assert!(run_in(&env, "later").is_err());
+        assert!(run_in(&env, "(var-get #'later)")
+            .unwrap_err()
+            .to_string()
+            .contains("unbound"));
+
+        run_in(&env, "(def later 1)").unwrap();
+        assert_eq!(
+            run_in(&env, "(bound? #'later)"),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        // Declaring a var again keeps its root
+        run_in(&env, "(def later)").unwrap();
+        assert_eq!(run_in(&env, "later"), Ok(Value::EDN(edn("1"))));
+    }
+
+    #[test]
+    fn test_binding() {
+        let env = create_env();
+        run_in(&env, "(def ^:dynamic *out* :stdout)").unwrap();
+        run_in(&env, "(defn show [] *out*)").unwrap();
+        assert_eq!(
+            run_str_in(
+                &env,
+                "(binding [*out* :buffer] (conj (conj [] (show)) (binding [*out* :inner] (show))))"
+            ),
+            "[:buffer :inner]"
+        );
+        assert_eq!(run_in(&env, "(show)"), Ok(Value::EDN(edn(":stdout"))));
+
+        // The binding is undone when the body fails
+        assert!(run_in(&env, "(binding [*out* :buffer] (undefined))").is_err());
+        assert_eq!(run_in(&env, "(show)"), Ok(Value::EDN(edn(":stdout"))));
+
+        run_in(&env, "(def ^{:dynamic true} *depth* 0)").unwrap();
+        assert_eq!(
+            run_in(&env, "(binding [*depth* 1] *depth*)"),
+            Ok(Value::EDN(edn("1")))
+        );
+
+        run_in(&env, "(def static 1)").unwrap();
+        assert!(run_in(&env, "(binding [static 2] static)")
+            .unwrap_err()
+            .to_string()
+            .contains("Can't dynamically bind non-dynamic var: user/static"));
+        // Redefining without ^:dynamic makes a var static
+        run_in(&env, "(def *out* :stdout)").unwrap();
+        assert!(run_in(&env, "(binding [*out* 1] *out*)").is_err());
+        assert!(run_in(&env, "(binding [*out*] 1)").is_err());
+    }
+
+    #[test]
+    fn test_binding_is_thread_local() {
+        let env = create_env();
+        run_in(&env, "(def ^:dynamic *level* :root)").unwrap();
+        run_in(&env, "(defn level [] *level*)").unwrap();
+        let value = run_in(&env, "(binding [*level* :bound] (level))").unwrap();
+        assert_eq!(value, Value::EDN(edn(":bound")));
+
+        let bound = run_in(&env, "(binding [*level* :bound] (fn [] (level)))").unwrap();
+        let other_thread = thread::spawn(move || match bound {
+            Value::Function(f) => f.call(vec![]).unwrap(),
+            other => panic!("expected a function, got {}", other),
+        });
+        // Bindings don't travel with closures to other threads
+        assert_eq!(other_thread.join().unwrap(), Value::EDN(edn(":root")));
+    }
+
+    #[test]
+    fn test_binding_across_namespaces() {
+        let env = create_env();
+        run_in(&env, "(ns app.config)").unwrap();
+        run_in(&env, "(def ^:dynamic *verbose* false)").unwrap();
+        run_in(&env, "(defn verbose? [] *verbose*)").unwrap();
+        run_in(&env, "(in-ns 'user)").unwrap();
+        run_in(&env, "(alias 'config 'app.config)").unwrap();
+        assert_eq!(
+            run_str_in(&env, "#'config/*verbose*"),
+            "#'app.config/*verbose*"
+        );
+        assert_eq!(
+            run_in(
+                &env,
+                "(binding [config/*verbose* true] (app.config/verbose?))"
+            ),
+            Ok(Value::EDN(EDN::Bool(true)))
+        );
+        assert_eq!(
+            run_in(&env, "(app.config/verbose?)"),
+            Ok(Value::EDN(EDN::Bool(false)))
+        );
+    }
+
+    #[test]
+    fn test_alter_var_root_and_with_redefs() {
+        let env = create_env();
+        run_in(&env, "(def n 1)").unwrap();
+        assert_eq!(run_str_in(&env, "(alter-var-root #'n + 10)"), "11");
+        assert_eq!(run_str_in(&env, "n"), "11");
+        assert!(run_in(&env, "(alter-var-root #'n 1)").is_err());
+
+        run_in(&env, "(defn fetch [] :network)").unwrap();
+        run_in(&env, "(defn load-data [] (fetch))").unwrap();
+        assert_eq!(
+            run_str_in(
+                &env,
+                "(with-redefs [fetch (fn [] :stub) n 0] (conj (conj [] (load-data)) n))"
+            ),
+            "[:stub 0]"
+        );
+        assert_eq!(run_in(&env, "(load-data)"), Ok(Value::EDN(edn(":network"))));
+        assert_eq!(run_str_in(&env, "n"), "11");
+
+        // Roots are restored when the body fails
+        assert!(run_in(&env, "(with-redefs [n 0] (undefined))").is_err());
+        assert_eq!(run_str_in(&env, "n"), "11");
+    }
+
+    #[test]
+    fn test_alter_var_root_keeps_concurrent_updates() {
+        let env = create_env();
+        run_in(&env, "(def counter 0)").unwrap();
+        run_in(&env, "(defn bump [] (alter-var-root #'counter + 1))").unwrap();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let env = env.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        run_in(&env, "(bump)").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(run_str_in(&env, "counter"), "400");
+
+        // A fn root is replaced only when it is still the same fn
+        run_in(&env, "(def f (fn [] 1))").unwrap();
+        run_in(&env, "(alter-var-root #'f (fn [g] (fn [] (+ (g) 1))))").unwrap();
+        assert_eq!(run_str_in(&env, "(f)"), "2");
+    }
+}
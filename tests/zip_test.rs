@@ -0,0 +1,242 @@
+use yinyang::clojure::{eval, read_string, Value};
+use yinyang::edn::EDN;
+use yinyang::immutant::list::List;
+use yinyang::repl::create_env;
+use yinyang::zip::{Kind, Loc};
+
+mod common;
+use common::edn;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation() {
+        let root = Loc::new(edn("[1 [2 3] 4]"));
+        assert!(root.up().is_none());
+        assert!(root.left().is_none());
+
+        let one = root.down().unwrap();
+        assert_eq!(one.node(), &edn("1"));
+        assert!(one.down().is_none());
+        assert!(one.left().is_none());
+
+        let inner = one.right().unwrap();
+        assert_eq!(inner.node(), &edn("[2 3]"));
+        assert_eq!(inner.lefts(), &[edn("1")]);
+        assert_eq!(inner.rights(), &[edn("4")]);
+        assert_eq!(inner.down().unwrap().rightmost().node(), &edn("3"));
+        assert_eq!(inner.rightmost().leftmost().node(), &edn("1"));
+        assert_eq!(inner.up().unwrap().node(), root.node());
+    }
+
+    #[test]
+    fn test_depth_first_walk() {
+        let mut loc = Loc::new(edn("(a (b c) d)"));
+        let mut seen = Vec::new();
+        while !loc.is_end() {
+            seen.push(loc.node().to_string());
+            loc = loc.next();
+        }
+        assert_eq!(seen, ["(a (b c) d)", "a", "(b c)", "b", "c", "d"]);
+        assert_eq!(loc.root(), edn("(a (b c) d)"));
+        assert_eq!(loc.next(), loc);
+    }
+
+    #[test]
+    fn test_prev() {
+        let d = Loc::new(edn("(a (b c) d)")).down().unwrap().rightmost();
+        let c = d.prev().unwrap();
+        assert_eq!(c.node(), &edn("c"));
+        assert_eq!(c.prev().unwrap().node(), &edn("b"));
+        assert!(Loc::new(edn("(a)")).prev().is_none());
+    }
+
+    #[test]
+    fn test_editing() {
+        let loc = Loc::new(edn("[1 [2 3] 4]"))
+            .down()
+            .unwrap()
+            .right()
+            .unwrap();
+        let edited = loc
+            .down()
+            .unwrap()
+            .replace(edn("20"))
+            .unwrap()
+            .insert_left(edn("0"))
+            .unwrap()
+            .insert_right(edn(":x"))
+            .unwrap();
+        assert_eq!(edited.root(), edn("[1 [0 20 :x 3] 4]"));
+
+        let grown = loc
+            .append_child(edn("9"))
+            .unwrap()
+            .insert_child(edn("-1"))
+            .unwrap();
+        assert_eq!(grown.root(), edn("[1 [-1 2 3 9] 4]"));
+
+        let doubled = loc
+            .edit(|node| {
+                Ok(EDN::List(Box::new(List::from_vec(
+                    node.as_vector().unwrap().to_vec(),
+                ))))
+            })
+            .unwrap();
+        assert_eq!(doubled.root(), edn("[1 (2 3) 4]"));
+
+        assert!(Loc::new(edn("[1]")).insert_left(edn("0")).is_err());
+        assert!(Loc::new(edn("1")).append_child(edn("0")).is_err());
+    }
+
+    #[test]
+    fn test_remove() {
+        let three = Loc::new(edn("[1 [2 3] 4]"))
+            .down()
+            .unwrap()
+            .right()
+            .unwrap()
+            .down()
+            .unwrap()
+            .right()
+            .unwrap();
+        let removed = three.remove().unwrap();
+        assert_eq!(removed.node(), &edn("2"));
+        assert_eq!(removed.root(), edn("[1 [2] 4]"));
+
+        let four = Loc::new(edn("[1 [2 3] 4]")).down().unwrap().rightmost();
+        assert_eq!(four.remove().unwrap().node(), &edn("3"));
+
+        let only = Loc::new(edn("[[1]]")).down().unwrap().down().unwrap();
+        let parent = only.remove().unwrap();
+        assert_eq!(parent.node(), &edn("[]"));
+        assert_eq!(parent.root(), edn("[[]]"));
+
+        assert!(Loc::new(edn("[1]")).remove().is_err());
+    }
+
+    #[test]
+    fn test_maps_and_sets() {
+        let entry = Loc::new(edn("{:a 1}")).down().unwrap();
+        assert_eq!(entry.node(), &edn("[:a 1]"));
+        let updated = entry
+            .down()
+            .unwrap()
+            .right()
+            .unwrap()
+            .replace(edn("2"))
+            .unwrap();
+        assert_eq!(updated.root(), edn("{:a 2}"));
+        assert!(entry.replace(edn("3")).is_err());
+        assert_eq!(
+            entry.insert_right(edn("[:b 2]")).unwrap().root(),
+            edn("{:a 1 :b 2}")
+        );
+
+        let set = Loc::new(edn("#{1}")).append_child(edn("2")).unwrap();
+        assert_eq!(set.root(), edn("#{1 2}"));
+    }
+
+    #[test]
+    fn test_map_entries_keep_their_size() {
+        let value = Loc::new(edn("{:a 1}"))
+            .down()
+            .unwrap()
+            .down()
+            .unwrap()
+            .right()
+            .unwrap();
+        assert!(value.remove().is_err());
+        assert!(value.insert_right(edn("2")).is_err());
+        assert!(value.insert_left(edn("2")).is_err());
+        assert_eq!(value.replace(edn("2")).unwrap().root(), edn("{:a 2}"));
+
+        // A decoded path can't smuggle in a broken entry either
+        let mut broken = value.to_edn();
+        broken = broken.assoc_in(&[edn("1"), edn(":l")], edn("[]")).unwrap();
+        assert!(Loc::from_edn(&broken).is_err());
+
+        let env = create_env();
+        let error = eval(
+            read_string(
+                "(zip/root (zip/remove (zip/right (zip/down (zip/down (zip/edn-zip {:a 1}))))))",
+            )
+            .unwrap(),
+            &env,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Map entries must stay [key value] vectors"));
+    }
+
+    #[test]
+    fn test_zipper_kinds() {
+        let env = create_env();
+        let run = |s: &str| eval(read_string(s).unwrap(), &env).unwrap();
+        let walk = |zipper: &str| {
+            run(&format!(
+                "(loop [loc ({} (quote [1 (2 3) {{:a 4}}])) seen []] (if (zip/end? loc) seen (recur (zip/next loc) (conj seen (zip/node loc)))))",
+                zipper
+            ))
+            .to_string()
+        };
+        assert_eq!(walk("zip/vector-zip"), "[[1 (2 3) {:a 4}] 1 (2 3) {:a 4}]");
+        assert_eq!(
+            walk("zip/edn-zip"),
+            "[[1 (2 3) {:a 4}] 1 (2 3) 2 3 {:a 4} [:a 4] :a 4]"
+        );
+        assert_eq!(
+            run("(zip/branch? (zip/seq-zip (quote [1 2])))"),
+            Value::EDN(EDN::Bool(false))
+        );
+        assert_eq!(
+            run("(zip/children (zip/seq-zip (quote (1 [2 3]))))"),
+            Value::EDN(edn("[1 [2 3]]"))
+        );
+        assert_eq!(
+            run("(zip/node (zip/down (zip/right (zip/down (zip/seq-zip (quote (1 (2 3))))))))"),
+            Value::EDN(edn("2"))
+        );
+        assert_eq!(Loc::new(edn("[1]")).down().unwrap().kind(), Kind::Edn);
+    }
+
+    #[test]
+    fn test_edn_round_trip() {
+        let loc = Loc::new(edn("[1 [2 3] 4]"))
+            .down()
+            .unwrap()
+            .right()
+            .unwrap()
+            .down()
+            .unwrap()
+            .replace(edn("5"))
+            .unwrap();
+        let decoded = Loc::from_edn(&loc.to_edn()).unwrap();
+        assert_eq!(decoded, loc);
+        assert_eq!(decoded.root(), edn("[1 [5 3] 4]"));
+        assert!(Loc::from_edn(&edn("[1 2 3]")).is_err());
+    }
+
+    #[test]
+    fn test_natives() {
+        let env = create_env();
+        let run = |s: &str| eval(read_string(s).unwrap(), &env).unwrap();
+
+        assert_eq!(
+            run("(zip/node (zip/right (zip/down (zip/edn-zip '[1 2 3]))))"),
+            Value::EDN(edn("2"))
+        );
+        assert_eq!(
+            run("(zip/root (zip/append-child (zip/edn-zip '[1]) 2))"),
+            Value::EDN(edn("[1 2]"))
+        );
+        assert_eq!(run("(zip/up (zip/edn-zip '[1]))"), Value::EDN(EDN::Nil));
+        assert_eq!(
+            run("(zip/root (zip/edit (zip/down (zip/edn-zip '[1 2])) (fn [n x] x) 7))"),
+            Value::EDN(edn("[7 2]"))
+        );
+    }
+}